@@ -65,7 +65,7 @@ impl XvcPathMetadataProvider {
                     let mut pmm = pmm.write().unwrap();
                     pmm.insert(xvc_path, xvc_md);
                 }
-                PathEvent::Update { path, metadata } => {
+                PathEvent::Update { path, metadata, .. } => {
                     let xvc_path = XvcPath::new(&xvc_root, &xvc_root, &path).unwrap();
                     let xvc_md = XvcMetadata::from(metadata);
                     let mut pmm = pmm.write().unwrap();
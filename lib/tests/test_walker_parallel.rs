@@ -49,7 +49,7 @@ fn new_dir_with_ignores(
 }
 
 fn create_patterns(root: &str, dir: Option<&str>, patterns: &str) -> Vec<Pattern> {
-    xvc_core::content_to_patterns(Path::new(root), dir.map(Path::new), patterns)
+    xvc_core::content_to_patterns(Path::new(root), dir.map(Path::new), patterns).0
 }
 
 #[test_case("", "" => it contains "dir-0002/file-0001.bin" ; "t3733909666")]
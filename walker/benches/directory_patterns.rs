@@ -0,0 +1,48 @@
+//! Compares [IgnoreRules::check] with [IgnoreRules::check_in_directory] on a single directory
+//! with 500k files.
+//!
+//! Run with `cargo bench -p xvc-walker --bench directory_patterns`.
+use std::path::PathBuf;
+use std::time::Instant;
+
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{content_to_patterns, IgnoreRules, MatchResult};
+
+const N_FILES: usize = 500_000;
+const N_IGNORE_DIRS: usize = 50;
+
+fn main() {
+    let root = create_temp_dir();
+    let ignore_rules =
+        IgnoreRules::from_global_patterns(&root, Some(".gitignore"), "*.tmp\n.DS_Store");
+
+    for i in 0..N_IGNORE_DIRS {
+        let ignore_file = root.join(format!("dir-{i:04}/.gitignore"));
+        let content = "/build/\n/output/*.bin\n!/output/keep.bin\nlogs/**/*.log\n/cache\n";
+        ignore_rules
+            .add_patterns(content_to_patterns(&root, Some(&ignore_file), content).0)
+            .unwrap();
+    }
+
+    let data_dir = root.join("data");
+    let paths: Vec<PathBuf> = (0..N_FILES)
+        .map(|i| data_dir.join(format!("file-{i:07}.bin")))
+        .collect();
+
+    let start = Instant::now();
+    let naive: Vec<MatchResult> = paths.iter().map(|p| ignore_rules.check(p)).collect();
+    let naive_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let dir_patterns = ignore_rules.directory_patterns(&data_dir);
+    let cached: Vec<MatchResult> = paths
+        .iter()
+        .map(|p| ignore_rules.check_in_directory(p, &dir_patterns))
+        .collect();
+    let cached_elapsed = start.elapsed();
+
+    assert_eq!(naive, cached);
+
+    println!("check:              {naive_elapsed:?}");
+    println!("check_in_directory: {cached_elapsed:?}");
+}
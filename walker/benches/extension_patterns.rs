@@ -0,0 +1,57 @@
+//! Compares extension patterns like `*.ext`, which are looked up by extension, with the
+//! equivalent `*.[e]xt` patterns, which are matched as globs.
+//!
+//! Run with `cargo bench -p xvc-walker --bench extension_patterns`.
+use std::path::PathBuf;
+use std::time::Instant;
+
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{IgnoreRules, MatchResult};
+
+const N_EXTENSIONS: usize = 50;
+const N_PATHS: usize = 200_000;
+
+fn main() {
+    let root = create_temp_dir();
+    let extensions: Vec<String> = (0..N_EXTENSIONS).map(|i| format!("ext{i:02}")).collect();
+
+    let extension_patterns: Vec<String> = extensions.iter().map(|e| format!("*.{e}")).collect();
+    let glob_patterns: Vec<String> = extensions
+        .iter()
+        .map(|e| format!("*.[{}]{}", &e[..1], &e[1..]))
+        .collect();
+
+    let extension_rules = IgnoreRules::from_global_patterns(
+        &root,
+        Some(".gitignore"),
+        &extension_patterns.join("\n"),
+    );
+    let glob_rules =
+        IgnoreRules::from_global_patterns(&root, Some(".gitignore"), &glob_patterns.join("\n"));
+
+    // Half of the paths have one of the extensions
+    let paths: Vec<PathBuf> = (0..N_PATHS)
+        .map(|i| {
+            let extension = if i % 2 == 0 {
+                extensions[i % N_EXTENSIONS].as_str()
+            } else {
+                "txt"
+            };
+            root.join(format!("dir-{:03}/file-{i:06}.{extension}", i % 100))
+        })
+        .collect();
+
+    let start = Instant::now();
+    let glob_results: Vec<MatchResult> = paths.iter().map(|p| glob_rules.check(p)).collect();
+    let glob_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let extension_results: Vec<MatchResult> =
+        paths.iter().map(|p| extension_rules.check(p)).collect();
+    let extension_elapsed = start.elapsed();
+
+    assert_eq!(glob_results, extension_results);
+
+    println!("glob patterns:      {glob_elapsed:?}");
+    println!("extension patterns: {extension_elapsed:?}");
+}
@@ -0,0 +1,69 @@
+//! Compares [glob_match] with [GlobPattern::matches], and [glob_match_ci] with
+//! [GlobPattern::matches_case_insensitive], on the globs of common ignore patterns.
+//!
+//! Run with `cargo bench -p xvc-walker --bench glob_pattern`.
+use std::time::Instant;
+
+use xvc_walker::{glob_match, glob_match_ci, GlobPattern, Pattern, Source};
+
+const N_PATHS: usize = 200_000;
+
+fn main() {
+    let patterns: Vec<Pattern> = [
+        "node_modules/",
+        "target/",
+        ".DS_Store",
+        "/build",
+        "/dist/",
+        "__pycache__/",
+        "docs/_site",
+        "src/generated/*.rs",
+        "*.py[cod]",
+        "logs/**/*.log",
+    ]
+    .iter()
+    .map(|p| Pattern::new(Source::Global, p))
+    .collect();
+    let globs: Vec<&GlobPattern> = patterns.iter().map(|p| p.compiled_glob()).collect();
+
+    let paths: Vec<String> = (0..N_PATHS)
+        .map(|i| format!("dir-{:03}/sub-{:02}/file-{i:06}.txt", i % 1000, i % 50))
+        .collect();
+
+    let start = Instant::now();
+    let glob_match_results: Vec<bool> = paths
+        .iter()
+        .map(|path| globs.iter().any(|g| glob_match(g.as_str(), path)))
+        .collect();
+    let glob_match_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let glob_pattern_results: Vec<bool> = paths
+        .iter()
+        .map(|path| globs.iter().any(|g| g.matches(path)))
+        .collect();
+    let glob_pattern_elapsed = start.elapsed();
+
+    assert_eq!(glob_match_results, glob_pattern_results);
+
+    let start = Instant::now();
+    let glob_match_ci_results: Vec<bool> = paths
+        .iter()
+        .map(|path| globs.iter().any(|g| glob_match_ci(g.as_str(), path)))
+        .collect();
+    let glob_match_ci_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let case_insensitive_results: Vec<bool> = paths
+        .iter()
+        .map(|path| globs.iter().any(|g| g.matches_case_insensitive(path)))
+        .collect();
+    let case_insensitive_elapsed = start.elapsed();
+
+    assert_eq!(glob_match_ci_results, case_insensitive_results);
+
+    println!("glob_match:                            {glob_match_elapsed:?}");
+    println!("GlobPattern::matches:                  {glob_pattern_elapsed:?}");
+    println!("glob_match_ci:                         {glob_match_ci_elapsed:?}");
+    println!("GlobPattern::matches_case_insensitive: {case_insensitive_elapsed:?}");
+}
@@ -0,0 +1,71 @@
+//! Compares the memory used by the paths from [walk_parallel] and [walk_parallel_interned] on a
+//! deep tree with 100k files.
+//!
+//! Run with `cargo bench -p xvc-walker --bench interned_paths`.
+use std::fs;
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use crossbeam_channel::unbounded;
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{
+    walk_parallel, walk_parallel_interned, IgnoreRules, InternedPath, PathInterner, WalkOptions,
+};
+
+const N_DIRS: usize = 1000;
+const N_FILES_PER_DIR: usize = 100;
+
+fn main() {
+    let root = create_temp_dir();
+    for i in 0..N_DIRS {
+        let dir = root.join(format!(
+            "project-{:02}/src/components/module-{:03}/generated",
+            i % 10,
+            i
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        for j in 0..N_FILES_PER_DIR {
+            fs::write(dir.join(format!("file-{j:03}.txt")), "").unwrap();
+        }
+    }
+
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+    let ignore_rules = || Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+
+    let start = Instant::now();
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel(ignore_rules(), &root, walk_options.clone(), path_sender).unwrap();
+    let paths: Vec<PathBuf> = path_receiver.into_iter().map(|r| r.unwrap().path).collect();
+    let plain_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let interner = PathInterner::new();
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel_interned(ignore_rules(), &root, walk_options, &interner, path_sender).unwrap();
+    let interned: Vec<InternedPath> = path_receiver.into_iter().map(|r| r.unwrap().path).collect();
+    let interned_elapsed = start.elapsed();
+
+    assert_eq!(paths.len(), interned.len());
+
+    let plain_bytes: usize = paths
+        .iter()
+        .map(|p| size_of::<PathBuf>() + p.capacity())
+        .sum();
+    let interned_bytes: usize = interned
+        .iter()
+        .map(|p| size_of::<InternedPath>() + p.name.len())
+        .sum::<usize>()
+        + interner.estimated_bytes();
+
+    println!("paths:          {} bytes in {plain_elapsed:?}", plain_bytes);
+    println!(
+        "interned paths: {} bytes in {interned_elapsed:?} ({} directories)",
+        interned_bytes,
+        interner.len()
+    );
+}
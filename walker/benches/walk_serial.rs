@@ -0,0 +1,62 @@
+//! Compares [walk_serial], which lists all directories into a single buffer, with a walk that
+//! allocates a new list for each directory, on a tree with 10k directories.
+//!
+//! Run with `cargo bench -p xvc-walker --bench walk_serial`.
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crossbeam_channel::unbounded;
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{directory_list, walk_serial, PathMetadata, WalkOptions};
+
+const N_DIRS: usize = 100;
+const N_SUBDIRS: usize = 100;
+const N_FILES: usize = 5;
+
+/// Walks `root` with a stack like [walk_serial], without the ignore rules.
+fn walk_allocating(root: &Path) -> Vec<PathMetadata> {
+    let mut paths = Vec::new();
+    let mut dir_stack = vec![root.to_path_buf()];
+    while let Some(dir) = dir_stack.pop() {
+        for pm in directory_list(&dir).unwrap().into_iter().flatten() {
+            if pm.metadata.is_dir() {
+                dir_stack.push(pm.path);
+            } else {
+                paths.push(pm);
+            }
+        }
+    }
+    paths
+}
+
+fn main() {
+    let root = create_temp_dir();
+    for d in 0..N_DIRS {
+        for s in 0..N_SUBDIRS {
+            let dir = root.join(format!("dir-{d:03}/sub-{s:03}"));
+            fs::create_dir_all(&dir).unwrap();
+            for f in 0..N_FILES {
+                fs::write(dir.join(format!("file-{f}.txt")), "x").unwrap();
+            }
+        }
+    }
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+    let (output_sender, _output_receiver) = unbounded();
+
+    let start = Instant::now();
+    let allocating = walk_allocating(&root);
+    let allocating_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let (buffered, _) = walk_serial(&output_sender, "", &root, &walk_options).unwrap();
+    let buffered_elapsed = start.elapsed();
+
+    assert_eq!(allocating.len(), buffered.len());
+
+    println!("allocating:  {allocating_elapsed:?}");
+    println!("walk_serial: {buffered_elapsed:?}");
+}
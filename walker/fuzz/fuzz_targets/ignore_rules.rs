@@ -0,0 +1,14 @@
+//! Feeds the contents of an ignore file through
+//! [content_to_patterns](xvc_walker::content_to_patterns) and
+//! [IgnoreRules::check](xvc_walker::IgnoreRules::check) for the candidate paths.
+//!
+//! The input is the file contents, a NUL byte and the candidate paths, one per line.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xvc_walker_fuzz::{check_ignore_file, split_input};
+
+fuzz_target!(|data: &[u8]| {
+    let (content, paths) = split_input(data);
+    check_ignore_file(&content, &paths);
+});
@@ -0,0 +1,15 @@
+//! Feeds a single pattern line through [Pattern::new](xvc_walker::Pattern::new) and its glob
+//! matcher, and compares the results with the reference matcher for the candidate paths.
+//!
+//! The input is the pattern line, a NUL byte and the candidate paths, one per line.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xvc_walker_fuzz::{check_pattern, split_input};
+
+fuzz_target!(|data: &[u8]| {
+    let (text, paths) = split_input(data);
+    if let Some(line) = text.lines().next() {
+        check_pattern(line, &paths);
+    }
+});
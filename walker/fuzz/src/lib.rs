@@ -0,0 +1,435 @@
+//! Shared code for the fuzz targets of xvc-walker.
+//!
+//! [reference_glob_match] is a slow glob matcher written from the documentation of
+//! [glob_match](xvc_walker::glob_match), to compare its results with the optimized matchers.
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use xvc_walker::glob::MAX_BRACE_GROUPS;
+use xvc_walker::{
+    content_to_patterns, glob_match, GlobPattern, IgnoreRules, MatchResult, PathKind, Pattern,
+    PatternEffect, PatternError, Source,
+};
+
+/// The root of the ignore rules in the fuzz targets. It doesn't need to exist, the paths under it
+/// are considered files.
+pub const FUZZ_ROOT: &str = "/xvc-walker-fuzz-root";
+
+/// Splits the input of a fuzz target to the text before the first NUL byte and the paths in the
+/// lines after it.
+///
+/// The paths are made relative by removing the empty and `.` components, and the empty paths are
+/// dropped. The input without a NUL byte is all text.
+pub fn split_input(data: &[u8]) -> (String, Vec<String>) {
+    let data = String::from_utf8_lossy(data);
+    let (text, paths) = data.split_once('\0').unwrap_or((&data, ""));
+    let paths = paths
+        .lines()
+        .map(|path| {
+            path.split('/')
+                .filter(|c| !c.is_empty() && *c != ".")
+                .collect::<Vec<&str>>()
+                .join("/")
+        })
+        .filter(|path| !path.is_empty())
+        .collect();
+    (text.to_string(), paths)
+}
+
+/// Checks that [glob_match], [GlobPattern::matches] and [reference_glob_match] agree on `path`.
+pub fn check_glob(glob: &str, path: &str) {
+    let matched = glob_match(glob, path);
+    assert_eq!(
+        GlobPattern::new(glob).matches(path),
+        matched,
+        "GlobPattern::matches({glob:?}, {path:?})"
+    );
+    if let Some(expected) = reference_glob_match(glob, path) {
+        assert_eq!(matched, expected, "glob_match({glob:?}, {path:?})");
+    }
+}
+
+/// Checks that the pattern parsed from `line` matches the `paths` as the reference matcher does,
+/// as files and as directories.
+///
+/// A path is ignored if a directory above it is ignored, and directory-only patterns never match
+/// files.
+pub fn check_pattern(line: &str, paths: &[String]) {
+    let pattern = Pattern::new(Source::Global, line);
+    let glob = pattern.effective_glob().to_string();
+    for path in paths {
+        check_glob(&glob, path);
+    }
+    if PatternError::check(line, None, 1).is_some() {
+        return;
+    }
+
+    let matched_result = match &pattern.effect {
+        PatternEffect::Ignore | PatternEffect::Override { whitelist: false } => MatchResult::Ignore,
+        PatternEffect::Whitelist | PatternEffect::Override { whitelist: true } => {
+            MatchResult::Whitelist
+        }
+    };
+    let path_kind = pattern.path_kind.clone();
+    let rules = IgnoreRules::from_patterns(Path::new(FUZZ_ROOT), None, vec![pattern]);
+    let matches_dir = |dir: &str| -> Option<bool> {
+        // `dir/*` matches the paths in `dir`, not `dir` itself
+        if glob == format!("{dir}/*") {
+            return Some(false);
+        }
+        Some(reference_glob_match(&glob, &format!("{dir}/"))? || reference_glob_match(&glob, dir)?)
+    };
+    let matches_file = |file: &str| -> Option<bool> {
+        Some(path_kind != PathKind::Directory && reference_glob_match(&glob, file)?)
+    };
+
+    for path in paths {
+        let ancestors: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+        let Some(ancestor_ignored) = ancestors.iter().try_fold(false, |ignored, dir| {
+            Some(ignored || (matched_result == MatchResult::Ignore && matches_dir(dir)?))
+        }) else {
+            continue;
+        };
+
+        for (kind, matches) in [
+            (PathKind::Any, matches_file(path)),
+            (PathKind::Directory, matches_dir(path)),
+        ] {
+            let Some(matches) = matches else {
+                continue;
+            };
+            let expected = if ancestor_ignored {
+                MatchResult::Ignore
+            } else if matches {
+                matched_result.clone()
+            } else {
+                MatchResult::NoMatch
+            };
+            assert_eq!(
+                rules.check_path_str_with_kind(path, kind.clone()),
+                expected,
+                "{line:?} {glob:?} {path:?} {kind:?}"
+            );
+        }
+    }
+}
+
+/// Checks the rules parsed from the ignore file `content` with the `paths`.
+///
+/// The rules must not panic, and the ways to check a path must agree.
+pub fn check_ignore_file(content: &str, paths: &[String]) {
+    let root = Path::new(FUZZ_ROOT);
+    let (patterns, _) = content_to_patterns(root, Some(&root.join(".gitignore")), content);
+    let rules = IgnoreRules::from_patterns(root, Some(".gitignore"), patterns);
+    rules.lint();
+    rules.snapshot_patterns();
+    rules.render_for_dir(root);
+
+    for path in paths {
+        let as_file = rules.check_path_str_with_kind(path, PathKind::Any);
+        assert_eq!(rules.check_path_str(path), as_file, "{path:?}");
+        assert_eq!(rules.check(&root.join(path)), as_file, "{path:?}");
+        assert_eq!(
+            rules.check_hypothetical(path, PathKind::Any),
+            as_file,
+            "{path:?}"
+        );
+
+        let as_dir = rules.check_path_str_with_kind(path, PathKind::Directory);
+        assert_eq!(
+            rules.check_path_str(&format!("{path}/")),
+            as_dir,
+            "{path:?}"
+        );
+        assert_eq!(
+            rules.check_hypothetical(path, PathKind::Directory),
+            as_dir,
+            "{path:?}"
+        );
+    }
+}
+
+/// A part of a glob without braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(u8),
+    Star,
+    /// `**/` as a whole component
+    Globstar,
+    /// `**` as the last whole component
+    TrailingGlobstar,
+    Question,
+    Class {
+        ranges: Vec<(u8, u8)>,
+        negated: bool,
+    },
+}
+
+/// Matches `path` to `glob` by simulating all the ways the tokens can match.
+///
+/// Returns `None` for the globs with unterminated escapes or classes, unbalanced braces, more
+/// than [MAX_BRACE_GROUPS] brace groups, a `**` next to a brace or braces in a class, as their
+/// results are not specified. Matching is done on
+/// bytes, like [glob_match](xvc_walker::glob_match), and only `/` is a separator.
+pub fn reference_glob_match(glob: &str, path: &str) -> Option<bool> {
+    let glob = glob.as_bytes();
+    let negations = glob.iter().take_while(|c| **c == b'!').count();
+    let glob = &glob[negations..];
+    if brace_groups(glob)? > MAX_BRACE_GROUPS
+        || globstar_next_to_brace(glob)
+        || class_has_braces(glob)
+    {
+        return None;
+    }
+
+    let mut matched = false;
+    for alternative in expand(glob)? {
+        let tokens = tokenize(&alternative)?;
+        matched |= match_tokens(&tokens, path.as_bytes());
+    }
+    Some(matched ^ (negations % 2 == 1))
+}
+
+/// Returns `true` if a `**` is next to a brace or a comma, e.g., `**{a,b}`.
+///
+/// Whether the `**` is a whole component depends on the alternative, and
+/// [glob_match](xvc_walker::glob_match) decides it before choosing the alternative.
+fn globstar_next_to_brace(glob: &[u8]) -> bool {
+    glob.windows(3).any(|w| match w {
+        [b'*', b'*', c] | [c, b'*', b'*'] => matches!(c, b'{' | b'}' | b','),
+        _ => false,
+    })
+}
+
+/// Returns `true` if a class has a brace, a comma or a `[` in it, e.g., `[]{]`.
+///
+/// [glob_match](xvc_walker::glob_match) looks for the braces before parsing the classes, so it
+/// may take them as brace groups.
+fn class_has_braces(glob: &[u8]) -> bool {
+    let mut i = 0;
+    while i < glob.len() {
+        match glob[i] {
+            b'\\' => i += 1,
+            b'[' => {
+                i += 1;
+                if matches!(glob.get(i), Some(b'!' | b'^')) {
+                    i += 1;
+                }
+                // `]` first in the class is a literal
+                if glob.get(i) == Some(&b']') {
+                    i += 1;
+                }
+                while i < glob.len() && glob[i] != b']' {
+                    match glob[i] {
+                        b'{' | b'}' | b',' | b'[' => return true,
+                        b'\\' => i += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Returns the number of brace groups in `glob`, or `None` if the braces are not balanced.
+fn brace_groups(glob: &[u8]) -> Option<usize> {
+    let mut groups = 0;
+    let mut depth = 0usize;
+    let mut in_class = false;
+    let mut i = 0;
+    while i < glob.len() {
+        match glob[i] {
+            b'\\' => i += 1,
+            b'[' if !in_class => in_class = true,
+            b']' => in_class = false,
+            b'{' if !in_class => {
+                groups += 1;
+                depth += 1;
+            }
+            b'}' if !in_class => depth = depth.checked_sub(1)?,
+            _ => {}
+        }
+        i += 1;
+    }
+    (depth == 0).then_some(groups)
+}
+
+/// Expands the first brace group in `glob` and the groups in its alternatives recursively.
+fn expand(glob: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut depth = 0;
+    let mut in_class = false;
+    let mut open = 0;
+    let mut bounds = Vec::new();
+    let mut i = 0;
+    while i < glob.len() {
+        match glob[i] {
+            b'\\' => i += 1,
+            b'[' if !in_class => in_class = true,
+            b']' => in_class = false,
+            b'{' if !in_class => {
+                if depth == 0 {
+                    open = i;
+                    bounds = vec![i];
+                }
+                depth += 1;
+            }
+            b',' if !in_class && depth == 1 => bounds.push(i),
+            b'}' if !in_class => {
+                depth -= 1;
+                if depth == 0 {
+                    bounds.push(i);
+                    let (prefix, suffix) = (&glob[..open], &glob[i + 1..]);
+                    let mut alternatives = Vec::new();
+                    for window in bounds.windows(2) {
+                        let branch = &glob[window[0] + 1..window[1]];
+                        let alternative = [prefix, branch, suffix].concat();
+                        alternatives.extend(expand(&alternative)?);
+                    }
+                    return Some(alternatives);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Some(vec![glob.to_vec()])
+}
+
+/// Returns the byte `\c` stands for.
+fn unescaped(c: u8) -> u8 {
+    match c {
+        b'b' => b'\x08',
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        c => c,
+    }
+}
+
+/// Splits a glob without braces into tokens, or returns `None` if it has an unterminated escape
+/// or class.
+fn tokenize(glob: &[u8]) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < glob.len() {
+        match glob[i] {
+            b'*' if glob.get(i + 1) == Some(&b'*') => {
+                let starts_component = i == 0 || glob[i - 1] == b'/';
+                let ends_component = matches!(glob.get(i + 2), None | Some(b'/'));
+                if starts_component && i + 2 == glob.len() {
+                    tokens.push(Token::TrailingGlobstar);
+                    i += 2;
+                } else if starts_component && ends_component {
+                    tokens.push(Token::Globstar);
+                    i += 3;
+                } else {
+                    tokens.push(Token::Star);
+                    i += 2;
+                }
+                continue;
+            }
+            b'*' => tokens.push(Token::Star),
+            b'?' => tokens.push(Token::Question),
+            b'\\' => {
+                i += 1;
+                tokens.push(Token::Literal(unescaped(*glob.get(i)?)));
+            }
+            b'[' => {
+                i += 1;
+                let negated = matches!(glob.get(i), Some(b'!' | b'^'));
+                if negated {
+                    i += 1;
+                }
+                let mut ranges = Vec::new();
+                loop {
+                    let c = *glob.get(i)?;
+                    if c == b']' && !ranges.is_empty() {
+                        break;
+                    }
+                    let low = class_byte(glob, &mut i)?;
+                    let high = match (glob.get(i), glob.get(i + 1)) {
+                        (Some(b'-'), Some(next)) if *next != b']' => {
+                            i += 1;
+                            class_byte(glob, &mut i)?
+                        }
+                        _ => low,
+                    };
+                    // A reversed range matches only its first byte
+                    ranges.push((low, high.max(low)));
+                }
+                tokens.push(Token::Class { ranges, negated });
+            }
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+    Some(tokens)
+}
+
+/// Reads a possibly escaped byte in a class.
+fn class_byte(glob: &[u8], i: &mut usize) -> Option<u8> {
+    let c = if glob[*i] == b'\\' {
+        *i += 1;
+        unescaped(*glob.get(*i)?)
+    } else {
+        glob[*i]
+    };
+    *i += 1;
+    Some(c)
+}
+
+/// Returns `true` if the tokens match the whole `path`.
+///
+/// Keeps the set of the path positions the tokens matched so far can end at.
+fn match_tokens(tokens: &[Token], path: &[u8]) -> bool {
+    let mut positions = BTreeSet::from([0]);
+    for token in tokens {
+        let mut next = BTreeSet::new();
+        for &i in &positions {
+            let byte = path.get(i).copied();
+            match token {
+                Token::Literal(c) => {
+                    if byte == Some(*c) {
+                        next.insert(i + 1);
+                    }
+                }
+                Token::Question => {
+                    if byte.is_some_and(|b| b != b'/') {
+                        next.insert(i + 1);
+                    }
+                }
+                Token::Class { ranges, negated } => {
+                    if let Some(b) = byte.filter(|b| *b != b'/') {
+                        let in_class = ranges.iter().any(|(low, high)| *low <= b && b <= *high);
+                        if in_class != *negated {
+                            next.insert(i + 1);
+                        }
+                    }
+                }
+                Token::Star => {
+                    let end = path[i..]
+                        .iter()
+                        .position(|b| *b == b'/')
+                        .map_or(path.len(), |len| i + len);
+                    next.extend(i..=end);
+                }
+                Token::Globstar => {
+                    next.insert(i);
+                    next.extend((i..path.len()).filter(|j| path[*j] == b'/').map(|j| j + 1));
+                }
+                Token::TrailingGlobstar => {
+                    next.insert(path.len());
+                }
+            }
+        }
+        if next.is_empty() {
+            return false;
+        }
+        positions = next;
+    }
+    positions.contains(&path.len())
+}
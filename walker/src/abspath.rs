@@ -2,7 +2,9 @@
 use std::ffi::OsString;
 use std::fmt::Display;
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+
+use crate::{Error, Result};
 
 /// A specialized path type for absolute paths.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -42,6 +44,8 @@ impl AbsolutePath {
     }
 
     /// Appends a relative path to this path to get another absolute path
+    ///
+    /// Panics if `p` is absolute. Use [AbsolutePath::join_within] for paths from user input.
     pub fn join<T>(&self, p: T) -> AbsolutePath
     where
         T: AsRef<Path>,
@@ -49,6 +53,56 @@ impl AbsolutePath {
         assert!(!p.as_ref().is_absolute());
         Self(self.0.join(p))
     }
+
+    /// Appends a relative path to this path, checking that the result stays within this path.
+    ///
+    /// Returns an error if `p` is absolute, or if its `..` components go above this path. The
+    /// components of `p` are kept as they are in the result.
+    pub fn join_within<T>(&self, p: T) -> Result<AbsolutePath>
+    where
+        T: AsRef<Path>,
+    {
+        self.normalize_within(p.as_ref())?;
+        Ok(Self(self.0.join(p)))
+    }
+
+    /// Appends a relative path to this path after resolving its `.` and `..` components
+    /// lexically.
+    ///
+    /// Returns an error if `p` is absolute, or if its `..` components go above this path.
+    pub fn join_normalized<T>(&self, p: T) -> Result<AbsolutePath>
+    where
+        T: AsRef<Path>,
+    {
+        let normalized = self.normalize_within(p.as_ref())?;
+        Ok(Self(self.0.join(normalized)))
+    }
+
+    /// Resolves `.` and `..` in `p` without leaving this path.
+    fn normalize_within(&self, p: &Path) -> Result<PathBuf> {
+        let mut normalized = PathBuf::new();
+        for component in p.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir => {
+                    return Err(Error::CannotJoinAbsolutePath {
+                        base: self.0.clone(),
+                        path: p.to_path_buf(),
+                    });
+                }
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return Err(Error::PathEscapesBase {
+                            base: self.0.clone(),
+                            path: p.to_path_buf(),
+                        });
+                    }
+                }
+                Component::Normal(c) => normalized.push(c),
+            }
+        }
+        Ok(normalized)
+    }
 }
 
 impl From<&PathBuf> for AbsolutePath {
@@ -0,0 +1,53 @@
+//! Metadata fields to detect file changes that keep the modification time.
+//!
+//! The modification time can be set back after a change, e.g., by `cp -p` or `touch -r`.
+//! [ChangeSignature] adds the fields the OS updates itself, so it can be compared or hashed to
+//! decide whether a file should be checked again.
+use std::fs::Metadata;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+/// The metadata fields that change when a file is modified or replaced.
+///
+/// On unix, the status change time is updated on every write and metadata change and can't be set
+/// by the user. The inode number changes when the file is replaced by another one, e.g., by
+/// editors that save to a temporary file and rename it. On other platforms, only the size and
+/// modification time are available, as std doesn't expose a file id on stable Rust.
+///
+/// The fields are compared and hashed together, so the signature can be used as a key to find
+/// the files that changed since the last walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChangeSignature {
+    /// The size of the file in bytes
+    pub len: u64,
+    /// The modification time, if the platform supports it
+    pub mtime: Option<SystemTime>,
+    /// The seconds part of the status change time
+    #[cfg(unix)]
+    pub ctime: i64,
+    /// The nanoseconds part of the status change time
+    #[cfg(unix)]
+    pub ctime_nsec: i64,
+    /// The inode number
+    #[cfg(unix)]
+    pub ino: u64,
+}
+
+impl From<&Metadata> for ChangeSignature {
+    fn from(metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            len: metadata.len(),
+            mtime: metadata.modified().ok(),
+            #[cfg(unix)]
+            ctime: metadata.ctime(),
+            #[cfg(unix)]
+            ctime_nsec: metadata.ctime_nsec(),
+            #[cfg(unix)]
+            ino: metadata.ino(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Directory listing for async code.
+//!
+//! [directory_list_async] lists a directory with [tokio::fs] like
+//! [directory_list](crate::directory_list) does with [std::fs], so listing large directories
+//! doesn't block the runtime threads.
+use std::path::Path;
+
+use anyhow::anyhow;
+use xvc_logging::warn;
+
+use crate::{Error, IgnoreRules, MatchResult, PathMetadata, Result};
+
+/// Return all childs of a directory regardless of any ignore rules, like
+/// [directory_list](crate::directory_list).
+///
+/// Returns an error if `dir` can't be read. The entries that can't be read are returned as errors
+/// in the list.
+pub async fn directory_list_async(dir: &Path) -> Result<Vec<Result<PathMetadata>>> {
+    let mut child_paths = Vec::<Result<PathMetadata>>::new();
+    let mut elements = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| anyhow!("Error reading directory: {:?}, {:?}", dir, e))?;
+
+    loop {
+        match elements.next_entry().await {
+            Ok(None) => break,
+            Err(err) => child_paths.push(Err(Error::from(anyhow!(
+                "Error reading entry in dir {:?} {:?}",
+                dir,
+                err
+            )))),
+            Ok(Some(entry)) => match entry.metadata().await {
+                Err(err) => child_paths.push(Err(Error::from(anyhow!(
+                    "Error getting metadata {:?} {:?}",
+                    entry,
+                    err
+                )))),
+                Ok(md) => {
+                    child_paths.push(Ok(PathMetadata::new(entry.path(), md)));
+                }
+            },
+        }
+    }
+    Ok(child_paths)
+}
+
+/// Return the childs of a directory that are not ignored by `ignore_rules`.
+///
+/// The entries that can't be read are logged as warnings and skipped.
+pub async fn directory_list_filtered_async(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+) -> Result<Vec<PathMetadata>> {
+    let child_paths = directory_list_async(dir).await?;
+    let dir_patterns = ignore_rules.directory_patterns(dir);
+    Ok(child_paths
+        .into_iter()
+        .filter_map(|pm_res| match pm_res {
+            Ok(pm) => match ignore_rules.check_in_directory(&pm.path, &dir_patterns) {
+                MatchResult::NoMatch | MatchResult::Whitelist => Some(pm),
+                MatchResult::Ignore => None,
+            },
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        })
+        .collect())
+}
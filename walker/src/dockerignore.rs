@@ -0,0 +1,95 @@
+//! Reads `.dockerignore` files as ignore patterns.
+//!
+//! The syntax is similar to `.gitignore`, but all patterns are relative to the directory of the
+//! file, as if they start with `/`, and `!` is a literal character rather than negation. Hence
+//! the patterns are never [PatternEffect::Whitelist](crate::PatternEffect::Whitelist).
+//! [WalkOptions::dockerignore](crate::WalkOptions::dockerignore) uses these rules.
+use std::path::Path;
+
+use crate::{Pattern, Source};
+
+/// The file name to read the docker ignore patterns from
+pub const DOCKERIGNORE_FILENAME: &str = ".dockerignore";
+
+/// Converts the lines in `content` to ignore patterns with Docker's rules.
+///
+/// `source` is the path of the `.dockerignore` file and must be within `root`. Empty lines and
+/// comments are skipped. Leading `./` and `/`, and trailing `/` are removed before the pattern is
+/// anchored to the directory of `source`.
+pub fn dockerignore_content_to_patterns(root: &Path, source: &Path, content: &str) -> Vec<Pattern> {
+    let source_path = source.strip_prefix(root).expect("path must be within root");
+
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let mut cleaned = line;
+            while let Some(rest) = cleaned.strip_prefix("./") {
+                cleaned = rest;
+            }
+            let cleaned = cleaned.trim_matches('/');
+            if cleaned.is_empty() || cleaned == "." {
+                return None;
+            }
+            // glob_match treats a leading `!` as negation
+            let cleaned = match cleaned.strip_prefix('!') {
+                Some(rest) => format!("/\\!{rest}"),
+                None => format!("/{cleaned}"),
+            };
+            let mut pattern = Pattern::new(
+                Source::File {
+                    path: source_path.to_path_buf(),
+                    line: i + 1,
+                    resolved_from: None,
+                },
+                &cleaned,
+            );
+            pattern.original = line.to_string();
+            Some(pattern)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PatternEffect;
+
+    fn patterns(content: &str) -> Vec<Pattern> {
+        dockerignore_content_to_patterns(
+            Path::new("/repo"),
+            Path::new("/repo/app/.dockerignore"),
+            content,
+        )
+    }
+
+    #[test]
+    fn test_patterns_are_anchored() {
+        let patterns = patterns("*.log\n./build/\n/dist\n**/*.tmp\n");
+        let globs: Vec<&str> = patterns.iter().map(|p| p.compiled_glob.as_str()).collect();
+        assert_eq!(
+            globs,
+            vec!["app/*.log", "app/build", "app/dist", "app/**/*.tmp"]
+        );
+        assert_eq!(patterns[1].original, "./build/");
+    }
+
+    #[test]
+    fn test_exclamation_is_literal() {
+        let patterns = patterns("!keep.txt\n");
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].effect, PatternEffect::Ignore);
+        assert!(patterns[0].compiled_glob.matches("app/!keep.txt"));
+        assert!(!patterns[0].compiled_glob.matches("app/keep.txt"));
+    }
+
+    #[test]
+    fn test_comments_and_empty_lines_are_skipped() {
+        let patterns = patterns("# comment\n\n   \n.\n/\n");
+        assert!(patterns.is_empty());
+    }
+}
@@ -1,6 +1,11 @@
 //! Error codes and messages for Xvc Walker
+use crate::FileKind;
+use crate::IgnoreFileError;
+use crate::PatternError;
+use crate::PatternLimitError;
 use log::{debug, error, info, trace, warn};
 use std::hash::Hash;
+use std::path::PathBuf;
 use thiserror::Error as ThisError;
 
 #[allow(missing_docs)]
@@ -32,6 +37,67 @@ pub enum Error {
 
     #[error("Cannot Merge Empty Ignore Rules")]
     CannotMergeEmptyIgnoreRules,
+
+    #[error("Broken symlink: {path:?}")]
+    BrokenSymlink { path: PathBuf },
+
+    #[error("Special file: {path:?} is a {kind:?}")]
+    SpecialFile { path: PathBuf, kind: FileKind },
+
+    #[error("Circular symlink: {link:?} points to its ancestor {target:?}")]
+    CircularSymlink { link: PathBuf, target: PathBuf },
+
+    #[error("Case collision: {conflicting:?} differs only in case from {kept:?}")]
+    CaseCollision { kept: PathBuf, conflicting: PathBuf },
+
+    #[error("Cannot join absolute path {path:?} to {base:?}")]
+    CannotJoinAbsolutePath { base: PathBuf, path: PathBuf },
+
+    #[error("Path {path:?} escapes {base:?}")]
+    PathEscapesBase { base: PathBuf, path: PathBuf },
+
+    #[error("Cannot read metadata of {path:?}: {source}")]
+    UnreadableMetadata {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Walk root not found: {path:?}")]
+    RootNotFound { path: PathBuf },
+
+    #[error("Invalid walk record at line {line}: {cause}")]
+    InvalidWalkRecord { line: usize, cause: String },
+
+    #[error("Receiver disconnected: {paths_sent} paths sent, {dirs_scanned} directories scanned, not delivered: {undelivered_dirs:?}")]
+    DeliveryIncomplete {
+        paths_sent: usize,
+        dirs_scanned: usize,
+        undelivered_dirs: Vec<PathBuf>,
+    },
+
+    #[error("Invalid pattern: {source}")]
+    InvalidPattern {
+        #[from]
+        source: PatternError,
+    },
+
+    #[error("Cannot read ignore file: {source}")]
+    UnreadableIgnoreFile {
+        #[from]
+        source: IgnoreFileError,
+    },
+
+    #[error("Walk option {option} is not supported by {walker}")]
+    UnsupportedWalkOption {
+        option: &'static str,
+        walker: &'static str,
+    },
+
+    #[error("Too many patterns in ignore file: {source}")]
+    TooManyPatterns {
+        #[from]
+        source: PatternLimitError,
+    },
 }
 
 impl Hash for Error {
@@ -0,0 +1,125 @@
+//! Reads `export-ignore` attributes from `.gitattributes` files as ignore patterns.
+//!
+//! Git excludes the paths with `export-ignore` attribute from archives. When
+//! [WalkOptions::read_gitattributes_export_ignore](crate::WalkOptions) is set, the walkers
+//! treat them as ignored paths. Other attributes are skipped.
+use std::fs;
+use std::path::Path;
+
+use crate::{IgnoreRules, Pattern, Result, Source};
+
+/// The file name to read the attributes from
+pub const GITATTRIBUTES_FILENAME: &str = ".gitattributes";
+
+const EXPORT_IGNORE_ATTRIBUTE: &str = "export-ignore";
+
+/// Converts the lines with `export-ignore` attribute in `content` to ignore patterns.
+///
+/// `source` is the path of the `.gitattributes` file and must be within `ignore_root`.
+/// Comments, negative patterns and lines without `export-ignore` are skipped.
+pub fn content_to_export_ignore_patterns(
+    ignore_root: &Path,
+    source: &Path,
+    content: &str,
+) -> Vec<Pattern> {
+    let source_path = source
+        .strip_prefix(ignore_root)
+        .expect("path must be within ignore_root");
+
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let (pattern, attributes) = split_pattern(line.trim())?;
+            if pattern.starts_with('!') {
+                return None;
+            }
+            if !attributes
+                .split_whitespace()
+                .any(|a| a == EXPORT_IGNORE_ATTRIBUTE)
+            {
+                return None;
+            }
+            Some(Pattern::new(
+                Source::GitAttributes {
+                    path: source_path.to_path_buf(),
+                    line: i + 1,
+                },
+                pattern,
+            ))
+        })
+        .collect()
+}
+
+/// Splits a line into its pattern and attributes. Pattern may be quoted to contain spaces.
+fn split_pattern(line: &str) -> Option<(&str, &str)> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    if let Some(quoted) = line.strip_prefix('"') {
+        let (pattern, attributes) = quoted.split_once('"')?;
+        Some((pattern, attributes))
+    } else {
+        line.split_once(char::is_whitespace)
+    }
+}
+
+/// Adds the `export-ignore` patterns in the `.gitattributes` file of `dir` to `ignore_rules`.
+pub fn update_export_ignore_rules(dir: &Path, ignore_rules: &IgnoreRules) -> Result<()> {
+    let attributes_path = dir.join(GITATTRIBUTES_FILENAME);
+    if attributes_path.is_file() {
+        let content = fs::read_to_string(&attributes_path)?;
+        let new_patterns =
+            content_to_export_ignore_patterns(&ignore_rules.root, &attributes_path, &content);
+        ignore_rules.add_patterns(new_patterns)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PatternEffect;
+    use std::path::PathBuf;
+
+    fn patterns(content: &str) -> Vec<Pattern> {
+        content_to_export_ignore_patterns(
+            Path::new("/repo"),
+            Path::new("/repo/data/.gitattributes"),
+            content,
+        )
+    }
+
+    #[test]
+    fn test_export_ignore_lines() {
+        let patterns = patterns("raw export-ignore\n*.csv export-ignore -diff\n");
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].original, "raw");
+        assert_eq!(patterns[0].compiled_glob.as_str(), "data/**/raw");
+        assert_eq!(patterns[0].effect, PatternEffect::Ignore);
+        assert_eq!(
+            patterns[0].source,
+            Source::GitAttributes {
+                path: PathBuf::from("data/.gitattributes"),
+                line: 1
+            }
+        );
+        assert_eq!(patterns[1].original, "*.csv");
+    }
+
+    #[test]
+    fn test_other_attributes_are_skipped() {
+        let patterns = patterns(
+            "# comment\n\n*.bin binary\n*.txt -export-ignore\n*.md !export-ignore\n!x export-ignore\nexport-ignore\n",
+        );
+        assert!(patterns.is_empty());
+    }
+
+    #[test]
+    fn test_quoted_pattern() {
+        let patterns = patterns("\"with space\" export-ignore\n");
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].original, "with space");
+    }
+}
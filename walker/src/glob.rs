@@ -6,9 +6,14 @@
  * Copyright (c) 2023 Devon Govett
  * https://github.com/devongovett/glob-match/tree/main/LICENSE
  */
+#[cfg(feature = "unicode-normalization")]
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::path::is_separator;
 
 use arrayvec::ArrayVec;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Clone, Debug, Default)]
 struct State {
@@ -27,14 +32,544 @@ struct Wildcard {
     brace_depth: u32,
 }
 
-type BraceStack = ArrayVec<(u32, u32), 10>;
-
+/// The maximum number of brace groups [glob_match] follows to match a path.
+pub const MAX_BRACE_GROUPS: usize = 10;
+
+type BraceStack = ArrayVec<(u32, u32), MAX_BRACE_GROUPS>;
+
+/// Returns `true` if `path` matches `glob` as a whole.
+///
+/// - `*` matches any sequence of characters except the path separator.
+/// - `**` matches any number of path components, including none, when it's a whole component,
+///   e.g., `a/**/b` matches `a/b` and `a/x/y/b`. Otherwise it behaves like `*`.
+/// - `?` matches a single character except the path separator.
+/// - `[abc]`, `[a-z]` match a single character in the class, `[!abc]` and `[^abc]` negate it.
+///   Classes may have multiple ranges like `[a-zA-Z0-9_]`. A `]` first in the class and a `-`
+///   first or last in it, or right after a range, are literal. A reversed range like `[z-a]` matches
+///   only its first character, like in git. Classes, also negated ones, never match a separator.
+/// - `{a,b}` matches either of the comma separated alternatives. A path that needs more than
+///   [MAX_BRACE_GROUPS] brace groups to match, nested or one after another, doesn't match.
+/// - `\` escapes the next character.
+/// - A leading `!` negates the whole match.
+///
+/// `/` in `glob` matches any path separator. Trailing slashes are not handled specially: `dir/`
+/// matches `dir/` but not `dir`. Ignore rules add a trailing slash to directory paths to match
+/// directory-only patterns.
 pub fn glob_match(glob: impl AsRef<[u8]>, path: impl AsRef<[u8]>) -> bool {
     let glob = glob.as_ref();
     let path = path.as_ref();
     glob_match_impl(glob, path)
 }
 
+/// Case insensitive version of [glob_match].
+///
+/// Both `glob` and `path` are converted to lowercase before matching.
+pub fn glob_match_ci(glob: &str, path: &str) -> bool {
+    glob_match(glob.to_lowercase(), path.to_lowercase())
+}
+
+/// Unicode normalized version of [glob_match].
+///
+/// Both `glob` and `path` are converted to NFC before matching, so `caf\u{E9}.txt` matches
+/// `cafe\u{301}.txt`, the same name as macOS file systems store it, and vice versa.
+#[cfg(feature = "unicode-normalization")]
+pub fn glob_match_nfc(glob: &str, path: &str) -> bool {
+    glob_match(nfc(glob).as_bytes(), nfc(path).as_bytes())
+}
+
+/// Returns `s` converted to NFC, or `s` itself if it's already in NFC.
+#[cfg(feature = "unicode-normalization")]
+pub(crate) fn nfc(s: &str) -> Cow<'_, str> {
+    if unicode_normalization::is_nfc(s) {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(s.nfc().collect())
+    }
+}
+
+/// A glob parsed once to match many paths.
+///
+/// Globs without wildcards and globs like `dir/**/name` are matched by comparing the literal
+/// parts. Others are matched with [glob_match].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobPattern {
+    glob: String,
+    matcher: GlobMatcher,
+    depth_range: DepthRange,
+    /// The lowercase glob for [GlobPattern::matches_case_insensitive], if it differs from `glob`.
+    lowercase: Option<Box<GlobPattern>>,
+    /// The glob in NFC for [GlobPattern::matches_nfc], if it differs from `glob`.
+    #[cfg(feature = "unicode-normalization")]
+    normalized: Option<Box<GlobPattern>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum GlobMatcher {
+    /// The glob has no wildcards and matches only itself.
+    Literal,
+    /// `prefix**/suffix` matches the paths that start with `prefix` and end with the components
+    /// in `suffix`.
+    Globstar {
+        prefix_len: usize,
+        suffix_start: usize,
+    },
+    /// Matched with [glob_match].
+    Glob,
+}
+
+impl GlobPattern {
+    /// Parses `glob`. See [glob_match] for the syntax.
+    pub fn new(glob: impl Into<String>) -> Self {
+        let glob = glob.into();
+        let is_literal = |s: &str| !s.contains(['*', '?', '[', '{', '\\']);
+
+        let matcher = if glob.starts_with('!') {
+            GlobMatcher::Glob
+        } else if is_literal(&glob) {
+            GlobMatcher::Literal
+        } else {
+            match glob.find("**/") {
+                Some(i)
+                    if (i == 0 || glob[..i].ends_with('/'))
+                        && is_literal(&glob[..i])
+                        && is_literal(&glob[i + 3..])
+                        && glob.len() > i + 3 =>
+                {
+                    GlobMatcher::Globstar {
+                        prefix_len: i,
+                        suffix_start: i + 3,
+                    }
+                }
+                _ => GlobMatcher::Glob,
+            }
+        };
+
+        let depth_range = DepthRange::of(&glob);
+        let lowercase = glob.to_lowercase();
+        let lowercase = (lowercase != glob).then(|| Box::new(GlobPattern::new(lowercase)));
+        #[cfg(feature = "unicode-normalization")]
+        let normalized = match nfc(&glob) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(normalized) => Some(Box::new(GlobPattern::new(normalized))),
+        };
+        GlobPattern {
+            glob,
+            matcher,
+            depth_range,
+            lowercase,
+            #[cfg(feature = "unicode-normalization")]
+            normalized,
+        }
+    }
+
+    /// Returns the glob string.
+    pub fn as_str(&self) -> &str {
+        &self.glob
+    }
+
+    /// Returns `false` if the glob can't match any path with `depth`, like
+    /// [glob_can_match_at_depth].
+    pub fn can_match_at_depth(&self, depth: usize) -> bool {
+        self.depth_range.contains(depth)
+    }
+
+    /// Returns `true` if `path` matches the glob, like [glob_match].
+    pub fn matches(&self, path: &str) -> bool {
+        let glob = self.glob.as_bytes();
+        let path = path.as_bytes();
+        match self.matcher {
+            GlobMatcher::Literal => literal_match(glob, path),
+            GlobMatcher::Globstar {
+                prefix_len,
+                suffix_start,
+            } => {
+                let (prefix, suffix) = (&glob[..prefix_len], &glob[suffix_start..]);
+                if path.len() < prefix.len() + suffix.len()
+                    || !literal_match(prefix, &path[..prefix.len()])
+                {
+                    return false;
+                }
+                let rest = &path[prefix.len()..];
+                let suffix_index = rest.len() - suffix.len();
+                literal_match(suffix, &rest[suffix_index..])
+                    && (suffix_index == 0 || is_separator(rest[suffix_index - 1] as char))
+            }
+            GlobMatcher::Glob => glob_match(glob, path),
+        }
+    }
+
+    /// Returns `true` if `path` matches the glob ignoring the case, like [glob_match_ci].
+    ///
+    /// The glob is converted to lowercase once, in [GlobPattern::new], so only `path` is converted
+    /// here.
+    pub fn matches_case_insensitive(&self, path: &str) -> bool {
+        self.lowercase
+            .as_deref()
+            .unwrap_or(self)
+            .matches(&path.to_lowercase())
+    }
+
+    /// Returns `true` if `path` matches the glob after both are converted to NFC, like
+    /// [glob_match_nfc].
+    #[cfg(feature = "unicode-normalization")]
+    pub fn matches_nfc(&self, path: &str) -> bool {
+        self.normalized().matches(&nfc(path))
+    }
+
+    /// Returns `true` if the glob is in NFC.
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) fn is_nfc(&self) -> bool {
+        self.normalized.is_none()
+    }
+
+    /// Returns the glob converted to NFC, to match the paths in NFC.
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) fn normalized(&self) -> &GlobPattern {
+        self.normalized.as_deref().unwrap_or(self)
+    }
+}
+
+impl std::fmt::Display for GlobPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.glob)
+    }
+}
+
+/// Returns `false` if `glob` can't match any path with `depth`.
+///
+/// The depth of a path is one more than the number of separators in it, e.g., `a/b/c.rs` and
+/// `a/b/` have depth 3. This is a quick check to skip matching the paths that are too shallow or
+/// too deep for the glob, e.g., `a/b/c/*.rs` can only match paths with depth 4.
+pub fn glob_can_match_at_depth(glob: &str, depth: usize) -> bool {
+    DepthRange::of(glob).contains(depth)
+}
+
+/// Returns the depth of `path` as in [glob_can_match_at_depth].
+pub(crate) fn path_depth(path: &str) -> usize {
+    path.bytes().filter(|c| is_separator(*c as char)).count() + 1
+}
+
+/// The minimum and maximum depths of the paths a glob can match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DepthRange {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl DepthRange {
+    const ANY: DepthRange = DepthRange { min: 0, max: None };
+
+    fn of(glob: &str) -> Self {
+        if glob.starts_with('!') {
+            return Self::ANY;
+        }
+
+        expand_braces(glob)
+            .iter()
+            .map(|alternative| Self::of_alternative(alternative))
+            .reduce(|a, b| DepthRange {
+                min: a.min.min(b.min),
+                max: a.max.zip(b.max).map(|(a, b)| a.max(b)),
+            })
+            .unwrap_or(Self::ANY)
+    }
+
+    fn of_alternative(glob: &str) -> Self {
+        let Some(tokens) = tokenize(glob.as_bytes()) else {
+            return Self::ANY;
+        };
+
+        let count = |f: fn(&Token) -> bool| tokens.iter().filter(|t| f(t)).count();
+        let separators = count(|t| *t == Token::Literal(b'/'));
+        let globstars = count(|t| matches!(t, Token::Globstar | Token::TrailingGlobstar));
+        // `**` that is not a whole component may still match any number of components in a brace
+        // alternative, and the separator after it may match nothing.
+        let other_globstars = glob.matches("**").count() - globstars;
+
+        DepthRange {
+            min: separators.saturating_sub(other_globstars) + 1,
+            max: (globstars + other_globstars == 0).then_some(separators + 1),
+        }
+    }
+
+    fn contains(&self, depth: usize) -> bool {
+        self.min <= depth && self.max.is_none_or(|max| depth <= max)
+    }
+}
+
+/// Compares a glob without wildcards to `path`. `/` in the glob matches any separator.
+fn literal_match(literal: &[u8], path: &[u8]) -> bool {
+    literal.len() == path.len()
+        && literal
+            .iter()
+            .zip(path)
+            .all(|(l, p)| l == p || (*l == b'/' && is_separator(*p as char)))
+}
+
+/// Expands the brace alternatives in `glob` into separate globs, e.g., `*.{js,ts}` into `*.js`
+/// and `*.ts`.
+///
+/// Nested braces are expanded recursively. Escaped braces, braces in character classes and
+/// unmatched braces are kept as they are. [glob_match] handles braces without expanding them,
+/// this is for the callers that need the alternatives themselves.
+pub fn expand_braces(glob: &str) -> Vec<String> {
+    let bytes = glob.as_bytes();
+    let mut open_brace_index = 0;
+    let mut comma_indices = Vec::new();
+    let mut brace_depth = 0;
+    let mut in_brackets = false;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'[' if !in_brackets => in_brackets = true,
+            b']' => in_brackets = false,
+            b'{' if !in_brackets => {
+                if brace_depth == 0 {
+                    open_brace_index = i;
+                    comma_indices.clear();
+                }
+                brace_depth += 1;
+            }
+            b',' if !in_brackets && brace_depth == 1 => comma_indices.push(i),
+            b'}' if !in_brackets && brace_depth > 0 => {
+                brace_depth -= 1;
+                if brace_depth == 0 {
+                    let prefix = &glob[..open_brace_index];
+                    let suffix = &glob[i + 1..];
+                    let mut bounds = vec![open_brace_index];
+                    bounds.append(&mut comma_indices);
+                    bounds.push(i);
+                    return bounds
+                        .windows(2)
+                        .flat_map(|w| {
+                            let branch = &glob[w[0] + 1..w[1]];
+                            expand_braces(&format!("{prefix}{branch}{suffix}"))
+                        })
+                        .collect();
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+
+    vec![glob.to_string()]
+}
+
+/// Returns the byte spans in `path` matched by the wildcards in `glob`, or `None` if `path`
+/// doesn't match.
+///
+/// Each span corresponds to a `*`, `**`, `?` or `[...]` in `glob`, in order. A `**` component
+/// doesn't include the separator after it, e.g., for `a/**/b` and `a/x/y/b` its span is `x/y`.
+/// When `glob` contains braces, the spans are for the wildcards in the first matching
+/// alternative. A negated glob doesn't match any part of the path and returns no spans.
+pub fn glob_match_with_spans(glob: &str, path: &str) -> Option<Vec<(usize, usize)>> {
+    if !glob_match(glob, path) {
+        return None;
+    }
+
+    let negations = glob.bytes().take_while(|c| *c == b'!').count();
+    if negations % 2 == 1 {
+        return Some(Vec::new());
+    }
+
+    expand_braces(&glob[negations..])
+        .iter()
+        .find_map(|alternative| {
+            let tokens = tokenize(alternative.as_bytes())?;
+            let mut matcher = SpanMatcher {
+                tokens: &tokens,
+                path: path.as_bytes(),
+                failed: HashSet::new(),
+                spans: Vec::new(),
+            };
+            matcher.match_from(0, 0).then_some(matcher.spans)
+        })
+}
+
+/// A part of a glob without braces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// A byte to match as is, `/` matches any separator.
+    Literal(u8),
+    /// `*`, or `**` that is not a whole component.
+    Star,
+    /// `**/` as a whole component, matches zero or more components.
+    Globstar,
+    /// `**` as the last component, matches the rest of the path.
+    TrailingGlobstar,
+    /// `?`
+    Question,
+    /// `[...]` with inclusive byte ranges.
+    Class {
+        ranges: Vec<(u8, u8)>,
+        negated: bool,
+    },
+}
+
+/// Returns the byte `\c` stands for, like [unescape] does.
+fn unescaped(c: u8) -> u8 {
+    match c {
+        b'b' => b'\x08',
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        c => c,
+    }
+}
+
+/// Splits a glob without braces and leading `!` into tokens. Returns `None` for unterminated
+/// escapes and classes, which can't match any path.
+fn tokenize(glob: &[u8]) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < glob.len() {
+        match glob[i] {
+            b'*' if glob.get(i + 1) == Some(&b'*') => {
+                let is_component_start = i == 0 || glob[i - 1] == b'/';
+                i += 2;
+                if is_component_start && i == glob.len() {
+                    tokens.push(Token::TrailingGlobstar);
+                } else if is_component_start && glob[i] == b'/' {
+                    tokens.push(Token::Globstar);
+                    i += 1;
+                } else {
+                    tokens.push(Token::Star);
+                }
+                continue;
+            }
+            b'*' => tokens.push(Token::Star),
+            b'?' => tokens.push(Token::Question),
+            b'[' => {
+                i += 1;
+                let negated = matches!(glob.get(i), Some(b'^' | b'!'));
+                if negated {
+                    i += 1;
+                }
+
+                let mut ranges = Vec::new();
+                let read_byte = |i: &mut usize| {
+                    let c = if glob[*i] == b'\\' {
+                        *i += 1;
+                        unescaped(*glob.get(*i)?)
+                    } else {
+                        glob[*i]
+                    };
+                    *i += 1;
+                    Some(c)
+                };
+                while i < glob.len() && (ranges.is_empty() || glob[i] != b']') {
+                    let low = read_byte(&mut i)?;
+                    let high = if i + 1 < glob.len() && glob[i] == b'-' && glob[i + 1] != b']' {
+                        i += 1;
+                        read_byte(&mut i)?
+                    } else {
+                        low
+                    };
+                    // Reversed ranges match only the first character, like in git
+                    ranges.push((low, if high < low { low } else { high }));
+                }
+
+                if i >= glob.len() {
+                    return None;
+                }
+                tokens.push(Token::Class { ranges, negated });
+            }
+            b'\\' => {
+                i += 1;
+                tokens.push(Token::Literal(unescaped(*glob.get(i)?)));
+            }
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+    Some(tokens)
+}
+
+/// Matches the tokens to a path by backtracking and records the spans of the wildcards.
+struct SpanMatcher<'a> {
+    tokens: &'a [Token],
+    path: &'a [u8],
+    /// `(token_index, path_index)` pairs known not to match, to avoid exponential backtracking.
+    failed: HashSet<(usize, usize)>,
+    spans: Vec<(usize, usize)>,
+}
+
+impl SpanMatcher<'_> {
+    fn match_from(&mut self, token_index: usize, path_index: usize) -> bool {
+        let tokens = self.tokens;
+        let path = self.path;
+        let Some(token) = tokens.get(token_index) else {
+            return path_index == path.len();
+        };
+        if self.failed.contains(&(token_index, path_index)) {
+            return false;
+        }
+
+        let next_byte = path.get(path_index).copied();
+        let matched = match token {
+            Token::Literal(c) => {
+                next_byte.is_some_and(|p| p == *c || (*c == b'/' && is_separator(p as char)))
+                    && self.match_from(token_index + 1, path_index + 1)
+            }
+            Token::Question => {
+                next_byte.is_some_and(|p| !is_separator(p as char))
+                    && self.match_wildcard(
+                        token_index,
+                        (path_index, path_index + 1),
+                        path_index + 1,
+                    )
+            }
+            Token::Class { ranges, negated } => {
+                next_byte.is_some_and(|p| {
+                    !is_separator(p as char)
+                        && ranges.iter().any(|(low, high)| *low <= p && p <= *high) != *negated
+                }) && self.match_wildcard(token_index, (path_index, path_index + 1), path_index + 1)
+            }
+            Token::Star => {
+                let component_end = path[path_index..]
+                    .iter()
+                    .position(|p| is_separator(*p as char))
+                    .map_or(path.len(), |len| path_index + len);
+                (path_index..=component_end)
+                    .any(|end| self.match_wildcard(token_index, (path_index, end), end))
+            }
+            Token::Globstar => {
+                self.match_wildcard(token_index, (path_index, path_index), path_index)
+                    || (path_index..path.len())
+                        .filter(|end| is_separator(path[*end] as char))
+                        .any(|end| self.match_wildcard(token_index, (path_index, end), end + 1))
+            }
+            Token::TrailingGlobstar => {
+                self.match_wildcard(token_index, (path_index, path.len()), path.len())
+            }
+        };
+
+        if !matched {
+            self.failed.insert((token_index, path_index));
+        }
+        matched
+    }
+
+    /// Matches the rest of the tokens from `next_path_index` after the wildcard at `token_index`
+    /// matches `span`.
+    fn match_wildcard(
+        &mut self,
+        token_index: usize,
+        span: (usize, usize),
+        next_path_index: usize,
+    ) -> bool {
+        self.spans.push(span);
+        if self.match_from(token_index + 1, next_path_index) {
+            return true;
+        }
+        self.spans.pop();
+        false
+    }
+}
+
 fn glob_match_impl(glob: &[u8], path: &[u8]) -> bool {
     let mut state = State::default();
 
@@ -44,8 +579,10 @@ fn glob_match_impl(glob: &[u8], path: &[u8]) -> bool {
         state.glob_index += 1;
     }
 
-    let mut brace_stack = ArrayVec::<_, 10>::new();
-    let matched = state.glob_match_from(glob, path, 0, &mut brace_stack);
+    let mut brace_stack = BraceStack::new();
+    // `**` right after the negations starts a component
+    let match_start = state.glob_index;
+    let matched = state.glob_match_from(glob, path, match_start, &mut brace_stack);
 
     negated ^ matched
 }
@@ -145,11 +682,17 @@ impl State {
         branch_index: usize,
         brace_stack: &mut BraceStack,
     ) -> bool {
-        brace_stack.push((open_brace_index as u32, branch_index as u32));
+        // The groups the match is in, including the ones before it that are closed
+        if brace_stack
+            .try_push((open_brace_index as u32, branch_index as u32))
+            .is_err()
+        {
+            return false;
+        }
 
         let mut branch_state = self.clone();
         branch_state.glob_index = branch_index;
-        branch_state.brace_depth = brace_stack.len();
+        branch_state.brace_depth = self.brace_depth + 1;
 
         let matched = branch_state.glob_match_from(glob, path, branch_index, brace_stack);
 
@@ -226,7 +769,11 @@ impl State {
                     b'*' => {
                         let is_globstar =
                             self.glob_index + 1 < glob.len() && glob[self.glob_index + 1] == b'*';
-                        if is_globstar {
+                        // Only the whole `**` components like `**/**/` match the same paths as one
+                        let starts_component = self.glob_index == 0
+                            || self.glob_index == match_start
+                            || glob[self.glob_index - 1] == b'/';
+                        if is_globstar && starts_component {
                             self.skip_globstars(glob);
                         }
 
@@ -312,7 +859,8 @@ impl State {
                                 low
                             };
 
-                            if low <= c && c <= high {
+                            // Reversed ranges match only the first character, like in git
+                            if (low <= c && c <= high) || c == low {
                                 is_match = true;
                             }
 
@@ -324,7 +872,7 @@ impl State {
                         }
 
                         self.glob_index += 1;
-                        if is_match != negated {
+                        if is_match != negated && !is_separator(c as char) {
                             self.path_index += 1;
                             continue;
                         }
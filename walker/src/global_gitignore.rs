@@ -0,0 +1,141 @@
+//! Reads git's global excludes file, set by `core.excludesFile` in the global git config.
+//!
+//! The config is read from `$GIT_CONFIG`, `~/.gitconfig` and `$XDG_CONFIG_HOME/git/config` in
+//! this order, and the first one that sets `core.excludesFile` is used. If none of them sets it,
+//! `$XDG_CONFIG_HOME/git/ignore` or `~/.gitignore_global` is used if it exists.
+//! `$XDG_CONFIG_HOME` defaults to `~/.config`.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{content_to_root_patterns, home_dir, xdg_config_home, IgnoreRules, Result};
+
+/// Returns the path of the global excludes file, if it's set or one of the default files exists.
+pub fn global_gitignore_path() -> Option<PathBuf> {
+    let home = home_dir();
+    let xdg_config_home = xdg_config_home();
+
+    let config_files = [
+        env::var_os("GIT_CONFIG").map(PathBuf::from),
+        home.as_ref().map(|home| home.join(".gitconfig")),
+        xdg_config_home
+            .as_ref()
+            .map(|config| config.join("git/config")),
+    ];
+    let configured = config_files.iter().flatten().find_map(|config_file| {
+        let content = fs::read_to_string(config_file).ok()?;
+        let excludes_file = excludes_file_in_config(&content)?;
+        Some(expand_home(&excludes_file, home.as_deref()))
+    });
+    if configured.is_some() {
+        return configured;
+    }
+
+    let default_files = [
+        xdg_config_home.map(|config| config.join("git/ignore")),
+        home.map(|home| home.join(".gitignore_global")),
+    ];
+    default_files.into_iter().flatten().find(|p| p.is_file())
+}
+
+/// Adds the patterns in the global excludes file to `ignore_rules` if its ignore file name is
+/// `.gitignore`. Returns the path of the file if it's read.
+///
+/// The patterns are relative to the root like the patterns in the root `.gitignore`. They should
+/// be added before all other patterns to have the lowest precedence.
+pub fn update_global_gitignore_rules(ignore_rules: &IgnoreRules) -> Result<Option<PathBuf>> {
+    if ignore_rules.ignore_filename.as_deref() != Some(".gitignore") {
+        return Ok(None);
+    }
+    let Some(global_path) = global_gitignore_path().filter(|p| p.is_file()) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&global_path)?;
+    let new_patterns = content_to_root_patterns(&ignore_rules.root, &global_path, &content);
+    ignore_rules.add_patterns(new_patterns)?;
+    Ok(Some(global_path))
+}
+
+/// Returns the last value of `core.excludesFile` in the git config `content`.
+fn excludes_file_in_config(content: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut excludes_file = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[') {
+            let name = section.split(']').next().unwrap_or_default().trim();
+            in_core = name.eq_ignore_ascii_case("core");
+            continue;
+        }
+        if !in_core {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("excludesfile") {
+                excludes_file = Some(config_value(value));
+            }
+        }
+    }
+    excludes_file
+}
+
+/// Removes the comments and the quotes around a config value.
+fn config_value(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => value
+            .split([';', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+/// Replaces the leading `~/` in `path` with `home`.
+fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_excludes_file_in_config() {
+        let content = r#"
+[user]
+    excludesFile = not/this
+[core]
+    editor = vim
+    excludesfile = ~/first ; comment
+[Core]
+    ExcludesFile = "~/with space/ignore"
+"#;
+        assert_eq!(
+            excludes_file_in_config(content).as_deref(),
+            Some("~/with space/ignore")
+        );
+        assert_eq!(excludes_file_in_config("[core]\n    editor = vim\n"), None);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(
+            expand_home("~/.ignore", Some(Path::new("/home/u"))),
+            PathBuf::from("/home/u/.ignore")
+        );
+        assert_eq!(
+            expand_home("/etc/ignore", None),
+            PathBuf::from("/etc/ignore")
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Reads Mercurial's `.hgignore` files as ignore patterns.
+//!
+//! `.hgignore` files may contain glob and regular expression patterns. Only the glob patterns are
+//! read, the regular expressions are skipped. The syntax is selected by `syntax: glob` and
+//! `syntax: regexp` lines for the following patterns, or with `glob:`, `rootglob:` and `re:`
+//! prefixes for a single pattern. As in Mercurial, the default syntax is regexp.
+//!
+//! Glob patterns are not rooted, i.e., `a/b` matches `x/a/b` as well, unless they have the
+//! `rootglob:` prefix. There is no negation in `.hgignore`, so the patterns are never
+//! [PatternEffect::Whitelist](crate::PatternEffect::Whitelist).
+//! [WalkOptions::hgignore](crate::WalkOptions::hgignore) uses these rules.
+use std::path::Path;
+
+use crate::{Pattern, Source};
+
+/// The file name to read the Mercurial ignore patterns from
+pub const HGIGNORE_FILENAME: &str = ".hgignore";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HgSyntax {
+    Glob,
+    RootGlob,
+    Skipped,
+}
+
+impl HgSyntax {
+    /// Returns the syntax for a `syntax:` line or a pattern prefix, or `None` if `name` is not
+    /// a syntax name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "glob" | "relglob" => Some(HgSyntax::Glob),
+            "rootglob" => Some(HgSyntax::RootGlob),
+            "re" | "regexp" | "relre" | "path" | "relpath" | "rootfilesin" | "include"
+            | "subinclude" | "listfile" | "listfile0" | "set" => Some(HgSyntax::Skipped),
+            _ => None,
+        }
+    }
+}
+
+/// Converts the glob patterns in `content` to ignore patterns with Mercurial's rules.
+///
+/// `source` is the path of the `.hgignore` file and must be within `root`. Empty lines, comments
+/// and regular expression patterns are skipped.
+pub fn hgignore_content_to_patterns(root: &Path, source: &Path, content: &str) -> Vec<Pattern> {
+    let source_path = source.strip_prefix(root).expect("path must be within root");
+
+    let mut syntax = HgSyntax::Skipped;
+    let mut patterns = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("syntax:") {
+            syntax = HgSyntax::from_name(name.trim()).unwrap_or(HgSyntax::Skipped);
+            continue;
+        }
+
+        let (line_syntax, glob) = line
+            .split_once(':')
+            .and_then(|(prefix, glob)| Some((HgSyntax::from_name(prefix)?, glob)))
+            .unwrap_or((syntax, line));
+
+        // glob_match treats a leading `!` as negation
+        let glob = match glob.strip_prefix('!') {
+            Some(rest) => format!("\\!{rest}"),
+            None => glob.to_string(),
+        };
+        let glob = match line_syntax {
+            HgSyntax::Skipped => continue,
+            HgSyntax::RootGlob => format!("/{glob}"),
+            HgSyntax::Glob if glob.contains('/') || glob.starts_with('\\') => format!("**/{glob}"),
+            HgSyntax::Glob => glob,
+        };
+
+        let mut pattern = Pattern::new(
+            Source::File {
+                path: source_path.to_path_buf(),
+                line: i + 1,
+                resolved_from: None,
+            },
+            &glob,
+        );
+        pattern.original = line.to_string();
+        patterns.push(pattern);
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PatternEffect;
+
+    fn globs(content: &str) -> Vec<String> {
+        hgignore_content_to_patterns(Path::new("/repo"), Path::new("/repo/.hgignore"), content)
+            .iter()
+            .map(|p| p.compiled_glob.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_only_glob_sections_are_read() {
+        let content = "\
+# regexp is the default
+^build$
+syntax: glob
+*.pyc
+docs/_build
+syntax: regexp
+\\.orig$
+syntax:glob
+tmp/
+";
+        assert_eq!(
+            globs(content),
+            vec!["**/*.pyc", "**/docs/_build", "**/tmp/"]
+        );
+    }
+
+    #[test]
+    fn test_prefixed_patterns() {
+        let content =
+            "glob:*.log\nrootglob:dist/*\nre:^out/\nsyntax: glob\nrelre:.*\\.bak\nnote:txt\n";
+        assert_eq!(globs(content), vec!["**/*.log", "dist/*", "**/note:txt"]);
+    }
+
+    #[test]
+    fn test_exclamation_is_literal() {
+        let patterns = hgignore_content_to_patterns(
+            Path::new("/repo"),
+            Path::new("/repo/.hgignore"),
+            "syntax: glob\n!keep\n",
+        );
+        assert_eq!(patterns[0].effect, PatternEffect::Ignore);
+        assert!(patterns[0].compiled_glob.matches("a/!keep"));
+        assert!(!patterns[0].compiled_glob.matches("a/keep"));
+    }
+}
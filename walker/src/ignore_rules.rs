@@ -1,11 +1,29 @@
-use crate::{pattern::PatternEffect, Result, Source};
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use crate::{
+    pattern::PatternEffect, read_ignore_patterns, xdg_config_home, Error, IgnoreFileErrorPolicy,
+    IgnoreSyntax, Result, Source,
+};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::iter;
+use std::path::{is_separator, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::glob::glob_match;
-use crate::pattern::{MatchResult, Pattern, PathKind};
+#[cfg(feature = "unicode-normalization")]
+use crate::glob::nfc;
+use crate::glob::{expand_braces, glob_match, path_depth, GlobPattern};
+use crate::lint::{lint_patterns, LintFinding};
+use crate::pattern::{trim_trailing_spaces, MatchResult, PathKind, Pattern, PatternError};
+use crate::snapshot::{precedence_key, sort_by_precedence, PatternSummary};
+use xvc_logging::warn;
+
+/// The path of the user's ignore file relative to the XDG config directory.
+pub const XDG_IGNORE_PATH: &str = "xvc/ignore";
 
 /// A set of rules to determine whether a path should be ignored.
+///
+/// Clones share the patterns: the patterns added to a clone are added to all of them. Use
+/// [IgnoreRules::deep_clone] for a copy with its own patterns.
 #[derive(Debug, Clone)]
 pub struct IgnoreRules {
     /// The root directory for which these ignore rules apply.
@@ -13,27 +31,355 @@ pub struct IgnoreRules {
     /// The name of the ignore file (e.g., `.gitignore`).
     pub ignore_filename: Option<String>,
     /// A list of patterns that define the ignore rules.
-    pub patterns: Arc<RwLock<Vec<Pattern>>>,
+    ///
+    /// The lock is not exposed, as a caller holding it while adding patterns would deadlock. See
+    /// [IgnoreRules::patterns_snapshot] and [IgnoreRules::iter_patterns].
+    patterns: Arc<RwLock<Vec<Pattern>>>,
+    /// A copy of `patterns` returned by [IgnoreRules::patterns_snapshot], reset when the patterns
+    /// are locked for writing.
+    snapshot: Arc<Mutex<Option<Arc<[Pattern]>>>>,
+    /// Whether to match the patterns case insensitively, e.g., for Windows and macOS file
+    /// systems.
+    pub case_insensitive: bool,
+    /// Whether to convert the patterns and the paths to NFC before matching them, so the names
+    /// with composed and decomposed characters match the same patterns, e.g., for macOS file
+    /// systems. See [glob_match_nfc](crate::glob_match_nfc).
+    #[cfg(feature = "unicode-normalization")]
+    pub unicode_normalize: bool,
+    /// Extension patterns in `patterns`, updated lazily when new patterns are added.
+    extension_table: Arc<RwLock<ExtensionTable>>,
+    /// Whether the directories checked are ignored, reset when new patterns are added.
+    ancestor_cache: Arc<RwLock<AncestorCache>>,
 }
 
 /// A thread-safe, reference-counted pointer to `IgnoreRules`.
 pub type SharedIgnoreRules = Arc<RwLock<IgnoreRules>>;
 
-fn pattern_has_wildcard(p: &str) -> bool {
-    p.contains('*') || p.contains('?') || p.contains('[')
+/// Locks the shared rules for reading, recovering them if a thread panicked while holding the
+/// lock, like the patterns in [IgnoreRules].
+pub(crate) fn read_shared_rules(
+    ignore_rules: &SharedIgnoreRules,
+) -> RwLockReadGuard<'_, IgnoreRules> {
+    ignore_rules.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The patterns of an [IgnoreRules], locked for reading. See [IgnoreRules::patterns_locked].
+///
+/// It dereferences to the slice of patterns, and `&PatternsGuard` iterates them without cloning.
+/// The patterns can't be added to the rules until it's dropped.
+#[derive(Debug)]
+pub struct PatternsGuard<'a> {
+    guard: RwLockReadGuard<'a, Vec<Pattern>>,
+}
+
+impl PatternsGuard<'_> {
+    /// Returns an iterator over the patterns in the order they're checked.
+    pub fn iter(&self) -> std::slice::Iter<'_, Pattern> {
+        self.guard.iter()
+    }
+}
+
+impl std::ops::Deref for PatternsGuard<'_> {
+    type Target = [Pattern];
+
+    fn deref(&self) -> &[Pattern] {
+        &self.guard
+    }
+}
+
+impl<'g> IntoIterator for &'g PatternsGuard<'_> {
+    type Item = &'g Pattern;
+    type IntoIter = std::slice::Iter<'g, Pattern>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The patterns of an [IgnoreRules] that can match the paths under a directory.
+///
+/// Walkers collect these once per directory with [IgnoreRules::directory_patterns] or
+/// [IgnoreRules::child_directory_patterns] and check the children with
+/// [IgnoreRules::check_in_directory].
+#[derive(Debug, Clone)]
+pub struct DirectoryPatterns {
+    /// Indices of the relevant patterns in increasing order.
+    indices: Vec<usize>,
+    /// The number of patterns in the rules when this is collected.
+    seen: usize,
+    /// The number of override patterns in the rules when this is collected.
+    ///
+    /// Override patterns are inserted before the others, so the indices are shifted by the
+    /// override patterns added later.
+    overrides: usize,
+}
+
+impl DirectoryPatterns {
+    /// Returns the indices of the patterns to check in `patterns`, which has `overrides` override
+    /// patterns: the relevant patterns and the patterns added after this is collected.
+    fn current_indices(&self, patterns: &[Pattern], overrides: usize) -> Vec<usize> {
+        let shift = overrides - self.overrides;
+        self.indices
+            .iter()
+            .map(|i| i + shift)
+            .chain(self.seen + shift..patterns.len())
+            .collect()
+    }
+
+    /// Returns `true` if no pattern could match the paths under the directory when this is
+    /// collected.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty() && self.overrides == 0
+    }
+}
+
+/// A path that would be ignored, found by [IgnoreRules::verify_not_ignored].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredTarget {
+    /// The path as given.
+    pub path: PathBuf,
+    /// The ignored path relative to the root: the path itself or the directory above it the
+    /// walkers don't descend into.
+    pub ignored_path: PathBuf,
+    /// The pattern that ignores `ignored_path`.
+    pub pattern: PatternSummary,
+}
+
+/// Indices of the patterns like `*.ext` grouped by their extensions.
+///
+/// These patterns match all paths with the extension, so they are looked up instead of
+/// evaluating their globs one by one.
+#[derive(Debug, Default)]
+struct ExtensionTable {
+    /// The number of patterns indexed.
+    seen: usize,
+    /// Indices of the extension patterns in increasing order, by lowercase extension.
+    by_extension: HashMap<String, Vec<usize>>,
+    /// Whether each indexed pattern is in `by_extension`.
+    indexed: Vec<bool>,
+}
+
+impl ExtensionTable {
+    /// Indexes the patterns added since the last update.
+    ///
+    /// Patterns are assumed to be only appended. The table is rebuilt if there are fewer patterns
+    /// than indexed, and must be reset when patterns are inserted before the others.
+    fn update(&mut self, patterns: &[Pattern]) {
+        if patterns.len() < self.seen {
+            *self = ExtensionTable::default();
+        }
+
+        for (i, pattern) in patterns.iter().enumerate().skip(self.seen) {
+            // Override patterns are checked before the table
+            let extension = match pattern.effect {
+                PatternEffect::Override { .. } => None,
+                _ => pattern.extension.as_ref(),
+            };
+            if let Some(extension) = extension {
+                self.by_extension
+                    .entry(extension_table_key(extension))
+                    .or_default()
+                    .push(i);
+            }
+            self.indexed.push(extension.is_some());
+        }
+        self.seen = patterns.len();
+    }
+
+    /// Returns the indices of the extension patterns for `extension`, ignoring its case and its
+    /// Unicode normalization.
+    fn lookup(&self, extension: &str) -> &[usize] {
+        self.by_extension
+            .get(&extension_table_key(extension))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the pattern with index `i` is in the table.
+    fn contains(&self, i: usize) -> bool {
+        self.indexed.get(i).copied().unwrap_or(false)
+    }
+}
+
+/// Returns the key of `extension` in [ExtensionTable], lowercase and, with the
+/// `unicode-normalization` feature, in NFC.
+fn extension_table_key(extension: &str) -> String {
+    let lowercase = extension.to_lowercase();
+    #[cfg(feature = "unicode-normalization")]
+    if let Cow::Owned(normalized) = nfc(&lowercase) {
+        return normalized;
+    }
+    lowercase
+}
+
+/// Whether the directories above the checked paths are ignored, by their paths relative to the
+/// root.
+///
+/// A directory is ignored also if a directory above it is ignored, as the walkers don't descend
+/// into the ignored directories and the patterns can't include the paths under them again.
+///
+/// At most [ANCESTOR_CACHE_CAPACITY] directories are kept. The cache is cleared when it's full, so
+/// the rules of a long running watcher don't keep all directories it has seen.
+#[derive(Debug, Default)]
+struct AncestorCache {
+    /// The number of patterns when the states are cached.
+    seen: usize,
+    /// Whether the states are cached with case insensitive matching.
+    case_insensitive: bool,
+    /// Whether the states are cached with Unicode normalized matching.
+    #[cfg(feature = "unicode-normalization")]
+    unicode_normalize: bool,
+    /// Whether each directory is ignored.
+    ignored: HashMap<String, bool>,
+}
+
+/// The maximum number of directories in [AncestorCache].
+const ANCESTOR_CACHE_CAPACITY: usize = 4096;
+
+impl AncestorCache {
+    /// Returns `true` if the states are cached for `patterns`.
+    ///
+    /// The cache must be reset when patterns are added or replaced without changing their number.
+    fn is_valid(&self, patterns: &[Pattern], rules: &IgnoreRules) -> bool {
+        #[cfg(feature = "unicode-normalization")]
+        if self.unicode_normalize != rules.unicode_normalize {
+            return false;
+        }
+        self.seen == patterns.len() && self.case_insensitive == rules.case_insensitive
+    }
+}
+
+/// Returns the directories above `relative_path`, starting from its parent.
+fn ancestor_dirs(relative_path: &str) -> impl Iterator<Item = &str> {
+    let mut dir = relative_path.trim_end_matches(is_separator);
+    iter::from_fn(move || {
+        let i = dir.rfind(is_separator)?;
+        dir = &dir[..i];
+        (!dir.is_empty()).then_some(dir)
+    })
+}
+
+/// Returns the directories above `relative_path` that are longer than `len`, starting from the
+/// topmost one.
+fn ancestor_dirs_below(relative_path: &str, len: usize) -> impl Iterator<Item = &str> {
+    let path = relative_path.trim_end_matches(is_separator);
+    path.char_indices()
+        .filter(move |(i, c)| *i > len && is_separator(*c))
+        .map(move |(i, _)| &path[..i])
+}
+
+/// Returns the extension of the last component in `path_str`.
+fn path_extension(path_str: &str) -> Option<&str> {
+    path_str
+        .trim_end_matches('/')
+        .rsplit(is_separator)
+        .next()
+        .and_then(|file_name| file_name.rsplit_once('.'))
+        .map(|(_, extension)| extension)
+}
+
+/// Returns the number of override patterns at the beginning of `patterns`.
+fn override_count(patterns: &[Pattern]) -> usize {
+    patterns
+        .iter()
+        .take_while(|p| matches!(p.effect, PatternEffect::Override { .. }))
+        .count()
+}
+
+/// Returns the override effect with the same result as `effect`.
+fn override_effect(effect: PatternEffect) -> PatternEffect {
+    match effect {
+        PatternEffect::Ignore => PatternEffect::Override { whitelist: false },
+        PatternEffect::Whitelist => PatternEffect::Override { whitelist: true },
+        effect @ PatternEffect::Override { .. } => effect,
+    }
+}
+
+/// The precedence of a pattern among the patterns that match a path, like git's.
+///
+/// The patterns from the ignore files in deeper directories override the patterns from the files
+/// above them, and the later patterns in a file override the earlier ones. The patterns that are
+/// not from an ignore file in the tree, e.g., the global, builtin and ancestor patterns, and the
+/// files with other names like `info/exclude`, are overridden by all ignore files in the tree.
+/// Among them, the later patterns override the earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Precedence {
+    /// The number of components in the path of the ignore file, or `None` for the patterns that
+    /// are not from a file in the tree
+    depth: Option<usize>,
+    /// The index of the pattern in the rules
+    index: usize,
+}
+
+impl Precedence {
+    /// Returns the precedence of `pattern` at `index` in the rules that read the ignore files
+    /// named `ignore_filename` in the tree.
+    fn of(pattern: &Pattern, index: usize, ignore_filename: Option<&str>) -> Self {
+        let depth = match &pattern.source {
+            Source::File { path, .. }
+                if path.is_relative()
+                    && ignore_filename.is_some()
+                    && path.file_name() == ignore_filename.map(OsStr::new) =>
+            {
+                Some(path.components().count())
+            }
+            Source::GitAttributes { path, .. } if path.is_relative() => {
+                Some(path.components().count())
+            }
+            _ => None,
+        };
+        Self { depth, index }
+    }
+}
+
+impl Default for IgnoreRules {
+    /// Creates an empty set of ignore rules for the current directory, without an ignore file.
+    fn default() -> Self {
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        IgnoreRules::empty(&current_dir, None)
+    }
 }
 
 impl IgnoreRules {
+    /// Sets the root directory the rules apply to.
+    ///
+    /// The patterns are relative to the root, so they are kept as they are.
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = root.to_path_buf();
+        self
+    }
+
+    /// Sets the name of the ignore files, e.g., `.xvcignore`.
+    pub fn with_ignore_filename(mut self, name: &str) -> Self {
+        self.ignore_filename = Some(name.to_string());
+        self
+    }
+
+    /// Sets [IgnoreRules::unicode_normalize].
+    #[cfg(feature = "unicode-normalization")]
+    pub fn with_unicode_normalize(mut self, unicode_normalize: bool) -> Self {
+        self.unicode_normalize = unicode_normalize;
+        self
+    }
+
     /// Creates an empty set of ignore rules for a given directory.
     pub fn empty(dir: &Path, ignore_filename: Option<&str>) -> Self {
         IgnoreRules {
             root: PathBuf::from(dir),
             ignore_filename: ignore_filename.map(|s| s.to_string()),
             patterns: Arc::new(RwLock::new(Vec::<Pattern>::new())),
+            snapshot: Arc::new(Mutex::new(None)),
+            case_insensitive: false,
+            #[cfg(feature = "unicode-normalization")]
+            unicode_normalize: false,
+            extension_table: Arc::new(RwLock::new(ExtensionTable::default())),
+            ancestor_cache: Arc::new(RwLock::new(AncestorCache::default())),
         }
     }
 
     /// Creates ignore rules from a string of global patterns.
+    ///
+    /// The empty lines and the lines with only spaces are skipped.
     pub fn from_global_patterns(
         ignore_root: &Path,
         ignore_filename: Option<&str>,
@@ -41,12 +387,58 @@ impl IgnoreRules {
     ) -> Self {
         let mut given_patterns = Vec::<Pattern>::new();
         for line in given.lines() {
+            if trim_trailing_spaces(line).is_empty() {
+                continue;
+            }
             let pattern = Pattern::new(Source::Global, line);
             given_patterns.push(pattern);
         }
         IgnoreRules::from_patterns(ignore_root, ignore_filename, given_patterns)
     }
 
+    /// Creates ignore rules rooted at `root` from the user's ignore file in
+    /// `$XDG_CONFIG_HOME/xvc/ignore`, or `~/.config/xvc/ignore` if `$XDG_CONFIG_HOME` is not set.
+    ///
+    /// The patterns are relative to `root` like the patterns in the root ignore file. The rules
+    /// are empty if the file doesn't exist.
+    pub fn from_xdg_config(root: &Path) -> Result<Self> {
+        let ignore_rules = IgnoreRules::empty(root, None);
+        let Some(xdg_path) = xdg_config_home().map(|config| config.join(XDG_IGNORE_PATH)) else {
+            return Ok(ignore_rules);
+        };
+        if xdg_path.is_file() {
+            let content = std::fs::read_to_string(&xdg_path)?;
+            ignore_rules.add_patterns(content_to_root_patterns(root, &xdg_path, &content))?;
+        }
+        Ok(ignore_rules)
+    }
+
+    /// Adds the patterns in the environment variable `var_name` to the rules.
+    ///
+    /// The patterns are separated by `:` or newlines, like `XVC_IGNORE=*.log:*.tmp`, and are
+    /// relative to the root. Nothing is added if the variable is not set.
+    pub fn add_from_env(&self, var_name: &str) -> Result<()> {
+        let value = match std::env::var(var_name) {
+            Ok(value) => value,
+            Err(std::env::VarError::NotPresent) => return Ok(()),
+            Err(e) => return Err(anyhow::Error::from(e).into()),
+        };
+        let (patterns, errors) = content_to_patterns(&self.root, None, &value.replace(':', "\n"));
+        for error in errors {
+            warn!("Skipping invalid pattern in ${}: {}", var_name, error);
+        }
+        let patterns = patterns
+            .into_iter()
+            .map(|mut pattern| {
+                pattern.source = Source::Environment {
+                    var_name: var_name.to_string(),
+                };
+                pattern
+            })
+            .collect();
+        self.add_patterns(patterns)
+    }
+
     /// Creates ignore rules from a vector of `Pattern`s.
     pub fn from_patterns(
         ignore_root: &Path,
@@ -57,146 +449,1023 @@ impl IgnoreRules {
             root: PathBuf::from(ignore_root),
             ignore_filename: ignore_filename.map(|s| s.to_string()),
             patterns: Arc::new(RwLock::new(patterns)),
+            snapshot: Arc::new(Mutex::new(None)),
+            case_insensitive: false,
+            #[cfg(feature = "unicode-normalization")]
+            unicode_normalize: false,
+            extension_table: Arc::new(RwLock::new(ExtensionTable::default())),
+            ancestor_cache: Arc::new(RwLock::new(AncestorCache::default())),
+        }
+    }
+
+    /// Returns a copy of the rules that doesn't share the patterns with these.
+    ///
+    /// The patterns added to the copy are not added to these, and vice versa.
+    pub fn deep_clone(&self) -> Self {
+        IgnoreRules {
+            root: self.root.clone(),
+            ignore_filename: self.ignore_filename.clone(),
+            patterns: Arc::new(RwLock::new(self.read_patterns().clone())),
+            snapshot: Arc::new(Mutex::new(None)),
+            case_insensitive: self.case_insensitive,
+            #[cfg(feature = "unicode-normalization")]
+            unicode_normalize: self.unicode_normalize,
+            extension_table: Arc::new(RwLock::new(ExtensionTable::default())),
+            ancestor_cache: Arc::new(RwLock::new(AncestorCache::default())),
+        }
+    }
+
+    /// Returns the rules for the paths under `subdir`, rooted at `subdir`.
+    ///
+    /// `subdir` is an absolute path under the root or a path relative to the root. The patterns
+    /// are translated to be relative to `subdir`, like the patterns in
+    /// [build_ignore_patterns_with_ancestors](crate::build_ignore_patterns_with_ancestors), and
+    /// the patterns that can't match under it, e.g., from the ignore files in its siblings, are
+    /// dropped. The patterns from the ignore files in the directories above `subdir` are kept
+    /// with [Source::Ancestor], and the paths of the ignore files under it are made relative to
+    /// it.
+    ///
+    /// Returns [Error::PathEscapesBase] if `subdir` is not within the root.
+    pub fn scoped_to(&self, subdir: &Path) -> Result<IgnoreRules> {
+        let subdir = self.root.join(subdir);
+        let relative_dir = subdir
+            .strip_prefix(&self.root)
+            .map_err(|_| Error::PathEscapesBase {
+                base: self.root.clone(),
+                path: subdir.clone(),
+            })?;
+        let root_components = relative_components(relative_dir);
+        let root_components: Vec<&str> = root_components.iter().map(String::as_str).collect();
+
+        let patterns = self
+            .read_patterns()
+            .iter()
+            .flat_map(|pattern| {
+                let source = self.scoped_source(&pattern.source, relative_dir);
+                translate_pattern(pattern, &root_components, source)
+            })
+            .collect();
+
+        let mut scoped =
+            IgnoreRules::from_patterns(&subdir, self.ignore_filename.as_deref(), patterns);
+        scoped.case_insensitive = self.case_insensitive;
+        #[cfg(feature = "unicode-normalization")]
+        {
+            scoped.unicode_normalize = self.unicode_normalize;
+        }
+        Ok(scoped)
+    }
+
+    /// Returns the source of a pattern in the rules scoped to `relative_dir` with
+    /// [IgnoreRules::scoped_to].
+    ///
+    /// The paths of the files under `relative_dir` are made relative to it, and the files above
+    /// it become [Source::Ancestor].
+    fn scoped_source(&self, source: &Source, relative_dir: &Path) -> Source {
+        match source {
+            Source::File {
+                path,
+                line,
+                resolved_from,
+            } => match path.strip_prefix(relative_dir) {
+                Ok(path) => Source::File {
+                    path: path.to_path_buf(),
+                    line: *line,
+                    resolved_from: resolved_from.clone(),
+                },
+                Err(_) => Source::Ancestor {
+                    path: self.root.join(path),
+                    line: *line,
+                },
+            },
+            Source::GitAttributes { path, line } => match path.strip_prefix(relative_dir) {
+                Ok(path) => Source::GitAttributes {
+                    path: path.to_path_buf(),
+                    line: *line,
+                },
+                Err(_) => Source::Ancestor {
+                    path: self.root.join(path),
+                    line: *line,
+                },
+            },
+            source => source.clone(),
         }
     }
 
+    /// Returns `true` if a thread panicked while holding the lock on the patterns.
+    ///
+    /// The rules keep working with the patterns in the poisoned lock, so this is only for
+    /// diagnostics.
+    pub fn is_lock_poisoned(&self) -> bool {
+        self.patterns.is_poisoned()
+    }
+
+    /// Locks the patterns for reading, recovering them if the lock is poisoned.
+    pub(crate) fn read_patterns(&self) -> RwLockReadGuard<'_, Vec<Pattern>> {
+        self.patterns.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the extension table after indexing the new `patterns`.
+    fn read_extension_table(&self, patterns: &[Pattern]) -> RwLockReadGuard<'_, ExtensionTable> {
+        {
+            let extension_table = self
+                .extension_table
+                .read()
+                .unwrap_or_else(|e| e.into_inner());
+            if extension_table.seen == patterns.len() {
+                return extension_table;
+            }
+        }
+
+        self.extension_table
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .update(patterns);
+        self.extension_table
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the patterns for writing, recovering them if the lock is poisoned.
+    ///
+    /// The snapshot of the patterns is reset while the lock is held, so a snapshot of the old
+    /// patterns can't be stored after it.
+    fn write_patterns(&self) -> RwLockWriteGuard<'_, Vec<Pattern>> {
+        let patterns = self.patterns.write().unwrap_or_else(|e| e.into_inner());
+        *self.snapshot.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        patterns
+    }
+
     /// Checks if a given path matches any of the ignore rules.
+    ///
+    /// The path is ignored also if a directory above it is ignored, as the walkers don't descend
+    /// into the ignored directories. Whether the directories are ignored is cached until new
+    /// patterns are added, so the paths under an ignored directory are not matched with the
+    /// patterns one by one.
+    ///
+    /// The file system is only accessed to find whether `path` is a directory. Use
+    /// [IgnoreRules::check_hypothetical] for the paths that don't exist yet.
     pub fn check(&self, path: &Path) -> MatchResult {
-        let relative_path = path.strip_prefix(&self.root).expect("path must be within root");
-        let mut path_str = relative_path.to_string_lossy().to_string();
-        if path_str.is_empty() && path.is_dir() {
-            path_str = "/".to_string();
-        } else if path.is_dir() && !path_str.ends_with('/') {
-            path_str.push('/');
-        }
+        self.check_with_ancestors(&self.relative_path(path).to_string_lossy(), || {
+            path.is_dir()
+        })
+    }
+
+    /// Checks `path` like [IgnoreRules::check] and returns a copy of the pattern that decides the
+    /// result, or `None` for [MatchResult::NoMatch].
+    ///
+    /// If a directory above `path` is ignored, the pattern that ignores the top most of them is
+    /// returned.
+    pub fn check_return_pattern(&self, path: &Path) -> (MatchResult, Option<Pattern>) {
+        self.decide_with_ancestors(
+            &self.relative_path(path).to_string_lossy(),
+            || path.is_dir(),
+            true,
+        )
+    }
+
+    /// Checks the directory in `path` like [IgnoreRules::check] without accessing the file
+    /// system, e.g., for the directories listed by a [WalkFs](crate::WalkFs).
+    pub(crate) fn check_dir_path(&self, path: &Path) -> MatchResult {
+        self.check_with_ancestors(&self.relative_path(path).to_string_lossy(), || true)
+    }
 
-        let patterns = self.patterns.read().unwrap();
+    /// Checks the directory in `path` like [IgnoreRules::check_return_pattern] without accessing
+    /// the file system.
+    pub(crate) fn check_dir_path_return_pattern(
+        &self,
+        path: &Path,
+    ) -> (MatchResult, Option<Pattern>) {
+        self.decide_with_ancestors(&self.relative_path(path).to_string_lossy(), || true, true)
+    }
+
+    /// Checks a path relative to the root, given as a string, like [IgnoreRules::check].
+    ///
+    /// The file system is not accessed, so `relative_path` is considered a directory only if it
+    /// ends with `/`. Use [IgnoreRules::check_path_str_with_kind] to give its kind.
+    pub fn check_path_str(&self, relative_path: &str) -> MatchResult {
+        let kind = if relative_path.ends_with('/') {
+            PathKind::Directory
+        } else {
+            PathKind::Any
+        };
+        self.check_path_str_with_kind(relative_path, kind)
+    }
 
-        let mut ignore_match: Option<&Pattern> = None;
-        let mut whitelist_match: Option<&Pattern> = None;
+    /// Checks a path relative to the root, given as a string, as a path of `kind`.
+    ///
+    /// Like in [IgnoreRules::check], the path is ignored also if a directory above it is
+    /// ignored. The components of `relative_path` must be separated with `/`.
+    pub fn check_path_str_with_kind(&self, relative_path: &str, kind: PathKind) -> MatchResult {
+        self.check_with_ancestors(relative_path.trim_end_matches('/'), || {
+            kind == PathKind::Directory
+        })
+    }
 
-        for pattern in patterns.iter().rev() {
-            if ignore_match.is_some() && whitelist_match.is_some() {
+    /// Checks a path that may not exist, without accessing the file system.
+    ///
+    /// `rel_path` is relative to the root, and `kind` tells whether it's a directory. Unlike
+    /// [IgnoreRules::check], this returns [MatchResult::Ignore] also if a parent directory of
+    /// `rel_path` is ignored, as the walkers don't descend into the ignored directories.
+    pub fn check_hypothetical(&self, rel_path: &str, kind: PathKind) -> MatchResult {
+        self.check_with_ancestors(rel_path.trim_end_matches(is_separator), || {
+            kind == PathKind::Directory
+        })
+    }
+
+    /// Checks `relative_path` with all patterns, or returns [MatchResult::Ignore] if a directory
+    /// above it is ignored. `is_dir` is called to find whether the path is a directory only if
+    /// its result is needed.
+    fn check_with_ancestors(
+        &self,
+        relative_path: &str,
+        is_dir: impl FnOnce() -> bool,
+    ) -> MatchResult {
+        self.decide_with_ancestors(relative_path, is_dir, false).0
+    }
+
+    /// Checks `relative_path` like [IgnoreRules::check_with_ancestors], and returns a copy of the
+    /// pattern that decides the result if `with_pattern` is set.
+    ///
+    /// The pattern of an ignored directory above the path is not cached, so it's found by
+    /// checking the directories again.
+    fn decide_with_ancestors(
+        &self,
+        relative_path: &str,
+        is_dir: impl FnOnce() -> bool,
+        with_pattern: bool,
+    ) -> (MatchResult, Option<Pattern>) {
+        let patterns = self.read_patterns();
+        let extension_table = self.read_extension_table(&patterns);
+        let indices = override_count(&patterns)..patterns.len();
+        if self.ancestor_ignored(relative_path, &patterns, &extension_table) {
+            if !with_pattern {
+                return (MatchResult::Ignore, None);
+            }
+            let pattern = ancestor_dirs_below(relative_path, 0).find_map(|dir| {
+                match self.decide_str_indices(
+                    dir,
+                    true,
+                    &patterns,
+                    &extension_table,
+                    indices.clone(),
+                ) {
+                    (MatchResult::Ignore, pattern) => pattern.cloned(),
+                    _ => None,
+                }
+            });
+            return (MatchResult::Ignore, pattern);
+        }
+        let (result, pattern) = self.decide_str_indices(
+            relative_path,
+            is_dir(),
+            &patterns,
+            &extension_table,
+            indices,
+        );
+        (result, pattern.filter(|_| with_pattern).cloned())
+    }
+
+    /// Returns `true` if a directory above `relative_path` is ignored.
+    ///
+    /// The nearest directory with a cached state is looked up, and only the directories below it
+    /// are checked with the patterns, until one of them is ignored. Their states are added to the
+    /// cache.
+    fn ancestor_ignored(
+        &self,
+        relative_path: &str,
+        patterns: &[Pattern],
+        extension_table: &ExtensionTable,
+    ) -> bool {
+        // The length of the nearest cached directory, which is 0 for the root
+        let (cached_len, ignored) = {
+            let cache = self
+                .ancestor_cache
+                .read()
+                .unwrap_or_else(|e| e.into_inner());
+            if cache.is_valid(patterns, self) {
+                ancestor_dirs(relative_path)
+                    .find_map(|dir| cache.ignored.get(dir).map(|ignored| (dir.len(), *ignored)))
+                    .unwrap_or((0, false))
+            } else {
+                (0, false)
+            }
+        };
+        if ignored {
+            return true;
+        }
+
+        let mut states = Vec::new();
+        let mut ignored = false;
+        for dir in ancestor_dirs_below(relative_path, cached_len) {
+            ignored =
+                self.decide_str_indices(
+                    dir,
+                    true,
+                    patterns,
+                    extension_table,
+                    override_count(patterns)..patterns.len(),
+                )
+                .0 == MatchResult::Ignore;
+            states.push((dir.to_string(), ignored));
+            // The directories below an ignored one are ignored too, and are found by its state
+            if ignored {
                 break;
             }
+        }
+        if states.is_empty() {
+            return false;
+        }
+
+        let mut cache = self
+            .ancestor_cache
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if !cache.is_valid(patterns, self) {
+            *cache = AncestorCache {
+                seen: patterns.len(),
+                case_insensitive: self.case_insensitive,
+                #[cfg(feature = "unicode-normalization")]
+                unicode_normalize: self.unicode_normalize,
+                ignored: HashMap::new(),
+            };
+        }
+        if cache.ignored.len() + states.len() > ANCESTOR_CACHE_CAPACITY {
+            cache.ignored.clear();
+        }
+        cache.ignored.extend(states);
+        ignored
+    }
+
+    /// Returns the paths in `paths` that would be ignored, with the patterns that ignore them.
+    ///
+    /// `paths` are absolute paths under the root or relative to the root, and don't need to
+    /// exist. The paths that don't exist are considered directories if they end with a
+    /// separator. A path is also ignored if a directory above it is ignored, as the walkers
+    /// don't descend into the ignored directories. The results for the directories are shared
+    /// between the paths, so this is faster than calling [IgnoreRules::check_hypothetical] for
+    /// each.
+    pub fn verify_not_ignored<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+    ) -> Vec<IgnoredTarget> {
+        let patterns = self.read_patterns();
+        let extension_table = self.read_extension_table(&patterns);
+        let indices = override_count(&patterns)..patterns.len();
+        // The ignored directory and its pattern for each directory checked
+        let mut dir_results: HashMap<PathBuf, Option<(PathBuf, &Pattern)>> = HashMap::new();
+
+        let decide = |rel_path: &Path, is_dir: bool| match self.decide_indices(
+            rel_path,
+            is_dir,
+            &patterns,
+            &extension_table,
+            indices.clone(),
+        ) {
+            (MatchResult::Ignore, Some(pattern)) => Some((rel_path.to_path_buf(), pattern)),
+            _ => None,
+        };
+
+        let mut ignored_targets = Vec::new();
+        for path in paths {
+            let rel_path = path.strip_prefix(&self.root).unwrap_or(path);
+            let path_str = rel_path.to_string_lossy();
+            let is_dir = if path_str.ends_with(is_separator) {
+                true
+            } else {
+                self.root.join(rel_path).is_dir()
+            };
+
+            // The top most ignored directory prunes the others
+            let mut ignored = None;
+            let mut parents: Vec<&Path> = rel_path
+                .ancestors()
+                .skip(1)
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .collect();
+            parents.reverse();
+            for parent in parents {
+                let result = match dir_results.get(parent) {
+                    Some(result) => result.clone(),
+                    None => {
+                        let result = decide(parent, true);
+                        dir_results.insert(parent.to_path_buf(), result.clone());
+                        result
+                    }
+                };
+                if result.is_some() {
+                    ignored = result;
+                    break;
+                }
+            }
+
+            if let Some((ignored_path, pattern)) = ignored.or_else(|| decide(rel_path, is_dir)) {
+                ignored_targets.push(IgnoredTarget {
+                    path: path.to_path_buf(),
+                    ignored_path,
+                    pattern: PatternSummary::from(pattern),
+                });
+            }
+        }
+        ignored_targets
+    }
+
+    /// Returns a copy of the patterns in the order they're stored.
+    ///
+    /// The patterns are in the order they're added, except that override patterns are inserted
+    /// before the others, see [IgnoreRules::prepend_pattern].
+    pub fn patterns_cloned(&self) -> Vec<Pattern> {
+        self.read_patterns().clone()
+    }
+
+    /// Returns the patterns in the order of [IgnoreRules::patterns_cloned], without holding a
+    /// lock on them.
+    ///
+    /// The patterns are copied once after they change, and the later calls share the copy. The
+    /// patterns added after this returns are not in the snapshot.
+    pub fn patterns_snapshot(&self) -> Arc<[Pattern]> {
+        // The snapshot is stored while the patterns are locked, see IgnoreRules::write_patterns
+        let patterns = self.read_patterns();
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(|| Arc::from(patterns.as_slice()))
+            .clone()
+    }
+
+    /// Calls `f` with each pattern in the order of [IgnoreRules::patterns_cloned].
+    ///
+    /// The patterns are not locked while `f` runs, so it can check paths with these rules or add
+    /// patterns to them. The patterns added in `f` are not iterated.
+    pub fn iter_patterns(&self, f: impl FnMut(&Pattern)) {
+        self.patterns_snapshot().iter().for_each(f);
+    }
+
+    /// Returns the number of patterns.
+    pub fn len(&self) -> usize {
+        self.read_patterns().len()
+    }
+
+    /// Returns `true` if there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.read_patterns().is_empty()
+    }
+
+    /// Returns the patterns like [IgnoreRules::patterns_snapshot].
+    ///
+    /// This is for the code that used to read the public `patterns` field, which is no longer
+    /// exposed.
+    #[deprecated(
+        since = "0.6.18",
+        note = "use `patterns_snapshot`, `iter_patterns` or `len` instead"
+    )]
+    pub fn patterns(&self) -> Arc<[Pattern]> {
+        self.patterns_snapshot()
+    }
+
+    /// Locks the patterns for reading and returns them in the order of
+    /// [IgnoreRules::patterns_cloned], without cloning.
+    ///
+    /// Adding patterns to these rules, or to their clones, blocks until the guard is dropped. Use
+    /// [IgnoreRules::patterns_snapshot] to add patterns while reading them.
+    #[deprecated(
+        since = "0.6.18",
+        note = "use `patterns_snapshot` or `iter_patterns` instead"
+    )]
+    pub fn patterns_locked(&self) -> PatternsGuard<'_> {
+        PatternsGuard {
+            guard: self.read_patterns(),
+        }
+    }
+
+    /// Returns the summaries of the patterns in the order of precedence.
+    ///
+    /// The order doesn't depend on the order the ignore files are read, so the snapshots from
+    /// different runs can be compared with [diff_pattern_sets](crate::diff_pattern_sets).
+    pub fn snapshot_patterns(&self) -> Vec<PatternSummary> {
+        let mut summaries: Vec<PatternSummary> = self
+            .read_patterns()
+            .iter()
+            .map(PatternSummary::from)
+            .collect();
+        sort_by_precedence(&mut summaries);
+        summaries
+    }
+
+    /// Renders the patterns relative to `dir` as the content of a gitignore file in `dir`.
+    ///
+    /// These are the patterns read from the ignore files in `dir`, and for the root, the global
+    /// patterns and the patterns translated to the root, like the ones in `info/exclude`. They
+    /// are written with [Pattern::to_line] in the order of precedence, see
+    /// [IgnoreRules::snapshot_patterns]. Override patterns are not included, as they can't be
+    /// written in gitignore syntax.
+    pub fn render_for_dir(&self, dir: &Path) -> String {
+        let dir_prefix = self.relative_dir_prefix(dir);
+        let dir_prefix = dir_prefix.trim_end_matches(is_separator);
+        let mut lines: Vec<(PatternSummary, String)> = self
+            .read_patterns()
+            .iter()
+            .filter(|pattern| !matches!(pattern.effect, PatternEffect::Override { .. }))
+            .filter(|pattern| pattern.split_glob().0 == dir_prefix)
+            .map(|pattern| (PatternSummary::from(pattern), pattern.to_line()))
+            .collect();
+        lines.sort_by(|(a, _), (b, _)| precedence_key(a).cmp(&precedence_key(b)));
+
+        let mut content = String::new();
+        for (_, line) in lines {
+            content.push_str(&line);
+            content.push('\n');
+        }
+        content
+    }
+
+    /// Returns the patterns that apply to the paths in `dir`, from the highest precedence to the
+    /// lowest.
+    ///
+    /// These are the patterns read from the ignore files in `dir` and its ancestors, and the
+    /// global, environment, builtin and command line patterns that apply to the whole tree. The
+    /// override patterns come first, in the order they are checked, followed by the other
+    /// patterns in the reverse order of [IgnoreRules::snapshot_patterns], so the patterns of the
+    /// deepest ignore file and the later lines come before the others.
+    pub fn effective_rules_for_dir(&self, dir: &Path) -> Vec<Pattern> {
+        let dir_prefix = self.relative_dir_prefix(dir);
+        let dir_prefix = Path::new(dir_prefix.trim_end_matches(is_separator));
+        let patterns = self.read_patterns();
+        let overrides = override_count(&patterns);
+        let covers_dir = |pattern: &&Pattern| dir_prefix.starts_with(pattern.split_glob().0);
+
+        let mut scoped: Vec<(PatternSummary, &Pattern)> = patterns[overrides..]
+            .iter()
+            .filter(covers_dir)
+            .map(|pattern| (PatternSummary::from(pattern), pattern))
+            .collect();
+        scoped.sort_by(|(a, _), (b, _)| precedence_key(a).cmp(&precedence_key(b)));
 
+        patterns[..overrides]
+            .iter()
+            .filter(covers_dir)
+            .chain(scoped.into_iter().rev().map(|(_, pattern)| pattern))
+            .cloned()
+            .collect()
+    }
+
+    /// Finds the patterns that can never take effect, e.g., a whitelist pattern overridden by a
+    /// later ignore pattern. See [LintKind](crate::LintKind) for the checks.
+    pub fn lint(&self) -> Vec<LintFinding> {
+        lint_patterns(&self.read_patterns())
+    }
+
+    /// Collects the patterns that can match the paths under `dir`.
+    ///
+    /// The result can be used with [IgnoreRules::check_in_directory] to check the children of
+    /// `dir` without evaluating the patterns that can't match them.
+    pub fn directory_patterns(&self, dir: &Path) -> DirectoryPatterns {
+        let patterns = self.read_patterns();
+        let dir_prefix = self.relative_dir_prefix(dir);
+        let overrides = override_count(&patterns);
+        DirectoryPatterns {
+            indices: (overrides..patterns.len())
+                .filter(|i| self.could_match_under(&patterns[*i], &dir_prefix))
+                .collect(),
+            seen: patterns.len(),
+            overrides,
+        }
+    }
+
+    /// Collects the patterns that can match the paths under `dir` using the patterns collected
+    /// for its parent.
+    ///
+    /// Only the patterns in `parent` and the patterns added after `parent` is collected are
+    /// considered.
+    pub fn child_directory_patterns(
+        &self,
+        parent: &DirectoryPatterns,
+        dir: &Path,
+    ) -> DirectoryPatterns {
+        let patterns = self.read_patterns();
+        let dir_prefix = self.relative_dir_prefix(dir);
+        let overrides = override_count(&patterns);
+        DirectoryPatterns {
+            indices: parent
+                .current_indices(&patterns, overrides)
+                .into_iter()
+                .filter(|i| self.could_match_under(&patterns[*i], &dir_prefix))
+                .collect(),
+            seen: patterns.len(),
+            overrides,
+        }
+    }
+
+    /// Checks a child path of the directory `dir_patterns` is collected for.
+    ///
+    /// This returns the same result with [IgnoreRules::check] for the paths in a directory that
+    /// is not ignored, but evaluates only the patterns in `dir_patterns` and the patterns added
+    /// after it's collected.
+    pub fn check_in_directory(&self, path: &Path, dir_patterns: &DirectoryPatterns) -> MatchResult {
+        let patterns = self.read_patterns();
+        let indices = dir_patterns.current_indices(&patterns, override_count(&patterns));
+        let extension_table = self.read_extension_table(&patterns);
+        self.check_indices(
+            self.relative_path(path),
+            path.is_dir(),
+            &patterns,
+            &extension_table,
+            indices.into_iter(),
+        )
+    }
+
+    fn could_match_under(&self, pattern: &Pattern, dir_prefix: &str) -> bool {
+        // The literal prefix of a glob that is not in NFC is not compared
+        #[cfg(feature = "unicode-normalization")]
+        if self.unicode_normalize && !pattern.compiled_glob.is_nfc() {
+            return true;
+        }
+        if self.case_insensitive {
+            pattern.could_match_under_case_insensitive(dir_prefix)
+        } else {
+            pattern.could_match_under(dir_prefix)
+        }
+    }
+
+    fn relative_dir_prefix(&self, dir: &Path) -> String {
+        let dir_prefix = dir
+            .strip_prefix(&self.root)
+            .expect("path must be within root")
+            .to_string_lossy();
+        self.normalized_path(&dir_prefix).to_string()
+    }
+
+    /// Returns `true` if [IgnoreRules::unicode_normalize] is set.
+    fn normalizes_unicode(&self) -> bool {
+        #[cfg(feature = "unicode-normalization")]
+        return self.unicode_normalize;
+        #[cfg(not(feature = "unicode-normalization"))]
+        false
+    }
+
+    /// Returns `path` converted to NFC if [IgnoreRules::unicode_normalize] is set.
+    fn normalized_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "unicode-normalization")]
+        if self.unicode_normalize {
+            return nfc(path);
+        }
+        Cow::Borrowed(path)
+    }
+
+    /// Returns the glob of `pattern` to match the paths returned by
+    /// [IgnoreRules::normalized_path].
+    fn glob_of<'p>(&self, pattern: &'p Pattern) -> &'p GlobPattern {
+        #[cfg(feature = "unicode-normalization")]
+        if self.unicode_normalize {
+            return pattern.compiled_glob.normalized();
+        }
+        &pattern.compiled_glob
+    }
+
+    /// Returns `true` if the extension of `pattern` is the same as `path_extension`, ignoring the
+    /// case if [IgnoreRules::case_insensitive] is set. `pattern` must be in the extension table
+    /// of `path_extension`, which must be returned by [IgnoreRules::normalized_path].
+    fn extensions_match(&self, pattern: &Pattern, path_extension: Option<&str>) -> bool {
+        match (pattern.extension.as_deref(), path_extension) {
+            // The table keys are lowercase, and in NFC with the `unicode-normalization` feature
+            (Some(pattern_extension), Some(path_extension)) if self.case_insensitive => {
+                !cfg!(feature = "unicode-normalization")
+                    || self.normalizes_unicode()
+                    || pattern_extension.to_lowercase() == path_extension.to_lowercase()
+            }
+            (pattern_extension, path_extension) => {
+                pattern_extension
+                    .map(|e| self.normalized_path(e))
+                    .as_deref()
+                    == path_extension
+            }
+        }
+    }
+
+    fn relative_path<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root)
+            .expect("path must be within root")
+    }
+
+    /// Checks `relative_path` against the override patterns and the patterns with `indices`,
+    /// which must be in increasing order and not include the override patterns. The file system
+    /// is not accessed, `is_dir` tells whether the path is a directory.
+    ///
+    /// Patterns in `extension_table` are not matched with their globs. They are looked up by the
+    /// extension of `relative_path` instead.
+    fn check_indices(
+        &self,
+        relative_path: &Path,
+        is_dir: bool,
+        patterns: &[Pattern],
+        extension_table: &ExtensionTable,
+        indices: impl DoubleEndedIterator<Item = usize>,
+    ) -> MatchResult {
+        self.decide_indices(relative_path, is_dir, patterns, extension_table, indices)
+            .0
+    }
+
+    /// Checks `relative_path` like [IgnoreRules::check_indices] and returns the pattern that
+    /// decides the result, if any.
+    fn decide_indices<'p>(
+        &self,
+        relative_path: &Path,
+        is_dir: bool,
+        patterns: &'p [Pattern],
+        extension_table: &ExtensionTable,
+        indices: impl DoubleEndedIterator<Item = usize>,
+    ) -> (MatchResult, Option<&'p Pattern>) {
+        self.decide_str_indices(
+            &relative_path.to_string_lossy(),
+            is_dir,
+            patterns,
+            extension_table,
+            indices,
+        )
+    }
+
+    /// Checks `relative_path` like [IgnoreRules::decide_indices] without converting it to a
+    /// string.
+    fn decide_str_indices<'p>(
+        &self,
+        relative_path: &str,
+        is_dir: bool,
+        patterns: &'p [Pattern],
+        extension_table: &ExtensionTable,
+        indices: impl DoubleEndedIterator<Item = usize>,
+    ) -> (MatchResult, Option<&'p Pattern>) {
+        let relative_path = &*self.normalized_path(relative_path);
+        let path_str: Cow<str> = if relative_path.is_empty() && is_dir {
+            Cow::Borrowed("/")
+        } else if is_dir && !relative_path.ends_with('/') {
+            Cow::Owned(format!("{relative_path}/"))
+        } else {
+            Cow::Borrowed(relative_path)
+        };
+
+        // The matching pattern with the highest precedence decides
+        let mut decisive_match: Option<(Precedence, &Pattern)> = None;
+        let mut add_match = |i: usize, pattern: &'p Pattern| {
+            let precedence = Precedence::of(pattern, i, self.ignore_filename.as_deref());
+            if decisive_match.is_none_or(|(p, _)| precedence > p) {
+                decisive_match = Some((precedence, pattern));
+            }
+        };
+
+        // Patterns from the ignore file in the directory don't apply to the directory itself
+        let is_from_own_ignore_file = |pattern: &Pattern| {
             if let Source::File {
                 path: ignore_file_path,
                 ..
             } = &pattern.source
             {
                 if let Some(ignore_file_dir) = ignore_file_path.parent() {
-                    if ignore_file_dir == relative_path {
-                        continue;
-                    }
+                    return ignore_file_dir == Path::new(relative_path);
                 }
             }
+            false
+        };
 
-            let matches = if path.is_dir() {
-                if pattern.glob.ends_with("/*") {
-                    if let Some(glob_prefix) = pattern.glob.strip_suffix("/*") {
-                        if relative_path.to_string_lossy() == glob_prefix {
+        let matches_glob = |pattern: &Pattern, path: &str| {
+            if self.case_insensitive {
+                self.glob_of(pattern).matches_case_insensitive(path)
+            } else {
+                self.glob_of(pattern).matches(path)
+            }
+        };
+
+        // Directories are matched with and without the trailing slash
+        let depth = path_depth(&path_str);
+        let trimmed_depth = path_depth(path_str.trim_end_matches('/'));
+
+        let matches_path = |pattern: &Pattern| {
+            if !self.glob_of(pattern).can_match_at_depth(depth)
+                && !self.glob_of(pattern).can_match_at_depth(trimmed_depth)
+            {
+                return false;
+            }
+
+            let matches = if is_dir {
+                let glob = self.glob_of(pattern).as_str();
+                if glob.ends_with("/*") {
+                    if let Some(glob_prefix) = glob.strip_suffix("/*") {
+                        let is_glob_prefix = if self.case_insensitive {
+                            relative_path.to_lowercase() == glob_prefix.to_lowercase()
+                        } else {
+                            relative_path == glob_prefix
+                        };
+                        if is_glob_prefix {
                             false
                         } else {
-                            glob_match(&pattern.glob, &path_str)
-                                || glob_match(&pattern.glob, path_str.trim_end_matches('/'))
+                            matches_glob(pattern, &path_str)
+                                || matches_glob(pattern, path_str.trim_end_matches('/'))
                         }
                     } else {
                         // This case should not be reachable
-                        glob_match(&pattern.glob, &path_str)
-                            || glob_match(&pattern.glob, path_str.trim_end_matches('/'))
+                        matches_glob(pattern, &path_str)
+                            || matches_glob(pattern, path_str.trim_end_matches('/'))
                     }
                 } else {
-                    glob_match(&pattern.glob, &path_str)
-                        || glob_match(&pattern.glob, path_str.trim_end_matches('/'))
+                    matches_glob(pattern, &path_str)
+                        || matches_glob(pattern, path_str.trim_end_matches('/'))
                 }
             } else {
-                glob_match(&pattern.glob, &path_str)
+                matches_glob(pattern, &path_str)
             };
 
-            if matches {
-                if pattern.path_kind == PathKind::Directory && !path.is_dir() {
-                    continue;
-                }
-                match pattern.effect {
-                    PatternEffect::Ignore if ignore_match.is_none() => {
-                        ignore_match = Some(pattern);
-                    }
-                    PatternEffect::Whitelist if whitelist_match.is_none() => {
-                        whitelist_match = Some(pattern);
-                    }
-                    _ => {}
+            matches && (pattern.path_kind != PathKind::Directory || is_dir)
+        };
+
+        // The first override pattern that matches decides, regardless of the other patterns
+        for pattern in &patterns[..override_count(patterns)] {
+            if let PatternEffect::Override { whitelist } = pattern.effect {
+                if !is_from_own_ignore_file(pattern) && matches_path(pattern) {
+                    let result = if whitelist {
+                        MatchResult::Whitelist
+                    } else {
+                        MatchResult::Ignore
+                    };
+                    return (result, Some(pattern));
                 }
             }
         }
 
-        match (ignore_match, whitelist_match) {
-            (None, None) => MatchResult::NoMatch,
-            (Some(_), None) => MatchResult::Ignore,
-            (None, Some(_)) => MatchResult::Whitelist,
-            (Some(im_pattern), Some(wm_pattern)) => {
-                let im_source_dir = im_pattern.source.dir_path();
-                let wm_source_dir = wm_pattern.source.dir_path();
+        let path_extension = path_extension(&path_str);
+        let extension_indices = path_extension
+            .map(|extension| extension_table.lookup(extension))
+            .unwrap_or_default();
 
-                if let (Some(isd), Some(wsd)) = (im_source_dir, wm_source_dir) {
-                    if wsd.starts_with(&isd) && wsd != isd {
-                        let has_slash = wm_pattern.original.contains('/');
-                        let has_wildcard = pattern_has_wildcard(&wm_pattern.original);
-                        if !has_slash && !has_wildcard {
-                            return MatchResult::Ignore;
-                        }
-                    }
-                }
-                if patterns
-                    .iter()
-                    .position(|p| p.original == wm_pattern.original)
-                    > patterns
-                        .iter()
-                        .position(|p| p.original == im_pattern.original)
-                {
-                    MatchResult::Whitelist
-                } else {
-                    MatchResult::Ignore
-                }
+        // All extension patterns for the path match, without matching their globs
+        for i in extension_indices.iter().copied() {
+            let pattern = &patterns[i];
+            if !self.extensions_match(pattern, path_extension)
+                || is_from_own_ignore_file(pattern)
+                || (pattern.path_kind == PathKind::Directory && !is_dir)
+                || matches!(pattern.effect, PatternEffect::Override { .. })
+            {
+                continue;
             }
+            add_match(i, pattern);
+        }
+
+        for i in indices {
+            let pattern = &patterns[i];
+            if extension_table.contains(i)
+                || matches!(pattern.effect, PatternEffect::Override { .. })
+                || is_from_own_ignore_file(pattern)
+            {
+                continue;
+            }
+            if matches_path(pattern) {
+                add_match(i, pattern);
+            }
+        }
+
+        match decisive_match {
+            None => (MatchResult::NoMatch, None),
+            Some((_, pattern)) if pattern.effect == PatternEffect::Whitelist => {
+                (MatchResult::Whitelist, Some(pattern))
+            }
+            Some((_, pattern)) => (MatchResult::Ignore, Some(pattern)),
         }
     }
 
     /// Merges another set of ignore rules into this one.
+    ///
+    /// The override patterns of `other` are inserted before the override patterns of this, and
+    /// the other patterns are appended.
     pub fn merge_with(&self, other: &IgnoreRules) -> Result<()> {
         assert_eq!(self.root, other.root);
 
         {
-            let mut patterns = self.patterns.write().unwrap();
-            let mut other_patterns = other.patterns.write().unwrap();
-            other_patterns.drain(..).for_each(|p| patterns.push(p));
+            let mut patterns = self.write_patterns();
+            let mut other_patterns = other.write_patterns();
+            let other_overrides = override_count(&other_patterns);
+            #[cfg(test)]
+            test_hooks::before_merge();
+
+            // The patterns are only moved from here, so a panic can't leave them half-merged
+            let mut appended = std::mem::take(&mut *other_patterns);
+            let overrides: Vec<Pattern> = appended.drain(..other_overrides).collect();
+            patterns.reserve(overrides.len() + appended.len());
+            patterns.append(&mut appended);
+            if !overrides.is_empty() {
+                patterns.splice(0..0, overrides);
+                self.reset_extension_table();
+            }
+            self.reset_ancestor_cache();
         }
 
         Ok(())
     }
+
+    /// Resets the extension table after patterns are inserted before the others, as the indices
+    /// of all patterns have changed.
+    fn reset_extension_table(&self) {
+        *self
+            .extension_table
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = ExtensionTable::default();
+    }
+
+    /// Resets the cached states of the directories after patterns are added.
+    fn reset_ancestor_cache(&self) {
+        *self
+            .ancestor_cache
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = AncestorCache::default();
+    }
+
+    /// Appends a single pattern after the existing patterns, like [IgnoreRules::add_patterns].
+    ///
+    /// Override patterns are inserted like [IgnoreRules::prepend_pattern].
+    pub fn push_pattern(&self, pattern: Pattern) -> Result<()> {
+        if let PatternEffect::Override { .. } = pattern.effect {
+            return self.prepend_pattern(pattern);
+        }
+        self.write_patterns().push(pattern);
+        self.reset_ancestor_cache();
+        Ok(())
+    }
+
+    /// Inserts a single pattern before the existing patterns, with the highest precedence.
+    ///
+    /// The pattern is converted to an override pattern like in
+    /// [IgnoreRules::add_override_patterns], so it takes precedence over all patterns added before.
+    pub fn prepend_pattern(&self, mut pattern: Pattern) -> Result<()> {
+        pattern.effect = override_effect(pattern.effect);
+        self.write_patterns().insert(0, pattern);
+        self.reset_extension_table();
+        self.reset_ancestor_cache();
+        Ok(())
+    }
+
     /// Adds a vector of `Pattern`s to the existing rules.
     pub fn add_patterns(&self, patterns: Vec<Pattern>) -> Result<()> {
         let other = IgnoreRules::from_patterns(&self.root, None, patterns);
         self.merge_with(&other)
     }
+
+    /// Reads the ignore files in `dirs` like [update_ignore_rules](crate::update_ignore_rules)
+    /// and adds their patterns at once, in the order of `dirs`.
+    ///
+    /// The patterns are locked for writing once, instead of once per directory. Fails without
+    /// adding any patterns if an ignore file can't be read or has too many patterns.
+    pub fn add_patterns_from_dirs(&self, dirs: &[PathBuf]) -> Result<()> {
+        let mut patterns = Vec::new();
+        for dir in dirs {
+            if let Some(dir_patterns) = read_ignore_patterns(
+                dir,
+                self,
+                IgnoreSyntax::Gitignore,
+                IgnoreFileErrorPolicy::Fail,
+                true,
+                Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+                &mut Vec::new(),
+            )? {
+                patterns.extend(dir_patterns);
+            }
+        }
+        if patterns.is_empty() {
+            return Ok(());
+        }
+        self.add_patterns(patterns)
+    }
+
+    /// Adds `patterns` as override patterns, which take precedence over all other patterns.
+    ///
+    /// Ignore patterns are converted to unbeatable ignores and whitelist patterns to unbeatable
+    /// whitelists, e.g., for command line `--exclude` flags. The patterns are inserted before the
+    /// existing patterns, and the first override pattern that matches a path decides the result,
+    /// so the patterns added later take precedence over the earlier override patterns.
+    pub fn add_override_patterns(&self, patterns: Vec<Pattern>) -> Result<()> {
+        let overrides = patterns
+            .into_iter()
+            .map(|mut pattern| {
+                pattern.effect = override_effect(pattern.effect);
+                pattern
+            })
+            .collect();
+        self.add_patterns(overrides)
+    }
 }
 
 /// convert a set of rules in `content` to glob patterns.
+///
+/// The lines that can't be parsed, like `[unclosed`, are returned as errors instead of patterns.
 pub fn content_to_patterns(
     ignore_root: &Path,
     source: Option<&Path>,
     content: &str,
-) -> Vec<Pattern> {
-    content
-        .lines()
-        .enumerate()
-        .filter(|(_, line)| !(line.trim().is_empty() || line.starts_with('#')))
-        .map(|(i, line)| {
-            if !line.ends_with("\\ ") {
-                (i, line.trim_end())
-            } else {
-                (i, line)
+) -> (Vec<Pattern>, Vec<PatternError>) {
+    let mut errors = Vec::new();
+    let patterns = pattern_lines(content)
+        .filter(|(i, line)| match PatternError::check(line, source, i + 1) {
+            Some(e) => {
+                errors.push(e);
+                false
             }
+            None => true,
         })
         .map(|(i, line)| {
             (
@@ -208,11 +1477,370 @@ pub fn content_to_patterns(
                             .expect("path must be within ignore_root")
                             .to_path_buf(),
                         line: (i + 1),
+                        resolved_from: None,
                     },
                     None => Source::Global,
                 },
             )
         })
         .map(|(line, source)| Pattern::new(source, line))
+        .collect();
+    (patterns, errors)
+}
+
+/// Returns the lines of `content` that are parsed to patterns, with their indices, skipping the
+/// empty lines and the comments. The trailing spaces are removed like in [Pattern::new].
+fn pattern_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .map(|(i, line)| (i, trim_trailing_spaces(line)))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+/// The default for [WalkOptions::max_patterns_per_file](crate::WalkOptions).
+pub const DEFAULT_MAX_PATTERNS_PER_FILE: usize = 10_000;
+
+/// An ignore file with more patterns than allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternLimitError {
+    /// The file the patterns are read from, if any.
+    pub source_file: Option<PathBuf>,
+    /// The number of patterns in the file.
+    pub found: usize,
+    /// The maximum number of patterns allowed.
+    pub limit: usize,
+}
+
+impl std::fmt::Display for PatternLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(source_file) = &self.source_file {
+            write!(f, "{}: ", source_file.to_string_lossy())?;
+        }
+        write!(
+            f,
+            "{} patterns found, more than the limit of {}",
+            self.found, self.limit
+        )
+    }
+}
+
+impl std::error::Error for PatternLimitError {}
+
+/// Converts `content` to patterns like [content_to_patterns], failing if there are more than
+/// `max_patterns` of them.
+///
+/// The patterns are counted before they are parsed, so a large file is rejected without building
+/// its patterns. The lines that can't be parsed are not counted, and are logged as warnings.
+pub fn content_to_patterns_limited(
+    ignore_root: &Path,
+    source: Option<&Path>,
+    content: &str,
+    max_patterns: usize,
+) -> std::result::Result<Vec<Pattern>, PatternLimitError> {
+    let found = pattern_lines(content)
+        .filter(|(i, line)| PatternError::check(line, source, i + 1).is_none())
+        .count();
+    if found > max_patterns {
+        return Err(PatternLimitError {
+            source_file: source.map(Path::to_path_buf),
+            found,
+            limit: max_patterns,
+        });
+    }
+    let (patterns, errors) = content_to_patterns(ignore_root, source, content);
+    warn_parse_errors(&errors);
+    Ok(patterns)
+}
+
+/// Logs the errors returned by [content_to_patterns].
+pub(crate) fn warn_parse_errors(errors: &[PatternError]) {
+    for error in errors {
+        warn!("Skipping invalid pattern: {}", error);
+    }
+}
+
+/// Converts the `content` of the ignore file `source` to the patterns relative to `ignore_root`,
+/// like the patterns in the ignore file in `ignore_root`.
+///
+/// This is for the files that apply to the whole tree but are not in `ignore_root`, like git's
+/// `info/exclude`. `source` is kept in [Source::File] as is.
+pub fn content_to_root_patterns(ignore_root: &Path, source: &Path, content: &str) -> Vec<Pattern> {
+    // Parse as the root `.gitignore` to make the patterns relative to the root
+    let root_ignore_file = ignore_root.join(".gitignore");
+    let (mut patterns, errors) = content_to_patterns(ignore_root, Some(&root_ignore_file), content);
+    let errors: Vec<PatternError> = errors
+        .into_iter()
+        .map(|mut e| {
+            e.source_file = Some(source.to_path_buf());
+            e
+        })
+        .collect();
+    warn_parse_errors(&errors);
+    for pattern in patterns.iter_mut() {
+        if let Source::File { path, .. } = &mut pattern.source {
+            *path = source.to_path_buf();
+        }
+    }
+    patterns
+}
+
+/// Converts the `content` of the ignore file `source` in an ancestor of `ignore_root` to the
+/// patterns relative to `ignore_root`.
+///
+/// `source` must be within `ancestor_root`, which is an ancestor of `ignore_root`, e.g., the
+/// repository root. The patterns that can't match under `ignore_root` are dropped. An ignore
+/// pattern that matches `ignore_root` or one of its ancestors ignores all paths under it.
+pub fn ancestor_content_to_patterns(
+    ancestor_root: &Path,
+    ignore_root: &Path,
+    source: &Path,
+    content: &str,
+) -> Vec<Pattern> {
+    let (patterns, errors) = content_to_patterns(ancestor_root, Some(source), content);
+    warn_parse_errors(&errors);
+    ancestor_patterns(ancestor_root, ignore_root, source, &patterns)
+}
+
+/// Translates the `patterns` read from `source` in an ancestor of `ignore_root` to the patterns
+/// relative to `ignore_root`, like [ancestor_content_to_patterns].
+///
+/// The `patterns` are relative to `ancestor_root`, e.g., parsed with [content_to_patterns] or
+/// [content_to_root_patterns].
+pub(crate) fn ancestor_patterns(
+    ancestor_root: &Path,
+    ignore_root: &Path,
+    source: &Path,
+    patterns: &[Pattern],
+) -> Vec<Pattern> {
+    let root_components = relative_components(
+        ignore_root
+            .strip_prefix(ancestor_root)
+            .expect("ignore_root must be within ancestor_root"),
+    );
+    let root_components: Vec<&str> = root_components.iter().map(String::as_str).collect();
+
+    patterns
+        .iter()
+        .flat_map(|pattern| {
+            let translated_source = match &pattern.source {
+                Source::File { line, .. } => Source::Ancestor {
+                    path: source.to_path_buf(),
+                    line: *line,
+                },
+                other => other.clone(),
+            };
+            translate_pattern(pattern, &root_components, translated_source)
+        })
+        .collect()
+}
+
+/// Returns the components of `relative_dir` as strings.
+fn relative_components(relative_dir: &Path) -> Vec<String> {
+    relative_dir
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Translates `pattern` to the patterns relative to the directory with `root_components` under
+/// the directory it's relative to, with `source`.
+///
+/// No patterns are returned if `pattern` can't match under the directory. An ignore pattern that
+/// matches the directory or one of its ancestors ignores all paths under it.
+fn translate_pattern(pattern: &Pattern, root_components: &[&str], source: Source) -> Vec<Pattern> {
+    let mut globs = Vec::new();
+    let mut covers_root = false;
+    let glob = pattern.compiled_glob.as_str().trim_end_matches('/');
+    for alternative in expand_braces(glob) {
+        let components: Vec<&str> = alternative.split('/').filter(|c| !c.is_empty()).collect();
+        translate_glob(&components, root_components, &mut globs, &mut covers_root);
+    }
+
+    // `**/x` already matches the paths `x` matches
+    let anywhere: HashSet<String> = globs
+        .iter()
+        .filter_map(|g| g.strip_prefix("**/").map(str::to_string))
+        .collect();
+    globs.retain(|g| !anywhere.contains(g));
+    globs.sort();
+    globs.dedup();
+
+    let is_ignore = matches!(
+        pattern.effect,
+        PatternEffect::Ignore | PatternEffect::Override { whitelist: false }
+    );
+    let prefix = if is_ignore { "/" } else { "!/" };
+    let suffix = match pattern.path_kind {
+        PathKind::Any => "",
+        PathKind::Directory => "/",
+    };
+    let mut lines: Vec<String> = globs
+        .iter()
+        .map(|g| format!("{prefix}{g}{suffix}"))
+        .collect();
+    // Whitelisting the directory doesn't whitelist the paths in it
+    if covers_root && is_ignore {
+        lines.push(format!("{prefix}**/*"));
+    }
+
+    lines
+        .into_iter()
+        .map(|line| {
+            // The translated globs are relative to the directory, like the global patterns
+            let mut translated = Pattern::new(Source::Global, &line);
+            translated.source = source.clone();
+            translated.effect = pattern.effect.clone();
+            translated.original = pattern.original.clone();
+            translated
+        })
         .collect()
-}
\ No newline at end of file
+}
+
+/// Matches the leading `glob` components with the `root` components and adds the remaining
+/// components as globs relative to `root` to `globs`. Sets `covers_root` if `glob` matches `root`
+/// or one of its ancestors.
+fn translate_glob(glob: &[&str], root: &[&str], globs: &mut Vec<String>, covers_root: &mut bool) {
+    match (glob.first(), root.first()) {
+        (None, _) => *covers_root = true,
+        (Some(&"**"), _) => {
+            // `**` matches some of the root components, or all of them and continues under it
+            globs.push(glob.join("/"));
+            for i in 0..=root.len() {
+                translate_glob(&glob[1..], &root[i..], globs, covers_root);
+            }
+        }
+        (Some(_), None) => globs.push(glob.join("/")),
+        (Some(component), Some(root_component)) => {
+            if glob_match(component, root_component) {
+                translate_glob(&glob[1..], &root[1..], globs, covers_root);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_hooks {
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Whether [IgnoreRules::merge_with](super::IgnoreRules::merge_with) panics while holding
+        /// the locks, reset when it panics.
+        pub static PANIC_IN_MERGE: Cell<bool> = const { Cell::new(false) };
+    }
+
+    pub fn before_merge() {
+        if PANIC_IN_MERGE.with(|panic| panic.replace(false)) {
+            panic!("panic in merge_with");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::thread;
+
+    #[test]
+    fn test_check_recovers_from_poisoned_lock() {
+        let root = Path::new("/repo");
+        let ignore_rules = IgnoreRules::from_global_patterns(root, None, "*.log");
+        assert!(!ignore_rules.is_lock_poisoned());
+
+        let panicking_rules = ignore_rules.clone();
+        let panic_res = thread::spawn(move || {
+            let _patterns = panicking_rules.patterns.write().unwrap();
+            panic!("panic while holding the patterns lock");
+        })
+        .join();
+        assert!(panic_res.is_err());
+        assert!(ignore_rules.is_lock_poisoned());
+
+        assert_eq!(ignore_rules.check(&root.join("a.log")), MatchResult::Ignore);
+        assert_eq!(
+            ignore_rules.check(&root.join("a.txt")),
+            MatchResult::NoMatch
+        );
+
+        ignore_rules
+            .add_patterns(content_to_patterns(root, None, "*.txt").0)
+            .unwrap();
+        assert_eq!(ignore_rules.check(&root.join("a.txt")), MatchResult::Ignore);
+        assert_eq!(ignore_rules.patterns_snapshot().len(), 2);
+    }
+
+    #[test]
+    fn test_panic_in_merge_keeps_the_patterns() {
+        let root = Path::new("/repo");
+        let ignore_rules = IgnoreRules::from_global_patterns(root, None, "*.log\n!keep.log");
+        ignore_rules.check(&root.join("a.log"));
+        let other = IgnoreRules::from_global_patterns(root, None, "*.txt\nkeep.log");
+
+        test_hooks::PANIC_IN_MERGE.with(|panic| panic.set(true));
+        let merge_res = catch_unwind(AssertUnwindSafe(|| ignore_rules.merge_with(&other)));
+        assert!(merge_res.is_err());
+        assert!(ignore_rules.is_lock_poisoned());
+        assert!(other.is_lock_poisoned());
+
+        assert_eq!(ignore_rules.patterns_cloned().len(), 2);
+        assert_eq!(ignore_rules.check(&root.join("a.log")), MatchResult::Ignore);
+        assert_eq!(
+            ignore_rules.check(&root.join("keep.log")),
+            MatchResult::Whitelist
+        );
+        assert_eq!(
+            ignore_rules.check(&root.join("a.txt")),
+            MatchResult::NoMatch
+        );
+
+        // The rules can still be merged after the panic
+        ignore_rules.merge_with(&other).unwrap();
+        assert_eq!(ignore_rules.patterns_cloned().len(), 4);
+        assert!(other.patterns_cloned().is_empty());
+        assert_eq!(ignore_rules.check(&root.join("a.txt")), MatchResult::Ignore);
+        assert_eq!(
+            ignore_rules.check(&root.join("keep.log")),
+            MatchResult::Ignore
+        );
+    }
+
+    #[test]
+    fn test_ancestor_cache_is_bounded() {
+        let root = Path::new("/repo");
+        let ignore_rules = IgnoreRules::from_global_patterns(root, None, "logs/\n!*.txt");
+        let cached_dirs = || ignore_rules.ancestor_cache.read().unwrap().ignored.len();
+
+        for i in 0..ANCESTOR_CACHE_CAPACITY + 10 {
+            let path = root.join(format!("dir{i}/logs/sub/a.txt"));
+            assert_eq!(ignore_rules.check(&path), MatchResult::Ignore);
+            assert!(cached_dirs() <= ANCESTOR_CACHE_CAPACITY);
+        }
+        // Each path adds dirN and dirN/logs, but not the directories under the ignored logs. The
+        // cache is full after 2048 paths, so the last 10 paths are cached.
+        assert_eq!(cached_dirs(), 2 * 10);
+        assert_eq!(
+            ignore_rules.check(&root.join("dir0/other/a.txt")),
+            MatchResult::Whitelist
+        );
+    }
+
+    #[test]
+    fn test_ancestor_dirs() {
+        assert_eq!(
+            ancestor_dirs("a/b/c.txt").collect::<Vec<_>>(),
+            vec!["a/b", "a"]
+        );
+        assert_eq!(
+            ancestor_dirs_below("a/b/c/", 0).collect::<Vec<_>>(),
+            vec!["a", "a/b"]
+        );
+        assert_eq!(
+            ancestor_dirs_below("a/b/c/d.txt", 1).collect::<Vec<_>>(),
+            vec!["a/b", "a/b/c"]
+        );
+        assert_eq!(ancestor_dirs("/a.txt").count(), 0);
+        assert_eq!(ancestor_dirs_below("a.txt", 0).count(), 0);
+    }
+}
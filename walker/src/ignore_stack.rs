@@ -0,0 +1,128 @@
+//! Ignore rules of the projects in a workspace, each with its own root.
+//!
+//! A workspace walked from a super-root may contain projects with their own ignore files.
+//! [IgnoreRulesStack] keeps the [IgnoreRules] of each root, and checks a path with the rules of
+//! the innermost root that contains it. The walkers accept the stack, or a single [IgnoreRules],
+//! through [IgnoreCheck], see [walk_serial_with_check](crate::walk_serial_with_check) and
+//! [walk_parallel_with_check](crate::walk_parallel_with_check).
+use std::path::Path;
+
+use crate::{IgnoreRules, MatchResult};
+
+/// Decides whether the walkers ignore a path.
+pub trait IgnoreCheck: Sync {
+    /// Checks the absolute `path`, which may be a file or a directory.
+    fn check(&self, path: &Path) -> MatchResult;
+}
+
+impl IgnoreCheck for IgnoreRules {
+    fn check(&self, path: &Path) -> MatchResult {
+        IgnoreRules::check(self, path)
+    }
+}
+
+/// An ordered collection of [IgnoreRules] with different roots.
+///
+/// A path is checked with the rules whose root contains it, starting from the innermost root. If
+/// the rules of a root don't match the path, the rules of the roots around it are checked. So a
+/// project can whitelist a path ignored in the super-root, and the super-root patterns apply to
+/// the paths the project doesn't mention.
+///
+/// The rules are pushed from the outermost root to the innermost, like the directories are
+/// entered. Of the rules with the same root, the last pushed are checked first.
+#[derive(Debug, Default)]
+pub struct IgnoreRulesStack {
+    rules: Vec<IgnoreRules>,
+}
+
+impl IgnoreRulesStack {
+    /// Creates an empty stack that doesn't ignore any path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `rules` on top of the stack.
+    pub fn push(&mut self, rules: IgnoreRules) {
+        self.rules.push(rules);
+    }
+
+    /// Removes the rules on top of the stack and returns them, or `None` if it's empty.
+    pub fn pop(&mut self) -> Option<IgnoreRules> {
+        self.rules.pop()
+    }
+
+    /// Returns the number of rules in the stack.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns `true` if the stack has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks the absolute `path` with the rules whose roots contain it, from the innermost root
+    /// outward, and returns the first result other than [MatchResult::NoMatch].
+    ///
+    /// A root is not checked with its own rules, as an ignore file doesn't apply to the directory
+    /// it's in. Paths outside all roots are not matched.
+    pub fn check(&self, path: &Path) -> MatchResult {
+        let mut containing: Vec<&IgnoreRules> = self
+            .rules
+            .iter()
+            .filter(|rules| path.starts_with(&rules.root) && path != rules.root)
+            .collect();
+        // Stable, so the rules pushed later stay after the others with the same root
+        containing.sort_by_key(|rules| rules.root.components().count());
+        containing
+            .into_iter()
+            .rev()
+            .map(|rules| rules.check(path))
+            .find(|result| *result != MatchResult::NoMatch)
+            .unwrap_or(MatchResult::NoMatch)
+    }
+}
+
+impl IgnoreCheck for IgnoreRulesStack {
+    fn check(&self, path: &Path) -> MatchResult {
+        IgnoreRulesStack::check(self, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rules(root: &str, patterns: &str) -> IgnoreRules {
+        IgnoreRules::from_global_patterns(Path::new(root), None, patterns)
+    }
+
+    #[test]
+    fn test_innermost_rules_first() {
+        let mut stack = IgnoreRulesStack::new();
+        stack.push(rules("/ws", "*.log\n*.tmp"));
+        stack.push(rules("/ws/project", "!debug.log\nbuild/"));
+        stack.push(rules("/ws/project", "*.tmp\n!keep.tmp"));
+
+        let check = |path: &str| stack.check(&PathBuf::from(path));
+        assert_eq!(check("/ws/project/debug.log"), MatchResult::Whitelist);
+        assert_eq!(check("/ws/project/other.log"), MatchResult::Ignore);
+        assert_eq!(check("/ws/project/keep.tmp"), MatchResult::Whitelist);
+        assert_eq!(check("/ws/other/debug.log"), MatchResult::Ignore);
+        assert_eq!(check("/ws/other/build"), MatchResult::NoMatch);
+        assert_eq!(check("/ws/project"), MatchResult::NoMatch);
+        assert_eq!(check("/elsewhere/x.log"), MatchResult::NoMatch);
+
+        assert_eq!(stack.len(), 3);
+        stack.pop();
+        stack.pop();
+        assert_eq!(
+            stack.check(Path::new("/ws/project/debug.log")),
+            MatchResult::Ignore
+        );
+        stack.pop();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop().map(|r| r.root), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Compact storage for the paths found in large walks.
+//!
+//! Paths found in a walk repeat long directory prefixes. [PathInterner] stores each directory once
+//! as the id of its parent and its name, and [InternedPath] refers to a path with the id of its
+//! directory and its file name.
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::PathMetadata;
+
+/// Stores directory paths as a tree of names.
+///
+/// Directories are only added, so the ids returned are valid as long as the interner. It can be
+/// shared between threads. Lookups for already interned directories take only a read lock.
+#[derive(Debug, Default)]
+pub struct PathInterner {
+    dirs: RwLock<InternedDirs>,
+}
+
+#[derive(Debug, Default)]
+struct InternedDirs {
+    /// Directories by their ids
+    dirs: Vec<InternedDir>,
+    /// Ids of the directories without a parent, i.e., the first components of the paths
+    roots: HashMap<Arc<OsStr>, u32>,
+}
+
+#[derive(Debug)]
+struct InternedDir {
+    parent: Option<u32>,
+    name: Arc<OsStr>,
+    children: HashMap<Arc<OsStr>, u32>,
+}
+
+impl InternedDirs {
+    fn children(&self, parent: Option<u32>) -> &HashMap<Arc<OsStr>, u32> {
+        match parent {
+            Some(parent) => &self.dirs[parent as usize].children,
+            None => &self.roots,
+        }
+    }
+}
+
+impl PathInterner {
+    /// Creates an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_dirs(&self) -> RwLockReadGuard<'_, InternedDirs> {
+        self.dirs.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_dirs(&self) -> RwLockWriteGuard<'_, InternedDirs> {
+        self.dirs.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the id of `dir`, adding it and its ancestors if they are not interned.
+    pub fn intern_dir(&self, dir: &Path) -> u32 {
+        let mut components = dir.components().map(|c| c.as_os_str()).peekable();
+        if components.peek().is_none() {
+            return self.intern_child(None, OsStr::new(""));
+        }
+
+        let mut id = None;
+        for name in components {
+            id = Some(self.intern_child(id, name));
+        }
+        id.expect("dir has at least one component")
+    }
+
+    fn intern_child(&self, parent: Option<u32>, name: &OsStr) -> u32 {
+        if let Some(id) = self.read_dirs().children(parent).get(name) {
+            return *id;
+        }
+
+        let mut dirs = self.write_dirs();
+        // Another thread may have added it after the read lock is released
+        if let Some(id) = dirs.children(parent).get(name) {
+            return *id;
+        }
+
+        let id = u32::try_from(dirs.dirs.len()).expect("too many directories to intern");
+        let name: Arc<OsStr> = Arc::from(name);
+        dirs.dirs.push(InternedDir {
+            parent,
+            name: name.clone(),
+            children: HashMap::new(),
+        });
+        match parent {
+            Some(parent) => dirs.dirs[parent as usize].children.insert(name, id),
+            None => dirs.roots.insert(name, id),
+        };
+        id
+    }
+
+    /// Splits `path` into its interned directory and its file name.
+    pub fn intern_path(&self, path: &Path) -> InternedPath {
+        match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => InternedPath {
+                dir_id: self.intern_dir(dir),
+                name: name.into(),
+            },
+            // Paths like `/` and `..` are kept as a whole under the empty directory
+            _ => InternedPath {
+                dir_id: self.intern_dir(Path::new("")),
+                name: path.as_os_str().into(),
+            },
+        }
+    }
+
+    /// Returns the path of the directory with `dir_id`.
+    ///
+    /// Panics if `dir_id` is not returned by this interner.
+    pub fn dir_path(&self, dir_id: u32) -> PathBuf {
+        let dirs = self.read_dirs();
+        let mut names = Vec::new();
+        let mut current = Some(dir_id);
+        while let Some(id) = current {
+            let dir = &dirs.dirs[id as usize];
+            names.push(dir.name.clone());
+            current = dir.parent;
+        }
+
+        names.iter().rev().map(|name| name.as_ref()).collect()
+    }
+
+    /// Returns the number of interned directories.
+    pub fn len(&self) -> usize {
+        self.read_dirs().dirs.len()
+    }
+
+    /// Returns `true` if no directories are interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total length of the directory names stored.
+    pub fn name_bytes(&self) -> usize {
+        self.read_dirs().dirs.iter().map(|d| d.name.len()).sum()
+    }
+
+    /// Returns an estimate of the memory used by the interner in bytes.
+    ///
+    /// This counts the directories, their names and the maps to their children, but not the
+    /// overhead of the allocator.
+    pub fn estimated_bytes(&self) -> usize {
+        let dirs = self.read_dirs();
+        let map_bytes = |map: &HashMap<Arc<OsStr>, u32>| {
+            // Each bucket has a control byte
+            map.capacity() * (size_of::<(Arc<OsStr>, u32)>() + 1)
+        };
+        // The counts of an Arc are stored before the name
+        let name_bytes = |name: &Arc<OsStr>| 2 * size_of::<usize>() + name.len();
+        dirs.dirs.capacity() * size_of::<InternedDir>()
+            + dirs
+                .dirs
+                .iter()
+                .map(|d| name_bytes(&d.name) + map_bytes(&d.children))
+                .sum::<usize>()
+            + map_bytes(&dirs.roots)
+    }
+}
+
+/// A path stored as the id of its directory in a [PathInterner] and its file name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InternedPath {
+    /// The id of the directory in the interner
+    pub dir_id: u32,
+    /// The file name of the path
+    pub name: Box<OsStr>,
+}
+
+impl InternedPath {
+    /// Returns the full path. `interner` must be the one the path is interned with.
+    pub fn resolve(&self, interner: &PathInterner) -> PathBuf {
+        let mut path = interner.dir_path(self.dir_id);
+        path.push(&*self.name);
+        path
+    }
+}
+
+/// [PathMetadata] with an [InternedPath].
+#[derive(Debug, Clone)]
+pub struct InternedPathMetadata {
+    /// The interned path
+    pub path: InternedPath,
+    /// The metadata of the path
+    pub metadata: Metadata,
+}
+
+impl InternedPathMetadata {
+    /// Interns the path of `pm`.
+    pub fn new(pm: PathMetadata, interner: &PathInterner) -> Self {
+        Self {
+            path: interner.intern_path(&pm.path),
+            metadata: pm.metadata,
+        }
+    }
+
+    /// Returns the [PathMetadata] with the full path.
+    pub fn resolve(&self, interner: &PathInterner) -> PathMetadata {
+        PathMetadata::new(self.path.resolve(interner), self.metadata.clone())
+    }
+}
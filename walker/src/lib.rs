@@ -9,56 +9,202 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 pub mod abspath;
+pub mod change_signature;
+#[cfg(feature = "tokio")]
+pub mod directory_list_async;
+pub mod dockerignore;
 pub mod error;
-mod glob;
+pub mod gitattributes;
+pub mod global_gitignore;
+pub mod hgignore;
+/// Glob matching used by the ignore patterns.
+pub mod glob;
 /// Rules for ignoring paths during directory traversal.
 pub mod ignore_rules;
+pub mod ignore_stack;
+pub mod interner;
+pub mod lint;
 pub mod notify;
+pub mod owner;
 /// Defines patterns for ignore rules.
 pub mod pattern;
+#[cfg(feature = "report")]
+pub mod report;
+pub mod snapshot;
 pub mod sync;
+pub mod timings;
+pub mod visitor;
+mod walk_core;
+pub mod walk_fs;
 /// Parallel directory traversal.
 pub mod walk_parallel;
 pub mod walk_serial;
+#[cfg(feature = "async")]
+pub mod walk_stream;
 
+pub use glob::expand_braces;
+pub use glob::glob_can_match_at_depth;
+pub use glob::glob_match;
+pub use glob::glob_match_ci;
+#[cfg(feature = "unicode-normalization")]
+pub use glob::glob_match_nfc;
+pub use glob::glob_match_with_spans;
+pub use glob::GlobPattern;
+
+pub use pattern::builtin_system_patterns;
 pub use pattern::MatchResult;
 pub use pattern::PathKind;
 pub use pattern::Pattern;
 pub use pattern::PatternEffect;
+pub use pattern::PatternError;
+pub use pattern::PatternParseErrorKind;
 pub use pattern::PatternRelativity;
 pub use pattern::Source;
 
+pub use lint::LintFinding;
+pub use lint::LintKind;
+
+pub use snapshot::diff_pattern_sets;
+pub use snapshot::MovedPattern;
+pub use snapshot::PatternSetDiff;
+pub use snapshot::PatternSummary;
+
 pub use walk_parallel::walk_parallel;
+pub use walk_parallel::walk_parallel_collect;
+pub use walk_parallel::walk_parallel_controlled;
+pub use walk_parallel::walk_parallel_interned;
+pub use walk_parallel::walk_parallel_scoped;
+pub use walk_parallel::walk_parallel_visit;
+pub use walk_parallel::walk_parallel_with_check;
+pub use walk_parallel::walk_parallel_with_stats;
+#[cfg(feature = "tracing")]
+pub use walk_parallel::walk_parallel_with_tracing;
+#[cfg(feature = "rayon")]
+pub use walk_parallel::walk_rayon;
+#[cfg(feature = "rayon")]
+pub use walk_parallel::walk_rayon_visit;
+pub use walk_parallel::ExtensionSummary;
+pub use walk_parallel::UnusedIgnoreFiles;
+pub use walk_parallel::WalkHandle;
+pub use walk_parallel::WalkHandleState;
+pub use walk_parallel::WalkMetrics;
+pub use walk_parallel::WalkPoolStats;
+pub use walk_parallel::WalkSummary;
+pub use walk_parallel::NO_EXTENSION;
+
+pub use timings::WalkTimings;
+pub use walk_core::find_case_collisions;
+#[cfg(feature = "test-support")]
+pub use walk_fs::MemFs;
+pub use walk_fs::OsFs;
+pub use walk_fs::WalkFs;
+
 pub use walk_serial::walk_serial;
+pub use walk_serial::walk_serial_visit;
+pub use walk_serial::walk_serial_with_check;
+
+pub use visitor::{ChannelVisitor, WalkAction, WalkVisitor};
+#[cfg(feature = "tokio")]
+pub use walk_stream::walk_parallel_tokio_stream;
+#[cfg(feature = "async")]
+pub use walk_stream::{walk_parallel_stream, WalkStream};
 
 pub use walk_serial::path_metadata_map_from_file_targets;
 
 pub use abspath::AbsolutePath;
+pub use change_signature::ChangeSignature;
+#[cfg(feature = "tokio")]
+pub use directory_list_async::{directory_list_async, directory_list_filtered_async};
 pub use error::{Error, Result};
 
+pub use dockerignore::dockerignore_content_to_patterns;
+pub use dockerignore::DOCKERIGNORE_FILENAME;
+
+pub use hgignore::hgignore_content_to_patterns;
+pub use hgignore::HGIGNORE_FILENAME;
+
+pub use global_gitignore::global_gitignore_path;
+pub use global_gitignore::update_global_gitignore_rules;
+
+pub use ignore_rules::ancestor_content_to_patterns;
 pub use ignore_rules::content_to_patterns;
+pub use ignore_rules::content_to_patterns_limited;
+pub use ignore_rules::content_to_root_patterns;
+pub use ignore_rules::DirectoryPatterns;
 pub use ignore_rules::IgnoreRules;
+pub use ignore_rules::IgnoredTarget;
+pub use ignore_rules::PatternLimitError;
+pub use ignore_rules::PatternsGuard;
 pub use ignore_rules::SharedIgnoreRules;
+pub use ignore_rules::DEFAULT_MAX_PATTERNS_PER_FILE;
+pub use ignore_rules::XDG_IGNORE_PATH;
+
+pub use ignore_stack::IgnoreCheck;
+pub use ignore_stack::IgnoreRulesStack;
+
+pub use interner::InternedPath;
+pub use interner::InternedPathMetadata;
+pub use interner::PathInterner;
+
+pub use owner::OwnerFilter;
 
 pub use std::hash::Hash;
 pub use sync::{PathSync, PathSyncSingleton};
-use xvc_logging::warn;
+use xvc_logging::{debug, warn};
 
 pub use notify::make_polling_watcher;
+pub use notify::events_to_walk_messages;
+pub use notify::make_scoped_watcher;
 pub use notify::make_watcher;
+pub use notify::minimal_watch_set;
+pub use notify::watch_and_walk;
+pub use notify::ModifiedFields;
 pub use notify::PathEvent;
 pub use notify::RecommendedWatcher;
+pub use notify::ScopedWatcher;
+pub use notify::WatchWalkHandle;
+pub use notify::WatcherHandle;
 
 use std::{
     fmt::Debug,
     fs::{self, Metadata},
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 
 use anyhow::anyhow;
+use ignore_rules::{ancestor_patterns, warn_parse_errors};
+use timings::{time_phase, WalkPhase};
+use walk_core::check_build_options;
 
+/// The number of threads of the parallel walkers if the number of CPUs can't be found.
 static MAX_THREADS_PARALLEL_WALK: usize = 8;
 
+/// The least number of threads [default_num_threads] returns.
+pub const MIN_DEFAULT_NUM_THREADS: usize = 1;
+
+/// The most threads [default_num_threads] returns. More threads than this rarely speed up the
+/// walks, as they wait for the same disks.
+pub const MAX_DEFAULT_NUM_THREADS: usize = 32;
+
+/// Returns the number of threads the parallel walkers use if [WalkOptions::num_threads] is not
+/// set: the number of CPUs between [MIN_DEFAULT_NUM_THREADS] and [MAX_DEFAULT_NUM_THREADS].
+pub fn default_num_threads() -> usize {
+    num_threads_within(MIN_DEFAULT_NUM_THREADS, MAX_DEFAULT_NUM_THREADS)
+}
+
+/// Returns the number of CPUs, limited to `floor` and `ceiling`.
+///
+/// If the number of CPUs can't be found, 8 threads are assumed. `floor` is returned if it's
+/// greater than `ceiling`.
+pub fn num_threads_within(floor: usize, ceiling: usize) -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(MAX_THREADS_PARALLEL_WALK)
+        .min(ceiling)
+        .max(floor)
+}
+
 /// Combine a path and its metadata in a single struct
 #[derive(Debug, Clone)]
 pub struct PathMetadata {
@@ -66,6 +212,343 @@ pub struct PathMetadata {
     pub path: PathBuf,
     /// metadata
     pub metadata: Metadata,
+    /// The type of the path, from `metadata`
+    pub file_kind: FileKind,
+}
+
+impl PathMetadata {
+    /// Combines `path` and `metadata`, and sets [PathMetadata::file_kind] from the metadata.
+    pub fn new(path: PathBuf, metadata: Metadata) -> Self {
+        let file_kind = FileKind::from(metadata.file_type());
+        Self {
+            path,
+            metadata,
+            file_kind,
+        }
+    }
+
+    /// Reads the metadata of `path` without following symlinks, like the walkers list the paths.
+    ///
+    /// Returns [Error::UnreadableMetadata] with `path` if the metadata can't be read.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.symlink_metadata() {
+            Ok(metadata) => Ok(Self::new(path.to_path_buf(), metadata)),
+            Err(source) => Err(Error::UnreadableMetadata {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Returns the metadata fields to detect the changes in the path, see [ChangeSignature].
+    pub fn change_signature(&self) -> ChangeSignature {
+        ChangeSignature::from(&self.metadata)
+    }
+
+    /// Returns the time since the path was last modified.
+    ///
+    /// Modification times in the future, e.g., after the clock is set back, return zero. Fails if
+    /// the platform doesn't record modification times.
+    pub fn file_age(&self) -> Result<Duration> {
+        Ok(time_since(self.metadata.modified()?))
+    }
+
+    /// Returns the time since the path was last accessed, like [PathMetadata::file_age].
+    ///
+    /// Many file systems are mounted to update the access times rarely or never.
+    pub fn file_access_age(&self) -> Result<Duration> {
+        Ok(time_since(self.metadata.accessed()?))
+    }
+
+    /// Returns the time since the path was created, like [PathMetadata::file_age].
+    ///
+    /// Fails on the platforms and file systems that don't record creation times.
+    pub fn file_creation_age(&self) -> Result<Duration> {
+        Ok(time_since(self.metadata.created()?))
+    }
+
+    /// Returns true if the path was last modified more than `duration` ago.
+    ///
+    /// Returns false if the modification time is not available.
+    pub fn is_older_than(&self, duration: Duration) -> bool {
+        self.file_age().is_ok_and(|age| age > duration)
+    }
+
+    /// Returns true if the path was last modified less than `duration` ago.
+    ///
+    /// Returns false if the modification time is not available.
+    pub fn is_newer_than(&self, duration: Duration) -> bool {
+        self.file_age().is_ok_and(|age| age < duration)
+    }
+
+    /// Returns the path relative to `root`, or `None` if it's not under `root`.
+    ///
+    /// The paths are compared by their components, so `root` may end with a separator or not.
+    pub fn relative_path(&self, root: &Path) -> Option<PathBuf> {
+        self.path.strip_prefix(root).ok().map(Path::to_path_buf)
+    }
+
+    /// Returns the path relative to `root` like [PathMetadata::relative_path], with `/` between
+    /// the components on all platforms.
+    pub fn relative_path_str(&self, root: &Path) -> Option<String> {
+        let relative = self.path.strip_prefix(root).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+/// Returns the paths in `items` that are not ignored by `ignore_rules`, e.g., to filter the paths
+/// found by another walker as the walkers here filter them.
+///
+/// A path is ignored also if a directory above it is ignored, like in [IgnoreRules::check]. The
+/// metadata of the items tells whether they are directories, so the file system is not accessed.
+/// The items that are not under the root of `ignore_rules` are skipped with a warning.
+pub fn filter_path_metadata<'a>(
+    ignore_rules: &'a IgnoreRules,
+    items: impl Iterator<Item = PathMetadata> + 'a,
+) -> impl Iterator<Item = PathMetadata> + 'a {
+    items.filter(move |pm| {
+        let Some(relative_path) = pm.relative_path_str(&ignore_rules.root) else {
+            warn!(
+                "Skipping {:?}: not under the root {:?}",
+                pm.path, ignore_rules.root
+            );
+            return false;
+        };
+        let kind = if pm.metadata.is_dir() {
+            PathKind::Directory
+        } else {
+            PathKind::Any
+        };
+        ignore_rules.check_path_str_with_kind(&relative_path, kind) != MatchResult::Ignore
+    })
+}
+
+/// Returns the time elapsed since `time`, or zero if it's in the future.
+fn time_since(time: SystemTime) -> Duration {
+    SystemTime::now()
+        .duration_since(time)
+        .unwrap_or(Duration::ZERO)
+}
+
+/// The type of a path in the file system.
+///
+/// FIFOs, sockets and device files are recognized only on unix. On other platforms, all paths
+/// are [FileKind::Regular], [FileKind::Dir] or [FileKind::Symlink].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// A regular file
+    Regular,
+    /// A directory
+    Dir,
+    /// A symbolic link
+    Symlink,
+    /// A named pipe
+    Fifo,
+    /// A unix domain socket
+    Socket,
+    /// A block device
+    BlockDev,
+    /// A character device
+    CharDev,
+}
+
+impl FileKind {
+    /// Returns `true` for FIFOs, sockets and device files.
+    pub fn is_special(&self) -> bool {
+        matches!(
+            self,
+            FileKind::Fifo | FileKind::Socket | FileKind::BlockDev | FileKind::CharDev
+        )
+    }
+}
+
+impl From<fs::FileType> for FileKind {
+    fn from(file_type: fs::FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return FileKind::Fifo;
+            } else if file_type.is_socket() {
+                return FileKind::Socket;
+            } else if file_type.is_block_device() {
+                return FileKind::BlockDev;
+            } else if file_type.is_char_device() {
+                return FileKind::CharDev;
+            }
+        }
+
+        if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Regular
+        }
+    }
+}
+
+/// A message about a path in the tree, from a walk or from the file system notifications.
+#[derive(Debug, Clone)]
+pub enum WalkMessage {
+    /// A path that exists, with its current metadata.
+    Path(PathMetadata),
+    /// A path that was removed.
+    Removed {
+        /// The removed path
+        path: PathBuf,
+    },
+}
+
+/// What to do when the walker finds a symlink whose target doesn't exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrokenSymlinkHandling {
+    /// Don't report broken symlinks at all.
+    Skip,
+    /// Report broken symlinks as [Error::BrokenSymlink].
+    EmitAsError,
+    /// Report broken symlinks as [PathMetadata] with the metadata of the link itself, i.e., the
+    /// result of [fs::symlink_metadata].
+    #[default]
+    EmitAsEntry,
+}
+
+/// What to do when the walker finds a FIFO, a socket or a device file.
+///
+/// Reading these like regular files may block forever, e.g., a FIFO without a writer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecialFileBehavior {
+    /// Don't report special files at all.
+    #[default]
+    Skip,
+    /// Report special files as [PathMetadata] with their [PathMetadata::file_kind].
+    Report,
+    /// Report special files as [Error::SpecialFile].
+    Error,
+}
+
+/// What to do when an ignore file in the tree can't be read, e.g., because of its permissions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IgnoreFileErrorPolicy {
+    /// Fail with [Error::UnreadableIgnoreFile].
+    #[default]
+    Fail,
+    /// Log a warning and continue without the patterns in the file.
+    WarnAndSkip,
+    /// Continue without the patterns in the file and report it to the caller.
+    ///
+    /// [build_ignore_patterns_result_with_options] returns the files in
+    /// [IgnoreBuildResult::unreadable_files], and [walk_serial_visit] passes them to the visitor
+    /// as [Error::UnreadableIgnoreFile].
+    Collect,
+}
+
+/// The order the parallel walkers list the queued directories in.
+///
+/// The same paths are found in all orders, only the order they are reported in changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// List the most recently found directory first, finishing a subtree before its siblings.
+    ///
+    /// This keeps fewer directories in the queue for wide trees.
+    DepthFirst,
+    /// List the directories in the order they are found.
+    #[default]
+    BreadthFirst,
+    /// List the directory with the fewest components under the root first.
+    ///
+    /// The paths close to the root are reported before the deeper ones, e.g., for interactive
+    /// commands. The threads list the directories in parallel, so a deeper directory may still be
+    /// listed while a shallower one is being found.
+    ShallowestFirst,
+}
+
+/// The kinds of paths the walkers report, combined with `|`, e.g.,
+/// `EmitKinds::FILES | EmitKinds::DIRS`.
+///
+/// The paths that are not reported are still walked: the directories are traversed and the ignore
+/// files in them are read. Errors are always reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmitKinds(u8);
+
+impl EmitKinds {
+    /// Report nothing but the errors.
+    pub const NONE: Self = Self(0);
+    /// Report the paths that are not walked as directories: files, symlinks and special files.
+    pub const FILES: Self = Self(1);
+    /// Report the directories. With [EmitKinds::FILES], they are reported only with
+    /// [WalkOptions::parents_first].
+    pub const DIRS: Self = Self(1 << 1);
+    /// Report all kinds of paths.
+    pub const ALL: Self = Self(Self::FILES.0 | Self::DIRS.0);
+
+    /// Returns `true` if all kinds in `other` are in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for EmitKinds {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::ops::BitOr for EmitKinds {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for EmitKinds {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The syntax of the ignore files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IgnoreSyntax {
+    /// Patterns are parsed like `.gitignore` files.
+    #[default]
+    Gitignore,
+    /// Patterns are parsed like `.dockerignore` files. See [dockerignore] for the differences.
+    Dockerignore,
+    /// Glob patterns are parsed like `.hgignore` files. See [hgignore] for the differences.
+    Hgignore,
+}
+
+impl IgnoreSyntax {
+    /// Converts the `content` of the ignore file `source` to patterns with this syntax.
+    ///
+    /// The lines that can't be parsed are returned as errors. Only [IgnoreSyntax::Gitignore]
+    /// reports errors.
+    pub fn content_to_patterns(
+        &self,
+        ignore_root: &Path,
+        source: &Path,
+        content: &str,
+    ) -> (Vec<Pattern>, Vec<PatternError>) {
+        match self {
+            IgnoreSyntax::Gitignore => content_to_patterns(ignore_root, Some(source), content),
+            IgnoreSyntax::Dockerignore => (
+                dockerignore_content_to_patterns(ignore_root, source, content),
+                Vec::new(),
+            ),
+            IgnoreSyntax::Hgignore => (
+                hgignore_content_to_patterns(ignore_root, source, content),
+                Vec::new(),
+            ),
+        }
+    }
 }
 
 /// Options to configure directory walking.
@@ -74,16 +557,198 @@ pub struct WalkOptions {
     /// The ignore filename (`.gitignore`, `.xvcignore`, `.ignore`, etc.) or `None` for not
     /// ignoring anything.
     pub ignore_filename: Option<String>,
+    /// The syntax of the ignore files.
+    pub ignore_syntax: IgnoreSyntax,
     /// Whether to ignore the `.git` directory.
     pub ignore_dot_git: bool,
+    /// How to report symlinks with missing targets.
+    pub broken_symlink_handling: BrokenSymlinkHandling,
+    /// How to report FIFOs, sockets and device files.
+    pub special_files: SpecialFileBehavior,
+    /// Whether to traverse the directories that symlinks point to.
+    ///
+    /// Symlinks that point to a directory they are under, e.g., `a/loop -> a`, are reported as
+    /// [Error::CircularSymlink] and not traversed. The other directories reached more than once are
+    /// handled with [WalkOptions::skip_duplicate_dirs].
+    pub follow_symlinks: bool,
+    /// Whether to ignore the paths with `export-ignore` attribute in `.gitattributes` files.
+    pub read_gitattributes_export_ignore: bool,
+    /// Whether to skip the directories already walked via another path, e.g., bind mounts or
+    /// followed symlinks.
+    ///
+    /// Directories are identified by their device and inode numbers, so this has no effect on
+    /// non-unix platforms.
+    pub skip_duplicate_dirs: bool,
+    /// Whether to report the directories, each before the paths in it.
+    ///
+    /// The directories are not reported by default, unless [WalkOptions::emit] is only
+    /// [EmitKinds::DIRS]. When they are, every directory under the root is reported before any of
+    /// its children, also in [walk_parallel], where the children may be found by another thread.
+    /// [WalkOptions::emit] without [EmitKinds::DIRS] turns off the directories, but not the order.
+    pub parents_first: bool,
+    /// Whether to skip the paths owned by other users, and not to descend into their
+    /// directories.
+    ///
+    /// The filter is applied after skipping `.git` and before the ignore rules, so the ignore
+    /// files of other users are not read by [walk_serial].
+    ///
+    /// The owners are checked only on Unix. On other platforms, the walks return
+    /// [Error::UnsupportedWalkOption] when this is set.
+    pub owner_filter: Option<OwnerFilter>,
+    /// Whether to read git's global excludes file, `core.excludesFile`, with `.gitignore` files.
+    ///
+    /// See [global_gitignore] for where it's looked up. Its patterns have the lowest precedence.
+    pub respect_global_gitignore: bool,
+    /// Whether to read the user's ignore file in `$XDG_CONFIG_HOME/xvc/ignore`.
+    ///
+    /// See [IgnoreRules::from_xdg_config]. Its patterns have lower precedence than the given
+    /// patterns and the ignore files in the tree.
+    pub respect_xdg_config: bool,
+    /// Whether to read the patterns in the [XVC_IGNORE_ENV] environment variable.
+    ///
+    /// See [IgnoreRules::add_from_env]. The patterns have lower precedence than the given patterns
+    /// and the ignore files in the tree.
+    pub respect_xvc_ignore_env: bool,
+    /// Whether to ignore the files operating systems create in directories, like `.DS_Store`.
+    ///
+    /// See [builtin_system_patterns]. The patterns have the lowest precedence, so the files can be
+    /// whitelisted in the ignore files.
+    pub skip_system_files: bool,
+    /// What to do when an ignore file in the tree can't be read.
+    ///
+    /// Applies to the ignore files read by [walk_serial] and by the functions that build ignore
+    /// rules with these options, like [build_ignore_patterns_with_options].
+    pub ignore_file_errors: IgnoreFileErrorPolicy,
+    /// The number of results waiting for the consumer at which the threads of the parallel
+    /// walkers stop listing new directories, or `None` to keep walking.
+    ///
+    /// The threads wait until the results drop below half of the threshold, except one that keeps
+    /// the walk going. With a bounded channel, this keeps the threads from blocking on a slow
+    /// consumer while holding the directories they listed. [walk_serial] walks in a single
+    /// thread that never waits, so it ignores this.
+    pub backpressure_threshold: Option<usize>,
+    /// The order the walkers list the queued directories in.
+    pub traversal_order: TraversalOrder,
+    /// Whether to read the ignore files that are symlinks to files outside the root.
+    ///
+    /// The patterns read from symlinks keep the resolved path in [Source::File]. When this is not
+    /// set, the symlinks pointing outside the root are skipped with a warning. Dangling symlinks
+    /// are always skipped with a warning.
+    pub follow_ignore_file_symlinks: bool,
+    /// Whether to measure the time spent in each phase of the walks.
+    ///
+    /// The timings are returned in [WalkMetrics::timings], also by [walk_serial_visit]. They are
+    /// zero when this is not set.
+    pub collect_timings: bool,
+    /// The maximum number of patterns read from an ignore file, or `None` for no limit.
+    ///
+    /// An ignore file with more patterns fails the walk with [Error::TooManyPatterns], as each
+    /// pattern slows down the checks of all paths under its directory.
+    pub max_patterns_per_file: Option<usize>,
+    /// The kinds of paths to report.
+    ///
+    /// With [EmitKinds::ALL], directories are reported only if [WalkOptions::parents_first] is
+    /// also set. [EmitKinds::DIRS] alone reports them without it, each before the paths in it. A
+    /// directory that is not reported is still walked, and its children are reported after the
+    /// place it would be. A root that is a file is reported only with [EmitKinds::FILES].
+    pub emit: EmitKinds,
+    /// Whether to log the directories the walkers don't descend into because they are ignored.
+    ///
+    /// Each directory is logged as a warning with its path relative to the root and the pattern
+    /// that ignores it, with the file and line the pattern is read from. Only the first
+    /// [PRUNE_LOGS_PER_PATTERN] directories ignored by each pattern are logged.
+    pub log_prunes: bool,
+    /// The number of threads of the parallel walkers, or `None` for [default_num_threads].
+    ///
+    /// At least one thread is used. [walk_serial] always walks in the calling thread.
+    pub num_threads: Option<usize>,
+    /// The names of the ignore files to look for in the tree, other than
+    /// [WalkOptions::ignore_filename].
+    ///
+    /// The files with these names are not read, so their patterns don't apply. The walkers count
+    /// the ones they find and return them in [WalkMetrics::unused_ignore_files], e.g., to tell
+    /// that a tree walked with `.xvcignore` also has `.gitignore` files. [KNOWN_IGNORE_FILENAMES]
+    /// by default, empty to not look for any.
+    pub known_ignore_filenames: Vec<String>,
+    /// Whether to skip the directories that can't be listed for lack of permissions.
+    ///
+    /// The skipped directories are logged as warnings, like `find -not -readable -prune`. When
+    /// this is not set, [walk_parallel] reports the error and [walk_serial] stops with it. Other
+    /// errors listing a directory are always reported.
+    pub skip_unreadable_dirs: bool,
+    /// Whether to count the files and their sizes by extension.
+    ///
+    /// The walkers return the counts in [WalkMetrics::extensions], also [walk_serial_visit],
+    /// which are empty when this is not set. The extensions are lowercased if the ignore rules
+    /// are [IgnoreRules::case_insensitive].
+    pub summarize_extensions: bool,
+    /// Whether to convert the patterns and the paths to NFC before matching them.
+    ///
+    /// The rules built by [walk_serial] and [build_ignore_patterns_with_options] set
+    /// [IgnoreRules::unicode_normalize] from this, so `caf\u{E9}.txt` in an ignore file matches
+    /// `cafe\u{301}.txt` as macOS stores it. [walk_parallel] sets it on the rules it's given,
+    /// so they match the same paths as the rules built with these options.
+    ///
+    /// This needs the `unicode-normalization` feature. Without it, the walks and the builders
+    /// return [Error::UnsupportedWalkOption] when this is set.
+    pub unicode_normalize: bool,
+    /// Whether to report the children of a directory whose names differ only in case.
+    ///
+    /// Both paths are reported, and [Error::CaseCollision] is reported after the later one, so
+    /// the walk is not complete, see [WalkSummary::is_complete]. The names are lowercased to
+    /// compare them, see [find_case_collisions]. Ignored paths are not compared.
+    pub detect_case_collisions: bool,
 }
 
+/// The ignore filenames the walkers look for by default, see
+/// [WalkOptions::known_ignore_filenames].
+pub const KNOWN_IGNORE_FILENAMES: [&str; 5] = [
+    ".gitignore",
+    ".xvcignore",
+    ".ignore",
+    DOCKERIGNORE_FILENAME,
+    HGIGNORE_FILENAME,
+];
+
+/// The number of paths kept in [UnusedIgnoreFiles::examples].
+pub const UNUSED_IGNORE_FILE_EXAMPLES: usize = 5;
+
+/// The number of directories ignored by the same pattern that are logged with
+/// [WalkOptions::log_prunes].
+pub const PRUNE_LOGS_PER_PATTERN: usize = 10;
+
 impl WalkOptions {
     /// Instantiate a Git repository walker that uses `.gitignore` as ignore file name.
     pub fn gitignore() -> Self {
         Self {
             ignore_filename: Some(".gitignore".into()),
+            ignore_syntax: IgnoreSyntax::Gitignore,
             ignore_dot_git: true,
+            broken_symlink_handling: BrokenSymlinkHandling::default(),
+            special_files: SpecialFileBehavior::default(),
+            follow_symlinks: false,
+            read_gitattributes_export_ignore: false,
+            skip_duplicate_dirs: true,
+            parents_first: false,
+            owner_filter: None,
+            respect_global_gitignore: false,
+            respect_xdg_config: false,
+            respect_xvc_ignore_env: false,
+            skip_system_files: false,
+            ignore_file_errors: IgnoreFileErrorPolicy::Fail,
+            backpressure_threshold: None,
+            traversal_order: TraversalOrder::BreadthFirst,
+            follow_ignore_file_symlinks: true,
+            collect_timings: false,
+            max_patterns_per_file: Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+            emit: EmitKinds::ALL,
+            log_prunes: false,
+            num_threads: None,
+            known_ignore_filenames: KNOWN_IGNORE_FILENAMES.map(String::from).to_vec(),
+            skip_unreadable_dirs: false,
+            summarize_extensions: false,
+            unicode_normalize: false,
+            detect_case_collisions: false,
         }
     }
 
@@ -91,104 +756,828 @@ impl WalkOptions {
     pub fn xvcignore() -> Self {
         Self {
             ignore_filename: Some(".xvcignore".into()),
+            ignore_syntax: IgnoreSyntax::Gitignore,
             ignore_dot_git: true,
+            broken_symlink_handling: BrokenSymlinkHandling::default(),
+            special_files: SpecialFileBehavior::default(),
+            follow_symlinks: false,
+            read_gitattributes_export_ignore: false,
+            skip_duplicate_dirs: true,
+            parents_first: false,
+            owner_filter: None,
+            respect_global_gitignore: false,
+            respect_xdg_config: false,
+            respect_xvc_ignore_env: false,
+            skip_system_files: false,
+            ignore_file_errors: IgnoreFileErrorPolicy::Fail,
+            backpressure_threshold: None,
+            traversal_order: TraversalOrder::BreadthFirst,
+            follow_ignore_file_symlinks: true,
+            collect_timings: false,
+            max_patterns_per_file: Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+            emit: EmitKinds::ALL,
+            log_prunes: false,
+            num_threads: None,
+            known_ignore_filenames: KNOWN_IGNORE_FILENAMES.map(String::from).to_vec(),
+            skip_unreadable_dirs: false,
+            summarize_extensions: false,
+            unicode_normalize: false,
+            detect_case_collisions: false,
+        }
+    }
+
+    /// Instantiate a walker that uses `.dockerignore` as ignore file name and parses it with
+    /// Docker's rules.
+    pub fn dockerignore() -> Self {
+        Self {
+            ignore_filename: Some(DOCKERIGNORE_FILENAME.into()),
+            ignore_syntax: IgnoreSyntax::Dockerignore,
+            ..Self::gitignore()
+        }
+    }
+
+    /// Instantiate a walker that uses `.hgignore` as ignore file name and reads the glob patterns
+    /// in it.
+    pub fn hgignore() -> Self {
+        Self {
+            ignore_filename: Some(HGIGNORE_FILENAME.into()),
+            ignore_syntax: IgnoreSyntax::Hgignore,
+            ..Self::gitignore()
+        }
+    }
+}
+
+/// The result of building ignore rules from a directory tree.
+///
+/// Directories pruned by already-loaded rules are not traversed, so the ignore files inside them
+/// are never read. They are listed in `unscanned_dirs` to let the callers decide whether to load
+/// their rules lazily, e.g., when a whitelisted path falls under one of them.
+#[derive(Debug, Clone)]
+pub struct IgnoreBuildResult {
+    /// The ignore rules collected from the tree.
+    pub rules: IgnoreRules,
+    /// Directories that are ignored and not traversed while building the rules.
+    pub unscanned_dirs: Vec<PathBuf>,
+    /// Ignore files read while building the rules.
+    pub files_read: Vec<PathBuf>,
+    /// Ignore files that can't be read, when built with [IgnoreFileErrorPolicy::Collect].
+    pub unreadable_files: Vec<IgnoreFileError>,
+    /// Ignore files in the `unscanned_dirs` that are not read, with the patterns that ignore their
+    /// directories.
+    ///
+    /// Like git, the patterns in these files don't apply, as their directories are ignored. Only
+    /// the directories in `unscanned_dirs` are checked for an ignore file, not the directories
+    /// under them.
+    pub skipped_ignore_files: Vec<(PathBuf, Pattern)>,
+}
+
+/// An ignore file that can't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreFileError {
+    /// The path of the ignore file
+    pub path: PathBuf,
+    /// The kind of the I/O error, e.g., [std::io::ErrorKind::PermissionDenied]
+    pub kind: std::io::ErrorKind,
+    /// The message of the I/O error
+    pub message: String,
+}
+
+impl IgnoreFileError {
+    /// Creates the error for `path` from the error returned while reading it.
+    pub fn new(path: &Path, err: &std::io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for IgnoreFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.to_string_lossy(), self.message)
+    }
+}
+
+impl std::error::Error for IgnoreFileError {}
+
+/// The content of an ignore file read by [read_ignore_file].
+struct IgnoreFileContent {
+    content: String,
+    /// The path the ignore file resolves to, if it's a symlink.
+    resolved_from: Option<PathBuf>,
+}
+
+impl IgnoreFileContent {
+    /// Parses the content of `ignore_file` with `syntax`, warning about the lines that can't be
+    /// parsed.
+    ///
+    /// Fails with [Error::TooManyPatterns] if there are more than `max_patterns`.
+    fn to_patterns(
+        &self,
+        syntax: IgnoreSyntax,
+        ignore_root: &Path,
+        ignore_file: &Path,
+        max_patterns: Option<usize>,
+    ) -> Result<Vec<Pattern>> {
+        let mut patterns = match (syntax, max_patterns) {
+            (IgnoreSyntax::Gitignore, Some(limit)) => {
+                content_to_patterns_limited(ignore_root, Some(ignore_file), &self.content, limit)?
+            }
+            _ => {
+                let (patterns, errors) =
+                    syntax.content_to_patterns(ignore_root, ignore_file, &self.content);
+                warn_parse_errors(&errors);
+                match max_patterns {
+                    Some(limit) if patterns.len() > limit => {
+                        return Err(PatternLimitError {
+                            source_file: Some(ignore_file.to_path_buf()),
+                            found: patterns.len(),
+                            limit,
+                        }
+                        .into())
+                    }
+                    _ => patterns,
+                }
+            }
+        };
+        if self.resolved_from.is_some() {
+            for pattern in patterns.iter_mut() {
+                if let Source::File { resolved_from, .. } = &mut pattern.source {
+                    resolved_from.clone_from(&self.resolved_from);
+                }
+            }
+        }
+        Ok(patterns)
+    }
+}
+
+/// Resolves the symlinked ignore file at `path`.
+///
+/// Returns `None` with a warning if the symlink is dangling, or if it points outside
+/// `ignore_root` and `follow_symlinks` is not set. Symlinks to other than files are skipped
+/// silently, like the directories with the ignore file's name.
+fn resolve_ignore_file_symlink(
+    path: &Path,
+    ignore_root: &Path,
+    follow_symlinks: bool,
+) -> Option<PathBuf> {
+    let target = match path.canonicalize() {
+        Ok(target) => target,
+        Err(e) => {
+            warn!("Skipping dangling ignore file symlink {:?}: {}", path, e);
+            return None;
+        }
+    };
+    if !target.is_file() {
+        return None;
+    }
+    if !follow_symlinks {
+        let in_root = ignore_root
+            .canonicalize()
+            .is_ok_and(|root| target.starts_with(root));
+        if !in_root {
+            warn!(
+                "Skipping ignore file symlink {:?} pointing outside the root: {:?}",
+                path, target
+            );
+            return None;
+        }
+    }
+    Some(target)
+}
+
+/// Reads the ignore file at `path`, handling the errors as set in `policy`.
+///
+/// Returns `None` if there is no ignore file at `path`, or if the file can't be read and the
+/// policy allows to continue. The error is added to `unreadable_files` with
+/// [IgnoreFileErrorPolicy::Collect]. The file is read with `walk_fs`, and the symlinks are
+/// resolved with [resolve_ignore_file_symlink].
+fn read_ignore_file(
+    path: &Path,
+    ignore_root: &Path,
+    policy: IgnoreFileErrorPolicy,
+    follow_symlinks: bool,
+    unreadable_files: &mut Vec<IgnoreFileError>,
+    walk_fs: &impl WalkFs,
+) -> Result<Option<IgnoreFileContent>> {
+    let resolved_from = match walk_fs.symlink_file_kind(path) {
+        Ok(FileKind::Symlink) => {
+            let Some(target) = resolve_ignore_file_symlink(path, ignore_root, follow_symlinks)
+            else {
+                return Ok(None);
+            };
+            Some(target)
+        }
+        Ok(FileKind::Regular) => None,
+        _ => return Ok(None),
+    };
+
+    match walk_fs.read_to_string(path) {
+        Ok(content) => Ok(Some(IgnoreFileContent {
+            content,
+            resolved_from,
+        })),
+        Err(e) => {
+            let error = IgnoreFileError::new(path, &e);
+            match policy {
+                IgnoreFileErrorPolicy::Fail => Err(error.into()),
+                IgnoreFileErrorPolicy::WarnAndSkip => {
+                    warn!("Skipping unreadable ignore file: {}", error);
+                    Ok(None)
+                }
+                IgnoreFileErrorPolicy::Collect => {
+                    unreadable_files.push(error);
+                    Ok(None)
+                }
+            }
         }
     }
 }
 
 /// Build the ignore rules with the given directory
+///
+/// Fails with [Error::TooManyPatterns] if an ignore file has more than
+/// [DEFAULT_MAX_PATTERNS_PER_FILE] patterns. Use [build_ignore_patterns_with_options] to change
+/// the limit.
 pub fn build_ignore_patterns(
     given: &str,
     ignore_root: &Path,
     ignore_filename: &str,
 ) -> Result<IgnoreRules> {
+    Ok(build_ignore_patterns_result(given, ignore_root, ignore_filename)?.rules)
+}
+
+/// Build the ignore rules with the given directory, parsing the ignore files with `syntax`.
+pub fn build_ignore_patterns_with_syntax(
+    given: &str,
+    ignore_root: &Path,
+    ignore_filename: &str,
+    syntax: IgnoreSyntax,
+) -> Result<IgnoreRules> {
+    root_metadata(ignore_root)?;
+    let ignore_rules = IgnoreRules::from_global_patterns(ignore_root, Some(ignore_filename), given);
+    let files_read = update_git_info_exclude_rules(&ignore_rules)?
+        .into_iter()
+        .collect();
+    Ok(traverse_ignore_files(
+        ignore_rules,
+        syntax,
+        IgnoreFileErrorPolicy::Fail,
+        true,
+        Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+        files_read,
+        &OsFs,
+    )?
+    .rules)
+}
+
+/// Build the ignore rules with the given directory, reading the ignore files as set in
+/// `walk_options`.
+///
+/// The `given` patterns are added after the global excludes file if
+/// [WalkOptions::respect_global_gitignore] is set, and before the ignore files in the tree.
+pub fn build_ignore_patterns_with_options(
+    given: &str,
+    ignore_root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<IgnoreRules> {
+    Ok(build_ignore_patterns_result_with_options(given, ignore_root, walk_options)?.rules)
+}
+
+/// Build the ignore rules like [build_ignore_patterns_with_options] and report the directories
+/// skipped and the ignore files read like [build_ignore_patterns_result].
+///
+/// The ignore files that can't be read are listed in [IgnoreBuildResult::unreadable_files] if
+/// [WalkOptions::ignore_file_errors] is [IgnoreFileErrorPolicy::Collect].
+pub fn build_ignore_patterns_result_with_options(
+    given: &str,
+    ignore_root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<IgnoreBuildResult> {
+    root_metadata(ignore_root)?;
+    let ignore_rules = initial_ignore_rules(given, ignore_root, walk_options)?;
+    if ignore_rules.ignore_filename.is_none() {
+        return Ok(IgnoreBuildResult {
+            rules: ignore_rules,
+            unscanned_dirs: Vec::new(),
+            files_read: Vec::new(),
+            unreadable_files: Vec::new(),
+            skipped_ignore_files: Vec::new(),
+        });
+    }
+
+    let files_read = update_git_info_exclude_rules(&ignore_rules)?
+        .into_iter()
+        .collect();
+    traverse_ignore_files(
+        ignore_rules,
+        walk_options.ignore_syntax,
+        walk_options.ignore_file_errors,
+        walk_options.follow_ignore_file_symlinks,
+        walk_options.max_patterns_per_file,
+        files_read,
+        &OsFs,
+    )
+}
+
+/// Creates the ignore rules with the builtin system patterns, the global excludes file, the user's
+/// ignore file and the patterns in [XVC_IGNORE_ENV], if [WalkOptions::skip_system_files],
+/// [WalkOptions::respect_global_gitignore], [WalkOptions::respect_xdg_config] and
+/// [WalkOptions::respect_xvc_ignore_env] are set, and the `given` patterns.
+pub(crate) fn initial_ignore_rules(
+    given: &str,
+    ignore_root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<IgnoreRules> {
+    check_build_options(walk_options)?;
+    let ignore_rules = IgnoreRules::empty(ignore_root, walk_options.ignore_filename.as_deref());
+    #[cfg(feature = "unicode-normalization")]
+    let ignore_rules = ignore_rules.with_unicode_normalize(walk_options.unicode_normalize);
+    if walk_options.skip_system_files {
+        ignore_rules.add_patterns(builtin_system_patterns())?;
+    }
+    if walk_options.respect_global_gitignore {
+        update_global_gitignore_rules(&ignore_rules)?;
+    }
+    if walk_options.respect_xdg_config {
+        ignore_rules.merge_with(&IgnoreRules::from_xdg_config(ignore_root)?)?;
+    }
+    if walk_options.respect_xvc_ignore_env {
+        ignore_rules.add_from_env(XVC_IGNORE_ENV)?;
+    }
+    ignore_rules.add_patterns(
+        given
+            .lines()
+            .map(|line| Pattern::new(Source::Global, line))
+            .collect(),
+    )?;
+    Ok(ignore_rules)
+}
+
+/// Build the ignore rules with the given directory and report the directories skipped and the
+/// ignore files read during the traversal.
+///
+/// Returns [Error::RootNotFound] if `ignore_root` doesn't exist. If it's a file, the result
+/// contains only the `given` rules.
+pub fn build_ignore_patterns_result(
+    given: &str,
+    ignore_root: &Path,
+    ignore_filename: &str,
+) -> Result<IgnoreBuildResult> {
+    build_ignore_patterns_result_with_fs(given, ignore_root, ignore_filename, &OsFs)
+}
+
+/// Build the ignore rules like [build_ignore_patterns_result], listing the directories and
+/// reading the ignore files with `walk_fs`.
+///
+/// The file system is not accessed unless `walk_fs` does, so the rules of a virtual tree can be
+/// built. Fails with [Error::RootNotFound] if `walk_fs` can't find `ignore_root`.
+pub fn build_ignore_patterns_result_with_fs(
+    given: &str,
+    ignore_root: &Path,
+    ignore_filename: &str,
+    walk_fs: &impl WalkFs,
+) -> Result<IgnoreBuildResult> {
+    walk_fs
+        .symlink_file_kind(ignore_root)
+        .map_err(|e| root_error(ignore_root, e))?;
     let ignore_rules = IgnoreRules::from_global_patterns(ignore_root, Some(ignore_filename), given);
+    let files_read = update_git_info_exclude_rules_with_fs(&ignore_rules, walk_fs)?
+        .into_iter()
+        .collect();
+    traverse_ignore_files(
+        ignore_rules,
+        IgnoreSyntax::Gitignore,
+        IgnoreFileErrorPolicy::Fail,
+        true,
+        Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+        files_read,
+        walk_fs,
+    )
+}
+
+/// Build the ignore rules for a walk rooted at `walk_root` in a repository rooted at `repo_root`.
+///
+/// In addition to the ignore files under `walk_root`, reads the ignore files in `repo_root` and
+/// the directories between it and `walk_root`, and git's `info/exclude` file in `repo_root` for
+/// `.gitignore`. Their patterns are translated to be relative to `walk_root`, and the anchored
+/// patterns that can't match under it are dropped. The ignore files are read like the ones under
+/// `walk_root`, so the symlinks outside `repo_root` are followed and an ignore file with more
+/// than [DEFAULT_MAX_PATTERNS_PER_FILE] patterns fails with [Error::TooManyPatterns].
+///
+/// Returns [Error::PathEscapesBase] if `walk_root` is not within `repo_root`.
+pub fn build_ignore_patterns_with_ancestors(
+    given: &str,
+    walk_root: &Path,
+    repo_root: &Path,
+    ignore_filename: &str,
+) -> Result<IgnoreRules> {
+    root_metadata(walk_root)?;
+    let relative_root = walk_root
+        .strip_prefix(repo_root)
+        .map_err(|_| Error::PathEscapesBase {
+            base: repo_root.to_path_buf(),
+            path: walk_root.to_path_buf(),
+        })?;
+    let ignore_rules = IgnoreRules::from_global_patterns(walk_root, Some(ignore_filename), given);
+    let mut files_read = Vec::<PathBuf>::new();
 
-    let mut dir_stack: Vec<PathBuf> = vec![ignore_root.to_path_buf()];
-    let ignore_fn = ignore_rules.ignore_filename.as_deref().unwrap();
+    let exclude_path = repo_root.join(GIT_INFO_EXCLUDE_PATH);
+    if ignore_filename == ".gitignore"
+        && matches!(OsFs.file_kind(&exclude_path), Ok(FileKind::Regular))
+    {
+        let content = OsFs.read_to_string(&exclude_path)?;
+        let patterns =
+            content_to_root_patterns(repo_root, Path::new(GIT_INFO_EXCLUDE_PATH), &content);
+        ignore_rules.add_patterns(ancestor_patterns(
+            repo_root,
+            walk_root,
+            &exclude_path,
+            &patterns,
+        ))?;
+        files_read.push(exclude_path);
+    }
+
+    let mut ancestor = repo_root.to_path_buf();
+    let mut unreadable_files = Vec::new();
+    for component in relative_root.components() {
+        let ignore_file = ancestor.join(ignore_filename);
+        let ignore_content = read_ignore_file(
+            &ignore_file,
+            repo_root,
+            IgnoreFileErrorPolicy::Fail,
+            true,
+            &mut unreadable_files,
+            &OsFs,
+        )?;
+        if let Some(ignore_content) = ignore_content {
+            let patterns = ignore_content.to_patterns(
+                IgnoreSyntax::Gitignore,
+                repo_root,
+                &ignore_file,
+                Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+            )?;
+            ignore_rules.add_patterns(ancestor_patterns(
+                repo_root,
+                walk_root,
+                &ignore_file,
+                &patterns,
+            ))?;
+            files_read.push(ignore_file);
+        }
+        ancestor.push(component);
+    }
+
+    Ok(traverse_ignore_files(
+        ignore_rules,
+        IgnoreSyntax::Gitignore,
+        IgnoreFileErrorPolicy::Fail,
+        true,
+        Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+        files_read,
+        &OsFs,
+    )?
+    .rules)
+}
+
+/// Reads the ignore files in the non-ignored directories under the root of `ignore_rules` and adds
+/// their patterns to it. The ignore files that can't be read are handled as set in `policy`, and
+/// the symlinked ones as set in [WalkOptions::follow_ignore_file_symlinks] with `follow_symlinks`.
+/// An ignore file with more than `max_patterns` patterns fails with [Error::TooManyPatterns].
+///
+/// The directories are listed and the ignore files are read with `walk_fs`.
+fn traverse_ignore_files(
+    ignore_rules: IgnoreRules,
+    syntax: IgnoreSyntax,
+    policy: IgnoreFileErrorPolicy,
+    follow_symlinks: bool,
+    max_patterns: Option<usize>,
+    mut files_read: Vec<PathBuf>,
+    walk_fs: &impl WalkFs,
+) -> Result<IgnoreBuildResult> {
+    let ignore_root = ignore_rules.root.clone();
+    let mut dir_stack: Vec<PathBuf> = vec![ignore_root.clone()];
+    let ignore_fn = ignore_rules.ignore_filename.clone().unwrap();
+    let mut unscanned_dirs = Vec::<PathBuf>::new();
+    let mut unreadable_files = Vec::<IgnoreFileError>::new();
+    let mut skipped_ignore_files = Vec::<(PathBuf, Pattern)>::new();
 
     while let Some(dir) = dir_stack.pop() {
-        let ignore_file = dir.join(ignore_fn);
-        if ignore_file.is_file() {
-            let ignore_content = fs::read_to_string(&ignore_file)?;
-            let new_patterns =
-                content_to_patterns(ignore_root, Some(&ignore_file), &ignore_content);
-            ignore_rules.add_patterns(new_patterns)?;
+        let ignore_file = dir.join(&ignore_fn);
+        if let Some(ignore_content) = read_ignore_file(
+            &ignore_file,
+            &ignore_root,
+            policy,
+            follow_symlinks,
+            &mut unreadable_files,
+            walk_fs,
+        )? {
+            ignore_rules.add_patterns(ignore_content.to_patterns(
+                syntax,
+                &ignore_root,
+                &ignore_file,
+                max_patterns,
+            )?)?;
+            files_read.push(ignore_file);
         }
 
-        if !dir.is_dir() {
+        if !matches!(walk_fs.file_kind(&dir), Ok(FileKind::Dir)) {
             continue;
         }
 
-        let mut subdirs: Vec<PathBuf> = dir
-            .read_dir()?
-            .filter_map(std::result::Result::ok)
-            .map(|e| e.path())
-            .filter(|p| p.is_dir())
-            .collect();
+        // Symlinks to directories are followed
+        let mut subdirs = Vec::new();
+        let mut ignored_subdirs = Vec::new();
+        let children = walk_fs
+            .read_dir(&dir)
+            .map_err(|e| anyhow!("Error reading directory: {:?}, {:?}", dir, e))?;
+        for (path, kind) in children {
+            let is_dir = match kind {
+                FileKind::Dir => true,
+                FileKind::Symlink => matches!(walk_fs.file_kind(&path), Ok(FileKind::Dir)),
+                _ => false,
+            };
+            if !is_dir {
+                continue;
+            }
+            match ignore_rules.check_dir_path(&path) {
+                MatchResult::NoMatch | MatchResult::Whitelist => subdirs.push(path),
+                MatchResult::Ignore => ignored_subdirs.push(path),
+            }
+        }
 
         subdirs.sort_by(|a, b| b.cmp(a));
+        ignored_subdirs.sort();
 
-        let filtered_subdirs: Vec<_> = subdirs
-            .into_iter()
-            .filter(|p| {
-                matches!(
-                    ignore_rules.check(p),
-                    MatchResult::NoMatch | MatchResult::Whitelist
-                )
-            })
-            .collect();
+        for subdir in &ignored_subdirs {
+            let skipped_file = subdir.join(&ignore_fn);
+            if walk_fs.symlink_file_kind(&skipped_file).is_err() {
+                continue;
+            }
+            if let (MatchResult::Ignore, Some(pattern)) =
+                ignore_rules.check_dir_path_return_pattern(subdir)
+            {
+                debug!(
+                    "Not reading {:?}: ignored by {:?}",
+                    skipped_file, pattern.original
+                );
+                skipped_ignore_files.push((skipped_file, pattern));
+            }
+        }
 
-        dir_stack.extend(filtered_subdirs.into_iter());
+        dir_stack.extend(subdirs);
+        unscanned_dirs.extend(ignored_subdirs);
     }
 
-    Ok(ignore_rules)
+    Ok(IgnoreBuildResult {
+        rules: ignore_rules,
+        unscanned_dirs,
+        files_read,
+        unreadable_files,
+        skipped_ignore_files,
+    })
+}
+
+/// The environment variable read when [WalkOptions::respect_xvc_ignore_env] is set.
+pub const XVC_IGNORE_ENV: &str = "XVC_IGNORE";
+
+/// The path of git's per-repository exclude file, relative to the repository root.
+pub const GIT_INFO_EXCLUDE_PATH: &str = ".git/info/exclude";
+
+/// Adds the patterns in git's `info/exclude` file to `ignore_rules` if its ignore file name is
+/// `.gitignore`. Returns the path of the file if it's read.
+///
+/// The patterns are relative to the root like the patterns in the root `.gitignore`, and they are
+/// added before the patterns in the `.gitignore` files to have lower precedence.
+pub fn update_git_info_exclude_rules(ignore_rules: &IgnoreRules) -> Result<Option<PathBuf>> {
+    update_git_info_exclude_rules_with_fs(ignore_rules, &OsFs)
+}
+
+/// Adds the patterns in git's `info/exclude` file like [update_git_info_exclude_rules], reading
+/// it with `walk_fs`.
+fn update_git_info_exclude_rules_with_fs(
+    ignore_rules: &IgnoreRules,
+    walk_fs: &impl WalkFs,
+) -> Result<Option<PathBuf>> {
+    if ignore_rules.ignore_filename.as_deref() != Some(".gitignore") {
+        return Ok(None);
+    }
+    let exclude_path = ignore_rules.root.join(GIT_INFO_EXCLUDE_PATH);
+    if !matches!(walk_fs.file_kind(&exclude_path), Ok(FileKind::Regular)) {
+        return Ok(None);
+    }
+
+    let content = walk_fs.read_to_string(&exclude_path)?;
+    let new_patterns =
+        content_to_root_patterns(&ignore_rules.root, Path::new(GIT_INFO_EXCLUDE_PATH), &content);
+    ignore_rules.add_patterns(new_patterns)?;
+    Ok(Some(exclude_path))
 }
 
 /// Updates the ignore rules from a given directory.
 pub fn update_ignore_rules(dir: &Path, ignore_rules: &IgnoreRules) -> Result<()> {
-    if let Some(ref ignore_filename) = ignore_rules.ignore_filename {
-        let ignore_root = &ignore_rules.root;
-        let ignore_path = dir.join(ignore_filename);
-        if ignore_path.is_file() {
-            let new_patterns: Vec<Pattern> = {
-                let content = fs::read_to_string(&ignore_path)?;
-                content_to_patterns(ignore_root, Some(ignore_path).as_deref(), &content)
-            };
+    update_ignore_rules_with_syntax(dir, ignore_rules, IgnoreSyntax::Gitignore)
+}
 
-            ignore_rules.add_patterns(new_patterns)?;
-        }
+/// Updates the ignore rules from a given directory, parsing the ignore file with `syntax`.
+///
+/// Fails with [Error::UnreadableIgnoreFile] if the ignore file can't be read, and with
+/// [Error::TooManyPatterns] if it has more than [DEFAULT_MAX_PATTERNS_PER_FILE] patterns.
+pub fn update_ignore_rules_with_syntax(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+    syntax: IgnoreSyntax,
+) -> Result<()> {
+    update_ignore_rules_with_policy(
+        dir,
+        ignore_rules,
+        syntax,
+        IgnoreFileErrorPolicy::Fail,
+        true,
+        Some(DEFAULT_MAX_PATTERNS_PER_FILE),
+        &mut Vec::new(),
+    )
+}
+
+/// Updates the ignore rules from a given directory like [update_ignore_rules_with_syntax],
+/// handling the errors in reading the ignore file as set in `policy`. Symlinked ignore files are
+/// handled as set in [WalkOptions::follow_ignore_file_symlinks] with `follow_symlinks`, and the
+/// number of patterns is limited to `max_patterns`.
+pub(crate) fn update_ignore_rules_with_policy(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+    syntax: IgnoreSyntax,
+    policy: IgnoreFileErrorPolicy,
+    follow_symlinks: bool,
+    max_patterns: Option<usize>,
+    unreadable_files: &mut Vec<IgnoreFileError>,
+) -> Result<()> {
+    if let Some(patterns) = read_ignore_patterns(
+        dir,
+        ignore_rules,
+        syntax,
+        policy,
+        follow_symlinks,
+        max_patterns,
+        unreadable_files,
+    )? {
+        ignore_rules.add_patterns(patterns)?;
     }
     Ok(())
 }
+
+/// Reads the patterns in the ignore file of `ignore_rules` in `dir` like
+/// [update_ignore_rules_with_policy], without adding them to the rules.
+///
+/// Returns `None` if there is no ignore file in `dir` or it's skipped as set in `policy`.
+pub(crate) fn read_ignore_patterns(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+    syntax: IgnoreSyntax,
+    policy: IgnoreFileErrorPolicy,
+    follow_symlinks: bool,
+    max_patterns: Option<usize>,
+    unreadable_files: &mut Vec<IgnoreFileError>,
+) -> Result<Option<Vec<Pattern>>> {
+    let Some(ref ignore_filename) = ignore_rules.ignore_filename else {
+        return Ok(None);
+    };
+    let ignore_root = &ignore_rules.root;
+    let ignore_path = dir.join(ignore_filename);
+    read_ignore_file(
+        &ignore_path,
+        ignore_root,
+        policy,
+        follow_symlinks,
+        unreadable_files,
+        &OsFs,
+    )?
+    .map(|ignore_content| {
+        ignore_content.to_patterns(syntax, ignore_root, &ignore_path, max_patterns)
+    })
+    .transpose()
+}
 /// Return all childs of a directory regardless of any ignore rules
 pub fn directory_list(dir: &Path) -> Result<Vec<Result<PathMetadata>>> {
-    let elements = dir
-        .read_dir()
-        .map_err(|e| anyhow!("Error reading directory: {:?}, {:?}", dir, e))?;
     let mut child_paths = Vec::<Result<PathMetadata>>::new();
+    directory_list_into(dir, &mut child_paths)?;
+    Ok(child_paths)
+}
+
+/// Return all childs of a directory like [directory_list], sorted by their paths.
+///
+/// The errors are after the paths, in the order they are found.
+pub fn directory_list_sorted(dir: &Path) -> Result<Vec<Result<PathMetadata>>> {
+    let mut child_paths = directory_list(dir)?;
+    child_paths.sort_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a.path.cmp(&b.path),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+    });
+    Ok(child_paths)
+}
+
+/// Return the childs of a directory that are not ignored by `ignore_rules`.
+///
+/// The childs are checked with [IgnoreRules::check] as they are listed. Returns an error if `dir`
+/// can't be read like [directory_list]. The entries that can't be read are logged as warnings and
+/// skipped.
+pub fn directory_list_with_rules(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+) -> Result<Vec<PathMetadata>> {
+    let mut child_paths = Vec::new();
+    directory_list_with_rules_into(dir, ignore_rules, &mut child_paths, |_| {})?;
+    Ok(child_paths)
+}
+
+/// Appends the childs of a directory that are not ignored by `ignore_rules` to `child_paths` like
+/// [directory_list_with_rules], and passes the ignored ones to `on_ignored`.
+pub(crate) fn directory_list_with_rules_into(
+    dir: &Path,
+    ignore_rules: &IgnoreRules,
+    child_paths: &mut Vec<PathMetadata>,
+    mut on_ignored: impl FnMut(PathMetadata),
+) -> Result<()> {
+    directory_list_each(dir, |pm_res| match pm_res {
+        Ok(pm) => match ignore_rules.check(&pm.path) {
+            MatchResult::NoMatch | MatchResult::Whitelist => child_paths.push(pm),
+            MatchResult::Ignore => on_ignored(pm),
+        },
+        Err(e) => warn!("{}", e),
+    })
+}
+
+/// Appends all childs of a directory to `child_paths` like [directory_list].
+///
+/// This lets the walkers reuse the same buffer for all directories.
+pub(crate) fn directory_list_into(
+    dir: &Path,
+    child_paths: &mut Vec<Result<PathMetadata>>,
+) -> Result<()> {
+    directory_list_each(dir, |pm_res| child_paths.push(pm_res))
+}
 
-    for entry in elements {
+/// Passes all childs of a directory, or the errors reading them, to `f` as they are listed.
+fn directory_list_each(dir: &Path, mut f: impl FnMut(Result<PathMetadata>)) -> Result<()> {
+    // The I/O error is kept to find its kind, see [walk_core::skips_unreadable_dir]
+    let mut elements = time_phase(WalkPhase::DirectoryRead, || dir.read_dir()).map_err(|e| {
+        let message = format!("Error reading directory: {:?}, {:?}", dir, e);
+        anyhow::Error::new(e).context(message)
+    })?;
+
+    while let Some(entry) = time_phase(WalkPhase::DirectoryRead, || elements.next()) {
         match entry {
-            Err(err) => child_paths.push(Err(Error::from(anyhow!(
+            Err(err) => f(Err(Error::from(anyhow!(
                 "Error reading entry in dir {:?} {:?}",
                 dir,
                 err
             )))),
-            Ok(entry) => match entry.metadata() {
-                Err(err) => child_paths.push(Err(Error::from(anyhow!(
+            Ok(entry) => match time_phase(WalkPhase::Metadata, || entry.metadata()) {
+                Err(err) => f(Err(Error::from(anyhow!(
                     "Error getting metadata {:?} {:?}",
                     entry,
                     err
                 )))),
                 Ok(md) => {
-                    child_paths.push(Ok(PathMetadata {
-                        path: entry.path(),
-                        metadata: md.clone(),
-                    }));
+                    f(Ok(PathMetadata::new(entry.path(), md.clone())));
                 }
             },
         }
     }
-    Ok(child_paths)
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Returns the home directory of the user from `$HOME`, or `%USERPROFILE%` on Windows.
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Returns `$XDG_CONFIG_HOME`, or `~/.config` if it's not set.
+pub(crate) fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+/// Returns the metadata of the walk root without following symlinks.
+///
+/// Returns [Error::RootNotFound] if `root` doesn't exist.
+pub(crate) fn root_metadata(root: &Path) -> Result<Metadata> {
+    fs::symlink_metadata(root).map_err(|e| root_error(root, e))
+}
+
+/// Converts the error reading `root` to [Error::RootNotFound] if it doesn't exist.
+fn root_error(root: &Path, e: std::io::Error) -> Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        Error::RootNotFound {
+            path: root.to_path_buf(),
+        }
+    } else {
+        e.into()
+    }
+}
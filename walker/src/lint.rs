@@ -0,0 +1,132 @@
+//! Static analysis of ignore rules to find the patterns that can never take effect.
+//!
+//! [IgnoreRules::lint](crate::IgnoreRules::lint) runs the checks in this module. The checks
+//! compare the globs of the patterns with each other and don't look at the file system, so they
+//! report only the cases that can be decided from the patterns.
+use crate::glob::glob_match;
+use crate::{PathKind, Pattern, PatternEffect, PatternSummary};
+
+/// The reason a pattern can't take effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A whitelist pattern is overridden by a later ignore pattern in the same directory, like
+    /// `!important.log` before `*.log`.
+    ShadowedWhitelist,
+    /// A whitelist pattern matches only the paths in a directory excluded by an ignore pattern.
+    /// Git doesn't re-include a file if a parent directory is excluded.
+    WhitelistUnderIgnoredDirectory,
+    /// A pattern repeats an earlier pattern without a pattern of the opposite effect between
+    /// them.
+    DuplicateRule,
+}
+
+/// A pattern that can't take effect, and the pattern that causes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    /// The reason `pattern` can't take effect.
+    pub kind: LintKind,
+    /// The pattern that can't take effect.
+    pub pattern: PatternSummary,
+    /// The pattern that overrides, excludes or duplicates `pattern`.
+    pub cause: PatternSummary,
+}
+
+impl LintFinding {
+    fn new(kind: LintKind, pattern: &Pattern, cause: &Pattern) -> Self {
+        LintFinding {
+            kind,
+            pattern: PatternSummary::from(pattern),
+            cause: PatternSummary::from(cause),
+        }
+    }
+}
+
+/// Returns the findings for `patterns` in the order of the patterns that can't take effect.
+pub(crate) fn lint_patterns(patterns: &[Pattern]) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    for (i, pattern) in patterns.iter().enumerate() {
+        if let Some(cause) = duplicated_by(patterns, i) {
+            findings.push(LintFinding::new(LintKind::DuplicateRule, pattern, cause));
+        }
+        if pattern.effect == PatternEffect::Whitelist {
+            if let Some(cause) = shadowed_by(patterns, i) {
+                findings.push(LintFinding::new(LintKind::ShadowedWhitelist, pattern, cause));
+            }
+            if let Some(cause) = excluded_parent_by(patterns, i) {
+                findings.push(LintFinding::new(
+                    LintKind::WhitelistUnderIgnoredDirectory,
+                    pattern,
+                    cause,
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// Returns the glob of `pattern` without the trailing `/` of directory patterns, or `None` if the
+/// glob is negated and can't be compared with the others.
+fn comparable_glob(pattern: &Pattern) -> Option<&str> {
+    let glob = pattern.compiled_glob.as_str();
+    (!glob.starts_with('!')).then(|| glob.trim_end_matches('/'))
+}
+
+/// Returns `true` if `pattern` matches all paths `other` matches.
+///
+/// The glob of `other` is matched as a path, so its wildcards match only the same wildcards in
+/// `pattern`.
+fn covers(pattern: &Pattern, other: &Pattern) -> bool {
+    if pattern.path_kind == PathKind::Directory && other.path_kind != PathKind::Directory {
+        return false;
+    }
+    match (comparable_glob(pattern), comparable_glob(other)) {
+        (Some(glob), Some(other_glob)) => glob == other_glob || glob_match(glob, other_glob),
+        _ => false,
+    }
+}
+
+/// Returns the earlier pattern that the pattern at `index` repeats.
+fn duplicated_by(patterns: &[Pattern], index: usize) -> Option<&Pattern> {
+    let pattern = &patterns[index];
+    patterns[..index]
+        .iter()
+        .rev()
+        .take_while(|p| p.effect == pattern.effect)
+        .find(|p| {
+            p.compiled_glob.as_str() == pattern.compiled_glob.as_str()
+                && p.path_kind == pattern.path_kind
+        })
+}
+
+/// Returns the later ignore pattern in the same directory that overrides the whitelist pattern at
+/// `index`.
+fn shadowed_by(patterns: &[Pattern], index: usize) -> Option<&Pattern> {
+    let pattern = &patterns[index];
+    let dir = pattern.source.dir_path();
+    patterns[index + 1..].iter().find(|p| {
+        p.effect == PatternEffect::Ignore && p.source.dir_path() == dir && covers(p, pattern)
+    })
+}
+
+/// Returns the ignore pattern that excludes a parent directory of all paths the whitelist pattern
+/// at `index` matches.
+fn excluded_parent_by(patterns: &[Pattern], index: usize) -> Option<&Pattern> {
+    let glob = comparable_glob(&patterns[index])?;
+    let components: Vec<&str> = glob.split('/').collect();
+    // A `**` may match no directories, so only the components before it are always parents
+    let parent_count = components
+        .iter()
+        .position(|c| *c == "**")
+        .unwrap_or(components.len() - 1);
+
+    (1..=parent_count).find_map(|k| {
+        let parent = components[..k].join("/");
+        // The last pattern that matches the parent decides whether it's excluded. The patterns in
+        // the ignore file of a directory don't apply to the directory itself.
+        let decisive = patterns.iter().rev().find(|p| {
+            p.source.dir_path().as_deref() != Some(parent.as_ref())
+                && comparable_glob(p).is_some_and(|g| glob_match(g, &parent))
+        })?;
+        (decisive.effect == PatternEffect::Ignore).then_some(decisive)
+    })
+}
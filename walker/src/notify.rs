@@ -4,19 +4,25 @@
 //! It filters relevant events, and also ignores the events from ignored paths.
 //! It defines [PathEvent] as a simple version of [notify::EventKind].
 //! It defines [PathEventHandler] that handles events from [notify::EventHandler].
+use crate::ignore_rules::read_shared_rules;
 use crate::{
+    directory_list, directory_list_with_rules,
     error::{Error, Result},
-    IgnoreRules, MatchResult,
+    update_ignore_rules, walk_parallel_collect, IgnoreRules, MatchResult, PathMetadata,
+    SharedIgnoreRules, WalkMessage, WalkOptions,
 };
 pub use notify::{
     Config, Event, EventHandler, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
 };
+use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs::Metadata;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use xvc_logging::watch;
 
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use log::{debug, warn};
 
 /// An walker-relevant event for changes in a directory.
@@ -30,12 +36,14 @@ pub enum PathEvent {
         /// The new metadata
         metadata: Metadata,
     },
-    /// Emitted after a new write to `path`.
+    /// Emitted after a new write to `path`, or a change in its metadata.
     Update {
         /// Updated path
         path: PathBuf,
         /// New metadata
         metadata: Metadata,
+        /// The fields of the metadata that changed since the last event of `path`
+        fields: ModifiedFields,
     },
     /// Emitted when [PathBuf] is deleted.
     Delete {
@@ -44,10 +52,149 @@ pub enum PathEvent {
     },
 }
 
+/// The fields of the metadata that changed in a [PathEvent::Update].
+///
+/// The watchers keep the metadata of the paths since their last event to compare, starting with the
+/// metadata of the paths that aren't ignored when the watcher is created. All fields are set for the
+/// paths whose previous metadata is not known, e.g., the paths created before their directories are
+/// watched by a [ScopedWatcher].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModifiedFields {
+    /// Whether the size changed
+    pub size_changed: bool,
+    /// Whether the modification time changed
+    pub mtime_changed: bool,
+    /// Whether the permissions changed
+    pub permissions_changed: bool,
+}
+
+impl ModifiedFields {
+    /// All fields changed, for a path whose previous metadata is not known.
+    pub const ALL: Self = Self {
+        size_changed: true,
+        mtime_changed: true,
+        permissions_changed: true,
+    };
+
+    /// Returns the fields that differ between `old` and `new` metadata of a path.
+    pub fn between(old: &Metadata, new: &Metadata) -> Self {
+        Self {
+            size_changed: old.len() != new.len(),
+            mtime_changed: old.modified().ok() != new.modified().ok(),
+            permissions_changed: old.permissions() != new.permissions(),
+        }
+    }
+}
+
+/// The sender of the [PathEvent]s of a watcher, shared by its [PathEventHandler] and
+/// [WatcherHandle] to close the channel when the watcher is shut down.
+#[derive(Debug, Clone)]
+struct EventSender(Arc<Mutex<Option<Sender<Option<PathEvent>>>>>);
+
+impl EventSender {
+    fn new(sender: Sender<Option<PathEvent>>) -> Self {
+        Self(Arc::new(Mutex::new(Some(sender))))
+    }
+
+    /// Sends `event` unless the sender is closed. Closes the sender if the receiver is dropped.
+    ///
+    /// The lock is not held while sending, so the sender can be closed while the channel is
+    /// full. The event is still delivered then.
+    fn send(&self, event: PathEvent) {
+        let Some(sender) = self.0.lock().unwrap().clone() else {
+            debug!("Watcher is shut down, event dropped: {:?}", event);
+            return;
+        };
+        if let Err(e) = sender.send(Some(event)) {
+            Error::from(e).warn();
+            self.close();
+        }
+    }
+
+    /// Drops the sender, so the receiver is disconnected after the events in the channel.
+    fn close(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    fn is_open(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+}
+
+/// A watcher created by [make_watcher] or [make_polling_watcher], to stop it with
+/// [WatcherHandle::shutdown].
+///
+/// Dropping the handle also closes the event channel, and drops the watcher without unwatching
+/// the paths first. The threads of [notify] are not joined in either case. They stop on their own
+/// after the watcher is dropped, and the events they find until then are discarded.
+#[derive(Debug)]
+pub struct WatcherHandle<W: Watcher> {
+    watcher: W,
+    sender: EventSender,
+    watched: Vec<PathBuf>,
+}
+
+impl<W: Watcher> WatcherHandle<W> {
+    /// Starts watching `root` recursively with `watcher`, whose handler sends to `sender`.
+    fn new(mut watcher: W, sender: EventSender, root: &Path) -> Result<Self> {
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        Ok(Self {
+            watcher,
+            sender,
+            watched: vec![root.to_path_buf()],
+        })
+    }
+
+    /// Returns `true` until the watcher is shut down, or an event can't be sent because the
+    /// receiver is dropped.
+    pub fn is_alive(&self) -> bool {
+        self.sender.is_open()
+    }
+
+    /// Stops the watcher: closes the event channel, unwatches the paths and drops the watcher.
+    ///
+    /// The receiver gets the events sent before the channel is closed, and then
+    /// [RecvError](crossbeam_channel::RecvError). The changes after this are not sent. Returns the
+    /// first error unwatching the paths, after trying all of them.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.sender.close();
+        let mut result = Ok(());
+        for path in std::mem::take(&mut self.watched) {
+            if let Err(e) = self.watcher.unwatch(&path) {
+                if result.is_ok() {
+                    result = Err(e.into());
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<W: Watcher> Drop for WatcherHandle<W> {
+    fn drop(&mut self) {
+        self.sender.close();
+    }
+}
+
 /// A struct that handles [notify::Event]s considering also [IgnoreRules]
 struct PathEventHandler {
-    sender: Sender<Option<PathEvent>>,
+    sender: EventSender,
     ignore_rules: IgnoreRules,
+    /// The metadata sent in the last events of the paths, to find the [ModifiedFields] of the
+    /// updates. It starts with [initial_metadata] and the paths are removed with their delete
+    /// events.
+    metadata_cache: HashMap<PathBuf, Metadata>,
+    /// Receives the changes that may require new watches, for [ScopedWatcher].
+    watch_updates: Option<Sender<WatchUpdate>>,
+}
+
+/// A change that may require watching new directories.
+#[derive(Debug)]
+enum WatchUpdate {
+    /// A directory that's not ignored is created.
+    Dir(PathBuf),
+    /// An ignore file is created or modified.
+    IgnoreFile(PathBuf),
 }
 
 impl EventHandler for PathEventHandler {
@@ -86,19 +233,51 @@ impl EventHandler for PathEventHandler {
     }
 }
 
+/// Returns the metadata of the paths under `root` that are not ignored by `ignore_rules`, for the
+/// [ModifiedFields] of their first updates.
+///
+/// The directories that can't be read are logged as warnings and skipped.
+fn initial_metadata(ignore_rules: &IgnoreRules, root: &Path) -> HashMap<PathBuf, Metadata> {
+    let mut metadata_cache = HashMap::new();
+    let mut dir_stack = vec![root.to_path_buf()];
+    while let Some(dir) = dir_stack.pop() {
+        let child_paths = match directory_list_with_rules(&dir, ignore_rules) {
+            Ok(child_paths) => child_paths,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        for pm in child_paths {
+            if pm.metadata.is_dir() {
+                dir_stack.push(pm.path.clone());
+            }
+            // Like the events, the metadata of the symlinks is of their targets
+            if let Ok(metadata) = pm.path.metadata() {
+                metadata_cache.insert(pm.path, metadata);
+            }
+        }
+    }
+    metadata_cache
+}
+
 impl PathEventHandler {
     fn write_event(&mut self, path: PathBuf) {
+        self.send_ignore_file_update(&path);
         match self.ignore_rules.check(&path) {
             MatchResult::Whitelist | MatchResult::NoMatch => {
                 if let Ok(metadata) = path.metadata() {
-                    self.sender
-                        .send(Some(PathEvent::Create {
-                            path: path.clone(),
-                            metadata,
-                        }))
-                        .unwrap_or_else(|e| {
-                            Error::from(e).warn();
+                    let fields = self
+                        .metadata_cache
+                        .insert(path.clone(), metadata.clone())
+                        .map_or(ModifiedFields::ALL, |old| {
+                            ModifiedFields::between(&old, &metadata)
                         });
+                    self.sender.send(PathEvent::Update {
+                        path: path.clone(),
+                        metadata,
+                        fields,
+                    });
                 } else {
                     debug!("Error in metadata for {}", path.to_string_lossy());
                 }
@@ -110,17 +289,18 @@ impl PathEventHandler {
     }
 
     fn create_event(&mut self, path: PathBuf) {
+        self.send_ignore_file_update(&path);
         match self.ignore_rules.check(&path) {
             MatchResult::Whitelist | MatchResult::NoMatch => {
                 if let Ok(metadata) = path.metadata() {
-                    self.sender
-                        .send(Some(PathEvent::Create {
-                            path: path.clone(),
-                            metadata,
-                        }))
-                        .unwrap_or_else(|e| {
-                            Error::from(e).warn();
-                        });
+                    if metadata.is_dir() {
+                        self.send_watch_update(WatchUpdate::Dir(path.clone()));
+                    }
+                    self.metadata_cache.insert(path.clone(), metadata.clone());
+                    self.sender.send(PathEvent::Create {
+                        path: path.clone(),
+                        metadata,
+                    });
                 } else {
                     debug!("Error in metadata for {}", path.to_string_lossy());
                 }
@@ -132,11 +312,10 @@ impl PathEventHandler {
     }
 
     fn remove_event(&mut self, path: PathBuf) {
+        self.metadata_cache.remove(&path);
         match self.ignore_rules.check(&path) {
             MatchResult::Whitelist | MatchResult::NoMatch => {
-                self.sender
-                    .send(Some(PathEvent::Delete { path }))
-                    .unwrap_or_else(|e| warn!("{}", e));
+                self.sender.send(PathEvent::Delete { path });
             }
             MatchResult::Ignore => {
                 debug!("FS Notification Ignored: {}", path.to_string_lossy());
@@ -148,24 +327,47 @@ impl PathEventHandler {
         self.remove_event(from);
         self.create_event(to);
     }
+
+    /// Sends `path` as [WatchUpdate::IgnoreFile] if it's an ignore file.
+    fn send_ignore_file_update(&self, path: &Path) {
+        let ignore_filename = self.ignore_rules.ignore_filename.as_deref();
+        if ignore_filename.is_some() && path.file_name() == ignore_filename.map(OsStr::new) {
+            self.send_watch_update(WatchUpdate::IgnoreFile(path.to_path_buf()));
+        }
+    }
+
+    fn send_watch_update(&self, update: WatchUpdate) {
+        if let Some(watch_updates) = &self.watch_updates {
+            watch_updates
+                .send(update)
+                .unwrap_or_else(|e| warn!("{}", e));
+        }
+    }
 }
 
 /// Create a [notify::RecommendedWatcher] and a [crossbeam_channel::Receiver] to receive
 /// [PathEvent]s. It creates the channel and [PathEventHandler] with its [Sender], then returns the
 /// [Receiver] for consumption.
 ///
-/// Paths ignored by `ignore_rules` do not emit any events.
+/// Paths ignored by `ignore_rules` do not emit any events. The watcher is returned in a
+/// [WatcherHandle] to stop it.
 pub fn make_watcher(
     ignore_rules: IgnoreRules,
-) -> Result<(RecommendedWatcher, Receiver<Option<PathEvent>>)> {
+) -> Result<(
+    WatcherHandle<RecommendedWatcher>,
+    Receiver<Option<PathEvent>>,
+)> {
     let (sender, receiver) = bounded(10000);
+    let sender = EventSender::new(sender);
     let root = ignore_rules.root.clone();
-    let mut watcher = notify::recommended_watcher(PathEventHandler {
+    let watcher = notify::recommended_watcher(PathEventHandler {
+        metadata_cache: initial_metadata(&ignore_rules, &root),
         ignore_rules,
-        sender,
+        sender: sender.clone(),
+        watch_updates: None,
     })?;
 
-    watcher.watch(&root, RecursiveMode::Recursive)?;
+    let watcher = WatcherHandle::new(watcher, sender, &root)?;
     watch!(watcher);
     Ok((watcher, receiver))
 }
@@ -179,18 +381,316 @@ pub fn make_watcher(
 /// performance than [`make_watcher`], but it works in all platforms.
 pub fn make_polling_watcher(
     ignore_rules: IgnoreRules,
-) -> Result<(PollWatcher, Receiver<Option<PathEvent>>)> {
+) -> Result<(WatcherHandle<PollWatcher>, Receiver<Option<PathEvent>>)> {
     let (sender, receiver) = bounded(10000);
+    let sender = EventSender::new(sender);
     let root = ignore_rules.root.clone();
-    let mut watcher = notify::poll::PollWatcher::new(
+    let watcher = notify::poll::PollWatcher::new(
         PathEventHandler {
+            metadata_cache: initial_metadata(&ignore_rules, &root),
             ignore_rules,
-            sender,
+            sender: sender.clone(),
+            watch_updates: None,
         },
         Config::default().with_poll_interval(Duration::from_secs(2)),
     )?;
 
-    watcher.watch(&root, RecursiveMode::Recursive)?;
+    let watcher = WatcherHandle::new(watcher, sender, &root)?;
     watch!(watcher);
     Ok((watcher, receiver))
 }
+
+/// The paths found by [watch_and_walk] and the events of the changes since the walk started.
+#[derive(Debug)]
+pub struct WatchWalkHandle {
+    /// The paths found by the walk
+    pub initial_snapshot: Vec<PathMetadata>,
+    /// The events of the changes under the root, including those during the walk
+    pub events: Receiver<Option<PathEvent>>,
+    watcher: WatcherHandle<RecommendedWatcher>,
+}
+
+impl WatchWalkHandle {
+    /// Stops the watcher with [WatcherHandle::shutdown] and returns the events not received from
+    /// [WatchWalkHandle::events] yet.
+    pub fn join(self) -> Vec<PathEvent> {
+        if let Err(e) = self.watcher.shutdown() {
+            e.warn();
+        }
+        self.events.iter().flatten().collect()
+    }
+}
+
+/// Starts watching `root` like [make_watcher], then walks it with [walk_parallel_collect] and
+/// returns the paths found with the events.
+///
+/// As the watcher is started before the walk, the changes during the walk are in the events
+/// too, and callers can reconcile them with [WatchWalkHandle::initial_snapshot]. A path may be
+/// in both with the same metadata. The errors in the walk are logged as warnings and the paths
+/// they cause to be missed are not in the snapshot.
+///
+/// The events are filtered with the patterns of `ignore_rules`, including the ones the walk adds to
+/// them, e.g., with [WalkOptions::read_gitattributes_export_ignore]. As nothing receives the events
+/// until the walk returns, they are sent to an unbounded channel, so the watcher doesn't block on a
+/// busy tree.
+pub fn watch_and_walk(
+    root: &Path,
+    ignore_rules: SharedIgnoreRules,
+    walk_options: WalkOptions,
+) -> Result<WatchWalkHandle> {
+    let (sender, events) = unbounded();
+    let sender = EventSender::new(sender);
+    let handler_rules = read_shared_rules(&ignore_rules).clone();
+    let watcher = notify::recommended_watcher(PathEventHandler {
+        metadata_cache: initial_metadata(&handler_rules, root),
+        ignore_rules: handler_rules,
+        sender: sender.clone(),
+        watch_updates: None,
+    })?;
+    let watcher = WatcherHandle::new(watcher, sender, root)?;
+    watch!(watcher);
+
+    let (paths, _) = walk_parallel_collect(ignore_rules, root, walk_options)?;
+    let initial_snapshot = paths
+        .into_iter()
+        .filter_map(|pm_res| pm_res.map_err(|e| e.warn()).ok())
+        .collect();
+    Ok(WatchWalkHandle {
+        initial_snapshot,
+        events,
+        watcher,
+    })
+}
+
+/// Returns the directories to watch to observe the changes in all paths under `root` that are not
+/// ignored by `ignore_rules`.
+///
+/// The directories are traversed like the walkers traverse them, so the ignored directories and
+/// the paths under them are not in the set. The subdirectories of a directory that no pattern can
+/// match under are not in the set either, as [make_scoped_watcher] watches it recursively. The
+/// other directories, including `root`, are watched non-recursively.
+pub fn minimal_watch_set(ignore_rules: &IgnoreRules, root: &Path) -> Vec<PathBuf> {
+    watch_plan(ignore_rules, root)
+        .into_iter()
+        .map(|(dir, _)| dir)
+        .collect()
+}
+
+/// Returns the directories in [minimal_watch_set] with the modes to watch them, sorted by path.
+fn watch_plan(ignore_rules: &IgnoreRules, root: &Path) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut plan = Vec::new();
+    let mut dir_stack = vec![(root.to_path_buf(), ignore_rules.directory_patterns(root))];
+    while let Some((dir, dir_patterns)) = dir_stack.pop() {
+        if dir_patterns.is_empty() {
+            plan.push((dir, RecursiveMode::Recursive));
+            continue;
+        }
+        let child_paths = match directory_list(&dir) {
+            Ok(child_paths) => child_paths,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        for child in child_paths {
+            match child {
+                Ok(pm)
+                    if pm.metadata.is_dir()
+                        && ignore_rules.check_in_directory(&pm.path, &dir_patterns)
+                            != MatchResult::Ignore =>
+                {
+                    let child_patterns =
+                        ignore_rules.child_directory_patterns(&dir_patterns, &pm.path);
+                    dir_stack.push((pm.path, child_patterns));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+        plan.push((dir, RecursiveMode::NonRecursive));
+    }
+    plan.sort_by(|(a, _), (b, _)| a.cmp(b));
+    plan
+}
+
+/// A [RecommendedWatcher] that watches only the directories in [minimal_watch_set], created by
+/// [make_scoped_watcher].
+///
+/// The directories created later and the directories whitelisted by the changes in the ignore
+/// files are watched by a background thread when their events arrive. Directories are never
+/// unwatched, the events of the paths ignored later are filtered instead.
+///
+/// Dropping this stops the watcher and the background thread.
+pub struct ScopedWatcher {
+    watcher: Arc<Mutex<RecommendedWatcher>>,
+    watched: Arc<Mutex<HashMap<PathBuf, RecursiveMode>>>,
+}
+
+impl ScopedWatcher {
+    /// Returns the watched directories in sorted order.
+    pub fn watched_dirs(&self) -> Vec<PathBuf> {
+        let mut watched_dirs: Vec<PathBuf> = self.watched.lock().unwrap().keys().cloned().collect();
+        watched_dirs.sort();
+        watched_dirs
+    }
+}
+
+impl std::fmt::Debug for ScopedWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopedWatcher")
+            .field("watcher", &self.watcher)
+            .field("watched", &self.watched_dirs())
+            .finish()
+    }
+}
+
+/// Create a [ScopedWatcher] for the root of `ignore_rules` and a [crossbeam_channel::Receiver] to
+/// receive [PathEvent]s like [make_watcher].
+///
+/// Instead of watching the whole tree, it watches the directories in [minimal_watch_set], to
+/// avoid running out of watches when most of the tree is ignored. When an ignore file is created
+/// or modified, its patterns are added to `ignore_rules` and the directories it whitelists are
+/// watched.
+pub fn make_scoped_watcher(
+    ignore_rules: IgnoreRules,
+) -> Result<(ScopedWatcher, Receiver<Option<PathEvent>>)> {
+    let (sender, receiver) = bounded(10000);
+    let (update_sender, update_receiver) = unbounded();
+    let root = ignore_rules.root.clone();
+    let watcher = Arc::new(Mutex::new(notify::recommended_watcher(PathEventHandler {
+        ignore_rules: ignore_rules.clone(),
+        sender: EventSender::new(sender),
+        metadata_cache: initial_metadata(&ignore_rules, &root),
+        watch_updates: Some(update_sender),
+    })?));
+    let watched = Arc::new(Mutex::new(HashMap::new()));
+    watch_new_dirs(&watcher, &watched, &ignore_rules, &root)?;
+
+    let weak_watcher = Arc::downgrade(&watcher);
+    let thread_watched = watched.clone();
+    // The sender is in the handler, so the loop ends when the watcher is dropped
+    std::thread::spawn(move || {
+        for update in update_receiver {
+            let Some(watcher) = weak_watcher.upgrade() else {
+                break;
+            };
+            let dir = match update {
+                WatchUpdate::Dir(dir) => dir,
+                WatchUpdate::IgnoreFile(path) => {
+                    let Some(dir) = path.parent() else {
+                        continue;
+                    };
+                    if let Err(e) = update_ignore_rules(dir, &ignore_rules) {
+                        warn!("{}", e);
+                        continue;
+                    }
+                    dir.to_path_buf()
+                }
+            };
+            if let Err(e) = watch_new_dirs(&watcher, &thread_watched, &ignore_rules, &dir) {
+                warn!("{}", e);
+            }
+        }
+    });
+
+    Ok((ScopedWatcher { watcher, watched }, receiver))
+}
+
+/// Watches the directories in [minimal_watch_set] of `dir` that are not in `watched` or under a
+/// directory watched recursively.
+fn watch_new_dirs(
+    watcher: &Mutex<RecommendedWatcher>,
+    watched: &Mutex<HashMap<PathBuf, RecursiveMode>>,
+    ignore_rules: &IgnoreRules,
+    dir: &Path,
+) -> Result<()> {
+    let mut watched = watched.lock().unwrap();
+    for (dir, mode) in watch_plan(ignore_rules, dir) {
+        let covered = dir
+            .ancestors()
+            .skip(1)
+            .any(|parent| watched.get(parent) == Some(&RecursiveMode::Recursive));
+        if !covered && !watched.contains_key(&dir) {
+            watcher.lock().unwrap().watch(&dir, mode)?;
+            watched.insert(dir, mode);
+        }
+    }
+    Ok(())
+}
+
+/// Converts the [PathEvent]s from `events` to [WalkMessage]s and sends them to `out`, until a
+/// `None` is received or the channel is closed.
+///
+/// Created and updated paths are sent with their current metadata, unless they're ignored by
+/// `ignore_rules` or no longer exist. A created directory is walked, as the files in it may be
+/// created before the directory is watched, and its files are sent instead. Updates of
+/// directories are skipped like the walkers skip directories. Removals are sent as
+/// [WalkMessage::Removed].
+pub fn events_to_walk_messages(
+    events: Receiver<Option<PathEvent>>,
+    ignore_rules: SharedIgnoreRules,
+    out: Sender<WalkMessage>,
+) -> Result<()> {
+    let is_ignored =
+        |path: &Path| read_shared_rules(&ignore_rules).check(path) == MatchResult::Ignore;
+
+    while let Ok(Some(event)) = events.recv() {
+        match event {
+            PathEvent::Create { path, .. } | PathEvent::Update { path, .. }
+                if is_ignored(&path) =>
+            {
+                debug!("FS Notification Ignored: {}", path.to_string_lossy());
+            }
+            PathEvent::Create { path, .. } => match path.symlink_metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    walk_created_dir(&path, &is_ignored, &out)?;
+                }
+                Ok(metadata) => out.send(WalkMessage::Path(PathMetadata::new(path, metadata)))?,
+                Err(e) => debug!("Error in metadata for {}: {}", path.to_string_lossy(), e),
+            },
+            PathEvent::Update { path, .. } => match path.symlink_metadata() {
+                Ok(metadata) if metadata.is_dir() => {}
+                Ok(metadata) => out.send(WalkMessage::Path(PathMetadata::new(path, metadata)))?,
+                Err(e) => debug!("Error in metadata for {}: {}", path.to_string_lossy(), e),
+            },
+            PathEvent::Delete { path } => {
+                if is_ignored(&path) {
+                    debug!("FS Notification Ignored: {}", path.to_string_lossy());
+                } else {
+                    out.send(WalkMessage::Removed { path })?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the files under `dir` that are not ignored to `out`.
+fn walk_created_dir(
+    dir: &Path,
+    is_ignored: &impl Fn(&Path) -> bool,
+    out: &Sender<WalkMessage>,
+) -> Result<()> {
+    let mut dir_stack = vec![dir.to_path_buf()];
+    while let Some(dir) = dir_stack.pop() {
+        let child_paths = match directory_list(&dir) {
+            Ok(child_paths) => child_paths,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        for child in child_paths {
+            match child {
+                Ok(pm) if is_ignored(&pm.path) => {
+                    debug!("FS Notification Ignored: {}", pm.path.to_string_lossy());
+                }
+                Ok(pm) if pm.metadata.is_dir() => dir_stack.push(pm.path),
+                Ok(pm) => out.send(WalkMessage::Path(pm))?,
+                Err(e) => warn!("{}", e),
+            }
+        }
+    }
+    Ok(())
+}
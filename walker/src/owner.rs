@@ -0,0 +1,62 @@
+//! Filters the walked paths by their owners.
+//!
+//! On shared file systems, walking into other users' directories produces permission errors and
+//! reads files the user may not want to touch. [WalkOptions::owner_filter](crate::WalkOptions)
+//! skips the paths owned by other users, and doesn't descend into their directories.
+//!
+//! The owners are checked only on Unix. On other platforms, the walks return
+//! [Error::UnsupportedWalkOption](crate::Error::UnsupportedWalkOption) if the filter is set.
+#[cfg(unix)]
+use std::fs::{self, Metadata};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(unix)]
+use std::sync::OnceLock;
+
+/// The owners whose paths the walkers report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerFilter {
+    /// Only the paths owned by the effective user of the process.
+    CurrentUserOnly,
+    /// Only the paths owned by the user with this id.
+    Uid(u32),
+}
+
+#[cfg(unix)]
+impl OwnerFilter {
+    /// Returns `true` if the path with `metadata` passes the filter.
+    pub fn allows(&self, metadata: &Metadata) -> bool {
+        self.allows_uid(metadata.uid())
+    }
+
+    /// Returns `true` if the paths owned by `uid` pass the filter.
+    ///
+    /// If the current user can't be determined, [OwnerFilter::CurrentUserOnly] allows all paths.
+    pub fn allows_uid(&self, uid: u32) -> bool {
+        match self {
+            OwnerFilter::CurrentUserOnly => current_uid().is_none_or(|current| current == uid),
+            OwnerFilter::Uid(allowed) => *allowed == uid,
+        }
+    }
+}
+
+/// Returns the effective user id of the process.
+#[cfg(unix)]
+///
+/// The id is read from the owner of `/proc/self` where available, and from the owner of a new
+/// temporary file otherwise.
+fn current_uid() -> Option<u32> {
+    static CURRENT_UID: OnceLock<Option<u32>> = OnceLock::new();
+    *CURRENT_UID.get_or_init(|| {
+        if let Ok(metadata) = fs::metadata("/proc/self") {
+            return Some(metadata.uid());
+        }
+        let probe = std::env::temp_dir().join(format!(".xvc-walker-uid-{}", std::process::id()));
+        let uid = fs::File::create(&probe)
+            .and_then(|file| file.metadata())
+            .map(|metadata| metadata.uid())
+            .ok();
+        let _ = fs::remove_file(&probe);
+        uid
+    })
+}
@@ -1,4 +1,6 @@
-use std::path::{Path, PathBuf};
+use std::path::{is_separator, Path, PathBuf};
+
+use crate::glob::GlobPattern;
 
 /// The result of matching a path against a set of ignore patterns.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,6 +41,14 @@ pub enum PatternEffect {
     Ignore,
     /// The matched path should be included (negated ignore).
     Whitelist,
+    /// The matched path is ignored or included regardless of the other patterns.
+    ///
+    /// These patterns are added with
+    /// [IgnoreRules::add_override_patterns](crate::IgnoreRules::add_override_patterns).
+    Override {
+        /// Whether the matched path is included instead of ignored.
+        whitelist: bool,
+    },
 }
 
 /// The origin of a pattern.
@@ -52,7 +62,34 @@ pub enum Source {
         path: PathBuf,
         /// The line number in the file where the pattern was found.
         line: usize,
+        /// The path the file resolves to, if it's a symlink.
+        ///
+        /// The target may be outside the ignore root, e.g., in a dotfiles directory. See
+        /// [WalkOptions::follow_ignore_file_symlinks](crate::WalkOptions::follow_ignore_file_symlinks).
+        resolved_from: Option<PathBuf>,
+    },
+    /// The pattern was read from a `.gitattributes` file with `export-ignore` attribute.
+    GitAttributes {
+        /// The path to the `.gitattributes` file.
+        path: PathBuf,
+        /// The line number in the file where the pattern was found.
+        line: usize,
     },
+    /// The pattern was read from an ignore file in a directory above the ignore root, and
+    /// translated to be relative to the ignore root.
+    Ancestor {
+        /// The absolute path to the file containing the pattern.
+        path: PathBuf,
+        /// The line number in the file where the pattern was found.
+        line: usize,
+    },
+    /// The pattern was read from an environment variable, like `XVC_IGNORE`.
+    Environment {
+        /// The name of the variable.
+        var_name: String,
+    },
+    /// The pattern is one of the [builtin_system_patterns].
+    Builtin,
     /// The pattern was provided via the command line.
     CommandLine {
         /// The current working directory when the command was invoked.
@@ -64,20 +101,25 @@ impl Source {
     /// Returns the directory path of the source, if applicable.
     pub fn dir_path(&self) -> Option<PathBuf> {
         match self {
-            Source::File { path, .. } => path.parent().map(Path::to_path_buf),
-            Source::Global => Some(PathBuf::from("")),
+            Source::File { path, .. } | Source::GitAttributes { path, .. } => {
+                path.parent().map(Path::to_path_buf)
+            }
+            Source::Global
+            | Source::Ancestor { .. }
+            | Source::Environment { .. }
+            | Source::Builtin => Some(PathBuf::from("")),
             Source::CommandLine { current_dir } => Some(current_dir.clone()),
         }
     }
 }
 
 /// Represents a single ignore pattern and its properties.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pattern {
-    /// The compiled glob pattern string.
-    pub glob: String,
-    /// The original, unmodified pattern string.
-    pub original: String,
+    /// The glob the pattern is compiled to, see [Pattern::effective_glob].
+    pub(crate) compiled_glob: GlobPattern,
+    /// The original, unmodified pattern string, see [Pattern::original_pattern].
+    pub(crate) original: String,
     /// The source of the pattern.
     pub source: Source,
     /// The effect of the pattern (ignore or whitelist).
@@ -86,6 +128,8 @@ pub struct Pattern {
     pub relativity: PatternRelativity,
     /// The kind of path this pattern applies to (file, directory, or any).
     pub path_kind: PathKind,
+    /// The extension if the pattern matches all paths with it anywhere, like `*.ext`.
+    pub extension: Option<String>,
 }
 
 impl Pattern {
@@ -93,8 +137,11 @@ impl Pattern {
     pub fn new(source: Source, original: &str) -> Self {
         let original_owned = original.to_owned();
         let mut current_dir = match &source {
-            Source::Global => "".to_string(),
-            Source::File { path, .. } => {
+            Source::Global
+            | Source::Ancestor { .. }
+            | Source::Environment { .. }
+            | Source::Builtin => "".to_string(),
+            Source::File { path, .. } | Source::GitAttributes { path, .. } => {
                 let parent = path.parent().unwrap_or_else(|| "".as_ref());
                 parent.to_string_lossy().to_string()
             }
@@ -114,9 +161,7 @@ impl Pattern {
             original.to_owned()
         };
 
-        if !line.ends_with("\\ ") {
-            line = line.trim_end().to_string();
-        }
+        line.truncate(trim_trailing_spaces(&line).len());
 
         let end_slash = line.ends_with('/');
         if end_slash {
@@ -170,15 +215,354 @@ impl Pattern {
             glob.push('/');
         }
 
+        let extension = glob
+            .strip_prefix("**/*.")
+            .filter(|ext| {
+                !ext.is_empty()
+                    && !ext.contains(['*', '?', '[', ']', '{', '}', '\\', '/', '!', '.'])
+            })
+            .map(|ext| ext.to_string());
+
         Pattern {
-            glob,
+            compiled_glob: GlobPattern::new(glob),
             original: original_owned,
             source,
             effect,
             relativity,
             path_kind,
+            extension,
+        }
+    }
+
+    /// Returns the glob the paths relative to the ignore root are matched with, e.g., `**/*.log`
+    /// for `*.log`.
+    ///
+    /// Directory patterns end with `/`, and the paths of the directories are matched with a
+    /// trailing `/`.
+    pub fn effective_glob(&self) -> &str {
+        self.compiled_glob.as_str()
+    }
+
+    /// Returns the glob of the pattern parsed to match many paths, see [Pattern::effective_glob].
+    pub fn compiled_glob(&self) -> &GlobPattern {
+        &self.compiled_glob
+    }
+
+    /// Returns the pattern as it's written in the ignore file, e.g., `!keep.log`.
+    pub fn original_pattern(&self) -> &str {
+        &self.original
+    }
+
+    /// Returns the error if [Pattern::original_pattern] can't be parsed, like a `[` without `]`.
+    ///
+    /// The location is taken from [Pattern::source]. Patterns are not checked when they are
+    /// created, [content_to_patterns](crate::content_to_patterns) checks the lines before.
+    pub fn is_valid_original(&self) -> Result<(), PatternError> {
+        let (source_file, line_number) = match &self.source {
+            Source::File { path, line, .. }
+            | Source::GitAttributes { path, line }
+            | Source::Ancestor { path, line } => (Some(path.as_path()), *line),
+            Source::Global
+            | Source::Environment { .. }
+            | Source::Builtin
+            | Source::CommandLine { .. } => (None, 0),
+        };
+        match PatternError::check(&self.original, source_file, line_number) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `false` if the pattern can't match any path under `dir_prefix`.
+    ///
+    /// `dir_prefix` is a directory relative to the ignore root. The check only compares the
+    /// literal prefix of the glob (up to the first wildcard) with the directory, so it may return
+    /// `true` for patterns that don't match anything there.
+    pub fn could_match_under(&self, dir_prefix: &str) -> bool {
+        match self.glob_literal_prefix() {
+            Some(literal) => literal_could_match_under(literal, dir_prefix),
+            None => true,
+        }
+    }
+
+    /// Case insensitive version of [Pattern::could_match_under].
+    pub fn could_match_under_case_insensitive(&self, dir_prefix: &str) -> bool {
+        match self.glob_literal_prefix() {
+            Some(literal) => {
+                literal_could_match_under(&literal.to_lowercase(), &dir_prefix.to_lowercase())
+            }
+            None => true,
+        }
+    }
+
+    /// Renders the pattern as a gitignore line, relative to the directory of its source.
+    ///
+    /// The line is built from the parsed pattern, not from [Pattern::original_pattern], so equivalent
+    /// patterns have the same line: the effect is written as a `!` prefix, the anchoring as a
+    /// leading `/` when the pattern has no other slash, and [PathKind::Directory] as a trailing
+    /// `/`. A leading `!` or `#` and trailing spaces are escaped with `\`. The escaped wildcards
+    /// like `\*` are kept as they are in the glob.
+    pub fn to_line(&self) -> String {
+        let (_, body) = self.split_glob();
+        let mut line = String::with_capacity(body.len() + 4);
+        if matches!(
+            self.effect,
+            PatternEffect::Whitelist | PatternEffect::Override { whitelist: true }
+        ) {
+            line.push('!');
+        }
+
+        let anchored = matches!(self.relativity, PatternRelativity::RelativeTo { .. });
+        if anchored && !body.contains('/') {
+            line.push('/');
+        } else if line.is_empty() && body.starts_with(['!', '#']) {
+            line.push('\\');
+        }
+
+        let trimmed = body.trim_end_matches(' ');
+        let mut trailing_spaces = body.len() - trimmed.len();
+        // The first space is already escaped after an odd number of backslashes
+        let backslashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+        if trailing_spaces > 0 && backslashes % 2 == 1 {
+            trailing_spaces -= 1;
+        }
+        line.push_str(&body[..body.len() - trailing_spaces]);
+        for _ in 0..trailing_spaces {
+            line.push_str("\\ ");
+        }
+
+        if self.path_kind == PathKind::Directory {
+            line.push('/');
         }
+        line
     }
+
+    /// Splits the glob into the directory the pattern is relative to, and the pattern in it.
+    ///
+    /// The trailing slash of the directory patterns is not included.
+    pub(crate) fn split_glob(&self) -> (&str, &str) {
+        let glob = self.compiled_glob.as_str();
+        let glob = match self.path_kind {
+            PathKind::Directory => glob.strip_suffix('/').unwrap_or(glob),
+            PathKind::Any => glob,
+        };
+        match &self.relativity {
+            PatternRelativity::RelativeTo { directory } if directory.is_empty() => ("", glob),
+            PatternRelativity::RelativeTo { directory } => glob
+                .strip_prefix(directory.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+                .map(|rest| (directory.as_str(), rest))
+                .unwrap_or(("", glob)),
+            // The pattern has no slash, so the last `/**/` separates it from the directory
+            PatternRelativity::Anywhere => match glob.strip_prefix("**/") {
+                Some(rest) => ("", rest),
+                None => glob
+                    .rfind("/**/")
+                    .map(|i| (&glob[..i], &glob[i + 4..]))
+                    .unwrap_or(("", glob)),
+            },
+        }
+    }
+
+    /// Returns the part of the glob before the first wildcard, or `None` if the glob is negated.
+    fn glob_literal_prefix(&self) -> Option<&str> {
+        // glob_match treats a leading `!` as negation
+        let glob = self.compiled_glob.as_str();
+        if glob.starts_with('!') {
+            return None;
+        }
+
+        let literal_len = glob.find(['*', '?', '[', '{', '\\']).unwrap_or(glob.len());
+        Some(&glob[..literal_len])
+    }
+}
+
+/// Removes the trailing spaces of a pattern line like git does.
+///
+/// A space escaped with `\` is kept and the spaces after it are removed, so `foo\  ` is `foo\ `.
+/// The spaces followed by other characters are part of the pattern. Only spaces are removed,
+/// tabs are kept.
+pub(crate) fn trim_trailing_spaces(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    let mut trailing_start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' => {
+                trailing_start.get_or_insert(i);
+            }
+            // The escaped character is not removed, even if it's a space
+            b'\\' => {
+                i += 1;
+                trailing_start = None;
+            }
+            _ => trailing_start = None,
+        }
+        i += 1;
+    }
+    &line[..trailing_start.unwrap_or(line.len())]
+}
+
+fn literal_could_match_under(literal: &str, dir_prefix: &str) -> bool {
+    let dir = dir_prefix.trim_end_matches(is_separator);
+
+    if dir.is_empty() {
+        return true;
+    }
+
+    // Paths under the directory start with `dir/`. The pattern can only match them if either
+    // of its literal part and `dir/` is a prefix of the other.
+    literal
+        .bytes()
+        .zip(dir.bytes().chain(std::iter::once(b'/')))
+        .all(|(l, d)| l == d || (l == b'/' && is_separator(d as char)))
+}
+
+/// The reason a line of an ignore file can't be parsed to a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternParseErrorKind {
+    /// A `[` without the closing `]`.
+    UnclosedBracket,
+    /// A line without a pattern after the leading `!` and the slashes, like `!` or `/`.
+    EmptyPattern,
+    /// A `\` at the end of the line, without a character to escape.
+    InvalidEscape,
+}
+
+impl std::fmt::Display for PatternParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternParseErrorKind::UnclosedBracket => write!(f, "Unclosed bracket"),
+            PatternParseErrorKind::EmptyPattern => write!(f, "Empty pattern"),
+            PatternParseErrorKind::InvalidEscape => write!(f, "Invalid escape at the end"),
+        }
+    }
+}
+
+/// A pattern that can't be parsed, with its location in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatternError {
+    /// The file the pattern is read from, if any.
+    pub source_file: Option<PathBuf>,
+    /// The line number in the source, starting from 1, or 0 if the source doesn't keep line
+    /// numbers.
+    pub line_number: usize,
+    /// The column of the error in the line, starting from 1, if it's at a specific character.
+    pub column: Option<usize>,
+    /// The line of the pattern.
+    pub original: String,
+    /// Why the pattern can't be parsed.
+    pub message: String,
+}
+
+impl PatternError {
+    /// Returns the error for `original` if it can't be parsed to a [Pattern].
+    ///
+    /// `original` is a line of an ignore file without the trailing whitespace, like the lines
+    /// [Pattern::new] receives.
+    pub fn check(
+        original: &str,
+        source_file: Option<&Path>,
+        line_number: usize,
+    ) -> Option<PatternError> {
+        check_pattern_syntax(original).map(|(kind, column)| PatternError {
+            source_file: source_file.map(Path::to_path_buf),
+            line_number,
+            column,
+            original: original.to_string(),
+            message: kind.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.source_file, self.line_number) {
+            (Some(source_file), 0) => write!(f, "{}: ", source_file.to_string_lossy())?,
+            (Some(source_file), line) => {
+                write!(f, "{}:{}", source_file.to_string_lossy(), line)?;
+                if let Some(column) = self.column {
+                    write!(f, ":{column}")?;
+                }
+                write!(f, ": ")?;
+            }
+            (None, 0) => {}
+            (None, line) => {
+                write!(f, "Line {line}")?;
+                if let Some(column) = self.column {
+                    write!(f, ", column {column}")?;
+                }
+                write!(f, ": ")?;
+            }
+        }
+        write!(f, "{}: {:?}", self.message, self.original)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Returns the reason `line` can't be parsed to a [Pattern] and the column of the error, or
+/// `None` if it's valid.
+///
+/// `line` is a line of an ignore file without the trailing whitespace, like the lines
+/// [Pattern::new] receives. Columns are counted in characters, starting from 1.
+pub fn check_pattern_syntax(line: &str) -> Option<(PatternParseErrorKind, Option<usize>)> {
+    let column = |i: usize| Some(line[..i].chars().count() + 1);
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if i + 1 == bytes.len() => {
+                return Some((PatternParseErrorKind::InvalidEscape, column(i)))
+            }
+            b'\\' => i += 1,
+            b'[' => {
+                let bracket = i;
+                i += 1;
+                if matches!(bytes.get(i), Some(b'!' | b'^')) {
+                    i += 1;
+                }
+                // `]` first in the class is literal
+                let class_start = i;
+                while i < bytes.len() && (i == class_start || bytes[i] != b']') {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if i >= bytes.len() {
+                    return Some((PatternParseErrorKind::UnclosedBracket, column(bracket)));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let pattern = line.strip_prefix('!').unwrap_or(line);
+    if pattern.trim_matches('/').is_empty() {
+        return Some((PatternParseErrorKind::EmptyPattern, None));
+    }
+    None
+}
+
+/// The files operating systems create in directories for their own metadata.
+///
+/// `Icon\r` is written as `Icon[\r]`, as the trailing whitespace of the patterns is removed.
+pub const BUILTIN_SYSTEM_PATTERNS: [&str; 4] =
+    [".DS_Store", "Thumbs.db", "desktop.ini", "Icon[\r]"];
+
+/// Returns the patterns to ignore the [BUILTIN_SYSTEM_PATTERNS], e.g., macOS's `.DS_Store`, with
+/// [Source::Builtin].
+///
+/// The walkers add these with the lowest precedence when
+/// [WalkOptions::skip_system_files](crate::WalkOptions::skip_system_files) is set, so the files
+/// can be whitelisted in the ignore files.
+pub fn builtin_system_patterns() -> Vec<Pattern> {
+    BUILTIN_SYSTEM_PATTERNS
+        .iter()
+        .map(|p| Pattern::new(Source::Builtin, p))
+        .collect()
 }
 
 /// Builds a list of `Pattern`s from a vector of strings.
@@ -187,4 +571,4 @@ pub fn build_pattern_list(patterns: Vec<String>, source: Source) -> Vec<Pattern>
         .iter()
         .map(|p| Pattern::new(source.clone(), p))
         .collect()
-}
\ No newline at end of file
+}
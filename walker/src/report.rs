@@ -0,0 +1,440 @@
+//! Newline delimited JSON dumps of walk results.
+//!
+//! [write_walk_json] writes a record per line for each walked path, without buffering the whole
+//! walk:
+//!
+//! ```json
+//! {"path": "dir/a.txt", "size": 3, "mtime": "2024-01-02T03:04:05.000000006Z", "is_dir": false, "error": null}
+//! ```
+//!
+//! Paths are relative to the walk root and use `/` as separator. `mtime` is `null` if the
+//! platform doesn't report modification times. Errors are written with an empty path and the
+//! error message in `error`. [read_walk_json] reads the records back as [WalkRecord]s.
+//!
+//! This module is enabled with the `report` feature.
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Error, PathMetadata, Result};
+
+/// A single line of a walk dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkRecord {
+    /// The path relative to the walk root with `/` separators. Empty for errors.
+    pub path: String,
+    /// The size of the path in bytes.
+    pub size: u64,
+    /// The modification time, if the platform supports it.
+    pub mtime: Option<SystemTime>,
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+    /// The error message if the walker reported an error instead of a path.
+    pub error: Option<String>,
+}
+
+impl WalkRecord {
+    /// Creates a record for `path_metadata` with the path relative to `root`.
+    ///
+    /// Paths outside `root` are kept as they are.
+    pub fn from_path_metadata(path_metadata: &PathMetadata, root: &Path) -> Self {
+        let path = path_metadata
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&path_metadata.path);
+        WalkRecord {
+            path: path.to_string_lossy().replace('\\', "/"),
+            size: path_metadata.metadata.len(),
+            mtime: path_metadata.metadata.modified().ok(),
+            is_dir: path_metadata.metadata.is_dir(),
+            error: None,
+        }
+    }
+
+    /// Creates a record for an error reported by the walker.
+    pub fn from_error(error: &Error) -> Self {
+        WalkRecord {
+            path: String::new(),
+            size: 0,
+            mtime: None,
+            is_dir: false,
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// Returns the record as a single line JSON object, without the newline.
+    pub fn to_json(&self) -> String {
+        let mtime = self
+            .mtime
+            .map(|mtime| format!("\"{}\"", format_mtime(mtime)))
+            .unwrap_or_else(|| "null".to_string());
+        let error = self
+            .error
+            .as_deref()
+            .map(json_string)
+            .unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"path\": {}, \"size\": {}, \"mtime\": {}, \"is_dir\": {}, \"error\": {}}}",
+            json_string(&self.path),
+            self.size,
+            mtime,
+            self.is_dir,
+            error
+        )
+    }
+
+    /// Parses a line written by [WalkRecord::to_json].
+    pub fn from_json(line: &str) -> std::result::Result<Self, String> {
+        let mut record = WalkRecord {
+            path: String::new(),
+            size: 0,
+            mtime: None,
+            is_dir: false,
+            error: None,
+        };
+        for (key, value) in JsonParser::new(line).parse_object()? {
+            match (key.as_str(), value) {
+                ("path", JsonValue::String(path)) => record.path = path,
+                ("size", JsonValue::Number(size)) => record.size = size,
+                ("mtime", JsonValue::String(mtime)) => record.mtime = Some(parse_mtime(&mtime)?),
+                ("mtime", JsonValue::Null) => record.mtime = None,
+                ("is_dir", JsonValue::Bool(is_dir)) => record.is_dir = is_dir,
+                ("error", JsonValue::String(error)) => record.error = Some(error),
+                ("error", JsonValue::Null) => record.error = None,
+                (key, value) => return Err(format!("Unexpected value for {key}: {value:?}")),
+            }
+        }
+        Ok(record)
+    }
+}
+
+/// Writes a JSON record per line to `w` for each of the `items`, with the paths relative to
+/// `root`.
+pub fn write_walk_json<W: Write>(
+    items: impl Iterator<Item = Result<PathMetadata>>,
+    root: &Path,
+    mut w: W,
+) -> Result<()> {
+    for item in items {
+        let record = match item {
+            Ok(path_metadata) => WalkRecord::from_path_metadata(&path_metadata, root),
+            Err(e) => WalkRecord::from_error(&e),
+        };
+        writeln!(w, "{}", record.to_json())?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Reads the records written by [write_walk_json]. Empty lines are skipped.
+///
+/// Returns [Error::InvalidWalkRecord] for the first line that can't be parsed.
+pub fn read_walk_json<R: BufRead>(r: R) -> Result<Vec<WalkRecord>> {
+    let mut records = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = WalkRecord::from_json(&line)
+            .map_err(|cause| Error::InvalidWalkRecord { line: i + 1, cause })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Formats `mtime` as an RFC 3339 timestamp in UTC with nanoseconds.
+fn format_mtime(mtime: SystemTime) -> String {
+    let (seconds, nanos) = match mtime.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    };
+    let days = seconds.div_euclid(86400);
+    let day_seconds = seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        day_seconds / 3600,
+        day_seconds % 3600 / 60,
+        day_seconds % 60,
+        nanos
+    )
+}
+
+/// Parses the timestamps written by [format_mtime].
+fn parse_mtime(s: &str) -> std::result::Result<SystemTime, String> {
+    let invalid = || format!("Invalid mtime: {s}");
+    let field = |range: std::ops::Range<usize>| -> std::result::Result<i64, String> {
+        s.get(range)
+            .and_then(|f| f.parse::<i64>().ok())
+            .ok_or_else(invalid)
+    };
+    let bytes = s.as_bytes();
+    if bytes.len() != 30
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'.'
+        || bytes[29] != b'Z'
+    {
+        return Err(invalid());
+    }
+    let days = days_from_civil(field(0..4)?, field(5..7)?, field(8..10)?);
+    let seconds = days * 86400 + field(11..13)? * 3600 + field(14..16)? * 60 + field(17..19)?;
+    let nanos = Duration::from_nanos(field(20..29)? as u64);
+    let mtime = if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    };
+    Ok(mtime + nanos)
+}
+
+/// Converts the days since 1970-01-01 to year, month and day in the proleptic Gregorian
+/// calendar.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a date in the proleptic Gregorian calendar to the days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Returns `s` as a quoted JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if u32::from(c) < 0x20 => quoted.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Debug)]
+enum JsonValue {
+    String(String),
+    Number(u64),
+    Bool(bool),
+    Null,
+}
+
+/// Parses the flat JSON objects of the records.
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(s: &'a str) -> Self {
+        JsonParser {
+            chars: s.chars().peekable(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> std::result::Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            other => Err(format!("Expected {expected:?}, found {other:?}")),
+        }
+    }
+
+    fn parse_object(&mut self) -> std::result::Result<Vec<(String, JsonValue)>, String> {
+        let mut fields = Vec::new();
+        self.expect('{')?;
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_none() {
+            loop {
+                self.skip_whitespace();
+                let key = self.parse_string()?;
+                self.expect(':')?;
+                fields.push((key, self.parse_value()?));
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some(',') => continue,
+                    Some('}') => break,
+                    other => return Err(format!("Expected ',' or '}}', found {other:?}")),
+                }
+            }
+        }
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(fields),
+            Some(c) => Err(format!("Unexpected {c:?} after the object")),
+        }
+    }
+
+    fn parse_value(&mut self) -> std::result::Result<JsonValue, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('"') => self.parse_string().map(JsonValue::String),
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+                    digits.push(c);
+                }
+                digits
+                    .parse()
+                    .map(JsonValue::Number)
+                    .map_err(|e| e.to_string())
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(char::is_ascii_alphabetic) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Ok(JsonValue::Bool(true)),
+                    "false" => Ok(JsonValue::Bool(false)),
+                    "null" => Ok(JsonValue::Null),
+                    _ => Err(format!("Unexpected value {word:?}")),
+                }
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> std::result::Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err("Unterminated string".to_string()),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let high = self.parse_hex4()?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex4()?;
+                            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                        } else {
+                            high
+                        };
+                        s.push(char::from_u32(code).ok_or("Invalid unicode escape")?);
+                    }
+                    other => return Err(format!("Invalid escape {other:?}")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> std::result::Result<u32, String> {
+        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&hex, 16).map_err(|_| format!("Invalid unicode escape {hex:?}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mtime_round_trip() {
+        for (seconds, expected) in [
+            (0_i64, "1970-01-01T00:00:00.000000000Z"),
+            (951_782_400, "2000-02-29T00:00:00.000000000Z"),
+            (1_704_164_645, "2024-01-02T03:04:05.000000000Z"),
+            (-86_400, "1969-12-31T00:00:00.000000000Z"),
+        ] {
+            let mtime = if seconds >= 0 {
+                UNIX_EPOCH + Duration::from_secs(seconds as u64)
+            } else {
+                UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+            };
+            assert_eq!(format_mtime(mtime), expected);
+            assert_eq!(parse_mtime(expected), Ok(mtime));
+        }
+
+        let mtime = UNIX_EPOCH - Duration::from_nanos(1);
+        assert_eq!(format_mtime(mtime), "1969-12-31T23:59:59.999999999Z");
+        assert_eq!(parse_mtime(&format_mtime(mtime)), Ok(mtime));
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let record = WalkRecord {
+            path: "dir/\"quoted\" \\ \u{1} é 😀".to_string(),
+            size: 42,
+            mtime: Some(UNIX_EPOCH + Duration::new(1_704_164_645, 6)),
+            is_dir: false,
+            error: None,
+        };
+        assert_eq!(WalkRecord::from_json(&record.to_json()), Ok(record));
+
+        let record = WalkRecord {
+            path: String::new(),
+            size: 0,
+            mtime: None,
+            is_dir: false,
+            error: Some("I/O Error:\nfailed".to_string()),
+        };
+        assert_eq!(WalkRecord::from_json(&record.to_json()), Ok(record));
+        assert_eq!(
+            WalkRecord::from_json(r#"{"path": "\ud83d\ude00"}"#).map(|r| r.path),
+            Ok("😀".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        for line in [
+            "",
+            "{",
+            r#"{"path": 1}"#,
+            r#"{"size": -1}"#,
+            r#"{"mtime": "yesterday"}"#,
+            r#"{"path": "a"} x"#,
+        ] {
+            assert!(WalkRecord::from_json(line).is_err(), "{line}");
+        }
+    }
+}
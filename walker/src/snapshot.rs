@@ -0,0 +1,192 @@
+//! Snapshots of ignore rules to compare them between runs.
+//!
+//! [IgnoreRules::snapshot_patterns](crate::IgnoreRules::snapshot_patterns) lists the patterns in
+//! an order that doesn't depend on the order ignore files are read, and [diff_pattern_sets]
+//! reports the rules added, removed or moved between two snapshots.
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::{Pattern, PatternEffect, Source};
+
+/// The parts of a [Pattern] to compare rule sets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatternSummary {
+    /// The glob the pattern is compiled to.
+    pub glob: String,
+    /// The pattern as written in its source.
+    pub original: String,
+    /// Whether the pattern ignores or whitelists the paths it matches.
+    pub effect: PatternEffect,
+    /// The file the pattern is read from, relative to the ignore root or absolute if it's outside
+    /// the ignore root. `None` for global, environment, builtin and command line patterns.
+    pub source_path: Option<PathBuf>,
+    /// The line of the pattern in `source_path`.
+    pub line: Option<usize>,
+}
+
+impl From<&Pattern> for PatternSummary {
+    fn from(pattern: &Pattern) -> Self {
+        let (source_path, line) = match &pattern.source {
+            Source::File { path, line, .. }
+            | Source::GitAttributes { path, line }
+            | Source::Ancestor { path, line } => (Some(path.clone()), Some(*line)),
+            Source::Global
+            | Source::Environment { .. }
+            | Source::Builtin
+            | Source::CommandLine { .. } => (None, None),
+        };
+
+        PatternSummary {
+            glob: pattern.compiled_glob.to_string(),
+            original: pattern.original.clone(),
+            effect: pattern.effect.clone(),
+            source_path,
+            line,
+        }
+    }
+}
+
+/// Sorts `summaries` in the order of precedence: global patterns first, then the patterns in the
+/// files above the root, and the files from the root to deeper directories, in line order.
+///
+/// The sort is stable, so patterns without a source file keep their order.
+pub(crate) fn sort_by_precedence(summaries: &mut [PatternSummary]) {
+    summaries.sort_by(|a, b| precedence_key(a).cmp(&precedence_key(b)));
+}
+
+/// Returns the key to sort the summaries in the order of precedence, see [sort_by_precedence].
+pub(crate) fn precedence_key(
+    s: &PatternSummary,
+) -> (Option<(bool, usize)>, Option<&PathBuf>, Option<usize>) {
+    let depth = s
+        .source_path
+        .as_ref()
+        .map(|p| (!p.is_absolute(), p.components().count()));
+    (depth, s.source_path.as_ref(), s.line)
+}
+
+/// A pattern whose precedence changed relative to the other patterns in both sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedPattern {
+    /// The pattern in the new set.
+    pub pattern: PatternSummary,
+    /// The index of the pattern in the old set.
+    pub old_index: usize,
+    /// The index of the pattern in the new set.
+    pub new_index: usize,
+}
+
+/// The differences between two pattern sets returned by [diff_pattern_sets].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatternSetDiff {
+    /// Patterns only in the new set.
+    pub added: Vec<PatternSummary>,
+    /// Patterns only in the old set.
+    pub removed: Vec<PatternSummary>,
+    /// Patterns in both sets with a different order relative to the others.
+    pub moved: Vec<MovedPattern>,
+}
+
+impl PatternSetDiff {
+    /// Returns `true` if the sets have the same patterns in the same order.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+type PatternKey<'a> = (Option<&'a PathBuf>, &'a str, usize);
+
+/// Keys the patterns by their source file and text. Repeated patterns in a file are numbered in
+/// order.
+fn pattern_keys(patterns: &[PatternSummary]) -> Vec<PatternKey<'_>> {
+    let mut counts = HashMap::<(Option<&PathBuf>, &str), usize>::new();
+    patterns
+        .iter()
+        .map(|p| {
+            let count = counts
+                .entry((p.source_path.as_ref(), p.original.as_str()))
+                .or_default();
+            *count += 1;
+            (p.source_path.as_ref(), p.original.as_str(), *count)
+        })
+        .collect()
+}
+
+/// Compares two snapshots from [IgnoreRules::snapshot_patterns](crate::IgnoreRules::snapshot_patterns).
+///
+/// Patterns are identified by their source file and original text, so a pattern edited in place
+/// or moved to another file is reported as removed and added. Line changes caused by adding or
+/// removing other patterns are not reported as moves: the patterns in both sets are compared by
+/// their order, and the fewest patterns that break the order are reported as moved.
+pub fn diff_pattern_sets(old: &[PatternSummary], new: &[PatternSummary]) -> PatternSetDiff {
+    let old_keys = pattern_keys(old);
+    let new_keys = pattern_keys(new);
+    let old_key_set: HashSet<&PatternKey> = old_keys.iter().collect();
+    let new_indices: HashMap<&PatternKey, usize> =
+        new_keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+
+    let removed = old
+        .iter()
+        .zip(&old_keys)
+        .filter(|(_, k)| !new_indices.contains_key(k))
+        .map(|(p, _)| p.clone())
+        .collect();
+    let added = new
+        .iter()
+        .zip(&new_keys)
+        .filter(|(_, k)| !old_key_set.contains(k))
+        .map(|(p, _)| p.clone())
+        .collect();
+
+    // (old_index, new_index) of the common patterns in the old order
+    let common: Vec<(usize, usize)> = old_keys
+        .iter()
+        .enumerate()
+        .filter_map(|(i, k)| new_indices.get(k).map(|j| (i, *j)))
+        .collect();
+    let in_order =
+        longest_increasing_subsequence(&common.iter().map(|(_, j)| *j).collect::<Vec<_>>());
+    let moved = common
+        .iter()
+        .zip(in_order)
+        .filter(|(_, in_order)| !in_order)
+        .map(|((old_index, new_index), _)| MovedPattern {
+            pattern: new[*new_index].clone(),
+            old_index: *old_index,
+            new_index: *new_index,
+        })
+        .collect();
+
+    PatternSetDiff {
+        added,
+        removed,
+        moved,
+    }
+}
+
+/// Returns whether each item is in a longest increasing subsequence of `items`.
+fn longest_increasing_subsequence(items: &[usize]) -> Vec<bool> {
+    // tails[k] is the index of the smallest last item of the increasing subsequences with length
+    // k + 1
+    let mut tails = Vec::<usize>::new();
+    let mut previous = vec![None; items.len()];
+    for (i, item) in items.iter().enumerate() {
+        let k = tails.partition_point(|t| items[*t] < *item);
+        if k > 0 {
+            previous[i] = Some(tails[k - 1]);
+        }
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut in_subsequence = vec![false; items.len()];
+    let mut current = tails.last().copied();
+    while let Some(i) = current {
+        in_subsequence[i] = true;
+        current = previous[i];
+    }
+    in_subsequence
+}
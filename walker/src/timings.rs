@@ -0,0 +1,127 @@
+//! Time spent in the phases of the walks, for performance diagnostics.
+//!
+//! The timings are collected only if
+//! [WalkOptions::collect_timings](crate::WalkOptions::collect_timings) is set. Each thread
+//! accumulates its timings in a thread-local [WalkTimings], and they are added to
+//! [WalkMetrics::timings](crate::WalkMetrics::timings) when the thread finishes.
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The time spent in each phase of a walk, summed over the threads.
+///
+/// As the threads run in parallel, the durations may add up to more than
+/// [WalkMetrics::elapsed](crate::WalkMetrics::elapsed).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalkTimings {
+    /// Opening the directories and reading their entries.
+    pub directory_read: Duration,
+    /// Reading the metadata of the entries.
+    pub metadata: Duration,
+    /// Checking the entries against the ignore rules.
+    pub ignore_checks: Duration,
+    /// Collecting the patterns for each directory, and reading the `.gitattributes` files if
+    /// [WalkOptions::read_gitattributes_export_ignore](crate::WalkOptions) is set.
+    pub rule_loading: Duration,
+    /// Passing the results to the consumer, including the time blocked on a full channel.
+    pub send_blocking: Duration,
+}
+
+impl WalkTimings {
+    /// Returns the sum of the durations of all phases.
+    pub fn total(&self) -> Duration {
+        self.directory_read
+            + self.metadata
+            + self.ignore_checks
+            + self.rule_loading
+            + self.send_blocking
+    }
+
+    /// Adds the durations in `other` to these.
+    pub(crate) fn merge(&mut self, other: &WalkTimings) {
+        self.directory_read += other.directory_read;
+        self.metadata += other.metadata;
+        self.ignore_checks += other.ignore_checks;
+        self.rule_loading += other.rule_loading;
+        self.send_blocking += other.send_blocking;
+    }
+
+    fn phase_mut(&mut self, phase: WalkPhase) -> &mut Duration {
+        match phase {
+            WalkPhase::DirectoryRead => &mut self.directory_read,
+            WalkPhase::Metadata => &mut self.metadata,
+            WalkPhase::IgnoreChecks => &mut self.ignore_checks,
+            WalkPhase::RuleLoading => &mut self.rule_loading,
+            WalkPhase::SendBlocking => &mut self.send_blocking,
+        }
+    }
+}
+
+impl fmt::Display for WalkTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, duration) in [
+            ("directory read", self.directory_read),
+            ("metadata", self.metadata),
+            ("ignore checks", self.ignore_checks),
+            ("rule loading", self.rule_loading),
+            ("send blocking", self.send_blocking),
+        ] {
+            writeln!(f, "{name:<16}{duration:>12.3?}")?;
+        }
+        write!(f, "{:<16}{:>12.3?}", "total", self.total())
+    }
+}
+
+/// The phases of a walk timed in [WalkTimings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WalkPhase {
+    DirectoryRead,
+    Metadata,
+    IgnoreChecks,
+    RuleLoading,
+    SendBlocking,
+}
+
+thread_local! {
+    /// The timings of the walk running in this thread, or `None` if they are not collected.
+    static THREAD_TIMINGS: Cell<Option<WalkTimings>> = const { Cell::new(None) };
+}
+
+/// Runs `f` and adds its duration to `phase` if the timings are collected in this thread.
+///
+/// Otherwise only `f` is run, without reading the clock.
+pub(crate) fn time_phase<T>(phase: WalkPhase, f: impl FnOnce() -> T) -> T {
+    if THREAD_TIMINGS.get().is_none() {
+        return f();
+    }
+    let start = Instant::now();
+    let res = f();
+    let elapsed = start.elapsed();
+    if let Some(mut timings) = THREAD_TIMINGS.get() {
+        *timings.phase_mut(phase) += elapsed;
+        THREAD_TIMINGS.set(Some(timings));
+    }
+    res
+}
+
+/// Collects the timings of the phases run in this thread until [TimingScope::finish].
+///
+/// The timings of an outer walk in the same thread, e.g., from a visitor, are kept apart and
+/// restored when the scope finishes.
+pub(crate) struct TimingScope {
+    previous: Option<WalkTimings>,
+}
+
+impl TimingScope {
+    /// Starts collecting the timings in this thread if `enabled` is set.
+    pub(crate) fn start(enabled: bool) -> Self {
+        TimingScope {
+            previous: THREAD_TIMINGS.replace(enabled.then(WalkTimings::default)),
+        }
+    }
+
+    /// Returns the timings collected in the scope, or zeros if they are not collected.
+    pub(crate) fn finish(self) -> WalkTimings {
+        THREAD_TIMINGS.replace(self.previous).unwrap_or_default()
+    }
+}
@@ -0,0 +1,84 @@
+//! Callbacks to consume the walk results without channels.
+//!
+//! [walk_parallel_visit](crate::walk_parallel_visit) and
+//! [walk_serial_visit](crate::walk_serial_visit) call a [WalkVisitor] for each path and error
+//! they find. The channel based walkers, like [walk_parallel](crate::walk_parallel), use a
+//! [ChannelVisitor].
+use crossbeam_channel::Sender;
+
+use crate::{Error, PathMetadata, Result};
+
+/// What the walker does after a [WalkVisitor] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkAction {
+    /// Continue the walk.
+    Continue,
+    /// Skip the remaining entries in the directory of the current entry. They are not visited nor
+    /// walked into, but the directories found before are walked.
+    SkipSiblings,
+    /// Stop the walk. The parallel walker may still call the visitor for the entries being
+    /// processed by the other threads.
+    Stop,
+}
+
+/// Receives the paths and errors found by a walk.
+///
+/// The parallel walker calls the methods from multiple threads, so the visitors for
+/// [walk_parallel_visit](crate::walk_parallel_visit) must be [Sync] and use interior mutability to
+/// keep state.
+pub trait WalkVisitor {
+    /// Called for each path that's not ignored.
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction;
+
+    /// Called for each error, e.g., a directory that can't be read.
+    fn on_error(&self, err: &Error) -> WalkAction;
+
+    /// Returns the number of results passed to the visitor but not processed yet, if it's known.
+    ///
+    /// The parallel walker slows down when this reaches
+    /// [WalkOptions::backpressure_threshold](crate::WalkOptions::backpressure_threshold).
+    fn pending(&self) -> Option<usize> {
+        None
+    }
+
+    /// Called by the walkers with each result.
+    ///
+    /// This calls [WalkVisitor::on_entry] or [WalkVisitor::on_error]. Visitors that keep the
+    /// results can override this to take them without cloning, like [ChannelVisitor].
+    fn visit(&self, path_res: Result<PathMetadata>) -> WalkAction {
+        match path_res {
+            Ok(entry) => self.on_entry(&entry),
+            Err(err) => self.on_error(&err),
+        }
+    }
+}
+
+/// Sends the walk results to a channel.
+///
+/// Stops the walk when the receiver is dropped.
+#[derive(Debug, Clone)]
+pub struct ChannelVisitor(pub Sender<Result<PathMetadata>>);
+
+impl WalkVisitor for ChannelVisitor {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.visit(Ok(entry.clone()))
+    }
+
+    /// Sends the error as [Error::AnyhowError] with the same message, as [Error] can't be cloned.
+    /// The walkers call [WalkVisitor::visit], which sends the error as it is.
+    fn on_error(&self, err: &Error) -> WalkAction {
+        self.visit(Err(anyhow::anyhow!("{}", err).into()))
+    }
+
+    /// Returns the number of results in the channel.
+    fn pending(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+
+    fn visit(&self, path_res: Result<PathMetadata>) -> WalkAction {
+        match self.0.send(path_res) {
+            Ok(()) => WalkAction::Continue,
+            Err(_) => WalkAction::Stop,
+        }
+    }
+}
@@ -0,0 +1,664 @@
+//! The parts of the directory walks shared by [walk_serial](crate::walk_serial) and
+//! [walk_parallel](crate::walk_parallel).
+//!
+//! Both walkers interpret [WalkOptions] with the functions here, so they skip, report and walk
+//! into the same paths. They differ only in how they schedule the directories in the [DirQueue]:
+//! [walk_serial](crate::walk_serial) lists them one by one in the calling thread, and
+//! [walk_parallel](crate::walk_parallel) in a thread pool.
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam::queue::SegQueue;
+use xvc_logging::{debug, warn};
+
+use crate::visitor::WalkAction;
+use crate::{
+    root_metadata, BrokenSymlinkHandling, DirectoryPatterns, EmitKinds, Error, ExtensionSummary,
+    IgnoreRules, IgnoredTarget, MatchResult, PathMetadata, PatternSummary, Result,
+    SpecialFileBehavior, TraversalOrder, UnusedIgnoreFiles, WalkOptions, NO_EXTENSION,
+    PRUNE_LOGS_PER_PATTERN, UNUSED_IGNORE_FILE_EXAMPLES,
+};
+
+/// Returns [Error::UnsupportedWalkOption] for the options that are set but can't be applied in
+/// this build.
+///
+/// These are [WalkOptions::unicode_normalize] without the `unicode-normalization` feature and
+/// [WalkOptions::owner_filter] on non-unix platforms.
+pub(crate) fn check_build_options(walk_options: &WalkOptions) -> Result<()> {
+    #[cfg(not(feature = "unicode-normalization"))]
+    if walk_options.unicode_normalize {
+        return Err(Error::UnsupportedWalkOption {
+            option: "unicode_normalize",
+            walker: "builds without the unicode-normalization feature",
+        });
+    }
+    #[cfg(not(unix))]
+    if walk_options.owner_filter.is_some() {
+        return Err(Error::UnsupportedWalkOption {
+            option: "owner_filter",
+            walker: "non-unix platforms",
+        });
+    }
+    #[cfg(all(feature = "unicode-normalization", unix))]
+    let _ = walk_options;
+    Ok(())
+}
+
+/// Returns `true` if `dir` is skipped as set in [WalkOptions::skip_unreadable_dirs] after
+/// listing it fails with `err`, and logs a warning for it.
+pub(crate) fn skips_unreadable_dir(dir: &Path, err: &Error, walk_options: &WalkOptions) -> bool {
+    let permission_denied = match err {
+        Error::AnyhowError { source } => source
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied),
+        _ => false,
+    };
+    if walk_options.skip_unreadable_dirs && permission_denied {
+        warn!("Skipping unreadable directory {:?}", dir);
+        return true;
+    }
+    false
+}
+
+/// What the walkers do with a child of a directory they list.
+#[derive(Debug)]
+pub(crate) enum ChildEntry {
+    /// Skipped with [WalkOptions::ignore_dot_git] or [WalkOptions::owner_filter].
+    Skipped,
+    /// Ignored by the ignore rules.
+    Ignored(PathMetadata),
+    /// A path that is not walked into, with the result to report if it's not skipped.
+    Path(Option<Result<PathMetadata>>),
+    /// A directory to walk into, with the metadata of the target for a followed symlink.
+    ///
+    /// It's reported before its children if `report` is set, see [reports_dirs].
+    Dir {
+        /// The directory to walk
+        pm: PathMetadata,
+        /// Whether to report the directory
+        report: bool,
+    },
+}
+
+/// Finds the paths in `paths`, the children of a directory, whose names differ only in case.
+///
+/// Returns the first path with each name as kept and each later path whose name is the same when
+/// lowercased as conflicting. Such paths can't be created side by side on case-insensitive file
+/// systems, like the defaults of macOS and Windows. The names that are not valid UTF-8 are
+/// compared as they are.
+pub fn find_case_collisions<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut names = CaseFoldedNames::default();
+    paths
+        .into_iter()
+        .filter_map(|path| names.add(path.as_ref()))
+        .collect()
+}
+
+/// The lowercased names of the children of a directory and their paths, to detect the children
+/// that collide for [WalkOptions::detect_case_collisions].
+#[derive(Debug, Default)]
+pub(crate) struct CaseFoldedNames(HashMap<OsString, PathBuf>);
+
+impl CaseFoldedNames {
+    /// Returns empty names if [WalkOptions::detect_case_collisions] is set.
+    pub(crate) fn new(walk_options: &WalkOptions) -> Option<Self> {
+        walk_options.detect_case_collisions.then(Self::default)
+    }
+
+    /// Adds `path`, and returns the path added before with the same lowercased name and `path`.
+    fn add(&mut self, path: &Path) -> Option<(PathBuf, PathBuf)> {
+        let name = path.file_name()?;
+        let folded = name
+            .to_str()
+            .map_or_else(|| name.to_os_string(), |name| name.to_lowercase().into());
+        match self.0.entry(folded) {
+            Entry::Occupied(kept) => Some((kept.get().clone(), path.to_path_buf())),
+            Entry::Vacant(vacant) => {
+                vacant.insert(path.to_path_buf());
+                None
+            }
+        }
+    }
+
+    /// Adds the path of `entry` if it's reported or walked into, and returns
+    /// [Error::CaseCollision] to report after it if it collides with a path added before.
+    pub(crate) fn check(names: &mut Option<Self>, entry: &ChildEntry) -> Option<Error> {
+        let path = match entry {
+            ChildEntry::Path(Some(Ok(pm))) | ChildEntry::Dir { pm, .. } => &pm.path,
+            _ => return None,
+        };
+        let (kept, conflicting) = names.as_mut()?.add(path)?;
+        Some(Error::CaseCollision { kept, conflicting })
+    }
+}
+
+/// Decides what to do with `pm`, a child of a directory being walked.
+///
+/// `check` returns the result of the ignore rules for the path. `ancestors` are the canonical paths
+/// of the directory being walked and the directories it's walked under, to detect circular
+/// symlinks.
+pub(crate) fn child_entry(
+    pm: PathMetadata,
+    walk_options: &WalkOptions,
+    check: impl FnOnce(&Path) -> MatchResult,
+    ancestors: Option<&CanonicalAncestors>,
+) -> ChildEntry {
+    if walk_options.ignore_dot_git && pm.path.file_name().is_some_and(|name| name == ".git") {
+        return ChildEntry::Skipped;
+    }
+    if skipped_by_owner(&pm, walk_options) {
+        debug!("Skipping {:?}: owned by another user", pm.path);
+        return ChildEntry::Skipped;
+    }
+    if check(&pm.path) == MatchResult::Ignore {
+        debug!("Ignored: {:?}", pm.path);
+        return ChildEntry::Ignored(pm);
+    }
+
+    let report = reports_dirs(walk_options);
+    if pm.metadata.is_dir() {
+        return ChildEntry::Dir { pm, report };
+    }
+    let symlinked_dir = if walk_options.follow_symlinks {
+        symlinked_dir(&pm)
+    } else {
+        None
+    };
+    match symlinked_dir {
+        Some((target, _)) if CanonicalAncestors::contains(ancestors, &target) => {
+            ChildEntry::Path(Some(Err(Error::CircularSymlink {
+                link: pm.path,
+                target,
+            })))
+        }
+        Some((_, metadata)) => ChildEntry::Dir {
+            pm: PathMetadata::new(pm.path, metadata),
+            report,
+        },
+        None => ChildEntry::Path(handle_non_dir(pm, walk_options)),
+    }
+}
+
+/// Logs the directories pruned by the ignore rules for [WalkOptions::log_prunes].
+///
+/// The directories are counted by the patterns that ignore them, and only the first
+/// [PRUNE_LOGS_PER_PATTERN] of each pattern are logged, so a pattern that ignores many
+/// directories, e.g., `node_modules/`, doesn't flood the logs.
+#[derive(Debug, Default)]
+pub(crate) struct PruneLog {
+    counts: Mutex<HashMap<PatternSummary, usize>>,
+}
+
+impl PruneLog {
+    /// Logs `pm` if it's a directory and the pattern that ignores it hasn't been logged
+    /// [PRUNE_LOGS_PER_PATTERN] times.
+    pub(crate) fn pruned(&self, ignore_rules: &IgnoreRules, pm: &PathMetadata) {
+        if !pm.metadata.is_dir() {
+            return;
+        }
+        let Some(target) = ignore_rules.verify_not_ignored([pm.path.as_path()]).pop() else {
+            return;
+        };
+        let count = {
+            let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+            let count = counts.entry(target.pattern.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        match count.cmp(&PRUNE_LOGS_PER_PATTERN) {
+            CmpOrdering::Less => warn!("{}", prune_message(&target)),
+            CmpOrdering::Equal => warn!(
+                "{} (not logging more directories ignored by this pattern)",
+                prune_message(&target)
+            ),
+            CmpOrdering::Greater => {}
+        }
+    }
+}
+
+/// Describes why the directory in `target` is not walked, e.g.,
+/// `Pruned "data/cache/": ignored by "cache/" at .gitignore:3`.
+fn prune_message(target: &IgnoredTarget) -> String {
+    let source = match (&target.pattern.source_path, target.pattern.line) {
+        (Some(path), Some(line)) => format!("at {}:{}", path.to_string_lossy(), line),
+        _ => "not read from a file".to_string(),
+    };
+    format!(
+        "Pruned \"{}/\": ignored by {:?} {}",
+        target.ignored_path.to_string_lossy(),
+        target.pattern.original,
+        source
+    )
+}
+
+/// Counts the ignore files the walkers find but don't read, for
+/// [WalkOptions::known_ignore_filenames].
+#[derive(Debug, Default)]
+pub(crate) struct UnusedIgnoreFileCounter {
+    found: Mutex<UnusedIgnoreFiles>,
+}
+
+impl UnusedIgnoreFileCounter {
+    /// Counts `pm` if it's not a directory and its name is one of
+    /// [WalkOptions::known_ignore_filenames] other than [WalkOptions::ignore_filename].
+    pub(crate) fn found(&self, pm: &PathMetadata, walk_options: &WalkOptions) {
+        if pm.metadata.is_dir() {
+            return;
+        }
+        let Some(name) = pm.path.file_name().and_then(|name| name.to_str()) else {
+            return;
+        };
+        if walk_options.ignore_filename.as_deref() == Some(name)
+            || !walk_options
+                .known_ignore_filenames
+                .iter()
+                .any(|known| known == name)
+        {
+            return;
+        }
+        let mut found = self.found.lock().unwrap_or_else(|e| e.into_inner());
+        found.count += 1;
+        if found.examples.len() < UNUSED_IGNORE_FILE_EXAMPLES {
+            found.examples.push(pm.path.clone());
+        }
+    }
+
+    /// Returns the files counted so far, with the examples sorted.
+    pub(crate) fn unused_ignore_files(&self) -> UnusedIgnoreFiles {
+        let mut found = self.found.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        found.examples.sort();
+        found
+    }
+}
+
+/// Counts the regular files visited by extension, for [WalkOptions::summarize_extensions].
+#[derive(Debug, Default)]
+pub(crate) struct ExtensionCounter {
+    case_insensitive: bool,
+    pub(crate) extensions: HashMap<OsString, ExtensionSummary>,
+}
+
+impl ExtensionCounter {
+    /// Returns a counter if `walk_options` summarizes the extensions. The extensions are
+    /// lowercased if `case_insensitive` is set, like the ignore rules match them.
+    pub(crate) fn new(walk_options: &WalkOptions, case_insensitive: bool) -> Option<Self> {
+        walk_options.summarize_extensions.then(|| ExtensionCounter {
+            case_insensitive,
+            extensions: HashMap::new(),
+        })
+    }
+
+    /// Passes `pm_res` to `visit` and counts it if it's a regular file that `visit` doesn't stop
+    /// at.
+    pub(crate) fn visit(
+        counter: &mut Option<Self>,
+        pm_res: Result<PathMetadata>,
+        visit: impl FnOnce(Result<PathMetadata>) -> WalkAction,
+    ) -> WalkAction {
+        let file = match (counter.as_ref(), &pm_res) {
+            (Some(counter), Ok(pm)) if pm.metadata.is_file() => {
+                Some((counter.extension(&pm.path), pm.metadata.len()))
+            }
+            _ => None,
+        };
+        let action = visit(pm_res);
+        if let (Some(counter), Some((extension, bytes))) = (counter.as_mut(), file) {
+            if action != WalkAction::Stop {
+                let summary = counter.extensions.entry(extension).or_default();
+                summary.count += 1;
+                summary.bytes += bytes;
+            }
+        }
+        action
+    }
+
+    /// Returns the key of `path` in [WalkMetrics::extensions](crate::WalkMetrics::extensions).
+    fn extension(&self, path: &Path) -> OsString {
+        match path.extension() {
+            Some(extension) if self.case_insensitive => match extension.to_str() {
+                Some(extension) => OsString::from(extension.to_lowercase()),
+                None => extension.to_os_string(),
+            },
+            Some(extension) => extension.to_os_string(),
+            None => OsString::from(NO_EXTENSION),
+        }
+    }
+}
+
+/// Applies `handling` to `pm` if it's a symlink with a missing target.
+///
+/// Returns `None` if the path should be skipped. Other paths are returned as they are.
+pub(crate) fn handle_broken_symlink(
+    pm: PathMetadata,
+    handling: BrokenSymlinkHandling,
+) -> Option<Result<PathMetadata>> {
+    if !pm.metadata.is_symlink() || fs::metadata(&pm.path).is_ok() {
+        return Some(Ok(pm));
+    }
+
+    match handling {
+        BrokenSymlinkHandling::Skip => None,
+        BrokenSymlinkHandling::EmitAsError => Some(Err(Error::BrokenSymlink { path: pm.path })),
+        BrokenSymlinkHandling::EmitAsEntry => Some(Ok(pm)),
+    }
+}
+
+/// Applies `behavior` to `pm` if it's a FIFO, a socket or a device file.
+///
+/// Returns `None` if the path should be skipped. Other paths are returned as they are.
+pub(crate) fn handle_special_file(
+    pm: PathMetadata,
+    behavior: SpecialFileBehavior,
+) -> Option<Result<PathMetadata>> {
+    if !pm.file_kind.is_special() {
+        return Some(Ok(pm));
+    }
+
+    match behavior {
+        SpecialFileBehavior::Skip => None,
+        SpecialFileBehavior::Report => Some(Ok(pm)),
+        SpecialFileBehavior::Error => Some(Err(Error::SpecialFile {
+            path: pm.path,
+            kind: pm.file_kind,
+        })),
+    }
+}
+
+/// Applies [WalkOptions::broken_symlink_handling], [WalkOptions::special_files] and
+/// [WalkOptions::emit] to a path that isn't walked as a directory.
+pub(crate) fn handle_non_dir(
+    pm: PathMetadata,
+    walk_options: &WalkOptions,
+) -> Option<Result<PathMetadata>> {
+    match handle_broken_symlink(pm, walk_options.broken_symlink_handling)? {
+        Ok(pm) => match handle_special_file(pm, walk_options.special_files)? {
+            Ok(_) if !walk_options.emit.contains(EmitKinds::FILES) => None,
+            pm_res => Some(pm_res),
+        },
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Returns `true` if the walkers report the directories, see [WalkOptions::emit].
+///
+/// A walk of only the directories reports them without [WalkOptions::parents_first].
+pub(crate) fn reports_dirs(walk_options: &WalkOptions) -> bool {
+    let emit = walk_options.emit;
+    emit.contains(EmitKinds::DIRS)
+        && (walk_options.parents_first || !emit.contains(EmitKinds::FILES))
+}
+
+/// Returns `true` if [WalkOptions::owner_filter] skips the path.
+#[cfg(unix)]
+pub(crate) fn skipped_by_owner(pm: &PathMetadata, walk_options: &WalkOptions) -> bool {
+    walk_options
+        .owner_filter
+        .is_some_and(|filter| !filter.allows(&pm.metadata))
+}
+
+/// Returns `true` if [WalkOptions::owner_filter] skips the path.
+#[cfg(not(unix))]
+pub(crate) fn skipped_by_owner(_pm: &PathMetadata, _walk_options: &WalkOptions) -> bool {
+    false
+}
+
+/// How the walkers handle the root path.
+// There is a single value per walk, boxing the path doesn't save anything.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum WalkRoot {
+    /// The root is a directory or a followed symlink to a directory. Its children are walked.
+    Directory(PathMetadata),
+    /// The root is reported as a single path, if it's not ignored. `None` if it's a broken symlink
+    /// or a special file that should be skipped.
+    Path(Option<Result<PathMetadata>>),
+}
+
+/// Decides whether to walk `root` as a directory or report it as a single path.
+///
+/// Files are handled like the other files in the walk: a symlink to a directory is walked only if
+/// [WalkOptions::follow_symlinks] is set, broken symlinks are handled with
+/// [WalkOptions::broken_symlink_handling] and special files with [WalkOptions::special_files].
+pub(crate) fn walk_root(root: &Path, walk_options: &WalkOptions) -> Result<WalkRoot> {
+    check_build_options(walk_options)?;
+    let pm = PathMetadata::new(root.to_path_buf(), root_metadata(root)?);
+
+    if pm.metadata.is_dir() || (walk_options.follow_symlinks && symlinked_dir(&pm).is_some()) {
+        Ok(WalkRoot::Directory(pm))
+    } else {
+        Ok(WalkRoot::Path(handle_non_dir(pm, walk_options)))
+    }
+}
+
+/// Returns the canonical path of `root` if it's a symlink to a directory that is walked with
+/// [WalkOptions::follow_symlinks], so the directory is listed with its real path and the walk
+/// can't enter it again through the symlink.
+///
+/// A warning is logged when the canonical path is returned. The other roots, including the ones
+/// with symlinks above them, are walked with the paths they are given, as [Path::canonicalize]
+/// would change the paths of all results, e.g., to `\\?\` paths on Windows.
+pub(crate) fn resolve_root_symlink(root: &Path, walk_options: &WalkOptions) -> Option<PathBuf> {
+    if !walk_options.follow_symlinks
+        || !fs::symlink_metadata(root).ok()?.is_symlink()
+        || !fs::metadata(root).ok()?.is_dir()
+    {
+        return None;
+    }
+    let canonical = root.canonicalize().ok()?;
+    warn!(
+        "Walking {:?} as {:?}, as the root is a symlink",
+        root, canonical
+    );
+    Some(canonical)
+}
+
+/// Returns the canonical target and its metadata if `pm` is a symlink to a directory.
+pub(crate) fn symlinked_dir(pm: &PathMetadata) -> Option<(PathBuf, Metadata)> {
+    if !pm.metadata.is_symlink() {
+        return None;
+    }
+
+    let metadata = fs::metadata(&pm.path).ok()?;
+    if !metadata.is_dir() {
+        return None;
+    }
+
+    let target = pm.path.canonicalize().ok()?;
+    Some((target, metadata))
+}
+
+/// The canonical paths of a directory being walked and the directories it's walked under, to
+/// detect circular symlinks with [WalkOptions::follow_symlinks].
+///
+/// The paths of the other directories walked so far are not kept, so a symlink to a directory that
+/// is not an ancestor is walked. It's skipped with [WalkOptions::skip_duplicate_dirs] if it's
+/// walked already.
+#[derive(Debug)]
+pub(crate) struct CanonicalAncestors {
+    path: PathBuf,
+    parent: Option<Arc<CanonicalAncestors>>,
+}
+
+impl CanonicalAncestors {
+    /// Returns the ancestors of the children of `dir`, which is walked under `parent`, if
+    /// [WalkOptions::follow_symlinks] is set.
+    pub(crate) fn of_dir(
+        parent: Option<Arc<CanonicalAncestors>>,
+        dir: &Path,
+        walk_options: &WalkOptions,
+    ) -> Result<Option<Arc<CanonicalAncestors>>> {
+        if !walk_options.follow_symlinks {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(CanonicalAncestors {
+            path: dir.canonicalize()?,
+            parent,
+        })))
+    }
+
+    /// Returns `true` if the canonical `target` is in `ancestors`.
+    pub(crate) fn contains(ancestors: Option<&CanonicalAncestors>, target: &Path) -> bool {
+        iter::successors(ancestors, |ancestor| ancestor.parent.as_deref())
+            .any(|ancestor| ancestor.path == target)
+    }
+}
+
+/// The directories visited during a walk, to skip the ones already walked via another path.
+#[derive(Debug, Default)]
+pub(crate) struct VisitedDirs {
+    /// The first path each directory is visited with, by its `(st_dev, st_ino)`.
+    #[cfg(unix)]
+    identities: HashMap<(u64, u64), PathBuf>,
+}
+
+impl VisitedDirs {
+    /// Marks the directory in `dir` as visited.
+    ///
+    /// Returns the path the directory is first visited with, if it's already visited via another
+    /// path and [WalkOptions::skip_duplicate_dirs] is set.
+    pub(crate) fn visit(
+        &mut self,
+        dir: &PathMetadata,
+        walk_options: &WalkOptions,
+    ) -> Result<Option<PathBuf>> {
+        #[cfg(unix)]
+        if walk_options.skip_duplicate_dirs {
+            use std::os::unix::fs::MetadataExt;
+            // Only a symlinked root has the metadata of the symlink, the followed symlinks under
+            // it have the metadata of their targets
+            let followed_metadata;
+            let metadata = if dir.metadata.is_symlink() {
+                followed_metadata = fs::metadata(&dir.path)?;
+                &followed_metadata
+            } else {
+                &dir.metadata
+            };
+            match self.identities.entry((metadata.dev(), metadata.ino())) {
+                Entry::Occupied(first_seen) => {
+                    return Ok(Some(first_seen.get().clone()));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(dir.path.clone());
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (dir, walk_options);
+
+        Ok(None)
+    }
+}
+
+/// A directory waiting to be listed by a walk.
+#[derive(Debug)]
+pub(crate) struct QueuedDir {
+    pub(crate) pm: PathMetadata,
+    /// The patterns of the parent directory, to collect the patterns of this one from.
+    /// `None` for the root.
+    pub(crate) parent_patterns: Option<Arc<DirectoryPatterns>>,
+    /// The canonical paths of the parent directory and its ancestors. `None` for the root or if
+    /// [WalkOptions::follow_symlinks] is not set.
+    pub(crate) ancestors: Option<Arc<CanonicalAncestors>>,
+    /// The number of components of the directory under the root.
+    pub(crate) depth: usize,
+    /// The order the directory is queued in, to list the directories at the same depth in order.
+    seq: usize,
+}
+
+impl PartialEq for QueuedDir {
+    fn eq(&self, other: &Self) -> bool {
+        (self.depth, self.seq) == (other.depth, other.seq)
+    }
+}
+
+impl Eq for QueuedDir {}
+
+impl PartialOrd for QueuedDir {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedDir {
+    /// The shallowest and the earliest queued directory is the greatest, to be popped first from
+    /// [BinaryHeap].
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (other.depth, other.seq).cmp(&(self.depth, self.seq))
+    }
+}
+
+/// The directories waiting to be listed, popped in a [TraversalOrder].
+///
+/// The queue is shared by the threads of [walk_parallel](crate::walk_parallel), and used by a
+/// single thread in [walk_serial](crate::walk_serial).
+#[derive(Debug)]
+pub(crate) enum DirQueue {
+    Stack(Mutex<Vec<QueuedDir>>),
+    /// Boxed, as [SegQueue] keeps its head and tail on separate cache lines.
+    Queue(Box<SegQueue<QueuedDir>>),
+    Heap {
+        heap: Mutex<BinaryHeap<QueuedDir>>,
+        next_seq: AtomicUsize,
+    },
+}
+
+impl DirQueue {
+    pub(crate) fn new(traversal_order: TraversalOrder) -> Self {
+        match traversal_order {
+            TraversalOrder::DepthFirst => DirQueue::Stack(Mutex::new(Vec::new())),
+            TraversalOrder::BreadthFirst => DirQueue::Queue(Box::new(SegQueue::new())),
+            TraversalOrder::ShallowestFirst => DirQueue::Heap {
+                heap: Mutex::new(BinaryHeap::new()),
+                next_seq: AtomicUsize::new(0),
+            },
+        }
+    }
+
+    pub(crate) fn push(
+        &self,
+        pm: PathMetadata,
+        parent_patterns: Option<Arc<DirectoryPatterns>>,
+        ancestors: Option<Arc<CanonicalAncestors>>,
+        depth: usize,
+    ) {
+        let mut queued = QueuedDir {
+            pm,
+            parent_patterns,
+            ancestors,
+            depth,
+            seq: 0,
+        };
+        match self {
+            DirQueue::Stack(stack) => stack.lock().unwrap().push(queued),
+            DirQueue::Queue(queue) => queue.push(queued),
+            DirQueue::Heap { heap, next_seq } => {
+                queued.seq = next_seq.fetch_add(1, Ordering::Relaxed);
+                heap.lock().unwrap().push(queued);
+            }
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<QueuedDir> {
+        match self {
+            DirQueue::Stack(stack) => stack.lock().unwrap().pop(),
+            DirQueue::Queue(queue) => queue.pop(),
+            DirQueue::Heap { heap, .. } => heap.lock().unwrap().pop(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            DirQueue::Stack(stack) => stack.lock().unwrap().is_empty(),
+            DirQueue::Queue(queue) => queue.is_empty(),
+            DirQueue::Heap { heap, .. } => heap.lock().unwrap().is_empty(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! File system access of the ignore rule builders.
+//!
+//! [build_ignore_patterns_result_with_fs](crate::build_ignore_patterns_result_with_fs) reads the
+//! directories and the ignore files through [WalkFs], so the ignore rules of a virtual tree, like
+//! the contents of an archive, can be built without writing it to disk. [OsFs] reads the file
+//! system, and with the `test-support` feature, [MemFs] keeps a tree in memory and can fail the
+//! operations on chosen paths.
+//!
+//! The walkers read the file system directly, as they return the [Metadata](std::fs::Metadata) of
+//! the paths.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use xvc_logging::warn;
+
+use crate::timings::{time_phase, WalkPhase};
+use crate::FileKind;
+
+/// The file system operations used to build the ignore rules.
+pub trait WalkFs: Sync {
+    /// Lists the paths in `dir` with their kinds, without following symlinks.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<(PathBuf, FileKind)>>;
+
+    /// Returns the kind of `path`, following symlinks, like [fs::metadata].
+    fn file_kind(&self, path: &Path) -> io::Result<FileKind>;
+
+    /// Returns the kind of `path` without following symlinks, like [fs::symlink_metadata].
+    fn symlink_file_kind(&self, path: &Path) -> io::Result<FileKind>;
+
+    /// Reads the file in `path` as UTF-8, like [fs::read_to_string].
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads the file system with [std::fs].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl WalkFs for OsFs {
+    /// The entries that can't be read are logged as warnings and skipped.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<(PathBuf, FileKind)>> {
+        let mut elements = time_phase(WalkPhase::DirectoryRead, || dir.read_dir())?;
+        let mut children = Vec::new();
+        while let Some(entry) = time_phase(WalkPhase::DirectoryRead, || elements.next()) {
+            match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))) {
+                Ok((path, file_type)) => children.push((path, FileKind::from(file_type))),
+                Err(e) => warn!("Error reading entry in dir {:?} {:?}", dir, e),
+            }
+        }
+        Ok(children)
+    }
+
+    fn file_kind(&self, path: &Path) -> io::Result<FileKind> {
+        fs::metadata(path).map(|md| FileKind::from(md.file_type()))
+    }
+
+    fn symlink_file_kind(&self, path: &Path) -> io::Result<FileKind> {
+        fs::symlink_metadata(path).map(|md| FileKind::from(md.file_type()))
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+#[cfg(feature = "test-support")]
+pub use mem_fs::MemFs;
+
+#[cfg(feature = "test-support")]
+mod mem_fs {
+    use std::collections::{BTreeMap, HashMap};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::WalkFs;
+    use crate::FileKind;
+
+    #[derive(Debug, Clone)]
+    enum MemEntry {
+        Dir,
+        File(String),
+    }
+
+    /// A tree of directories and files in memory.
+    ///
+    /// The parents of the paths added are added as directories. There are no symlinks, so
+    /// [WalkFs::file_kind] and [WalkFs::symlink_file_kind] return the same kind.
+    #[derive(Debug, Clone, Default)]
+    pub struct MemFs {
+        entries: BTreeMap<PathBuf, MemEntry>,
+        errors: HashMap<PathBuf, io::ErrorKind>,
+    }
+
+    impl MemFs {
+        /// Creates an empty tree.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Adds a file with `content` in `path`.
+        pub fn with_file(mut self, path: impl AsRef<Path>, content: &str) -> Self {
+            self.add(path.as_ref(), MemEntry::File(content.to_string()));
+            self
+        }
+
+        /// Adds an empty directory in `path`.
+        pub fn with_dir(mut self, path: impl AsRef<Path>) -> Self {
+            self.add(path.as_ref(), MemEntry::Dir);
+            self
+        }
+
+        /// Fails reading the file or listing the directory in `path` with an error of `kind`,
+        /// e.g., to test an unreadable ignore file.
+        ///
+        /// `path` is still listed in its parent directory. With [io::ErrorKind::NotFound], its
+        /// kind can't be read either, like a path removed after its parent is listed.
+        pub fn with_error(mut self, path: impl AsRef<Path>, kind: io::ErrorKind) -> Self {
+            self.errors.insert(path.as_ref().to_path_buf(), kind);
+            self
+        }
+
+        fn add(&mut self, path: &Path, entry: MemEntry) {
+            for ancestor in path.ancestors().skip(1) {
+                if ancestor.as_os_str().is_empty() {
+                    break;
+                }
+                self.entries
+                    .entry(ancestor.to_path_buf())
+                    .or_insert(MemEntry::Dir);
+            }
+            self.entries.insert(path.to_path_buf(), entry);
+        }
+
+        /// Returns the entry in `path` to read its kind.
+        fn entry(&self, path: &Path) -> io::Result<&MemEntry> {
+            match self.errors.get(path) {
+                Some(io::ErrorKind::NotFound) => Err(not_found(path)),
+                _ => self.entries.get(path).ok_or_else(|| not_found(path)),
+            }
+        }
+
+        /// Returns the entry in `path` to read its content.
+        fn read_entry(&self, path: &Path) -> io::Result<&MemEntry> {
+            match self.errors.get(path) {
+                Some(kind) => Err(io::Error::new(
+                    *kind,
+                    format!("{} fails in MemFs", path.display()),
+                )),
+                None => self.entry(path),
+            }
+        }
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not in MemFs", path.display()),
+        )
+    }
+
+    fn kind(entry: &MemEntry) -> FileKind {
+        match entry {
+            MemEntry::Dir => FileKind::Dir,
+            MemEntry::File(_) => FileKind::Regular,
+        }
+    }
+
+    impl WalkFs for MemFs {
+        fn read_dir(&self, dir: &Path) -> io::Result<Vec<(PathBuf, FileKind)>> {
+            match self.read_entry(dir)? {
+                MemEntry::Dir => Ok(self
+                    .entries
+                    .range(dir.to_path_buf()..)
+                    .skip(1)
+                    .take_while(|(path, _)| path.starts_with(dir))
+                    .filter(|(path, _)| path.parent() == Some(dir))
+                    .map(|(path, entry)| (path.clone(), kind(entry)))
+                    .collect()),
+                MemEntry::File(_) => Err(io::Error::other(format!(
+                    "{} is not a directory",
+                    dir.display()
+                ))),
+            }
+        }
+
+        fn file_kind(&self, path: &Path) -> io::Result<FileKind> {
+            self.entry(path).map(kind)
+        }
+
+        fn symlink_file_kind(&self, path: &Path) -> io::Result<FileKind> {
+            self.entry(path).map(kind)
+        }
+
+        fn read_to_string(&self, path: &Path) -> io::Result<String> {
+            match self.read_entry(path)? {
+                MemEntry::File(content) => Ok(content.clone()),
+                MemEntry::Dir => Err(io::Error::other(format!(
+                    "{} is a directory",
+                    path.display()
+                ))),
+            }
+        }
+    }
+}
@@ -1,56 +1,643 @@
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use crossbeam::queue::SegQueue;
 use crossbeam_channel::Sender;
+use xvc_logging::warn;
 
+use crate::gitattributes::update_export_ignore_rules;
+use crate::ignore_rules::read_shared_rules;
+use crate::interner::{InternedPathMetadata, PathInterner};
+use crate::timings::{time_phase, TimingScope, WalkPhase, WalkTimings};
+use crate::visitor::{ChannelVisitor, WalkAction, WalkVisitor};
+use crate::walk_core::{
+    child_entry, resolve_root_symlink, skips_unreadable_dir, walk_root, CanonicalAncestors,
+    CaseFoldedNames, ChildEntry, DirQueue, ExtensionCounter, PruneLog, QueuedDir,
+    UnusedIgnoreFileCounter, VisitedDirs, WalkRoot,
+};
 use crate::{
-    directory_list, MatchResult, PathMetadata, Result, SharedIgnoreRules,
-    WalkOptions, MAX_THREADS_PARALLEL_WALK,
+    default_num_threads, directory_list, DirectoryPatterns, Error, IgnoreCheck, IgnoreRules,
+    MatchResult, PathMetadata, Pattern, Result, SharedIgnoreRules, WalkOptions,
 };
 
+/// How long the threads waiting for the consumer sleep between the checks of its pending results.
+const BACKPRESSURE_PARK_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Summary statistics of a walk, returned by [walk_parallel_collect] and
+/// [walk_serial_visit](crate::walk_serial_visit).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkMetrics {
+    /// The number of paths other than directories sent, e.g., files and symlinks.
+    pub files_visited: usize,
+    /// The number of directories listed, including the root.
+    pub directories_visited: usize,
+    /// The number of files and directories skipped by the ignore rules.
+    pub entries_ignored: usize,
+    /// The number of errors sent.
+    pub errors_encountered: usize,
+    /// The time from the start to the end of the walk.
+    pub elapsed: Duration,
+    /// The time spent in each phase of the walk, if
+    /// [WalkOptions::collect_timings](crate::WalkOptions::collect_timings) is set.
+    pub timings: WalkTimings,
+    /// The ignore files found but not read, see
+    /// [WalkOptions::known_ignore_filenames](crate::WalkOptions::known_ignore_filenames).
+    pub unused_ignore_files: UnusedIgnoreFiles,
+    /// The number and the size of the regular files sent, by their extensions, if
+    /// [WalkOptions::summarize_extensions](crate::WalkOptions::summarize_extensions) is set. The
+    /// files without an extension are counted in [NO_EXTENSION].
+    pub extensions: HashMap<OsString, ExtensionSummary>,
+}
+
+/// What a parallel walk reported, returned by [walk_parallel] after the paths are sent.
+///
+/// The paths the receiver got are all the paths in the tree only if [WalkSummary::is_complete].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalkSummary {
+    /// The counts of the walk, like the ones returned by [walk_parallel_collect].
+    pub metrics: WalkMetrics,
+    /// Whether the walk was stopped before all directories were listed.
+    ///
+    /// Dropping the receiver stops the walk with [Error::DeliveryIncomplete] instead, so this
+    /// is set only by the walks that can be cancelled otherwise.
+    pub was_cancelled: bool,
+}
+
+impl WalkSummary {
+    fn new(metrics: WalkMetrics, was_cancelled: bool) -> Self {
+        WalkSummary {
+            metrics,
+            was_cancelled,
+        }
+    }
+
+    /// Returns `true` if the walk wasn't cancelled and no errors were sent.
+    pub fn is_complete(&self) -> bool {
+        !self.was_cancelled && self.metrics.errors_encountered == 0
+    }
+}
+
+/// The ignore files a parallel walk found in the tree but didn't read, because their names are
+/// not [WalkOptions::ignore_filename](crate::WalkOptions::ignore_filename).
+///
+/// The files in the ignored directories are not found, as the walk doesn't list them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnusedIgnoreFiles {
+    /// The number of files found.
+    pub count: usize,
+    /// The paths of the first [UNUSED_IGNORE_FILE_EXAMPLES](crate::UNUSED_IGNORE_FILE_EXAMPLES)
+    /// files found, sorted.
+    pub examples: Vec<PathBuf>,
+}
+
+/// The key of the files without an extension in [WalkMetrics::extensions].
+///
+/// The files with a name ending in a dot, like `notes.`, are counted with them.
+pub const NO_EXTENSION: &str = "";
+
+/// The number and the total size of the regular files with an extension, see
+/// [WalkMetrics::extensions].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtensionSummary {
+    /// The number of files.
+    pub count: usize,
+    /// The sum of the sizes of the files in bytes.
+    pub bytes: u64,
+}
+
+impl ExtensionSummary {
+    pub(crate) fn merge(&mut self, other: &ExtensionSummary) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
+
+/// A snapshot of the thread pool of a parallel walk, see [walk_parallel_with_stats].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalkPoolStats {
+    /// The number of threads taking directories from the queue.
+    pub active_threads: usize,
+    /// The number of directories waiting in the queue to be listed.
+    pub queued_dirs: usize,
+    /// The number of directories listed, including the root.
+    pub completed_dirs: usize,
+}
+
+/// The handle of a parallel walk running in a background thread, see [walk_parallel_controlled]
+/// and [walk_parallel_with_stats].
+#[derive(Debug)]
+pub struct WalkHandle {
+    thread: JoinHandle<Result<WalkMetrics>>,
+    state: Arc<WalkState>,
+}
+
+/// Whether a parallel walk running in a background thread is paused, see [WalkHandle::state].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkHandleState {
+    /// The threads are listing the directories.
+    Running,
+    /// The threads stop before listing the next directory, see [WalkHandle::pause].
+    Paused,
+    /// The walk is stopped with [WalkHandle::abort]. The threads may still be finishing the
+    /// directories they are listing.
+    Aborted,
+    /// The walk has finished and its metrics can be taken with [WalkHandle::join].
+    Finished,
+}
+
+impl WalkHandle {
+    /// Returns true if the walk has finished.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the walk to finish and returns its metrics, see [walk_parallel_collect].
+    pub fn join(self) -> Result<WalkMetrics> {
+        self.thread.join().expect("Error in walk_parallel thread")
+    }
+
+    /// Pauses the walk.
+    ///
+    /// Each thread finishes the directory it's listing, and waits for [WalkHandle::resume] before
+    /// listing the next one. The paths in the directories being listed are still sent.
+    pub fn pause(&self) {
+        self.state.control.set_paused(true);
+    }
+
+    /// Resumes the walk paused with [WalkHandle::pause].
+    pub fn resume(&self) {
+        self.state.control.set_paused(false);
+    }
+
+    /// Stops the walk, also if it's paused.
+    ///
+    /// The threads stop listing new directories, and [WalkHandle::join] returns the metrics until
+    /// then. The paths in the directories being listed may still be sent.
+    pub fn abort(&self) {
+        self.state.control.abort();
+    }
+
+    /// Returns whether the walk is running, paused, aborted or finished.
+    pub fn state(&self) -> WalkHandleState {
+        if self.state.control.aborted.load(Ordering::Relaxed) {
+            WalkHandleState::Aborted
+        } else if self.is_finished() {
+            WalkHandleState::Finished
+        } else if self.state.control.is_paused() {
+            WalkHandleState::Paused
+        } else {
+            WalkHandleState::Running
+        }
+    }
+
+    /// Returns the metrics of the walk so far, like [WalkHandle::join] returns at the end.
+    pub fn progress(&self) -> WalkMetrics {
+        self.state.metrics()
+    }
+}
+
+/// Pauses and aborts the threads of a parallel walk, see [WalkHandle].
+#[derive(Debug, Default)]
+struct WalkControl {
+    /// Whether the threads wait before listing the next directory.
+    paused: Mutex<bool>,
+    /// Notified when the walk is resumed or aborted.
+    resumed: Condvar,
+    /// Whether the walk is aborted, passed to the walk as its cancellation flag.
+    aborted: AtomicBool,
+}
+
+impl WalkControl {
+    fn set_paused(&self, paused: bool) {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = paused;
+        self.resumed.notify_all();
+    }
+
+    fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn abort(&self) {
+        self.aborted.store(true, Ordering::Relaxed);
+        // Locked, so a thread that hasn't seen the flag is waiting when notified
+        let _paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        self.resumed.notify_all();
+    }
+
+    /// Blocks the thread while the walk is paused, until it's resumed or `cancelled` is set.
+    ///
+    /// `cancelled` must be [WalkControl::aborted] to be woken up by [WalkControl::abort]. Other
+    /// flags are checked when the walk is resumed.
+    fn wait_while_paused(&self, cancelled: &AtomicBool) {
+        let paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        let _resumed = self
+            .resumed
+            .wait_while(paused, |paused| {
+                *paused && !cancelled.load(Ordering::Relaxed)
+            })
+            .unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+/// The directories of a parallel walk whose paths are not all visited, to walk them again after
+/// the receiver is dropped.
+///
+/// A directory is added when it's queued and removed after all its paths are passed to the
+/// visitor, so the directories queued but not listed yet are in it too.
+#[derive(Debug, Default)]
+struct UndeliveredDirs(Mutex<HashSet<PathBuf>>);
+
+impl UndeliveredDirs {
+    fn add(&self, dir: &Path) {
+        self.0.lock().unwrap().insert(dir.to_path_buf());
+    }
+
+    fn remove(&self, dir: &Path) {
+        self.0.lock().unwrap().remove(dir);
+    }
+
+    /// Returns the directories, sorted, without the ones under the others, as walking a directory
+    /// walks them again.
+    fn roots(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self.0.lock().unwrap().iter().cloned().collect();
+        dirs.sort();
+        let mut roots: Vec<PathBuf> = Vec::new();
+        for dir in dirs {
+            if !roots.last().is_some_and(|root| dir.starts_with(root)) {
+                roots.push(dir);
+            }
+        }
+        roots
+    }
+}
+
+/// The state shared by the threads of a parallel walk.
+#[derive(Debug)]
+struct WalkState {
+    /// The directories traversed, to skip the directories already walked via another path.
+    visited_dirs: Mutex<VisitedDirs>,
+    /// The number of paths and errors visited.
+    paths_sent: AtomicUsize,
+    /// The number of directories listed.
+    dirs_scanned: AtomicUsize,
+    /// The number of paths other than directories visited.
+    files_sent: AtomicUsize,
+    /// The number of errors visited.
+    errors_sent: AtomicUsize,
+    /// The number of paths ignored.
+    paths_ignored: AtomicUsize,
+    /// Whether the visitor returned [WalkAction::Stop].
+    stopped: AtomicBool,
+    /// The number of threads walking the directories in the queue.
+    active_threads: AtomicUsize,
+    /// The number of threads waiting for the consumer, see [WalkState::wait_for_consumer].
+    parked_threads: AtomicUsize,
+    /// When the walk started.
+    start: Instant,
+    /// The thread pool statistics to update as the walk progresses, if they are inspected.
+    pool_stats: Option<Arc<Mutex<WalkPoolStats>>>,
+    /// The timings of the threads that finished.
+    timings: Mutex<WalkTimings>,
+    /// The directories ignored by each pattern, for [WalkOptions::log_prunes].
+    prune_log: PruneLog,
+    /// The ignore files found, for [WalkOptions::known_ignore_filenames].
+    unused_ignore_files: UnusedIgnoreFileCounter,
+    /// The files sent by extension, for [WalkOptions::summarize_extensions].
+    extensions: Mutex<HashMap<OsString, ExtensionSummary>>,
+    /// The directories queued or listed whose paths are not all visited yet.
+    undelivered_dirs: UndeliveredDirs,
+    /// Pauses and aborts the walk from its [WalkHandle].
+    control: WalkControl,
+}
+
+impl WalkState {
+    fn new() -> Self {
+        WalkState {
+            visited_dirs: Mutex::new(VisitedDirs::default()),
+            paths_sent: AtomicUsize::new(0),
+            dirs_scanned: AtomicUsize::new(0),
+            files_sent: AtomicUsize::new(0),
+            errors_sent: AtomicUsize::new(0),
+            paths_ignored: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+            active_threads: AtomicUsize::new(0),
+            parked_threads: AtomicUsize::new(0),
+            start: Instant::now(),
+            pool_stats: None,
+            timings: Mutex::new(WalkTimings::default()),
+            prune_log: PruneLog::default(),
+            unused_ignore_files: UnusedIgnoreFileCounter::default(),
+            extensions: Mutex::new(HashMap::new()),
+            undelivered_dirs: UndeliveredDirs::default(),
+            control: WalkControl::default(),
+        }
+    }
+
+    fn with_pool_stats(pool_stats: Arc<Mutex<WalkPoolStats>>) -> Self {
+        WalkState {
+            pool_stats: Some(pool_stats),
+            ..WalkState::new()
+        }
+    }
+
+    /// Applies `update` to the thread pool statistics, if they are inspected.
+    fn update_pool_stats(&self, update: impl FnOnce(&mut WalkPoolStats)) {
+        if let Some(pool_stats) = &self.pool_stats {
+            update(&mut pool_stats.lock().unwrap());
+        }
+    }
+
+    /// Passes `path_res` to `visitor` unless the walk is already stopped.
+    ///
+    /// The paths the visitor stops at are not counted.
+    fn visit(&self, visitor: &impl WalkVisitor, path_res: Result<PathMetadata>) -> WalkAction {
+        if self.is_stopped() {
+            return WalkAction::Stop;
+        }
+        let counter = match &path_res {
+            Ok(pm) if pm.metadata.is_dir() => None,
+            Ok(_) => Some(&self.files_sent),
+            Err(_) => Some(&self.errors_sent),
+        };
+        let action = time_phase(WalkPhase::SendBlocking, || visitor.visit(path_res));
+        if action == WalkAction::Stop {
+            self.stopped.store(true, Ordering::Relaxed);
+        } else {
+            self.paths_sent.fetch_add(1, Ordering::Relaxed);
+            if let Some(counter) = counter {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        action
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Passes the error listing `dir` to `visitor`, and removes `dir` from the undelivered
+    /// directories if it's passed.
+    fn visit_dir_error(&self, visitor: &impl WalkVisitor, dir: &Path, err: Error) {
+        if self.visit(visitor, Err(err)) != WalkAction::Stop {
+            self.undelivered_dirs.remove(dir);
+        }
+    }
+
+    /// Returns [Error::DeliveryIncomplete] if the visitor stopped the walk, as the channel visitor
+    /// stops only when the receiver is dropped.
+    fn delivery_result(&self) -> Result<()> {
+        if self.is_stopped() {
+            Err(Error::DeliveryIncomplete {
+                paths_sent: self.paths_sent.load(Ordering::Relaxed),
+                dirs_scanned: self.dirs_scanned.load(Ordering::Relaxed),
+                undelivered_dirs: self.undelivered_dirs.roots(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parks the thread while `visitor` has `threshold` or more pending results, until they drop
+    /// below half of the threshold.
+    ///
+    /// One of the active threads never parks, so the walk continues, and a dropped receiver is
+    /// detected, even if the pending results don't decrease.
+    fn wait_for_consumer(
+        &self,
+        visitor: &impl WalkVisitor,
+        threshold: usize,
+        cancelled: &AtomicBool,
+    ) {
+        let pending_at_least = |limit| visitor.pending().is_some_and(|pending| pending >= limit);
+        if !pending_at_least(threshold) {
+            return;
+        }
+        if self.parked_threads.fetch_add(1, Ordering::Relaxed) + 1
+            >= self.active_threads.load(Ordering::Relaxed)
+        {
+            self.parked_threads.fetch_sub(1, Ordering::Relaxed);
+            return;
+        }
+
+        let resume_limit = (threshold / 2).max(1);
+        while pending_at_least(resume_limit)
+            && !cancelled.load(Ordering::Relaxed)
+            && !self.is_stopped()
+            && self.parked_threads.load(Ordering::Relaxed)
+                < self.active_threads.load(Ordering::Relaxed)
+        {
+            std::thread::park_timeout(BACKPRESSURE_PARK_TIMEOUT);
+        }
+        self.parked_threads.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Adds the files counted by `counter`, e.g., in a directory.
+    fn add_extensions(&self, counter: Option<ExtensionCounter>) {
+        let Some(counter) = counter.filter(|c| !c.extensions.is_empty()) else {
+            return;
+        };
+        let mut all = self.extensions.lock().unwrap();
+        for (extension, summary) in counter.extensions {
+            all.entry(extension).or_default().merge(&summary);
+        }
+    }
+
+    /// Adds the timings of a thread that finished walking.
+    fn add_timings(&self, timings: WalkTimings) {
+        self.timings.lock().unwrap().merge(&timings);
+    }
+
+    /// Returns the metrics of the walk so far.
+    fn metrics(&self) -> WalkMetrics {
+        WalkMetrics {
+            files_visited: self.files_sent.load(Ordering::Relaxed),
+            directories_visited: self.dirs_scanned.load(Ordering::Relaxed),
+            entries_ignored: self.paths_ignored.load(Ordering::Relaxed),
+            errors_encountered: self.errors_sent.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+            timings: *self.timings.lock().unwrap(),
+            unused_ignore_files: self.unused_ignore_files.unused_ignore_files(),
+            extensions: self.extensions.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// The child directories of a listed directory, with the patterns and the canonical ancestors to
+/// walk them with.
+type ListedDir = (
+    Vec<PathMetadata>,
+    Arc<DirectoryPatterns>,
+    Option<Arc<CanonicalAncestors>>,
+);
+
+/// Lists the directory in `dir_pm` and passes the files that are not ignored to `visitor`.
+///
+/// Returns the child directories to walk with the patterns that can match under it and its
+/// canonical ancestors, see [ListedDir]. The patterns are computed from `parent_patterns` if
+/// given, and the ancestors from `parent_ancestors`. If [WalkOptions::parents_first] is set, the child
+/// directories are visited before they are returned to be queued, so their children are visited
+/// after them.
+///
+/// Traversed directories are kept in `state` to skip the directories already walked via another
+/// path. Nothing is visited after the walk is stopped.
+///
+/// If `span_root` is given, the directory is listed in a tracing span, see [list_directory]. If
+/// `ignore_check` is given, the children are checked with it instead of `ignore_rules`.
+#[allow(clippy::too_many_arguments)]
 fn walk_parallel_inner(
     ignore_rules: SharedIgnoreRules,
-    dir: &Path,
+    dir_pm: &PathMetadata,
+    parent_patterns: Option<&DirectoryPatterns>,
+    parent_ancestors: Option<Arc<CanonicalAncestors>>,
     walk_options: WalkOptions,
-    path_sender: Sender<Result<PathMetadata>>,
-) -> Result<Vec<PathMetadata>> {
-    Ok(directory_list(dir)?
-        .drain(..)
-        .filter_map(|pm_res| match pm_res {
-            Ok(pm) => Some(pm),
-            Err(e) => {
-                path_sender
-                    .send(Err(e))
-                    .expect("Channel error in walk_parallel");
-                None
-            }
-        })
-        .filter_map(|pm| {
-            if walk_options.ignore_dot_git {
-                if let Some(file_name) = pm.path.file_name() {
-                    if file_name == ".git" {
-                        return None;
+    state: &WalkState,
+    visitor: &impl WalkVisitor,
+    span_root: Option<&Path>,
+    ignore_check: Option<&dyn IgnoreCheck>,
+) -> Result<ListedDir> {
+    let dir = dir_pm.path.as_path();
+    let dir_patterns = || {
+        let ignore_rules = read_shared_rules(&ignore_rules);
+        match parent_patterns {
+            Some(parent_patterns) => ignore_rules.child_directory_patterns(parent_patterns, dir),
+            None => ignore_rules.directory_patterns(dir),
+        }
+    };
+
+    let first_seen = state
+        .visited_dirs
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .visit(dir_pm, &walk_options)?;
+    if let Some(first_seen) = first_seen {
+        warn!("Skipping {:?}: already walked as {:?}", dir, first_seen);
+        state.undelivered_dirs.remove(dir);
+        return Ok((Vec::new(), Arc::new(dir_patterns()), None));
+    }
+    let ancestors = CanonicalAncestors::of_dir(parent_ancestors, dir, &walk_options)?;
+
+    // The patterns of a file are added at once, so they are not half-applied if the walk stops
+    let dir_patterns = time_phase(WalkPhase::RuleLoading, || {
+        if walk_options.read_gitattributes_export_ignore && !state.is_stopped() {
+            update_export_ignore_rules(dir, &read_shared_rules(&ignore_rules))?;
+        }
+        Ok::<_, Error>(dir_patterns())
+    })?;
+
+    let dir_list = match list_directory(dir, span_root) {
+        Ok(dir_list) => dir_list,
+        Err(e) if skips_unreadable_dir(dir, &e, &walk_options) => {
+            state.undelivered_dirs.remove(dir);
+            return Ok((Vec::new(), Arc::new(dir_patterns), ancestors));
+        }
+        Err(e) => return Err(e),
+    };
+    state.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+    let mut child_dirs = Vec::new();
+    let mut extension_counter = ExtensionCounter::new(
+        &walk_options,
+        read_shared_rules(&ignore_rules).case_insensitive,
+    );
+    let mut case_folded_names = CaseFoldedNames::new(&walk_options);
+    // Whether all paths are passed to the visitor, or skipped by it
+    let mut delivered = true;
+    for pm_res in dir_list {
+        let pm = match pm_res {
+            Ok(pm) => pm,
+            Err(e) => match state.visit(visitor, Err(e)) {
+                WalkAction::Continue => continue,
+                WalkAction::SkipSiblings => break,
+                WalkAction::Stop => {
+                    delivered = false;
+                    break;
+                }
+            },
+        };
+        state.unused_ignore_files.found(&pm, &walk_options);
+
+        let entry = child_entry(
+            pm,
+            &walk_options,
+            |path| {
+                time_phase(WalkPhase::IgnoreChecks, || match ignore_check {
+                    Some(ignore_check) => ignore_check.check(path),
+                    None => {
+                        read_shared_rules(&ignore_rules).check_in_directory(path, &dir_patterns)
                     }
+                })
+            },
+            ancestors.as_deref(),
+        );
+        let collision = CaseFoldedNames::check(&mut case_folded_names, &entry);
+        let action = match entry {
+            ChildEntry::Skipped => continue,
+            ChildEntry::Ignored(pm) => {
+                state.paths_ignored.fetch_add(1, Ordering::Relaxed);
+                if walk_options.log_prunes {
+                    state
+                        .prune_log
+                        .pruned(&read_shared_rules(&ignore_rules), &pm);
                 }
+                continue;
             }
-            let ignore_res = ignore_rules.read().unwrap().check(pm.path.as_ref());
-            match ignore_res {
-                MatchResult::NoMatch | MatchResult::Whitelist => {
-                    if pm.metadata.is_dir() {
-                        Some(pm)
-                    } else {
-                        path_sender
-                            .send(Ok(pm.clone()))
-                            .expect("Channel error in walk_parallel");
-                        None
-                    }
+            ChildEntry::Path(Some(pm_res)) => {
+                ExtensionCounter::visit(&mut extension_counter, pm_res, |pm_res| {
+                    state.visit(visitor, pm_res)
+                })
+            }
+            ChildEntry::Path(None) => WalkAction::Continue,
+            ChildEntry::Dir { pm, report: true } => {
+                let action = state.visit(visitor, Ok(pm.clone()));
+                // The directory is not walked into if the visitor stops at it
+                if action != WalkAction::Stop {
+                    child_dirs.push(pm);
                 }
-
-                MatchResult::Ignore => None,
+                action
+            }
+            ChildEntry::Dir { pm, report: false } => {
+                child_dirs.push(pm);
+                WalkAction::Continue
             }
-        })
-        .collect::<Vec<PathMetadata>>())
+        };
+        let action = match collision {
+            Some(e) if action == WalkAction::Continue => state.visit(visitor, Err(e)),
+            _ => action,
+        };
+        if action != WalkAction::Continue {
+            delivered = action != WalkAction::Stop;
+            break;
+        }
+    }
+    state.add_extensions(extension_counter);
+    if delivered {
+        state.undelivered_dirs.remove(dir);
+    }
+
+    Ok((child_dirs, Arc::new(dir_patterns), ancestors))
+}
+
+/// Lists `dir` with [directory_list].
+///
+/// With the `tracing` feature, if `span_root` is given, the listing is done in a `walk_dir` span
+/// with the path of `dir` and its depth under `span_root`. Otherwise `span_root` is not used.
+fn list_directory(dir: &Path, span_root: Option<&Path>) -> Result<Vec<Result<PathMetadata>>> {
+    #[cfg(feature = "tracing")]
+    if let Some(span_root) = span_root {
+        let depth = dir
+            .strip_prefix(span_root)
+            .map_or(0, |p| p.components().count());
+        let span = tracing::info_span!("walk_dir", path = %dir.display(), depth = depth);
+        let _entered = span.enter();
+        return directory_list(dir);
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = span_root;
+
+    directory_list(dir)
 }
 
 /// Walks a directory in parallel, sending found paths through a channel.
@@ -59,22 +646,359 @@ fn walk_parallel_inner(
 /// concurrently for high performance. The provided `ignore_rules` are used throughout the walk.
 /// This function does not discover new ignore files on its own; they must be provided
 /// in the initial `ignore_rules`.
+///
+/// The patterns found during the walk, i.e., `export-ignore` attributes with
+/// [WalkOptions::read_gitattributes_export_ignore], are added to `ignore_rules`. Other walks
+/// sharing the same rules see them as soon as they are added, so their results may depend on the
+/// timing. Use [walk_parallel_scoped] to keep them apart.
+///
+/// If `dir` doesn't exist, returns [Error::RootNotFound] before starting the walk. If it's not a
+/// directory, it's sent as the only path unless it's ignored. If it's a symlink to a directory
+/// followed with [WalkOptions::follow_symlinks] and `ignore_rules` are rooted at it, the
+/// directory is walked with its canonical path, and a warning is logged.
+///
+/// If the receiver of `path_sender` is dropped during the walk, the threads stop listing new
+/// directories and [Error::DeliveryIncomplete] is returned with the number of paths sent and
+/// directories listed until then. A directory is delivered when all its paths are sent, and the
+/// directories that are not are returned in `undelivered_dirs`, without the ones under the others.
+/// Walking them again with the same rules sends the paths missing from the first walk, and may
+/// send some paths again. The paths in the channel when the receiver is dropped are lost, so a
+/// receiver that resumes the walk should use a zero capacity channel, see
+/// [bounded](crossbeam_channel::bounded).
+///
+/// Otherwise, returns a [WalkSummary] after all paths are sent, to tell whether the walk is
+/// complete or some paths are missing because of errors.
 pub fn walk_parallel(
     ignore_rules: SharedIgnoreRules,
     dir: &Path,
     walk_options: WalkOptions,
     path_sender: Sender<Result<PathMetadata>>,
-) -> Result<()> {
-    let dir_queue = Arc::new(SegQueue::<PathMetadata>::new());
+) -> Result<WalkSummary> {
+    walk_parallel_summary(ignore_rules, dir, walk_options, path_sender, false)
+}
 
-    let child_dirs = walk_parallel_inner(
-        ignore_rules.clone(),
+/// Walks a directory in parallel like [walk_parallel], listing each directory in a tracing span.
+///
+/// The spans are named `walk_dir` and have the `path` of the directory and its `depth` under
+/// `dir`, so the slow directories can be found in the traces. Otherwise this is the same as
+/// [walk_parallel].
+#[cfg(feature = "tracing")]
+pub fn walk_parallel_with_tracing(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> Result<WalkSummary> {
+    walk_parallel_summary(ignore_rules, dir, walk_options, path_sender, true)
+}
+
+/// Walks a directory like [walk_parallel_until] and returns the [WalkSummary] of the walk.
+fn walk_parallel_summary(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+    trace_spans: bool,
+) -> Result<WalkSummary> {
+    let cancelled = AtomicBool::new(false);
+    let metrics = walk_parallel_until(
+        ignore_rules,
         dir,
-        walk_options.clone(),
-        path_sender.clone(),
+        walk_options,
+        path_sender,
+        &cancelled,
+        trace_spans,
     )?;
+    Ok(WalkSummary::new(metrics, cancelled.load(Ordering::Relaxed)))
+}
+
+/// Walks a directory in parallel like [walk_parallel], checking the paths with `ignore_check`
+/// instead of the ignore files.
+///
+/// The ignore files are not read during the walk, so [WalkOptions::ignore_filename] and
+/// [WalkOptions::read_gitattributes_export_ignore] have no effect, but the files named
+/// [WalkOptions::ignore_filename] are not counted in [WalkMetrics::unused_ignore_files], as
+/// `ignore_check` is expected to be built from them. `ignore_check` can be an
+/// [IgnoreRules] built beforehand, or an [IgnoreRulesStack](crate::IgnoreRulesStack) with the
+/// rules of the projects in a workspace.
+pub fn walk_parallel_with_check(
+    ignore_check: &impl IgnoreCheck,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> Result<WalkSummary> {
+    let known_ignore_filenames = walk_options
+        .known_ignore_filenames
+        .iter()
+        .filter(|name| walk_options.ignore_filename.as_ref() != Some(*name))
+        .cloned()
+        .collect();
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        read_gitattributes_export_ignore: false,
+        known_ignore_filenames,
+        ..walk_options
+    };
+    let cancelled = AtomicBool::new(false);
+    let metrics = walk_parallel_channel(
+        Arc::new(RwLock::new(IgnoreRules::empty(dir, None))),
+        dir,
+        walk_options,
+        path_sender,
+        &cancelled,
+        false,
+        &WalkState::new(),
+        Some(ignore_check),
+    )?;
+    Ok(WalkSummary::new(metrics, cancelled.load(Ordering::Relaxed)))
+}
+
+/// Walks a directory in parallel like [walk_parallel] with a private copy of `ignore_rules`.
+///
+/// The patterns found during the walk are added to the copy, not to `ignore_rules`, so they don't
+/// affect the other walks sharing the rules. They are returned after the walk, to be merged into
+/// the shared rules with [IgnoreRules::add_patterns] if needed.
+pub fn walk_parallel_scoped(
+    ignore_rules: &IgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> Result<Vec<Pattern>> {
+    let working_rules = ignore_rules.deep_clone();
+    let initial_len = working_rules.len();
+    let working_rules = Arc::new(RwLock::new(working_rules));
+    walk_parallel(working_rules.clone(), dir, walk_options, path_sender)?;
+
+    // The walk only appends patterns
+    let found_patterns = read_shared_rules(&working_rules).read_patterns()[initial_len..].to_vec();
+    Ok(found_patterns)
+}
+
+/// Walks a directory in parallel like [walk_parallel] and returns the paths with the metrics of
+/// the walk.
+///
+/// The paths and the errors are returned in the order they are found.
+pub fn walk_parallel_collect(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+) -> Result<(Vec<Result<PathMetadata>>, WalkMetrics)> {
+    let (path_sender, path_receiver) = crossbeam_channel::unbounded();
+    let metrics = walk_parallel_until(
+        ignore_rules,
+        dir,
+        walk_options,
+        path_sender,
+        &AtomicBool::new(false),
+        false,
+    )?;
+    Ok((path_receiver.into_iter().collect(), metrics))
+}
+
+/// Walks a directory in parallel like [walk_parallel] in a background thread, and returns the
+/// handle of the walk with the statistics of its thread pool.
+///
+/// The statistics are updated as the threads start and finish, and as the directories are queued
+/// and listed, so they can be inspected from other threads while the walk is running. Errors of
+/// the walk, e.g., a missing `dir`, are returned from [WalkHandle::join].
+pub fn walk_parallel_with_stats(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> (WalkHandle, Arc<Mutex<WalkPoolStats>>) {
+    let pool_stats = Arc::new(Mutex::new(WalkPoolStats::default()));
+    let state = WalkState::with_pool_stats(pool_stats.clone());
+    let handle = spawn_walk(ignore_rules, dir, walk_options, path_sender, state);
+    (handle, pool_stats)
+}
+
+/// Walks a directory in parallel like [walk_parallel] in a background thread, and returns the
+/// handle to pause, resume or abort the walk.
+///
+/// The progress of the walk can be followed with [WalkHandle::progress], also while it's paused.
+/// Errors of the walk, e.g., a missing `dir`, are returned from [WalkHandle::join].
+pub fn walk_parallel_controlled(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> WalkHandle {
+    spawn_walk(
+        ignore_rules,
+        dir,
+        walk_options,
+        path_sender,
+        WalkState::new(),
+    )
+}
+
+/// Starts a walk like [walk_parallel] in a background thread with `state`, and returns its handle.
+///
+/// The walk is cancelled with the abort flag of `state`, see [WalkHandle::abort].
+fn spawn_walk(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+    state: WalkState,
+) -> WalkHandle {
+    let state = Arc::new(state);
+    let thread_state = state.clone();
+    let dir = dir.to_path_buf();
+    let thread = std::thread::spawn(move || {
+        walk_parallel_channel(
+            ignore_rules,
+            &dir,
+            walk_options,
+            path_sender,
+            &thread_state.control.aborted,
+            false,
+            &thread_state,
+            None,
+        )
+    });
+    WalkHandle { thread, state }
+}
+
+/// Walks a directory in parallel like [walk_parallel] and passes the paths and errors to
+/// `visitor` instead of sending them to a channel.
+///
+/// The walk stops when `visitor` returns [WalkAction::Stop]. Returns the metrics of the walk
+/// until then, see [walk_parallel_collect].
+pub fn walk_parallel_visit(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    visitor: &(impl WalkVisitor + Sync),
+) -> Result<WalkMetrics> {
+    walk_parallel_visit_until(
+        ignore_rules,
+        dir,
+        walk_options,
+        visitor,
+        &AtomicBool::new(false),
+    )
+}
+
+/// Walks a directory like [walk_parallel_visit] until `cancelled` is set, like
+/// [walk_parallel_until].
+pub(crate) fn walk_parallel_visit_until(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    visitor: &(impl WalkVisitor + Sync),
+    cancelled: &AtomicBool,
+) -> Result<WalkMetrics> {
+    let state = WalkState::new();
+    walk_parallel_state(
+        ignore_rules,
+        dir,
+        walk_options,
+        visitor,
+        &state,
+        cancelled,
+        false,
+        None,
+    )?;
+    Ok(state.metrics())
+}
+
+/// Walks a directory like [walk_parallel] until `cancelled` is set.
+///
+/// The threads check `cancelled` before listing each directory, so the paths in the directories
+/// being listed may still be sent after it's set. If `trace_spans` is set, the directories are
+/// listed in tracing spans like in `walk_parallel_with_tracing`.
+///
+/// Returns the metrics of the walk, see [walk_parallel_collect].
+pub(crate) fn walk_parallel_until(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+    cancelled: &AtomicBool,
+    trace_spans: bool,
+) -> Result<WalkMetrics> {
+    walk_parallel_channel(
+        ignore_rules,
+        dir,
+        walk_options,
+        path_sender,
+        cancelled,
+        trace_spans,
+        &WalkState::new(),
+        None,
+    )
+}
+
+/// Walks a directory like [walk_parallel_until], counting the results in `state`.
+///
+/// If `ignore_check` is given, the paths are checked with it instead of `ignore_rules`.
+#[allow(clippy::too_many_arguments)]
+fn walk_parallel_channel(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+    cancelled: &AtomicBool,
+    trace_spans: bool,
+    state: &WalkState,
+    ignore_check: Option<&dyn IgnoreCheck>,
+) -> Result<WalkMetrics> {
+    walk_parallel_state(
+        ignore_rules,
+        dir,
+        walk_options,
+        &ChannelVisitor(path_sender),
+        state,
+        cancelled,
+        trace_spans,
+        ignore_check,
+    )?;
+
+    state.delivery_result()?;
+    Ok(state.metrics())
+}
+
+/// Walks a directory in parallel, passing the results to `visitor` and counting them in `state`.
+///
+/// The threads stop when `cancelled` is set or `visitor` stops the walk. If `ignore_check` is
+/// given, the paths are checked with it instead of `ignore_rules`.
+#[allow(clippy::too_many_arguments)]
+fn walk_parallel_state(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    visitor: &(impl WalkVisitor + Sync),
+    state: &WalkState,
+    cancelled: &AtomicBool,
+    trace_spans: bool,
+    ignore_check: Option<&dyn IgnoreCheck>,
+) -> Result<()> {
+    let (dir, ignore_rules) = resolve_parallel_root(dir, ignore_rules, &walk_options, ignore_check);
+    let dir = dir.as_path();
+    let span_root = trace_spans.then_some(dir);
+    let Some((child_dirs, dir_patterns, ancestors)) = walk_parallel_root(
+        ignore_rules.clone(),
+        dir,
+        &walk_options,
+        visitor,
+        state,
+        span_root,
+        ignore_check,
+    )?
+    else {
+        return Ok(());
+    };
+
+    let dir_queue = DirQueue::new(walk_options.traversal_order);
+    state.update_pool_stats(|stats| {
+        stats.completed_dirs += 1;
+        stats.queued_dirs += child_dirs.len();
+    });
     child_dirs.into_iter().for_each(|pm| {
-        dir_queue.push(pm);
+        state.undelivered_dirs.add(&pm.path);
+        dir_queue.push(pm, Some(dir_patterns.clone()), ancestors.clone(), 1);
     });
 
     if dir_queue.is_empty() {
@@ -82,30 +1006,388 @@ pub fn walk_parallel(
     }
 
     crossbeam::scope(|s| {
-        for _thread_i in 0..MAX_THREADS_PARALLEL_WALK {
-            let path_sender = path_sender.clone();
+        let num_threads = walk_options
+            .num_threads
+            .unwrap_or_else(default_num_threads)
+            .max(1);
+        for _thread_i in 0..num_threads {
             let walk_options = walk_options.clone();
             let ignore_rules = ignore_rules.clone();
-            let dir_queue = dir_queue.clone();
+            let dir_queue = &dir_queue;
 
             s.spawn(move |_| {
-                while let Some(pm) = dir_queue.pop() {
-                    let child_dirs = walk_parallel_inner(
+                state.active_threads.fetch_add(1, Ordering::Relaxed);
+                state.update_pool_stats(|stats| stats.active_threads += 1);
+                let timing_scope = TimingScope::start(walk_options.collect_timings);
+                while let Some(QueuedDir {
+                    pm,
+                    parent_patterns,
+                    ancestors,
+                    depth,
+                    ..
+                }) = dir_queue.pop()
+                {
+                    state.update_pool_stats(|stats| stats.queued_dirs -= 1);
+                    state.control.wait_while_paused(cancelled);
+                    if let Some(threshold) = walk_options.backpressure_threshold {
+                        state.wait_for_consumer(visitor, threshold, cancelled);
+                    }
+                    if cancelled.load(Ordering::Relaxed) || state.is_stopped() {
+                        break;
+                    }
+                    let dir_res = walk_parallel_inner(
                         ignore_rules.clone(),
-                        &pm.path,
+                        &pm,
+                        parent_patterns.as_deref(),
+                        ancestors,
                         walk_options.clone(),
-                        path_sender.clone(),
-                    )
-                    .unwrap();
+                        state,
+                        visitor,
+                        span_root,
+                        ignore_check,
+                    );
+                    let (child_dirs, dir_patterns, ancestors) = match dir_res {
+                        Ok(listed) => listed,
+                        Err(e) => {
+                            state.visit_dir_error(visitor, &pm.path, e);
+                            state.update_pool_stats(|stats| stats.completed_dirs += 1);
+                            continue;
+                        }
+                    };
 
+                    state.update_pool_stats(|stats| {
+                        stats.completed_dirs += 1;
+                        stats.queued_dirs += child_dirs.len();
+                    });
                     for child_dir in child_dirs {
-                        dir_queue.push(child_dir);
+                        state.undelivered_dirs.add(&child_dir.path);
+                        dir_queue.push(
+                            child_dir,
+                            Some(dir_patterns.clone()),
+                            ancestors.clone(),
+                            depth + 1,
+                        );
                     }
                 }
+                state.update_pool_stats(|stats| stats.active_threads -= 1);
+                state.add_timings(timing_scope.finish());
+                state.active_threads.fetch_sub(1, Ordering::Relaxed);
             });
         }
     })
     .expect("Error in crossbeam scope in walk_parallel");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Returns the canonical path of `dir` and the rules rooted at it if `dir` is a symlink to a
+/// directory, see [resolve_root_symlink].
+///
+/// The root is resolved only if it's the root of `ignore_rules` and there is no `ignore_check`,
+/// as the paths under the canonical root can't be checked with the rules of the other roots. The
+/// rules share the patterns of `ignore_rules`, so the patterns found during the walk are added to
+/// both.
+fn resolve_parallel_root(
+    dir: &Path,
+    ignore_rules: SharedIgnoreRules,
+    walk_options: &WalkOptions,
+    ignore_check: Option<&dyn IgnoreCheck>,
+) -> (PathBuf, SharedIgnoreRules) {
+    #[cfg(feature = "unicode-normalization")]
+    if walk_options.unicode_normalize && ignore_check.is_none() {
+        ignore_rules
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .unicode_normalize = true;
+    }
+    if ignore_check.is_none() && read_shared_rules(&ignore_rules).root == dir {
+        if let Some(canonical) = resolve_root_symlink(dir, walk_options) {
+            let rules = read_shared_rules(&ignore_rules)
+                .clone()
+                .with_root(&canonical);
+            return (canonical, Arc::new(RwLock::new(rules)));
+        }
+    }
+    (dir.to_path_buf(), ignore_rules)
+}
+
+/// Walks the root `dir` of a parallel walk with [walk_parallel_inner] in the current thread.
+///
+/// If `dir` is not a directory, it's passed to `visitor` unless it's ignored, and `None` is
+/// returned. Otherwise, returns the child directories to walk and the patterns of `dir`.
+fn walk_parallel_root(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &impl WalkVisitor,
+    state: &WalkState,
+    span_root: Option<&Path>,
+    ignore_check: Option<&dyn IgnoreCheck>,
+) -> Result<Option<ListedDir>> {
+    let dir_pm = match walk_root(dir, walk_options)? {
+        WalkRoot::Directory(pm) => pm,
+        WalkRoot::Path(Some(Ok(pm))) => {
+            let result = match ignore_check {
+                Some(ignore_check) => ignore_check.check(&pm.path),
+                None => read_shared_rules(&ignore_rules).check(&pm.path),
+            };
+            if result != MatchResult::Ignore {
+                let path = pm.path.clone();
+                let mut extension_counter = ExtensionCounter::new(
+                    walk_options,
+                    read_shared_rules(&ignore_rules).case_insensitive,
+                );
+                let action = ExtensionCounter::visit(&mut extension_counter, Ok(pm), |pm_res| {
+                    state.visit(visitor, pm_res)
+                });
+                state.add_extensions(extension_counter);
+                if action == WalkAction::Stop {
+                    state.undelivered_dirs.add(&path);
+                }
+            } else {
+                state.paths_ignored.fetch_add(1, Ordering::Relaxed);
+            }
+            return Ok(None);
+        }
+        WalkRoot::Path(Some(Err(e))) => {
+            state.undelivered_dirs.add(dir);
+            state.visit_dir_error(visitor, dir, e);
+            return Ok(None);
+        }
+        WalkRoot::Path(None) => return Ok(None),
+    };
+
+    state.undelivered_dirs.add(dir);
+    let timing_scope = TimingScope::start(walk_options.collect_timings);
+    let root_res = walk_parallel_inner(
+        ignore_rules,
+        &dir_pm,
+        None,
+        None,
+        walk_options.clone(),
+        state,
+        visitor,
+        span_root,
+        ignore_check,
+    );
+    state.add_timings(timing_scope.finish());
+    root_res.map(Some)
+}
+
+/// Walks a directory in parallel like [walk_parallel] on the current rayon thread pool, instead of
+/// starting its own threads.
+///
+/// The directories are listed in rayon tasks, so a walk started in a [rayon::ThreadPool] with
+/// [install](rayon::ThreadPool::install) uses only the threads of that pool, and the threads
+/// are shared with the other work in the pool. [WalkOptions::num_threads],
+/// [WalkOptions::traversal_order] and [WalkOptions::backpressure_threshold] have no effect, as
+/// the pool decides which directories are listed when. The paths found are the same as
+/// [walk_parallel].
+///
+/// The errors in listing a directory are sent to `path_sender`, like the errors of the paths in
+/// it.
+#[cfg(feature = "rayon")]
+pub fn walk_rayon(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    path_sender: Sender<Result<PathMetadata>>,
+) -> Result<WalkSummary> {
+    let state = WalkState::new();
+    walk_rayon_state(
+        ignore_rules,
+        dir,
+        &walk_options,
+        &ChannelVisitor(path_sender),
+        &state,
+    )?;
+
+    state.delivery_result()?;
+    Ok(WalkSummary::new(state.metrics(), false))
+}
+
+/// Walks a directory in parallel like [walk_rayon] and passes the paths and errors to `visitor`
+/// instead of sending them to a channel.
+///
+/// The walk stops when `visitor` returns [WalkAction::Stop]. Returns the metrics of the walk
+/// until then, see [walk_parallel_visit].
+#[cfg(feature = "rayon")]
+pub fn walk_rayon_visit(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    visitor: &(impl WalkVisitor + Sync),
+) -> Result<WalkMetrics> {
+    let state = WalkState::new();
+    walk_rayon_state(ignore_rules, dir, &walk_options, visitor, &state)?;
+    Ok(state.metrics())
+}
+
+/// Walks a directory like [walk_rayon], passing the results to `visitor` and counting them in
+/// `state`.
+#[cfg(feature = "rayon")]
+fn walk_rayon_state(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &(impl WalkVisitor + Sync),
+    state: &WalkState,
+) -> Result<()> {
+    let (dir, ignore_rules) = resolve_parallel_root(dir, ignore_rules, walk_options, None);
+    let Some((child_dirs, dir_patterns, ancestors)) = walk_parallel_root(
+        ignore_rules.clone(),
+        &dir,
+        walk_options,
+        visitor,
+        state,
+        None,
+        None,
+    )?
+    else {
+        return Ok(());
+    };
+
+    rayon::scope(|s| {
+        for pm in child_dirs {
+            state.undelivered_dirs.add(&pm.path);
+            let ignore_rules = ignore_rules.clone();
+            let dir_patterns = dir_patterns.clone();
+            let ancestors = ancestors.clone();
+            s.spawn(move |s| {
+                walk_rayon_dir(
+                    s,
+                    ignore_rules,
+                    pm,
+                    dir_patterns,
+                    ancestors,
+                    walk_options,
+                    visitor,
+                    state,
+                )
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Lists the directory in `pm` with [walk_parallel_inner] and spawns a task in `scope` for each
+/// of its child directories.
+#[cfg(feature = "rayon")]
+#[allow(clippy::too_many_arguments)]
+fn walk_rayon_dir<'s>(
+    scope: &rayon::Scope<'s>,
+    ignore_rules: SharedIgnoreRules,
+    pm: PathMetadata,
+    parent_patterns: Arc<DirectoryPatterns>,
+    parent_ancestors: Option<Arc<CanonicalAncestors>>,
+    walk_options: &'s WalkOptions,
+    visitor: &'s (impl WalkVisitor + Sync),
+    state: &'s WalkState,
+) {
+    if state.is_stopped() {
+        return;
+    }
+    let timing_scope = TimingScope::start(walk_options.collect_timings);
+    let dir_res = walk_parallel_inner(
+        ignore_rules.clone(),
+        &pm,
+        Some(&parent_patterns),
+        parent_ancestors,
+        walk_options.clone(),
+        state,
+        visitor,
+        None,
+        None,
+    );
+    state.add_timings(timing_scope.finish());
+    let (child_dirs, dir_patterns, ancestors) = match dir_res {
+        Ok(listed) => listed,
+        Err(e) => {
+            state.visit_dir_error(visitor, &pm.path, e);
+            return;
+        }
+    };
+    for child_dir in child_dirs {
+        state.undelivered_dirs.add(&child_dir.path);
+        let ignore_rules = ignore_rules.clone();
+        let dir_patterns = dir_patterns.clone();
+        let ancestors = ancestors.clone();
+        scope.spawn(move |s| {
+            walk_rayon_dir(
+                s,
+                ignore_rules,
+                child_dir,
+                dir_patterns,
+                ancestors,
+                walk_options,
+                visitor,
+                state,
+            )
+        });
+    }
+}
+
+/// Walks a directory in parallel like [walk_parallel], sending the paths interned with `interner`.
+///
+/// This keeps the memory used by the results of large walks low, as the directories of the paths
+/// are stored once in `interner`. The paths are interned in the threads of the walk.
+pub fn walk_parallel_interned(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+    interner: &PathInterner,
+    path_sender: Sender<Result<InternedPathMetadata>>,
+) -> Result<WalkSummary> {
+    let state = WalkState::new();
+    let cancelled = AtomicBool::new(false);
+    walk_parallel_state(
+        ignore_rules,
+        dir,
+        walk_options,
+        &InterningVisitor {
+            interner,
+            path_sender,
+        },
+        &state,
+        &cancelled,
+        false,
+        None,
+    )?;
+
+    state.delivery_result()?;
+    Ok(WalkSummary::new(
+        state.metrics(),
+        cancelled.load(Ordering::Relaxed),
+    ))
+}
+
+/// Interns the paths with `interner` and sends them to a channel, for [walk_parallel_interned].
+///
+/// Stops the walk when the receiver is dropped, like [ChannelVisitor].
+struct InterningVisitor<'a> {
+    interner: &'a PathInterner,
+    path_sender: Sender<Result<InternedPathMetadata>>,
+}
+
+impl WalkVisitor for InterningVisitor<'_> {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.visit(Ok(entry.clone()))
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        self.visit(Err(anyhow::anyhow!("{}", err).into()))
+    }
+
+    /// Returns the number of results in the channel.
+    fn pending(&self) -> Option<usize> {
+        Some(self.path_sender.len())
+    }
+
+    fn visit(&self, path_res: Result<PathMetadata>) -> WalkAction {
+        let path_res = path_res.map(|pm| InternedPathMetadata::new(pm, self.interner));
+        match self.path_sender.send(path_res) {
+            Ok(()) => WalkAction::Continue,
+            Err(_) => WalkAction::Stop,
+        }
+    }
+}
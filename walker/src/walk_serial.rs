@@ -1,12 +1,24 @@
 //! Serial directory walker without parallelization
 //! See [`walk_parallel`] for parallel version.
+use std::cell::RefCell;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 use xvc_logging::{debug, error, warn, XvcOutputSender};
 
+use crate::gitattributes::update_export_ignore_rules;
+use crate::timings::{time_phase, TimingScope, WalkPhase, WalkTimings};
+use crate::visitor::{WalkAction, WalkVisitor};
+use crate::walk_core::{
+    child_entry, resolve_root_symlink, skips_unreadable_dir, walk_root, CanonicalAncestors,
+    CaseFoldedNames, ChildEntry, DirQueue, ExtensionCounter, PruneLog, QueuedDir,
+    UnusedIgnoreFileCounter, VisitedDirs, WalkRoot,
+};
 use crate::{
-    build_ignore_patterns, directory_list, pattern::MatchResult, update_ignore_rules, IgnoreRules,
-    PathMetadata, Result, WalkOptions,
+    build_ignore_patterns_with_options, directory_list_into, initial_ignore_rules,
+    pattern::MatchResult, update_git_info_exclude_rules, update_ignore_rules_with_policy, Error,
+    IgnoreCheck, IgnoreRules, PathMetadata, Result, WalkMetrics, WalkOptions,
 };
 
 /// Walk `dir` with `walk_options`, with the given _initial_ `ignore_rules`.
@@ -16,68 +28,363 @@ use crate::{
 ///
 /// It collects all [`PathMetadata`] of the child paths.
 /// Filters paths with the rules found in child directories and the given `ignore_rules`.
+///
+/// If `dir` doesn't exist, returns [Error::RootNotFound]. If it's not a directory, it's returned
+/// as the only path unless it's ignored. If it's a symlink to a directory followed with
+/// [WalkOptions::follow_symlinks], the directory is walked with its canonical path, and a warning
+/// is logged.
+///
+/// Use [walk_serial_visit] to get the [WalkMetrics] of the walk, e.g., with
+/// [WalkOptions::collect_timings] or [WalkOptions::summarize_extensions].
 pub fn walk_serial(
     output_snd: &XvcOutputSender,
     global_ignore_rules: &str,
     dir: &Path,
     walk_options: &WalkOptions,
 ) -> Result<(Vec<PathMetadata>, IgnoreRules)> {
-    let ignore_rules = IgnoreRules::from_global_patterns(
+    let visitor = CollectingVisitor {
+        output_snd,
+        paths: RefCell::new(Vec::new()),
+    };
+    let (ignore_rules, _) = walk_serial_visit(global_ignore_rules, dir, walk_options, &visitor)?;
+    Ok((visitor.paths.into_inner(), ignore_rules))
+}
+
+/// Collects the paths for [walk_serial] and reports the errors as warnings.
+struct CollectingVisitor<'a> {
+    output_snd: &'a XvcOutputSender,
+    paths: RefCell<Vec<PathMetadata>>,
+}
+
+impl WalkVisitor for CollectingVisitor<'_> {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths.borrow_mut().push(entry.clone());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        warn!(self.output_snd, "{}", err);
+        WalkAction::Continue
+    }
+
+    fn visit(&self, path_res: Result<PathMetadata>) -> WalkAction {
+        match path_res {
+            Ok(pm) => {
+                self.paths.borrow_mut().push(pm);
+                WalkAction::Continue
+            }
+            Err(e) => self.on_error(&e),
+        }
+    }
+}
+
+/// Walks `dir` like [walk_serial] and passes the paths and errors to `visitor` instead of
+/// collecting them.
+///
+/// The walk stops when `visitor` returns [WalkAction::Stop]. Returns the ignore rules read until
+/// then, and the metrics of the paths visited like [walk_parallel_collect](crate::walk_parallel_collect).
+///
+/// With [IgnoreFileErrorPolicy::Collect](crate::IgnoreFileErrorPolicy::Collect), the ignore files
+/// that can't be read are passed to `visitor` as [Error::UnreadableIgnoreFile] before the paths in
+/// their directories.
+pub fn walk_serial_visit(
+    global_ignore_rules: &str,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &impl WalkVisitor,
+) -> Result<(IgnoreRules, WalkMetrics)> {
+    let resolved_dir = resolve_root_symlink(dir, walk_options);
+    let dir = resolved_dir.as_deref().unwrap_or(dir);
+    let ignore_rules = initial_ignore_rules(global_ignore_rules, dir, walk_options)?;
+    // The path relative to `ignore_rules.root` is empty if `dir` is a file, check the file name
+    // instead.
+    let root_file_check = |path: &Path| {
+        IgnoreRules::from_global_patterns(path.parent().unwrap_or(path), None, global_ignore_rules)
+            .check(path)
+    };
+    walk_serial_rules(
+        ignore_rules,
+        dir,
+        walk_options,
+        visitor,
+        None,
+        root_file_check,
+    )
+}
+
+/// Walks `dir` like [walk_serial_visit], checking the paths with `ignore_check` instead of the
+/// ignore files.
+///
+/// The ignore files are not read during the walk, so [WalkOptions::ignore_filename] and
+/// [WalkOptions::read_gitattributes_export_ignore] have no effect. `ignore_check` can be an
+/// [IgnoreRules] built beforehand, or an [IgnoreRulesStack](crate::IgnoreRulesStack) with the
+/// rules of the projects in a workspace. The files named [WalkOptions::ignore_filename] are not
+/// counted in [WalkMetrics::unused_ignore_files], as `ignore_check` is expected to be built from
+/// them.
+pub fn walk_serial_with_check(
+    ignore_check: &impl IgnoreCheck,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &impl WalkVisitor,
+) -> Result<WalkMetrics> {
+    let known_ignore_filenames = walk_options
+        .known_ignore_filenames
+        .iter()
+        .filter(|name| walk_options.ignore_filename.as_ref() != Some(*name))
+        .cloned()
+        .collect();
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        read_gitattributes_export_ignore: false,
+        known_ignore_filenames,
+        ..walk_options.clone()
+    };
+    let (_, metrics) = walk_serial_rules(
+        IgnoreRules::empty(dir, None),
+        dir,
+        &walk_options,
+        visitor,
+        Some(ignore_check),
+        |path: &Path| ignore_check.check(path),
+    )?;
+    Ok(metrics)
+}
+
+/// Counts the paths a serial walk passes to its visitor, for the [WalkMetrics] it returns.
+struct SerialCounter {
+    metrics: WalkMetrics,
+    start: Instant,
+    unused_ignore_files: UnusedIgnoreFileCounter,
+    extensions: Option<ExtensionCounter>,
+}
+
+impl SerialCounter {
+    fn new(walk_options: &WalkOptions, ignore_rules: &IgnoreRules) -> Self {
+        SerialCounter {
+            metrics: WalkMetrics::default(),
+            start: Instant::now(),
+            unused_ignore_files: UnusedIgnoreFileCounter::default(),
+            extensions: ExtensionCounter::new(walk_options, ignore_rules.case_insensitive),
+        }
+    }
+
+    /// Passes `path_res` to `visitor` and counts it, unless the visitor stops at it.
+    fn visit(&mut self, visitor: &impl WalkVisitor, path_res: Result<PathMetadata>) -> WalkAction {
+        let counter = match &path_res {
+            Ok(pm) if pm.metadata.is_dir() => None,
+            Ok(_) => Some(&mut self.metrics.files_visited),
+            Err(_) => Some(&mut self.metrics.errors_encountered),
+        };
+        let action = ExtensionCounter::visit(&mut self.extensions, path_res, |path_res| {
+            time_phase(WalkPhase::SendBlocking, || visitor.visit(path_res))
+        });
+        if let Some(counter) = counter.filter(|_| action != WalkAction::Stop) {
+            *counter += 1;
+        }
+        action
+    }
+
+    /// Returns the metrics of the walk with the `timings` collected in it.
+    fn finish(self, timings: WalkTimings) -> WalkMetrics {
+        WalkMetrics {
+            elapsed: self.start.elapsed(),
+            timings,
+            unused_ignore_files: self.unused_ignore_files.unused_ignore_files(),
+            extensions: self
+                .extensions
+                .map(|counter| counter.extensions)
+                .unwrap_or_default(),
+            ..self.metrics
+        }
+    }
+}
+
+/// Walks `dir` with `ignore_rules`, reading the ignore files in the directories into them, and
+/// passes the paths and errors to `visitor`.
+///
+/// If `ignore_check` is given, the paths are checked with it instead of `ignore_rules`.
+/// `root_file_check` checks `dir` if it's not a directory. Returns the rules with the metrics of
+/// the walk.
+fn walk_serial_rules(
+    ignore_rules: IgnoreRules,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &impl WalkVisitor,
+    ignore_check: Option<&dyn IgnoreCheck>,
+    root_file_check: impl FnOnce(&Path) -> MatchResult,
+) -> Result<(IgnoreRules, WalkMetrics)> {
+    let mut counter = SerialCounter::new(walk_options, &ignore_rules);
+    let timing_scope = TimingScope::start(walk_options.collect_timings);
+    let res = walk_serial_dirs(
+        &ignore_rules,
         dir,
-        walk_options.ignore_filename.as_deref(),
-        global_ignore_rules,
+        walk_options,
+        visitor,
+        ignore_check,
+        root_file_check,
+        &mut counter,
     );
+    let timings = timing_scope.finish();
+    res?;
+    Ok((ignore_rules, counter.finish(timings)))
+}
 
-    let mut dir_stack = Vec::new();
+/// Walks the directories for [walk_serial_rules], counting the paths in `counter`.
+fn walk_serial_dirs(
+    ignore_rules: &IgnoreRules,
+    dir: &Path,
+    walk_options: &WalkOptions,
+    visitor: &impl WalkVisitor,
+    ignore_check: Option<&dyn IgnoreCheck>,
+    root_file_check: impl FnOnce(&Path) -> MatchResult,
+    counter: &mut SerialCounter,
+) -> Result<()> {
+    let dir_queue = DirQueue::new(walk_options.traversal_order);
+    match walk_root(dir, walk_options)? {
+        WalkRoot::Directory(pm) => {
+            if ignore_check.is_none() {
+                update_git_info_exclude_rules(ignore_rules)?;
+            }
+            dir_queue.push(pm, None, None, 0);
+        }
+        WalkRoot::Path(Some(Ok(pm))) => {
+            if root_file_check(&pm.path) == MatchResult::Ignore {
+                debug!("Ignored: {:?}", pm.path);
+                counter.metrics.entries_ignored += 1;
+            } else {
+                counter.visit(visitor, Ok(pm));
+            }
+            return Ok(());
+        }
+        WalkRoot::Path(Some(Err(e))) => {
+            counter.visit(visitor, Err(e));
+            return Ok(());
+        }
+        WalkRoot::Path(None) => return Ok(()),
+    }
 
-    dir_stack.push(dir.to_path_buf());
+    let mut visited_dirs = VisitedDirs::default();
+    let prune_log = PruneLog::default();
+    // The children of each directory are listed into the same buffer
+    let mut child_paths = Vec::new();
 
-    let get_child_paths = |dir: &Path| -> Result<Vec<PathMetadata>> {
-        Ok(directory_list(dir)?
-            .into_iter()
-            .filter_map(|pm_res| match pm_res {
-                Ok(pm) => Some(pm),
-                Err(e) => {
-                    warn!(output_snd, "{}", e);
-                    None
-                }
-            })
-            .collect())
-    };
+    while let Some(QueuedDir {
+        pm,
+        parent_patterns,
+        ancestors,
+        depth,
+        ..
+    }) = dir_queue.pop()
+    {
+        if let Some(first_seen) = visited_dirs.visit(&pm, walk_options)? {
+            warn!("Skipping {:?}: already walked as {:?}", pm.path, first_seen);
+            continue;
+        }
+        let dir = pm.path;
+        let ancestors = CanonicalAncestors::of_dir(ancestors, &dir, walk_options)?;
 
-    let mut res_paths = Vec::new();
-    while let Some(dir) = dir_stack.pop() {
         // TODO: Keep ignore rules in a single file in the root. Most of the time, we don't need to
         // read the ignore rules in all over the repository.
-        update_ignore_rules(&dir, &ignore_rules)?;
+        let mut unreadable_files = Vec::new();
+        let dir_patterns = time_phase(WalkPhase::RuleLoading, || {
+            update_ignore_rules_with_policy(
+                &dir,
+                ignore_rules,
+                walk_options.ignore_syntax,
+                walk_options.ignore_file_errors,
+                walk_options.follow_ignore_file_symlinks,
+                walk_options.max_patterns_per_file,
+                &mut unreadable_files,
+            )?;
+            if walk_options.read_gitattributes_export_ignore {
+                update_export_ignore_rules(&dir, ignore_rules)?;
+            }
+            Ok::<_, Error>(Arc::new(match parent_patterns {
+                Some(parent_patterns) => {
+                    ignore_rules.child_directory_patterns(&parent_patterns, &dir)
+                }
+                None => ignore_rules.directory_patterns(&dir),
+            }))
+        })?;
+        for unreadable_file in unreadable_files {
+            if counter.visit(visitor, Err(unreadable_file.into())) == WalkAction::Stop {
+                return Ok(());
+            }
+        }
 
-        res_paths.extend(get_child_paths(&dir)?.drain(..).filter_map(|p| {
-            if walk_options.ignore_dot_git {
-                if let Some(name) = p.path.file_name() {
-                    if name == ".git" {
-                        return None;
+        child_paths.clear();
+        if let Err(e) = directory_list_into(&dir, &mut child_paths) {
+            if skips_unreadable_dir(&dir, &e, walk_options) {
+                continue;
+            }
+            return Err(e);
+        }
+        counter.metrics.directories_visited += 1;
+        let mut case_folded_names = CaseFoldedNames::new(walk_options);
+        for p_res in child_paths.drain(..) {
+            let p = match p_res {
+                Ok(p) => p,
+                Err(e) => match counter.visit(visitor, Err(e)) {
+                    WalkAction::Continue => continue,
+                    WalkAction::SkipSiblings => break,
+                    WalkAction::Stop => return Ok(()),
+                },
+            };
+
+            counter.unused_ignore_files.found(&p, walk_options);
+
+            let entry = child_entry(
+                p,
+                walk_options,
+                |path| {
+                    time_phase(WalkPhase::IgnoreChecks, || match ignore_check {
+                        Some(ignore_check) => ignore_check.check(path),
+                        None => ignore_rules.check_in_directory(path, &dir_patterns),
+                    })
+                },
+                ancestors.as_deref(),
+            );
+            let collision = CaseFoldedNames::check(&mut case_folded_names, &entry);
+            let action = match entry {
+                ChildEntry::Skipped | ChildEntry::Path(None) => WalkAction::Continue,
+                ChildEntry::Ignored(pm) => {
+                    counter.metrics.entries_ignored += 1;
+                    if walk_options.log_prunes {
+                        prune_log.pruned(ignore_rules, &pm);
                     }
+                    WalkAction::Continue
                 }
-            }
-            let ignore_result = ignore_rules.check(p.path.as_ref());
-            match ignore_result {
-                MatchResult::NoMatch | MatchResult::Whitelist => {
-                    if p.metadata.is_dir() {
-                        dir_stack.push(p.path.clone());
-                        None
+                ChildEntry::Path(Some(p_res)) => counter.visit(visitor, p_res),
+                ChildEntry::Dir { pm, report } => {
+                    let action = if report {
+                        counter.visit(visitor, Ok(pm.clone()))
                     } else {
-                        Some(p)
+                        WalkAction::Continue
+                    };
+                    if action != WalkAction::Stop {
+                        dir_queue.push(
+                            pm,
+                            Some(dir_patterns.clone()),
+                            ancestors.clone(),
+                            depth + 1,
+                        );
                     }
+                    action
                 }
-                MatchResult::Ignore => {
-                    debug!(output_snd, "Ignored: {:?}", p.path);
-                    None
-                }
+            };
+            let action = match collision {
+                Some(e) if action == WalkAction::Continue => counter.visit(visitor, Err(e)),
+                _ => action,
+            };
+            match action {
+                WalkAction::Continue => {}
+                WalkAction::SkipSiblings => break,
+                WalkAction::Stop => return Ok(()),
             }
-        }));
+        }
     }
 
-    Ok((res_paths, ignore_rules))
+    Ok(())
 }
 
 /// Return path metadata only for the listed files to avoid recursing directories
@@ -90,9 +397,7 @@ pub fn path_metadata_map_from_file_targets(
 ) -> Result<(Vec<PathMetadata>, IgnoreRules)> {
     let ignore_filename = walk_options.ignore_filename.as_deref();
     let ignore_rules = ignore_filename
-        .map(|ignore_filename| {
-            build_ignore_patterns(global_ignore_rules, ignore_root, ignore_filename)
-        })
+        .map(|_| build_ignore_patterns_with_options(global_ignore_rules, ignore_root, walk_options))
         .unwrap_or_else(|| {
             Ok(IgnoreRules::from_global_patterns(
                 ignore_root,
@@ -117,7 +422,7 @@ pub fn path_metadata_map_from_file_targets(
             MatchResult::NoMatch | MatchResult::Whitelist => {
                 let md_res = path.metadata();
                 match md_res {
-                    Ok(metadata) => Some(PathMetadata { path, metadata }),
+                    Ok(metadata) => Some(PathMetadata::new(path, metadata)),
                     Err(e) => {
                         error!(output_snd, "{}", e);
                         None
@@ -133,4 +438,4 @@ pub fn path_metadata_map_from_file_targets(
     }));
 
     Ok((res_paths, ignore_rules))
-}
\ No newline at end of file
+}
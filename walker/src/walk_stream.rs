@@ -0,0 +1,224 @@
+//! Parallel directory walk as a [Stream] for async code.
+//!
+//! [walk_parallel_stream] runs [walk_parallel](crate::walk_parallel) in threads and forwards the
+//! results to the stream, so the paths can be consumed, e.g., with `tokio::select!` along with
+//! network IO. The file system is not accessed asynchronously.
+//!
+//! With the `tokio` feature, [walk_parallel_tokio_stream] runs the walk in a blocking task of the
+//! Tokio runtime instead, when the stream is first polled.
+use std::path::Path;
+#[cfg(feature = "tokio")]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::Stream;
+
+use crate::walk_parallel::walk_parallel_until;
+#[cfg(feature = "tokio")]
+use crate::{walk_parallel::walk_parallel_visit_until, Error, WalkAction, WalkVisitor};
+use crate::{PathMetadata, Result, SharedIgnoreRules, WalkOptions};
+
+/// The number of results [walk_parallel_tokio_stream] keeps until they are polled. The walk
+/// waits when the channel is full.
+#[cfg(feature = "tokio")]
+const TOKIO_STREAM_CAPACITY: usize = 1024;
+
+/// The paths found by [walk_parallel_stream].
+///
+/// The stream ends when the walk finishes. If the walk fails, e.g., with
+/// [Error::RootNotFound](crate::Error::RootNotFound), the error is the last item.
+///
+/// Dropping the stream stops the walk and waits for its threads to finish.
+pub struct WalkStream {
+    receiver: UnboundedReceiver<Result<PathMetadata>>,
+    cancelled: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Stream for WalkStream {
+    type Item = Result<PathMetadata>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for WalkStream {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.receiver.close();
+        for thread in self.threads.drain(..) {
+            // The threads don't panic unless the walk does, nothing to report here
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Walks `dir` in parallel like [walk_parallel](crate::walk_parallel) and returns the paths as a
+/// [Stream].
+///
+/// The walk starts immediately in background threads, not when the stream is first polled.
+pub fn walk_parallel_stream(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+) -> WalkStream {
+    let (path_sender, path_receiver) = crossbeam_channel::unbounded();
+    let (stream_sender, stream_receiver) = unbounded();
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let walk_thread = {
+        let dir = dir.to_path_buf();
+        let cancelled = cancelled.clone();
+        std::thread::spawn(move || {
+            let res = walk_parallel_until(
+                ignore_rules,
+                &dir,
+                walk_options,
+                path_sender.clone(),
+                &cancelled,
+                false,
+            );
+            if let Err(e) = res {
+                // The bridge thread keeps receiving until all senders are dropped
+                let _ = path_sender.send(Err(e));
+            }
+        })
+    };
+
+    let bridge_thread = {
+        let cancelled = cancelled.clone();
+        std::thread::spawn(move || {
+            // Keep receiving after the stream is dropped, the walk stops when `cancelled` is set
+            for path_res in path_receiver {
+                if stream_sender.unbounded_send(path_res).is_err() {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+        })
+    };
+
+    WalkStream {
+        receiver: stream_receiver,
+        cancelled,
+        threads: vec![walk_thread, bridge_thread],
+    }
+}
+
+/// Walks `dir` in parallel like [walk_parallel](crate::walk_parallel) in a Tokio blocking task,
+/// and returns the paths as a [Stream].
+///
+/// The walk starts when the stream is first polled, so the stream must be polled in a Tokio
+/// runtime. The results are sent through a bounded [tokio::sync::mpsc::channel], and the walk
+/// waits for the stream to be polled when it's full. The stream ends when the walk finishes. If
+/// the walk fails, the error is the last item, like [WalkStream].
+///
+/// Dropping the stream stops the walk, but doesn't wait for the task to finish.
+#[cfg(feature = "tokio")]
+pub fn walk_parallel_tokio_stream(
+    ignore_rules: SharedIgnoreRules,
+    dir: &Path,
+    walk_options: WalkOptions,
+) -> impl Stream<Item = Result<PathMetadata>> {
+    TokioWalkStream {
+        walk: Some((ignore_rules, dir.to_path_buf(), walk_options)),
+        receiver: None,
+        cancelled: Arc::new(AtomicBool::new(false)),
+    }
+}
+
+/// The stream returned by [walk_parallel_tokio_stream].
+#[cfg(feature = "tokio")]
+struct TokioWalkStream {
+    /// The walk to start when the stream is first polled.
+    walk: Option<(SharedIgnoreRules, PathBuf, WalkOptions)>,
+    receiver: Option<tokio::sync::mpsc::Receiver<Result<PathMetadata>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioWalkStream {
+    /// Starts the walk in a blocking task and returns the receiver of its results.
+    fn start(
+        &self,
+        (ignore_rules, dir, walk_options): (SharedIgnoreRules, PathBuf, WalkOptions),
+    ) -> tokio::sync::mpsc::Receiver<Result<PathMetadata>> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(TOKIO_STREAM_CAPACITY);
+        let cancelled = self.cancelled.clone();
+        tokio::task::spawn_blocking(move || {
+            let visitor = TokioChannelVisitor {
+                sender,
+                cancelled: &cancelled,
+            };
+            let res =
+                walk_parallel_visit_until(ignore_rules, &dir, walk_options, &visitor, &cancelled);
+            if let Err(e) = res {
+                // The stream may be dropped already
+                let _ = visitor.sender.blocking_send(Err(e));
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Stream for TokioWalkStream {
+    type Item = Result<PathMetadata>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(walk) = self.walk.take() {
+            self.receiver = Some(self.start(walk));
+        }
+        match self.receiver.as_mut() {
+            Some(receiver) => receiver.poll_recv(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for TokioWalkStream {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Sends the walk results to [walk_parallel_tokio_stream], like
+/// [ChannelVisitor](crate::ChannelVisitor).
+///
+/// Stops the walk when the stream is dropped.
+#[cfg(feature = "tokio")]
+struct TokioChannelVisitor<'a> {
+    sender: tokio::sync::mpsc::Sender<Result<PathMetadata>>,
+    cancelled: &'a AtomicBool,
+}
+
+#[cfg(feature = "tokio")]
+impl WalkVisitor for TokioChannelVisitor<'_> {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.visit(Ok(entry.clone()))
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        self.visit(Err(anyhow::anyhow!("{}", err).into()))
+    }
+
+    fn pending(&self) -> Option<usize> {
+        Some(self.sender.max_capacity() - self.sender.capacity())
+    }
+
+    fn visit(&self, path_res: Result<PathMetadata>) -> WalkAction {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return WalkAction::Stop;
+        }
+        match self.sender.blocking_send(path_res) {
+            Ok(()) => WalkAction::Continue,
+            Err(_) => WalkAction::Stop,
+        }
+    }
+}
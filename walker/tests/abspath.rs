@@ -0,0 +1,46 @@
+use xvc_walker::{AbsolutePath, Error};
+
+#[test]
+fn test_join_within_rejects_absolute_paths() {
+    let base = AbsolutePath::from("/home/user/repo");
+    assert!(matches!(
+        base.join_within("/etc/passwd"),
+        Err(Error::CannotJoinAbsolutePath { .. })
+    ));
+    assert!(matches!(
+        base.join_normalized("/etc/passwd"),
+        Err(Error::CannotJoinAbsolutePath { .. })
+    ));
+}
+
+#[test]
+fn test_join_within_accepts_parent_dirs_inside_base() {
+    let base = AbsolutePath::from("/home/user/repo");
+    assert_eq!(
+        base.join_within("a/b/../c").unwrap().as_path(),
+        base.as_path().join("a/b/../c")
+    );
+    assert_eq!(
+        base.join_normalized("./a/b/../../c/./d").unwrap().as_path(),
+        base.as_path().join("c/d")
+    );
+    assert_eq!(
+        base.join_normalized("a/..").unwrap().as_path(),
+        base.as_path()
+    );
+}
+
+#[test]
+fn test_join_within_rejects_escaping_paths() {
+    let base = AbsolutePath::from("/home/user/repo");
+    for p in ["..", "a/../../b", "./../repo/a", "a/b/../../../.."] {
+        assert!(
+            matches!(base.join_within(p), Err(Error::PathEscapesBase { .. })),
+            "{p}"
+        );
+        assert!(
+            matches!(base.join_normalized(p), Err(Error::PathEscapesBase { .. })),
+            "{p}"
+        );
+    }
+}
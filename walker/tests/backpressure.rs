@@ -0,0 +1,78 @@
+use anyhow::Result;
+use crossbeam_channel::bounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, Error, IgnoreRules, PathMetadata, WalkOptions};
+
+const DIRS: usize = 50;
+const FILES: usize = 10;
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir-{d:03}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..FILES {
+            fs::write(dir.join(format!("file-{f:02}.txt")), "x")?;
+        }
+    }
+    Ok(root)
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        backpressure_threshold: Some(8),
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[test]
+fn test_backpressure_slow_consumer() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let (path_sender, path_receiver) = bounded::<xvc_walker::Result<PathMetadata>>(16);
+    let receiver = thread::spawn(move || {
+        let mut received = 0;
+        for path_res in path_receiver {
+            assert!(path_res.is_ok());
+            received += 1;
+            if received % 10 == 0 {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        received
+    });
+
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    walk_parallel(ignore_rules, &root, walk_options(), path_sender)?;
+    assert_eq!(receiver.join().unwrap(), DIRS * FILES);
+    Ok(())
+}
+
+#[test]
+fn test_backpressure_receiver_dropped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    // The waiting threads must notice the walk is stopped even though the channel never drains
+    let (path_sender, path_receiver) = bounded(16);
+    let receiver = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        let _ = path_receiver.recv().unwrap();
+    });
+
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    let start = Instant::now();
+    let res = walk_parallel(ignore_rules, &root, walk_options(), path_sender);
+    assert!(start.elapsed() < Duration::from_secs(30));
+    receiver.join().unwrap();
+    assert!(matches!(res, Err(Error::DeliveryIncomplete { .. })));
+    Ok(())
+}
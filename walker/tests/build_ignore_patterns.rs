@@ -0,0 +1,268 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_result, build_ignore_patterns_with_ancestors, Error, MatchResult, Source,
+    DEFAULT_MAX_PATTERNS_PER_FILE,
+};
+
+fn setup_test_directory(structure: &[&str], ignore_files: &[(&str, &str)]) -> Result<PathBuf> {
+    let root = create_temp_dir();
+
+    for path_str in structure.iter().chain(ignore_files.iter().map(|(p, _)| p)) {
+        let path = root.join(path_str);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, path_str)?;
+    }
+
+    for (path_str, content) in ignore_files {
+        fs::write(root.join(path_str), content)?;
+    }
+
+    Ok(root)
+}
+
+fn relative(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+    let mut relative: Vec<String> = paths
+        .iter()
+        .map(|p| {
+            p.strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    relative.sort();
+    relative
+}
+
+#[test]
+fn test_ignored_dir_is_reported_as_unscanned() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["a.txt", "build/out.bin", "src/main.rs"],
+        &[
+            (".gitignore", "build/"),
+            ("build/.gitignore", "!out.bin"),
+            ("src/.gitignore", "*.o"),
+        ],
+    )?;
+
+    let result = build_ignore_patterns_result("", &root, ".gitignore")?;
+
+    assert_eq!(relative(&root, &result.unscanned_dirs), vec!["build"]);
+    assert_eq!(
+        relative(&root, &result.files_read),
+        vec![".gitignore", "src/.gitignore"]
+    );
+    assert_eq!(result.rules.check(&root.join("build")), MatchResult::Ignore);
+    Ok(())
+}
+
+#[test]
+fn test_nothing_unscanned_without_ignored_dirs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["a.txt", "dir/b.txt"],
+        &[(".gitignore", "*.log"), ("dir/.gitignore", "*.tmp")],
+    )?;
+
+    let result = build_ignore_patterns_result("", &root, ".gitignore")?;
+
+    assert!(result.unscanned_dirs.is_empty());
+    assert_eq!(
+        relative(&root, &result.files_read),
+        vec![".gitignore", "dir/.gitignore"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ignore_files_in_ignored_dirs_are_reported() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &[
+            "a.txt",
+            "legacy/old.txt",
+            "cache/x.bin",
+            "legacy/nested/y.txt",
+        ],
+        &[
+            (".gitignore", "legacy/\ncache\n"),
+            ("legacy/.gitignore", "!old.txt"),
+            ("legacy/nested/.gitignore", "*.txt"),
+        ],
+    )?;
+
+    let result = build_ignore_patterns_result("", &root, ".gitignore")?;
+
+    assert_eq!(
+        relative(&root, &result.unscanned_dirs),
+        vec!["cache", "legacy"]
+    );
+    assert_eq!(relative(&root, &result.files_read), vec![".gitignore"]);
+    // The directories under the ignored ones are not checked
+    assert_eq!(result.skipped_ignore_files.len(), 1);
+    let (skipped_file, pattern) = &result.skipped_ignore_files[0];
+    assert_eq!(*skipped_file, root.join("legacy/.gitignore"));
+    assert_eq!(pattern.original_pattern(), "legacy/");
+    assert!(
+        matches!(&pattern.source, Source::File { path, line: 1, .. } if path == Path::new(".gitignore"))
+    );
+    // Like git, the patterns in the skipped file don't apply
+    assert_eq!(
+        result.rules.check(&root.join("legacy/old.txt")),
+        MatchResult::Ignore
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parent_ignore_file_applies_to_walk_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["data/a.tmp", "data/b.txt", "data/c.log", "data/sub/d.tmp"],
+        &[(".gitignore", "data/*.tmp\n*.log\nother/*.txt\n")],
+    )?;
+    let walk_root = root.join("data");
+
+    let rules = build_ignore_patterns_with_ancestors("", &walk_root, &root, ".gitignore")?;
+
+    assert_eq!(rules.check(&walk_root.join("a.tmp")), MatchResult::Ignore);
+    assert_eq!(rules.check(&walk_root.join("c.log")), MatchResult::Ignore);
+    assert_eq!(
+        rules.check(&walk_root.join("sub/d.tmp")),
+        MatchResult::NoMatch
+    );
+    assert_eq!(rules.check(&walk_root.join("b.txt")), MatchResult::NoMatch);
+    Ok(())
+}
+
+#[test]
+fn test_ignored_walk_root_ignores_its_contents() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["data/b.txt", "data/sub/c.txt"],
+        &[(".gitignore", "data/")],
+    )?;
+    let walk_root = root.join("data");
+
+    let rules = build_ignore_patterns_with_ancestors("", &walk_root, &root, ".gitignore")?;
+
+    assert_eq!(rules.check(&walk_root.join("b.txt")), MatchResult::Ignore);
+    assert_eq!(
+        rules.check(&walk_root.join("sub/c.txt")),
+        MatchResult::Ignore
+    );
+    Ok(())
+}
+
+#[test]
+fn test_git_info_exclude_applies_to_walk_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["data/a.tmp", "data/b.txt", "data/c.bak", "data/sub/d.tmp"],
+        &[(".git/info/exclude", "data/*.tmp\n*.bak\n")],
+    )?;
+    let walk_root = root.join("data");
+
+    let rules = build_ignore_patterns_with_ancestors("", &walk_root, &root, ".gitignore")?;
+
+    assert_eq!(rules.check(&walk_root.join("a.tmp")), MatchResult::Ignore);
+    assert_eq!(rules.check(&walk_root.join("c.bak")), MatchResult::Ignore);
+    assert_eq!(
+        rules.check(&walk_root.join("sub/d.tmp")),
+        MatchResult::NoMatch
+    );
+    assert_eq!(rules.check(&walk_root.join("b.txt")), MatchResult::NoMatch);
+    let summaries = rules.snapshot_patterns();
+    let exclude = summaries.iter().find(|s| s.original == "*.bak").unwrap();
+    assert_eq!(
+        exclude.source_path.as_deref(),
+        Some(root.join(".git/info/exclude").as_path())
+    );
+    assert_eq!(exclude.line, Some(2));
+
+    // Only the rules for .gitignore read it
+    let rules = build_ignore_patterns_with_ancestors("", &walk_root, &root, ".xvcignore")?;
+    assert_eq!(rules.check(&walk_root.join("c.bak")), MatchResult::NoMatch);
+    Ok(())
+}
+
+#[test]
+fn test_ancestor_ignore_file_over_the_pattern_limit() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let patterns = (0..=DEFAULT_MAX_PATTERNS_PER_FILE)
+        .map(|i| format!("file-{i}.txt\n"))
+        .collect::<String>();
+    let root = setup_test_directory(&["data/a.txt"], &[(".gitignore", &patterns)])?;
+    let walk_root = root.join("data");
+
+    let result = build_ignore_patterns_with_ancestors("", &walk_root, &root, ".gitignore");
+
+    match result {
+        Err(Error::TooManyPatterns { source }) => {
+            assert_eq!(source.source_file, Some(root.join(".gitignore")));
+            assert_eq!(source.found, DEFAULT_MAX_PATTERNS_PER_FILE + 1);
+        }
+        res => panic!("Unexpected result: {res:?}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_walk_root_outside_repo_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(&["repo/a.txt", "other/b.txt"], &[])?;
+
+    let result = build_ignore_patterns_with_ancestors(
+        "",
+        &root.join("other"),
+        &root.join("repo"),
+        ".gitignore",
+    );
+
+    assert!(matches!(result, Err(Error::PathEscapesBase { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_git_info_exclude_is_loaded_before_gitignore() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["a.log", "keep.log", "dir/b.log", "c.txt"],
+        &[
+            (".git/info/exclude", "# local\n*.log\n"),
+            (".gitignore", "!keep.log\n"),
+        ],
+    )?;
+
+    let result = build_ignore_patterns_result("", &root, ".gitignore")?;
+
+    assert_eq!(
+        relative(&root, &result.files_read),
+        vec![".git/info/exclude", ".gitignore"]
+    );
+    let rules = result.rules;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("dir/b.log")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("keep.log")), MatchResult::Whitelist);
+    assert_eq!(rules.check(&root.join("c.txt")), MatchResult::NoMatch);
+
+    let summaries = rules.snapshot_patterns();
+    let exclude = summaries.iter().find(|s| s.original == "*.log").unwrap();
+    assert_eq!(
+        exclude.source_path.as_deref(),
+        Some(Path::new(".git/info/exclude"))
+    );
+    assert_eq!(exclude.line, Some(2));
+
+    // Other ignore files don't read it
+    let rules = build_ignore_patterns_result("", &root, ".xvcignore")?.rules;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::NoMatch);
+    Ok(())
+}
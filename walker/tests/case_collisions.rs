@@ -0,0 +1,158 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    find_case_collisions, walk_parallel, walk_serial_visit, Error, IgnoreRules, PathMetadata,
+    WalkAction, WalkOptions, WalkVisitor,
+};
+
+#[test]
+fn test_find_case_collisions() {
+    let paths = [
+        "d/README.md",
+        "d/src",
+        "d/readme.md",
+        "d/Src",
+        "d/b",
+        "d/Readme.MD",
+    ];
+    assert_eq!(
+        find_case_collisions(paths),
+        vec![
+            (PathBuf::from("d/README.md"), PathBuf::from("d/readme.md")),
+            (PathBuf::from("d/src"), PathBuf::from("d/Src")),
+            (PathBuf::from("d/README.md"), PathBuf::from("d/Readme.MD")),
+        ]
+    );
+    // Non-ASCII names are lowercased too
+    assert_eq!(
+        find_case_collisions(["\u{C9}t\u{C9}", "\u{E9}t\u{E9}"]).len(),
+        1
+    );
+    assert!(find_case_collisions(["a", "b", "a.txt"]).is_empty());
+}
+
+#[derive(Default)]
+struct Collector {
+    paths: Mutex<Vec<PathBuf>>,
+    collisions: Mutex<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl WalkVisitor for Collector {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths.lock().unwrap().push(entry.path.clone());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        if let Error::CaseCollision { kept, conflicting } = err {
+            self.collisions
+                .lock()
+                .unwrap()
+                .push((kept.clone(), conflicting.clone()));
+        }
+        WalkAction::Continue
+    }
+}
+
+fn walk_options(detect_case_collisions: bool) -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        detect_case_collisions,
+        ..WalkOptions::gitignore()
+    }
+}
+
+/// Returns the collisions as a sorted pair of paths, as the order of the listing is not known.
+fn sorted_pair((kept, conflicting): (PathBuf, PathBuf)) -> (PathBuf, PathBuf) {
+    if kept < conflicting {
+        (kept, conflicting)
+    } else {
+        (conflicting, kept)
+    }
+}
+
+/// The kept and conflicting paths of the collisions.
+type Collisions = Vec<(PathBuf, PathBuf)>;
+
+fn walk_parallel_paths(
+    root: &Path,
+    walk_options: WalkOptions,
+) -> Result<(Vec<PathBuf>, Collisions)> {
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel(
+        Arc::new(RwLock::new(IgnoreRules::empty(root, None))),
+        root,
+        walk_options,
+        path_sender,
+    )?;
+    let mut paths = Vec::new();
+    let mut collisions = Vec::new();
+    for pm_res in path_receiver {
+        match pm_res {
+            Ok(pm) => paths.push(pm.path),
+            Err(Error::CaseCollision { kept, conflicting }) => {
+                collisions.push(sorted_pair((kept, conflicting)))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    paths.sort();
+    Ok((paths, collisions))
+}
+
+#[test]
+fn test_walk_case_collisions() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join("dir/a.txt"), "a")?;
+    fs::write(root.join("dir/A.txt"), "A")?;
+    fs::write(root.join("b.txt"), "b")?;
+    // The second file replaces the first on case-insensitive file systems
+    if fs::read_dir(root.join("dir"))?.count() != 2 {
+        return Ok(());
+    }
+    fs::create_dir_all(root.join("Dir"))?;
+    fs::write(root.join("Dir/c.txt"), "c")?;
+
+    let expected_paths = vec![
+        root.join("Dir/c.txt"),
+        root.join("b.txt"),
+        root.join("dir/A.txt"),
+        root.join("dir/a.txt"),
+    ];
+    let expected_collisions = vec![
+        (root.join("Dir"), root.join("dir")),
+        (root.join("dir/A.txt"), root.join("dir/a.txt")),
+    ];
+
+    let (paths, mut collisions) = walk_parallel_paths(&root, walk_options(true))?;
+    collisions.sort();
+    assert_eq!(paths, expected_paths);
+    assert_eq!(collisions, expected_collisions);
+
+    let visitor = Collector::default();
+    walk_serial_visit("", &root, &walk_options(true), &visitor)?;
+    let mut paths = visitor.paths.into_inner().unwrap();
+    paths.sort();
+    let mut collisions: Vec<_> = visitor
+        .collisions
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(sorted_pair)
+        .collect();
+    collisions.sort();
+    assert_eq!(paths, expected_paths);
+    assert_eq!(collisions, expected_collisions);
+
+    let (paths, collisions) = walk_parallel_paths(&root, walk_options(false))?;
+    assert_eq!(paths, expected_paths);
+    assert!(collisions.is_empty());
+    Ok(())
+}
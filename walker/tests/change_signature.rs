@@ -0,0 +1,92 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{ChangeSignature, PathMetadata};
+
+fn path_metadata(path: &Path) -> Result<PathMetadata> {
+    Ok(PathMetadata::new(
+        path.to_path_buf(),
+        path.symlink_metadata()?,
+    ))
+}
+
+/// Rewrites `path` with `content` and sets its modification time back.
+fn rewrite_keeping_mtime(path: &Path, content: &str) -> Result<()> {
+    let mtime = path.metadata()?.modified()?;
+    fs::write(path, content)?;
+    fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(mtime)?;
+    Ok(())
+}
+
+#[test]
+fn test_change_signature_unchanged() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let path = root.join("file.txt");
+    fs::write(&path, "content")?;
+
+    let signature = path_metadata(&path)?.change_signature();
+    assert_eq!(signature.len, 7);
+    assert_eq!(signature, path_metadata(&path)?.change_signature());
+
+    let signatures = HashSet::from([signature]);
+    assert!(signatures.contains(&ChangeSignature::from(&path.metadata()?)));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_change_signature_same_size_rewrite() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let path = root.join("file.txt");
+    fs::write(&path, "content")?;
+    let before = path_metadata(&path)?.change_signature();
+
+    // The status change time may have a coarse resolution
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    rewrite_keeping_mtime(&path, "CONTENT")?;
+    let after = path_metadata(&path)?.change_signature();
+
+    assert_eq!(before.len, after.len);
+    assert_eq!(before.mtime, after.mtime);
+    assert_eq!(before.ino, after.ino);
+    assert_ne!(
+        (before.ctime, before.ctime_nsec),
+        (after.ctime, after.ctime_nsec)
+    );
+    assert_ne!(before, after);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_change_signature_replaced_file() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let path = root.join("file.txt");
+    fs::write(&path, "content")?;
+    let before = path_metadata(&path)?.change_signature();
+
+    // Save to a temporary file and rename it, like some editors do
+    let temp_path = root.join("file.txt.tmp");
+    fs::write(&temp_path, "CONTENT")?;
+    fs::File::options()
+        .write(true)
+        .open(&temp_path)?
+        .set_modified(before.mtime.unwrap())?;
+    fs::rename(&temp_path, &path)?;
+    let after = path_metadata(&path)?.change_signature();
+
+    assert_eq!(before.len, after.len);
+    assert_eq!(before.mtime, after.mtime);
+    assert_ne!(before.ino, after.ino);
+    assert_ne!(before, after);
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::PathBuf;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    directory_list, directory_list_sorted, directory_list_with_rules, IgnoreRules, MatchResult,
+};
+
+#[test]
+fn test_directory_list_sorted() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    for name in [
+        "b.txt", "a-1", "Z.txt", "a", "10.txt", "2.txt", ".hidden", "a.b",
+    ] {
+        fs::write(root.join(name), name)?;
+    }
+    fs::create_dir_all(root.join("a-dir/sub"))?;
+
+    let sorted = directory_list_sorted(&root)?
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<PathBuf>>>()?;
+    let expected = [
+        ".hidden", "10.txt", "2.txt", "Z.txt", "a", "a-1", "a-dir", "a.b", "b.txt",
+    ]
+    .iter()
+    .map(|name| root.join(name))
+    .collect::<Vec<_>>();
+    assert_eq!(sorted, expected);
+
+    // The same paths as the unsorted list
+    let mut unsorted = directory_list(&root)?
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<PathBuf>>>()?;
+    unsorted.sort();
+    assert_eq!(unsorted, sorted);
+
+    assert!(directory_list_sorted(&root.join("missing")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_directory_list_with_rules() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    for name in ["a.txt", "b.log", "keep.log", ".hidden"] {
+        fs::write(root.join(name), name)?;
+    }
+    fs::create_dir_all(root.join("logs"))?;
+    fs::create_dir_all(root.join("src/logs"))?;
+    let ignore_rules =
+        IgnoreRules::from_global_patterns(&root, None, "*.log\n!keep.log\n/logs/\n.*");
+
+    let mut paths = directory_list_with_rules(&root, &ignore_rules)?
+        .into_iter()
+        .map(|pm| pm.path)
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    let expected = ["a.txt", "keep.log", "src"]
+        .iter()
+        .map(|name| root.join(name))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, expected);
+
+    // The same paths as filtering the full list
+    let mut filtered = directory_list(&root)?
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<PathBuf>>>()?
+        .into_iter()
+        .filter(|path| ignore_rules.check(path) != MatchResult::Ignore)
+        .collect::<Vec<_>>();
+    filtered.sort();
+    assert_eq!(filtered, paths);
+
+    assert!(directory_list_with_rules(&root.join("missing"), &ignore_rules).is_err());
+    Ok(())
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "tokio")]
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::PathBuf;
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    directory_list, directory_list_async, directory_list_filtered_async, IgnoreRules, MatchResult,
+};
+
+fn setup_dir() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for name in ["a.txt", "b.log", ".hidden", "c.txt"] {
+        fs::write(root.join(name), name)?;
+    }
+    fs::create_dir_all(root.join("dir/sub"))?;
+    fs::create_dir_all(root.join("logs"))?;
+    make_symlink(root.join("a.txt"), root.join("link"))?;
+    make_symlink(root.join("missing.txt"), root.join("broken-link"))?;
+    Ok(root)
+}
+
+/// Returns the paths, whether each is a directory or a symlink, and their sizes, sorted by path.
+fn summary(
+    child_paths: Vec<xvc_walker::Result<xvc_walker::PathMetadata>>,
+) -> Result<Vec<(PathBuf, bool, bool, u64)>> {
+    let mut summary = child_paths
+        .into_iter()
+        .map(|pm_res| {
+            pm_res.map(|pm| {
+                (
+                    pm.path,
+                    pm.metadata.is_dir(),
+                    pm.metadata.is_symlink(),
+                    pm.metadata.len(),
+                )
+            })
+        })
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    summary.sort();
+    Ok(summary)
+}
+
+#[tokio::test]
+async fn test_directory_list_async_is_the_same_as_sync() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_dir()?;
+
+    let sync_list = summary(directory_list(&root)?)?;
+    let async_list = summary(directory_list_async(&root).await?)?;
+    assert_eq!(sync_list.len(), 8);
+    assert_eq!(sync_list, async_list);
+
+    // Symlinks are not followed
+    assert!(async_list
+        .iter()
+        .any(|(p, _, is_symlink, _)| *p == root.join("broken-link") && *is_symlink));
+
+    assert!(directory_list(&root.join("missing")).is_err());
+    assert!(directory_list_async(&root.join("missing")).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_directory_list_filtered_async() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_dir()?;
+    let ignore_rules = IgnoreRules::from_global_patterns(&root, None, "*.log\nlogs/\n.*\n!.hidden");
+
+    let mut paths = directory_list_filtered_async(&root, &ignore_rules)
+        .await?
+        .into_iter()
+        .map(|pm| pm.path)
+        .collect::<Vec<_>>();
+    paths.sort();
+    let expected = [".hidden", "a.txt", "broken-link", "c.txt", "dir", "link"]
+        .iter()
+        .map(|name| root.join(name))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, expected);
+
+    // The same paths as the ignore rules accept in the sync list
+    let mut sync_paths = directory_list(&root)?
+        .into_iter()
+        .filter_map(|pm_res| pm_res.ok())
+        .filter(|pm| !matches!(ignore_rules.check(&pm.path), MatchResult::Ignore))
+        .map(|pm| pm.path)
+        .collect::<Vec<_>>();
+    sync_paths.sort();
+    assert_eq!(paths, sync_paths);
+
+    assert!(
+        directory_list_filtered_async(&root.join("missing"), &ignore_rules)
+            .await
+            .is_err()
+    );
+    Ok(())
+}
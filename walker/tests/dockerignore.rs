@@ -0,0 +1,58 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_serial, WalkOptions};
+
+fn run_walk(root: &Path, walk_options: &WalkOptions) -> Result<HashSet<String>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", root, walk_options)?;
+    Ok(paths
+        .iter()
+        .map(|pm| {
+            pm.path
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect())
+}
+
+#[test]
+fn test_dockerignore_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("src/logs"))?;
+    fs::create_dir_all(root.join("target"))?;
+    fs::write(root.join("a.log"), "a")?;
+    fs::write(root.join("src/b.log"), "b")?;
+    fs::write(root.join("src/logs/c.txt"), "c")?;
+    fs::write(root.join("target/d.bin"), "d")?;
+    fs::write(root.join("!e.txt"), "e")?;
+    fs::write(root.join("e.txt"), "e")?;
+    fs::write(root.join(".dockerignore"), "*.log\ntarget/\n!e.txt\n")?;
+
+    let found = run_walk(&root, &WalkOptions::dockerignore())?;
+    // `*.log` is relative to the root and `!` is not negation
+    let expected: HashSet<String> = [".dockerignore", "src/b.log", "src/logs/c.txt", "e.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(found, expected);
+
+    let walk_options = WalkOptions {
+        ignore_filename: Some(".dockerignore".into()),
+        ..WalkOptions::gitignore()
+    };
+    let found = run_walk(&root, &walk_options)?;
+    let expected: HashSet<String> = [".dockerignore", "src/logs/c.txt", "!e.txt", "e.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(found, expected);
+    Ok(())
+}
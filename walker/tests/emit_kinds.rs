@@ -0,0 +1,152 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_serial, EmitKinds, IgnoreRules,
+    WalkOptions,
+};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..3 {
+        let dir = root.join(format!("d{i}/e/f"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("a.txt"), "a")?;
+        fs::write(root.join(format!("d{i}/b.txt")), "b")?;
+        fs::write(root.join(format!("d{i}/b.log")), "b")?;
+    }
+    fs::create_dir_all(root.join("empty"))?;
+    fs::create_dir_all(root.join("ignored"))?;
+    fs::write(root.join("ignored/c.txt"), "c")?;
+    fs::write(root.join(".gitignore"), "*.log\nignored/\n")?;
+    Ok(root)
+}
+
+/// Walks `root` with both walkers and returns the paths and whether each is a directory, after
+/// checking that they agree.
+fn walk(root: &Path, walk_options: &WalkOptions) -> Result<BTreeSet<(PathBuf, bool)>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", root, walk_options)?;
+    let serial = paths
+        .into_iter()
+        .map(|pm| (pm.path, pm.metadata.is_dir()))
+        .collect::<BTreeSet<_>>();
+
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = if root.is_dir() {
+        build_ignore_patterns_with_options("", root, walk_options)?
+    } else {
+        IgnoreRules::empty(root.parent().unwrap(), None)
+    };
+    let ignore_rules = Arc::new(RwLock::new(ignore_rules));
+    walk_parallel(ignore_rules, root, walk_options.clone(), path_sender)?;
+    let parallel = path_receiver
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| (pm.path, pm.metadata.is_dir())))
+        .collect::<xvc_walker::Result<BTreeSet<_>>>()?;
+
+    assert_eq!(serial, parallel, "{:?}", walk_options.emit);
+    Ok(serial)
+}
+
+#[test]
+fn test_emit_kinds_filter_the_full_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let full = walk(
+        &root,
+        &WalkOptions {
+            parents_first: true,
+            ..WalkOptions::gitignore()
+        },
+    )?;
+    // 3 * 3 + 1 directories and 3 * 2 + 1 files
+    assert_eq!(full.iter().filter(|(_, is_dir)| *is_dir).count(), 10);
+    assert_eq!(full.len(), 17);
+
+    for emit in [
+        EmitKinds::ALL,
+        EmitKinds::FILES | EmitKinds::DIRS,
+        EmitKinds::FILES,
+        EmitKinds::DIRS,
+        EmitKinds::NONE,
+    ] {
+        for parents_first in [true, false] {
+            let walk_options = WalkOptions {
+                parents_first,
+                emit,
+                ..WalkOptions::gitignore()
+            };
+            let expected = full
+                .iter()
+                .filter(|(_, is_dir)| {
+                    if *is_dir {
+                        // Only the directories are reported without parents_first
+                        emit.contains(EmitKinds::DIRS)
+                            && (parents_first || !emit.contains(EmitKinds::FILES))
+                    } else {
+                        emit.contains(EmitKinds::FILES)
+                    }
+                })
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            assert_eq!(walk(&root, &walk_options)?, expected);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_emit_dirs_without_parents_first() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let dirs_only = WalkOptions {
+        parents_first: false,
+        emit: EmitKinds::DIRS,
+        ..WalkOptions::gitignore()
+    };
+    let dirs = walk(&root, &dirs_only)?;
+    assert_eq!(dirs.len(), 10);
+    assert!(dirs.iter().all(|(_, is_dir)| *is_dir));
+    assert!(dirs.contains(&(root.join("d0/e/f"), true)));
+    assert!(dirs.contains(&(root.join("empty"), true)));
+    Ok(())
+}
+
+#[test]
+fn test_emit_kinds_with_file_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let file = root.join("d0/b.txt");
+    let files_only = WalkOptions {
+        emit: EmitKinds::FILES,
+        ..WalkOptions::gitignore()
+    };
+    assert_eq!(walk(&file, &files_only)?.len(), 1);
+
+    let dirs_only = WalkOptions {
+        parents_first: true,
+        emit: EmitKinds::DIRS,
+        ..WalkOptions::gitignore()
+    };
+    assert!(walk(&file, &dirs_only)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_emit_kinds_default() {
+    assert_eq!(EmitKinds::default(), EmitKinds::ALL);
+    assert_eq!(WalkOptions::xvcignore().emit, EmitKinds::ALL);
+    assert!(EmitKinds::ALL.contains(EmitKinds::FILES | EmitKinds::DIRS));
+    assert!(!EmitKinds::FILES.contains(EmitKinds::DIRS));
+    assert!(EmitKinds::DIRS.contains(EmitKinds::NONE));
+
+    let mut emit = EmitKinds::NONE;
+    emit |= EmitKinds::DIRS;
+    assert_eq!(emit, EmitKinds::DIRS);
+}
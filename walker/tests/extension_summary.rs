@@ -0,0 +1,119 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel_collect, walk_serial_visit, Error, ExtensionSummary, IgnoreRules, PathMetadata,
+    WalkAction, WalkOptions, WalkVisitor, NO_EXTENSION,
+};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("src/deep"))?;
+    fs::create_dir_all(root.join("data.d"))?;
+    fs::write(root.join("README"), "readme")?;
+    fs::write(root.join("notes."), "ab")?;
+    fs::write(root.join("src/main.rs"), "fn main() {}")?;
+    fs::write(root.join("src/deep/lib.rs"), "mod a;")?;
+    fs::write(root.join("src/deep/Old.RS"), "old")?;
+    fs::write(root.join("data.d/a.csv"), "1,2,3\n")?;
+    fs::write(root.join("data.d/b.log"), "ignored")?;
+    Ok(root)
+}
+
+fn walk(
+    root: &Path,
+    case_insensitive: bool,
+    summarize_extensions: bool,
+) -> Result<HashMap<OsString, ExtensionSummary>> {
+    let mut ignore_rules = IgnoreRules::from_global_patterns(root, None, "*.log");
+    ignore_rules.case_insensitive = case_insensitive;
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        summarize_extensions,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, metrics) =
+        walk_parallel_collect(Arc::new(RwLock::new(ignore_rules)), root, walk_options)?;
+    assert!(paths.iter().all(|pm_res| pm_res.is_ok()));
+    Ok(metrics.extensions)
+}
+
+fn summary(count: usize, bytes: u64) -> ExtensionSummary {
+    ExtensionSummary { count, bytes }
+}
+
+#[test]
+fn test_extension_summary() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let extensions = walk(&root, false, true)?;
+    let expected = HashMap::from([
+        (OsString::from("rs"), summary(2, 12 + 6)),
+        (OsString::from("RS"), summary(1, 3)),
+        (OsString::from("csv"), summary(1, 6)),
+        // README and notes.
+        (OsString::from(NO_EXTENSION), summary(2, 6 + 2)),
+    ]);
+    assert_eq!(extensions, expected);
+
+    // The extensions are folded with case-insensitive rules
+    let extensions = walk(&root, true, true)?;
+    assert_eq!(extensions[&OsString::from("rs")], summary(3, 12 + 6 + 3));
+    assert!(!extensions.contains_key(&OsString::from("RS")));
+
+    // A root that is a file is counted too
+    let (_, metrics) = walk_parallel_collect(
+        Arc::new(RwLock::new(IgnoreRules::empty(&root, None))),
+        &root.join("src/main.rs"),
+        WalkOptions {
+            ignore_filename: None,
+            summarize_extensions: true,
+            ..WalkOptions::gitignore()
+        },
+    )?;
+    assert_eq!(
+        metrics.extensions,
+        HashMap::from([(OsString::from("rs"), summary(1, 12))])
+    );
+
+    assert!(walk(&root, false, false)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_extension_summary_of_serial_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    struct Nothing;
+    impl WalkVisitor for Nothing {
+        fn on_entry(&self, _entry: &PathMetadata) -> WalkAction {
+            WalkAction::Continue
+        }
+
+        fn on_error(&self, _err: &Error) -> WalkAction {
+            WalkAction::Continue
+        }
+    }
+
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        summarize_extensions: true,
+        ..WalkOptions::gitignore()
+    };
+    let (_, metrics) = walk_serial_visit("*.log", &root, &walk_options, &Nothing)?;
+    assert_eq!(metrics.extensions, walk(&root, false, true)?);
+
+    let walk_options = WalkOptions {
+        summarize_extensions: false,
+        ..walk_options
+    };
+    let (_, metrics) = walk_serial_visit("*.log", &root, &walk_options, &Nothing)?;
+    assert!(metrics.extensions.is_empty());
+    Ok(())
+}
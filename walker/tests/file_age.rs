@@ -0,0 +1,42 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::PathMetadata;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn write_with_mtime(path: &Path, mtime: SystemTime) -> Result<PathMetadata> {
+    fs::write(path, "content")?;
+    fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(mtime)?;
+    Ok(PathMetadata::new(path.to_path_buf(), path.metadata()?))
+}
+
+#[test]
+fn test_file_age() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+
+    let old = write_with_mtime(&root.join("old.txt"), SystemTime::now() - 2 * HOUR)?;
+    assert!(old.file_age()? >= 2 * HOUR);
+    assert!(old.is_older_than(HOUR));
+    assert!(!old.is_newer_than(HOUR));
+
+    let new = write_with_mtime(&root.join("new.txt"), SystemTime::now())?;
+    assert!(new.file_age()? < HOUR);
+    assert!(new.is_newer_than(HOUR));
+    assert!(!new.is_older_than(HOUR));
+
+    // Modification times in the future are not errors
+    let future = write_with_mtime(&root.join("future.txt"), SystemTime::now() + HOUR)?;
+    assert_eq!(future.file_age()?, Duration::ZERO);
+    assert!(future.is_newer_than(HOUR));
+
+    assert!(old.file_access_age()? < 2 * HOUR);
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    content_to_patterns, path_metadata_map_from_file_targets, IgnoreRules, MatchResult, WalkOptions,
+};
+
+#[test]
+fn test_file_targets_under_ignored_dirs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    for dir in ["logs/old", "src", "a/build/deep"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for file in [
+        "logs/app.txt",
+        "logs/old/app.txt",
+        "logs/keep.txt",
+        "src/main.rs",
+        "a/build/deep/out.o",
+        "a/main.c",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    fs::write(root.join(".gitignore"), "logs/\n!logs/keep.txt\n")?;
+    fs::write(root.join("a/.gitignore"), "build/\n")?;
+
+    let targets = [
+        "logs/app.txt",
+        "logs/old/app.txt",
+        "logs/keep.txt",
+        "src/main.rs",
+        "a/build/deep/out.o",
+        "a/main.c",
+    ]
+    .iter()
+    .map(|t| root.join(t).to_string_lossy().to_string())
+    .collect();
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = path_metadata_map_from_file_targets(
+        &output_sender,
+        "",
+        &root,
+        targets,
+        &WalkOptions::gitignore(),
+    )?;
+
+    let mut found: Vec<String> = paths
+        .iter()
+        .map(|pm| {
+            pm.path
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    found.sort();
+    assert_eq!(found, vec!["a/main.c", "src/main.rs"]);
+    Ok(())
+}
+
+#[test]
+fn test_ancestor_cache_is_reset_on_new_patterns() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("data/raw"))?;
+    fs::write(root.join("data/raw/a.csv"), "a")?;
+
+    let ignore_rules = IgnoreRules::empty(&root, Some(".gitignore"));
+    let file = root.join("data/raw/a.csv");
+    assert_eq!(ignore_rules.check(&file), MatchResult::NoMatch);
+    assert_eq!(
+        ignore_rules.check_path_str("data/raw/a.csv"),
+        MatchResult::NoMatch
+    );
+
+    ignore_rules.add_patterns(content_to_patterns(&root, None, "raw/").0)?;
+    assert_eq!(ignore_rules.check(&file), MatchResult::Ignore);
+    assert_eq!(
+        ignore_rules.check_path_str("data/raw/a.csv"),
+        MatchResult::Ignore
+    );
+    assert_eq!(ignore_rules.check(&root.join("data")), MatchResult::NoMatch);
+
+    // Clones share the patterns and the cached states
+    let clone = ignore_rules.clone();
+    clone.add_override_patterns(content_to_patterns(&root, None, "!data/raw/").0)?;
+    assert_eq!(ignore_rules.check(&file), MatchResult::NoMatch);
+    Ok(())
+}
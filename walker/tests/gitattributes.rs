@@ -0,0 +1,56 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, IgnoreRules, WalkOptions};
+
+fn run_walk(root: &Path, read_gitattributes_export_ignore: bool) -> Result<HashSet<String>> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        read_gitattributes_export_ignore,
+        ..WalkOptions::gitignore()
+    };
+    walk_parallel(ignore_rules, root, walk_options, path_sender)?;
+
+    let mut found_paths = HashSet::new();
+    for path_res in path_receiver {
+        let path_meta = path_res?;
+        let relative_path = path_meta.path.strip_prefix(root)?;
+        found_paths.insert(relative_path.to_string_lossy().replace('\\', "/"));
+    }
+    Ok(found_paths)
+}
+
+#[test]
+fn test_export_ignore_prunes_only_when_enabled() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("data/raw"))?;
+    fs::write(root.join("data/raw/a.bin"), "a")?;
+    fs::write(root.join("data/b.txt"), "b")?;
+    fs::write(
+        root.join("data/.gitattributes"),
+        "raw export-ignore\n*.txt diff\n",
+    )?;
+
+    let with_attributes = run_walk(&root, true)?;
+    let expected: HashSet<String> = ["data/.gitattributes", "data/b.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(with_attributes, expected);
+
+    let without_attributes = run_walk(&root, false)?;
+    let expected: HashSet<String> = ["data/.gitattributes", "data/b.txt", "data/raw/a.bin"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(without_attributes, expected);
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use xvc_walker::glob::MAX_BRACE_GROUPS;
+use xvc_walker::{
+    expand_braces, glob_can_match_at_depth, glob_match, glob_match_ci, glob_match_with_spans,
+    GlobPattern,
+};
+
+#[test]
+fn test_glob_match_wildcards() {
+    assert!(glob_match("*.rs", "main.rs"));
+    assert!(!glob_match("*.rs", "src/main.rs"));
+    assert!(glob_match("**/*.rs", "main.rs"));
+    assert!(glob_match("**/*.rs", "src/bin/main.rs"));
+    assert!(glob_match("a/**/b", "a/b"));
+    assert!(glob_match("a/**/b", "a/x/y/b"));
+    assert!(glob_match("a?c", "abc"));
+    assert!(!glob_match("a?c", "a/c"));
+}
+
+#[test]
+fn test_glob_match_classes_and_braces() {
+    assert!(glob_match("data[0-9].csv", "data1.csv"));
+    assert!(!glob_match("data[0-9].csv", "dataA.csv"));
+    assert!(glob_match("data[!0-9].csv", "dataA.csv"));
+    assert!(glob_match("[!.RS]", "x"));
+    assert!(!glob_match("[!.RS]", "R"));
+    assert!(!glob_match("[^.RS]", "."));
+    assert!(glob_match("*.{js,ts}", "index.ts"));
+    assert!(!glob_match("*.{js,ts}", "index.rs"));
+    assert!(glob_match(r"\*.txt", "*.txt"));
+    assert!(!glob_match(r"\*.txt", "a.txt"));
+}
+
+#[test]
+fn test_glob_match_class_grammar() {
+    // (glob, path, matches), checked against `git check-ignore`
+    let cases = [
+        ("file[a-zA-Z0-9_].dat", "filea.dat", true),
+        ("file[a-zA-Z0-9_].dat", "fileZ.dat", true),
+        ("file[a-zA-Z0-9_].dat", "file5.dat", true),
+        ("file[a-zA-Z0-9_].dat", "file_.dat", true),
+        ("file[a-zA-Z0-9_].dat", "file-.dat", false),
+        ("file[a-zA-Z0-9_].dat", "file..dat", false),
+        ("[a-]", "a", true),
+        ("[a-]", "-", true),
+        ("[a-]", "b", false),
+        ("[-a]", "-", true),
+        ("[-a]", "a", true),
+        ("[-a]", "b", false),
+        ("[]x]", "]", true),
+        ("[]x]", "x", true),
+        ("[]x]", "a", false),
+        ("[!]x]", "]", false),
+        ("[!]x]", "a", true),
+        ("[^]x]", "x", false),
+        ("[]-b]", "]", true),
+        ("[]-b]", "_", true),
+        ("[]-b]", "b", true),
+        ("[]-b]", "c", false),
+        ("[z-a]", "z", true),
+        ("[z-a]", "a", false),
+        ("[z-a]", "-", false),
+        ("[z-a]", "m", false),
+        ("[z-ab]", "b", true),
+        ("[a-c-m]", "b", true),
+        ("[a-c-m]", "-", true),
+        ("[a-c-m]", "m", true),
+        ("[a-c-m]", "_", false),
+        ("[--z]", "-", true),
+        ("[--z]", "]", true),
+        ("[+--]", "-", true),
+        ("[+--]", ",", true),
+        ("[a-a]", "a", true),
+        (r"[\]]", "]", true),
+        (r"[a\-z]", "-", true),
+        (r"[a\-z]", "m", false),
+        ("a[!b]c", "a/c", false),
+        ("a[/]c", "a/c", false),
+        ("[a-z", "a", false),
+        ("[]", "]", false),
+    ];
+    for (glob, path, expected) in cases {
+        assert_eq!(glob_match(glob, path), expected, "{glob} {path}");
+        assert_eq!(
+            glob_match_with_spans(glob, path).is_some(),
+            expected,
+            "{glob} {path}"
+        );
+        assert_eq!(
+            GlobPattern::new(glob).matches(path),
+            expected,
+            "{glob} {path}"
+        );
+    }
+}
+
+#[test]
+fn test_glob_match_trailing_slash() {
+    assert!(glob_match("dir/", "dir/"));
+    assert!(!glob_match("dir/", "dir"));
+    assert!(glob_match("!*.rs", "main.js"));
+}
+
+#[test]
+fn test_glob_match_fuzz_findings() {
+    // `**` after the negation is a whole component
+    assert!(!glob_match("!**", "a/b"));
+    assert!(glob_match("!**/b", "a/c"));
+    // `**/**` is merged only if the first `**` is a whole component
+    assert!(!glob_match("?**/**", "a"));
+    assert!(glob_match("?**/**", "ab/c"));
+    // A comma after the brace groups is a literal
+    assert!(!glob_match("{a,b}{},c", "b"));
+    assert!(glob_match("{a,b}{},c", "b,c"));
+    // Too many brace groups don't match instead of panicking
+    assert!(glob_match(
+        "{a,b}".repeat(MAX_BRACE_GROUPS),
+        "a".repeat(MAX_BRACE_GROUPS)
+    ));
+    let glob = "{a,b}".repeat(MAX_BRACE_GROUPS + 1);
+    assert!(!glob_match(&glob, "a".repeat(MAX_BRACE_GROUPS + 1)));
+    let nested = format!("{}a{}", "{".repeat(20), "}".repeat(20));
+    assert!(!glob_match(nested, "a"));
+}
+
+#[test]
+fn test_glob_match_case_insensitive() {
+    assert!(glob_match_ci("*.RS", "foo.rs"));
+    assert!(glob_match_ci("src/*.rs", "SRC/Main.RS"));
+    assert!(!glob_match("*.RS", "foo.rs"));
+    assert!(!glob_match_ci("*.RS", "foo.rsx"));
+
+    for (glob, path) in [
+        ("*.RS", "foo.rs"),
+        ("src/*.rs", "SRC/Main.RS"),
+        ("*.RS", "foo.rsx"),
+        ("Build/", "build/"),
+        ("Docs/**/README", "docs/a/b/readme"),
+        ("[A-C]*.txt", "b.TXT"),
+        ("!*.Log", "a.log"),
+    ] {
+        assert_eq!(
+            GlobPattern::new(glob).matches_case_insensitive(path),
+            glob_match_ci(glob, path),
+            "{glob} {path}"
+        );
+    }
+}
+
+#[test]
+fn test_brace_expansion() {
+    for path in ["app.js", "app.ts"] {
+        assert!(glob_match("*.{js,ts}", path));
+    }
+    assert!(!glob_match("*.{js,ts}", "app.rs"));
+    assert_eq!(expand_braces("*.{js,ts}"), vec!["*.js", "*.ts"]);
+
+    assert_eq!(expand_braces("{a,{b,c}}"), vec!["a", "b", "c"]);
+    assert_eq!(
+        expand_braces("src/{a,b}/{x,y{1,2}}.rs"),
+        vec![
+            "src/a/x.rs",
+            "src/a/y1.rs",
+            "src/a/y2.rs",
+            "src/b/x.rs",
+            "src/b/y1.rs",
+            "src/b/y2.rs"
+        ]
+    );
+    for path in ["a", "b", "c"] {
+        assert!(glob_match("{a,{b,c}}", path));
+    }
+
+    for literal in [r"\{a,b}", "[{]a,b}", "{a,b"] {
+        assert_eq!(expand_braces(literal), vec![literal]);
+    }
+}
+
+#[test]
+fn test_glob_match_with_spans() {
+    assert_eq!(
+        glob_match_with_spans("a/**/b", "a/x/y/b"),
+        Some(vec![(2, 5)])
+    );
+    assert_eq!(glob_match_with_spans("a/**/b", "a/b"), Some(vec![(2, 2)]));
+    assert_eq!(
+        glob_match_with_spans("**/*.rs", "src/main.rs"),
+        Some(vec![(0, 3), (4, 8)])
+    );
+    assert_eq!(
+        glob_match_with_spans("data/**", "data/x/y"),
+        Some(vec![(5, 8)])
+    );
+    assert_eq!(
+        glob_match_with_spans("src/?[a-c]*.txt", "src/xby.txt"),
+        Some(vec![(4, 5), (5, 6), (6, 7)])
+    );
+    assert_eq!(
+        glob_match_with_spans("*.{js,ts}", "app.ts"),
+        Some(vec![(0, 3)])
+    );
+    assert_eq!(glob_match_with_spans("main.rs", "main.rs"), Some(vec![]));
+    assert_eq!(glob_match_with_spans("!*.rs", "main.js"), Some(vec![]));
+
+    assert_eq!(glob_match_with_spans("a/**/b", "a/x/c"), None);
+    assert_eq!(glob_match_with_spans("*.rs", "src/main.rs"), None);
+}
+
+#[test]
+fn test_glob_pattern_matches_like_glob_match() {
+    let globs = [
+        "target",
+        "target/",
+        "dir/file.txt",
+        "**/node_modules",
+        "**/node_modules/",
+        "src/**/mod.rs",
+        "src/**/a/b",
+        "**/*.rs",
+        "src/*/mod.rs",
+        "!target",
+        r"a\ ",
+        "{a,b}/c",
+        "data[0-9]",
+    ];
+    let paths = [
+        "",
+        "/",
+        "target",
+        "target/",
+        "a/target",
+        "dir/file.txt",
+        "dir/file.txtx",
+        "node_modules",
+        "node_modules/",
+        "a/node_modules",
+        "a/b/node_modules/",
+        "anode_modules",
+        "src/mod.rs",
+        "src/x/mod.rs",
+        "src/x/y/mod.rs",
+        "src/xmod.rs",
+        "srcmod.rs",
+        "src/a/b",
+        "src/x/a/b",
+        "main.rs",
+        "a ",
+        "a/c",
+        "data1",
+    ];
+    for glob in globs {
+        let glob_pattern = GlobPattern::new(glob);
+        assert_eq!(glob_pattern.as_str(), glob);
+        for path in paths {
+            assert_eq!(
+                glob_pattern.matches(path),
+                glob_match(glob, path),
+                "{glob} {path}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_glob_can_match_at_depth() {
+    assert!(!glob_can_match_at_depth("a/b/c/*.rs", 1));
+    assert!(!glob_can_match_at_depth("a/b/c/*.rs", 3));
+    assert!(glob_can_match_at_depth("a/b/c/*.rs", 4));
+    assert!(!glob_can_match_at_depth("a/b/c/*.rs", 5));
+    assert!(!glob_can_match_at_depth("a/**/b", 1));
+    assert!(glob_can_match_at_depth("a/**/b", 2));
+    assert!(glob_can_match_at_depth("a/**/b", 10));
+    assert!(glob_can_match_at_depth("{a,b/c}", 1));
+    assert!(glob_can_match_at_depth("{a,b/c}", 2));
+    assert!(!glob_can_match_at_depth("{a,b/c}", 3));
+    assert!(glob_can_match_at_depth("!a/b", 1));
+
+    let globs = [
+        "a/*",
+        "a/**",
+        "a/**/",
+        "**/b",
+        "x{**/a,c}",
+        "a[!x]b",
+        "a/*/b/",
+        "**",
+    ];
+    let paths = [
+        "a", "a/", "a/b", "a/x/b", "a/x/b/", "xa", "x/a", "a/b/c/d", "b",
+    ];
+    for glob in globs {
+        for path in paths {
+            let depth = path.matches('/').count() + 1;
+            if glob_match(glob, path) {
+                assert!(glob_can_match_at_depth(glob, depth), "{glob} {path}");
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::env;
+use std::fs;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, global_gitignore_path, walk_serial, MatchResult,
+    WalkOptions,
+};
+
+// The environment is shared by the tests in a binary, so this is the only test here.
+#[test]
+fn test_global_gitignore_has_lowest_precedence() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let home = create_temp_dir();
+    let root = create_temp_dir();
+    fs::write(root.join("a.log"), "a")?;
+    fs::write(root.join("keep.log"), "keep")?;
+    fs::write(root.join(".gitignore"), "!keep.log\n")?;
+
+    env::set_var("HOME", &home);
+    env::set_var("XDG_CONFIG_HOME", home.join("xdg"));
+    fs::write(home.join(".gitignore_global"), "*.log\n")?;
+    env::remove_var("GIT_CONFIG");
+    assert_eq!(
+        global_gitignore_path(),
+        Some(home.join(".gitignore_global"))
+    );
+
+    // $GIT_CONFIG has priority over ~/.gitconfig
+    fs::write(
+        home.join(".gitconfig"),
+        "[core]\n\texcludesFile = ~/from-home\n",
+    )?;
+    fs::write(home.join("config"), "[core]\n\texcludesFile = ~/excludes\n")?;
+    env::set_var("GIT_CONFIG", home.join("config"));
+    fs::write(home.join("excludes"), "*.log\n")?;
+    assert_eq!(global_gitignore_path(), Some(home.join("excludes")));
+
+    let walk_options = WalkOptions {
+        respect_global_gitignore: true,
+        ..WalkOptions::gitignore()
+    };
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("keep.log")), MatchResult::Whitelist);
+    assert_eq!(
+        rules.snapshot_patterns()[0].source_path,
+        Some(home.join("excludes"))
+    );
+
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options)?;
+    let mut found: Vec<_> = paths.iter().map(|pm| pm.path.clone()).collect();
+    found.sort();
+    assert_eq!(found, vec![root.join(".gitignore"), root.join("keep.log")]);
+
+    let rules = build_ignore_patterns_with_options("", &root, &WalkOptions::gitignore())?;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::NoMatch);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_serial, WalkOptions};
+
+#[test]
+fn test_hgignore_walk_reads_only_glob_section() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("src/build"))?;
+    fs::write(root.join("a.pyc"), "a")?;
+    fs::write(root.join("src/b.pyc"), "b")?;
+    fs::write(root.join("src/build/c.txt"), "c")?;
+    fs::write(root.join("d.orig"), "d")?;
+    fs::write(root.join("e.txt"), "e")?;
+    fs::write(
+        root.join(".hgignore"),
+        "syntax: regexp\n\\.orig$\n^e\\.txt$\n\nsyntax: glob\n*.pyc\nbuild/\n",
+    )?;
+
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &WalkOptions::hgignore())?;
+
+    let found: HashSet<String> = paths
+        .iter()
+        .map(|pm| {
+            pm.path
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    let expected: HashSet<String> = [".hgignore", "d.orig", "e.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(found, expected);
+    Ok(())
+}
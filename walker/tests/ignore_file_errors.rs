@@ -0,0 +1,133 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_result_with_options, build_ignore_patterns_with_options, walk_serial,
+    walk_serial_visit, Error, IgnoreFileErrorPolicy, MatchResult, PathMetadata, WalkAction,
+    WalkOptions, WalkVisitor,
+};
+
+/// Creates a tree with an ignore file in `dir` that can't be read, and returns the root.
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join(".gitignore"), "*.log\n")?;
+    fs::write(root.join("a.log"), "a")?;
+    fs::write(root.join("dir/b.txt"), "b")?;
+    fs::write(root.join("dir/c.log"), "c")?;
+
+    // Root can read the files without permissions, so the content is not valid UTF-8 either
+    let unreadable = root.join("dir/.gitignore");
+    fs::write(&unreadable, b"*.txt\n\xff\xfe\n")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o000))?;
+    }
+    Ok(root)
+}
+
+fn walk_options(policy: IgnoreFileErrorPolicy) -> WalkOptions {
+    WalkOptions {
+        ignore_file_errors: policy,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[derive(Default)]
+struct ErrorCollector {
+    paths: Mutex<Vec<PathBuf>>,
+    errors: Mutex<Vec<String>>,
+}
+
+impl WalkVisitor for ErrorCollector {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths.lock().unwrap().push(entry.path.clone());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        if let Error::UnreadableIgnoreFile { source } = err {
+            self.errors
+                .lock()
+                .unwrap()
+                .push(source.path.to_string_lossy().to_string());
+        }
+        WalkAction::Continue
+    }
+}
+
+fn relative_paths(root: &Path, paths: impl IntoIterator<Item = PathBuf>) -> Vec<String> {
+    let mut paths = paths
+        .into_iter()
+        .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_unreadable_ignore_file_fails() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = walk_options(IgnoreFileErrorPolicy::Fail);
+
+    let unreadable = root.join("dir/.gitignore");
+    let res = build_ignore_patterns_with_options("", &root, &walk_options);
+    assert!(
+        matches!(res, Err(Error::UnreadableIgnoreFile { source }) if source.path == unreadable)
+    );
+
+    let (output_sender, _output_receiver) = unbounded();
+    let res = walk_serial(&output_sender, "", &root, &walk_options);
+    assert!(matches!(res, Err(Error::UnreadableIgnoreFile { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_ignore_file_skipped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = walk_options(IgnoreFileErrorPolicy::WarnAndSkip);
+
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("dir/c.log")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("dir/b.txt")), MatchResult::NoMatch);
+
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options)?;
+    assert_eq!(
+        relative_paths(&root, paths.into_iter().map(|pm| pm.path)),
+        vec![".gitignore", "dir/.gitignore", "dir/b.txt"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_ignore_file_collected() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = walk_options(IgnoreFileErrorPolicy::Collect);
+    let unreadable = root.join("dir/.gitignore");
+
+    let build_result = build_ignore_patterns_result_with_options("", &root, &walk_options)?;
+    assert_eq!(build_result.unreadable_files.len(), 1);
+    assert_eq!(build_result.unreadable_files[0].path, unreadable);
+    assert!(!build_result.files_read.contains(&unreadable));
+
+    let visitor = ErrorCollector::default();
+    walk_serial_visit("", &root, &walk_options, &visitor)?;
+    assert_eq!(
+        *visitor.errors.lock().unwrap(),
+        vec![unreadable.to_string_lossy().to_string()]
+    );
+    assert_eq!(
+        relative_paths(&root, visitor.paths.into_inner().unwrap()),
+        vec![".gitignore", "dir/.gitignore", "dir/b.txt"]
+    );
+    Ok(())
+}
@@ -0,0 +1,109 @@
+#![cfg(unix)]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_serial, IgnoreRules, MatchResult, Source, WalkOptions,
+};
+
+/// Creates a root with `dir/.gitignore` symlinked to `target`, and returns the root.
+fn setup_tree(target: &Path) -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join("dir/a.log"), "a")?;
+    fs::write(root.join("dir/b.txt"), "b")?;
+    symlink(target, root.join("dir/.gitignore"))?;
+    Ok(root)
+}
+
+fn walk_options(follow_ignore_file_symlinks: bool) -> WalkOptions {
+    WalkOptions {
+        follow_ignore_file_symlinks,
+        ..WalkOptions::gitignore()
+    }
+}
+
+fn resolved_sources(rules: &IgnoreRules) -> Vec<Option<PathBuf>> {
+    rules
+        .patterns_snapshot()
+        .iter()
+        .filter_map(|p| match &p.source {
+            Source::File { resolved_from, .. } => Some(resolved_from.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_ignore_file_symlink_in_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let rules_file = root.join("shared-ignore");
+    fs::write(&rules_file, "*.log\n")?;
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join("dir/a.log"), "a")?;
+    symlink(&rules_file, root.join("dir/.gitignore"))?;
+
+    // Symlinks in the root are followed in any case
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options(false))?;
+    assert_eq!(rules.check(&root.join("dir/a.log")), MatchResult::Ignore);
+    assert_eq!(
+        resolved_sources(&rules),
+        vec![Some(rules_file.canonicalize()?)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ignore_file_symlink_outside_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let dotfiles = create_temp_dir();
+    let external = dotfiles.join("gitignore");
+    fs::write(&external, "*.log\n")?;
+    let root = setup_tree(&external)?;
+
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options(true))?;
+    assert_eq!(rules.check(&root.join("dir/a.log")), MatchResult::Ignore);
+    assert_eq!(
+        resolved_sources(&rules),
+        vec![Some(external.canonicalize()?)]
+    );
+
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options(false))?;
+    assert_eq!(rules.check(&root.join("dir/a.log")), MatchResult::NoMatch);
+    assert!(resolved_sources(&rules).is_empty());
+
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options(false))?;
+    assert!(paths.iter().any(|pm| pm.path == root.join("dir/a.log")));
+    Ok(())
+}
+
+#[test]
+fn test_dangling_ignore_file_symlink() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree(Path::new("/nonexistent/xvc/gitignore"))?;
+
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options(true))?;
+    assert!(resolved_sources(&rules).is_empty());
+
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options(true))?;
+    let mut names: Vec<String> = paths
+        .iter()
+        .map(|pm| {
+            pm.path
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["dir/.gitignore", "dir/a.log", "dir/b.txt"]);
+    Ok(())
+}
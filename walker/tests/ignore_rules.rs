@@ -0,0 +1,1051 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{
+    content_to_patterns, Error, IgnoreRules, MatchResult, PathKind, Pattern, PatternEffect,
+    PatternParseErrorKind, Source,
+};
+
+fn rules_with_ignore_file(root: &Path, ignore_file: &str, content: &str) -> IgnoreRules {
+    let ignore_rules = IgnoreRules::empty(root, Some(".gitignore"));
+    ignore_rules
+        .add_patterns(content_to_patterns(root, Some(&root.join(ignore_file)), content).0)
+        .unwrap();
+    ignore_rules
+}
+
+#[test]
+fn test_check_in_directory_matches_check() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(
+        &root,
+        "a/.gitignore",
+        "/b/*.txt\n!/b/keep.txt\n*.log\n/c/\nd/**/e",
+    );
+
+    let root_patterns = ignore_rules.directory_patterns(&root);
+    let a_patterns = ignore_rules.child_directory_patterns(&root_patterns, &root.join("a"));
+    let b_patterns = ignore_rules.child_directory_patterns(&a_patterns, &root.join("a/b"));
+    let x_patterns = ignore_rules.child_directory_patterns(&root_patterns, &root.join("x"));
+
+    for (dir_patterns, paths) in [
+        (&a_patterns, vec!["a/b", "a/c", "a/x.log", "a/y.txt"]),
+        (
+            &b_patterns,
+            vec!["a/b/x.txt", "a/b/keep.txt", "a/b/y.log", "a/b/z.bin"],
+        ),
+        (&x_patterns, vec!["x/b", "x/x.log", "x/y.txt"]),
+    ] {
+        for path in paths {
+            let path = root.join(path);
+            assert_eq!(
+                ignore_rules.check(&path),
+                ignore_rules.check_in_directory(&path, dir_patterns),
+                "{path:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_extension_patterns_match_like_globs() {
+    let root = create_temp_dir();
+    for dir in ["logs.log", "src"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+
+    let content = "*.log\n!keep.log\n*.tmp\n!*.tmp\nsrc/*.log\n!src/*.log\n*.bak";
+    // `*.[l]og` matches the same paths with `*.log` but is not an extension pattern
+    let bracketed = content
+        .replace("*.log", "*.[l]og")
+        .replace("*.tmp", "*.[t]mp")
+        .replace("*.bak", "*.[b]ak");
+    let extension_rules = rules_with_ignore_file(&root, ".gitignore", content);
+    let glob_rules = rules_with_ignore_file(&root, ".gitignore", &bracketed);
+
+    for path in [
+        "a.log",
+        "keep.log",
+        ".log",
+        "a.tar.log",
+        "a.tmp",
+        "a.bak",
+        "a.txt",
+        "logs.log",
+        "src/a.log",
+        "src/b/c.log",
+        "src/a.bak",
+    ] {
+        let path = root.join(path);
+        assert_eq!(
+            extension_rules.check(&path),
+            glob_rules.check(&path),
+            "{path:?}"
+        );
+    }
+}
+
+#[test]
+fn test_case_insensitive_rules() {
+    let root = create_temp_dir();
+    let mut ignore_rules = rules_with_ignore_file(&root, ".gitignore", "*.RS\n/Build/*.O");
+    let root_patterns = ignore_rules.directory_patterns(&root);
+    let build_patterns = ignore_rules.child_directory_patterns(&root_patterns, &root.join("build"));
+    for path in ["foo.rs", "build/a.o"] {
+        assert_eq!(ignore_rules.check(&root.join(path)), MatchResult::NoMatch);
+    }
+
+    ignore_rules.case_insensitive = true;
+    let root_patterns = ignore_rules.directory_patterns(&root);
+    let build_patterns_ci =
+        ignore_rules.child_directory_patterns(&root_patterns, &root.join("build"));
+    assert_eq!(
+        ignore_rules.check(&root.join("foo.rs")),
+        MatchResult::Ignore
+    );
+    assert_eq!(
+        ignore_rules.check(&root.join("src/Foo.Rs")),
+        MatchResult::Ignore
+    );
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("build/a.o"), &build_patterns_ci),
+        MatchResult::Ignore
+    );
+    // Patterns are filtered case sensitively when the flag is not set
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("build/a.o"), &build_patterns),
+        MatchResult::NoMatch
+    );
+}
+
+#[test]
+fn test_check_hypothetical_paths() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(
+        &root,
+        ".gitignore",
+        "build/\nout/*.tmp\n*.log\n!keep.log\nsrc/**/gen",
+    );
+
+    for (path, kind, expected) in [
+        ("build", PathKind::Directory, MatchResult::Ignore),
+        ("build/", PathKind::Directory, MatchResult::Ignore),
+        // Directory patterns don't match the paths that may be files
+        ("build", PathKind::Any, MatchResult::NoMatch),
+        ("out/a.tmp", PathKind::Any, MatchResult::Ignore),
+        ("out/a.txt", PathKind::Any, MatchResult::NoMatch),
+        ("a/b/c.log", PathKind::Any, MatchResult::Ignore),
+        ("a/b/keep.log", PathKind::Any, MatchResult::Whitelist),
+        ("src/main.rs", PathKind::Any, MatchResult::NoMatch),
+        // The paths under ignored parents are ignored, even if they are whitelisted
+        ("build/model.bin", PathKind::Any, MatchResult::Ignore),
+        ("build/keep.log", PathKind::Any, MatchResult::Ignore),
+        ("a/build/x/y", PathKind::Directory, MatchResult::Ignore),
+        ("src/a/gen/lib.rs", PathKind::Any, MatchResult::Ignore),
+    ] {
+        assert!(!root.join(path).exists());
+        assert_eq!(
+            ignore_rules.check_hypothetical(path, kind),
+            expected,
+            "{path}"
+        );
+    }
+
+    // The existing paths give the same results with check
+    std::fs::create_dir_all(root.join("out")).unwrap();
+    std::fs::write(root.join("out/a.tmp"), "").unwrap();
+    assert_eq!(
+        ignore_rules.check(&root.join("out/a.tmp")),
+        ignore_rules.check_hypothetical("out/a.tmp", PathKind::Any)
+    );
+}
+
+#[test]
+fn test_check_return_pattern() {
+    let root = create_temp_dir();
+    let ignore_rules =
+        rules_with_ignore_file(&root, ".gitignore", "build/\n*.log\n!keep.log\n/data/*.csv");
+    fs::create_dir_all(root.join("build/sub")).unwrap();
+
+    let original = |path: &str| {
+        let (result, pattern) = ignore_rules.check_return_pattern(&root.join(path));
+        assert_eq!(result, ignore_rules.check(&root.join(path)), "{path}");
+        (result, pattern.map(|p| p.original_pattern().to_string()))
+    };
+    assert_eq!(
+        original("a/debug.log"),
+        (MatchResult::Ignore, Some("*.log".to_string()))
+    );
+    assert_eq!(
+        original("a/keep.log"),
+        (MatchResult::Whitelist, Some("!keep.log".to_string()))
+    );
+    assert_eq!(
+        original("data/x.csv"),
+        (MatchResult::Ignore, Some("/data/*.csv".to_string()))
+    );
+    assert_eq!(original("src/main.rs"), (MatchResult::NoMatch, None));
+    // The pattern of the ignored directory above the path
+    assert_eq!(
+        original("build/sub/keep.log"),
+        (MatchResult::Ignore, Some("build/".to_string()))
+    );
+    assert_eq!(
+        original("build/sub"),
+        (MatchResult::Ignore, Some("build/".to_string()))
+    );
+}
+
+#[test]
+fn test_override_patterns() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(&root, "a/.gitignore", "*.log\n!keep.log\n");
+    // Collected before the override patterns are inserted
+    let root_patterns = ignore_rules.directory_patterns(&root);
+    let a_patterns = ignore_rules.child_directory_patterns(&root_patterns, &root.join("a"));
+
+    ignore_rules
+        .add_override_patterns(content_to_patterns(&root, None, "keep.log\n!important.tmp").0)
+        .unwrap();
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, None, "*.tmp").0)
+        .unwrap();
+    let b_patterns = ignore_rules.child_directory_patterns(&a_patterns, &root.join("a/b"));
+
+    for (path, expected) in [
+        ("a/x.log", MatchResult::Ignore),
+        ("a/keep.log", MatchResult::Ignore),
+        ("a/important.tmp", MatchResult::Whitelist),
+        ("a/x.tmp", MatchResult::Ignore),
+        ("a/x.txt", MatchResult::NoMatch),
+    ] {
+        let path = root.join(path);
+        assert_eq!(ignore_rules.check(&path), expected, "{path:?}");
+        assert_eq!(
+            ignore_rules.check_in_directory(&path, &a_patterns),
+            expected,
+            "{path:?}"
+        );
+    }
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("a/b/keep.log"), &b_patterns),
+        MatchResult::Ignore
+    );
+
+    // The override patterns added later take precedence
+    ignore_rules
+        .add_override_patterns(content_to_patterns(&root, None, "!keep.log").0)
+        .unwrap();
+    assert_eq!(
+        ignore_rules.check(&root.join("a/keep.log")),
+        MatchResult::Whitelist
+    );
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("a/b/keep.log"), &b_patterns),
+        MatchResult::Whitelist
+    );
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("a/x.log"), &a_patterns),
+        MatchResult::Ignore
+    );
+}
+
+#[test]
+fn test_push_and_prepend_pattern() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(&root, ".gitignore", "*.log\n!keep.log\n");
+    let root_patterns = ignore_rules.directory_patterns(&root);
+
+    ignore_rules
+        .push_pattern(Pattern::new(Source::Global, "*.tmp"))
+        .unwrap();
+    ignore_rules
+        .push_pattern(Pattern::new(Source::Global, "!keep.tmp"))
+        .unwrap();
+    // The last pattern that matches decides among the pushed patterns
+    assert_eq!(ignore_rules.check(&root.join("a.tmp")), MatchResult::Ignore);
+    assert_eq!(
+        ignore_rules.check(&root.join("keep.tmp")),
+        MatchResult::Whitelist
+    );
+    assert_eq!(
+        ignore_rules.check(&root.join("keep.log")),
+        MatchResult::Whitelist
+    );
+
+    // A prepended ignore pattern overrides the existing whitelist for the same glob
+    ignore_rules
+        .prepend_pattern(Pattern::new(Source::Global, "keep.log"))
+        .unwrap();
+    assert_eq!(
+        ignore_rules.check(&root.join("keep.log")),
+        MatchResult::Ignore
+    );
+    assert_eq!(
+        ignore_rules.check_in_directory(&root.join("keep.log"), &root_patterns),
+        MatchResult::Ignore
+    );
+    assert_eq!(ignore_rules.check(&root.join("a.log")), MatchResult::Ignore);
+    assert_eq!(
+        ignore_rules.check(&root.join("keep.tmp")),
+        MatchResult::Whitelist
+    );
+
+    // The last prepended pattern has the highest precedence
+    ignore_rules
+        .prepend_pattern(Pattern::new(Source::Global, "!*.log"))
+        .unwrap();
+    assert_eq!(
+        ignore_rules.check(&root.join("keep.log")),
+        MatchResult::Whitelist
+    );
+    assert_eq!(
+        ignore_rules.check(&root.join("a.log")),
+        MatchResult::Whitelist
+    );
+}
+
+#[test]
+fn test_content_to_patterns_reports_malformed_lines() {
+    let root = create_temp_dir();
+    let ignore_file = root.join(".gitignore");
+    let content = "*.log\n[abc\n!\nfoo\\\n\n# comment\n/\n[]]\nbar\\\\\n[!\\]\n";
+    let (patterns, errors) = content_to_patterns(&root, Some(&ignore_file), content);
+
+    let lines: Vec<&str> = patterns.iter().map(|p| p.original_pattern()).collect();
+    assert_eq!(lines, vec!["*.log", "[]]", "bar\\\\"]);
+
+    let errors: Vec<(usize, Option<usize>, &str, String)> = errors
+        .iter()
+        .map(|e| {
+            assert_eq!(e.source_file.as_ref(), Some(&ignore_file));
+            (
+                e.line_number,
+                e.column,
+                e.original.as_str(),
+                e.message.clone(),
+            )
+        })
+        .collect();
+    let message = |kind: PatternParseErrorKind| kind.to_string();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                2,
+                Some(1),
+                "[abc",
+                message(PatternParseErrorKind::UnclosedBracket)
+            ),
+            (3, None, "!", message(PatternParseErrorKind::EmptyPattern)),
+            (
+                4,
+                Some(4),
+                "foo\\",
+                message(PatternParseErrorKind::InvalidEscape)
+            ),
+            (7, None, "/", message(PatternParseErrorKind::EmptyPattern)),
+            (
+                10,
+                Some(1),
+                "[!\\]",
+                message(PatternParseErrorKind::UnclosedBracket)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_pattern_error_display() {
+    let (_, errors) = content_to_patterns(
+        Path::new("/repo"),
+        Some(Path::new("/repo/data/.gitignore")),
+        "*.csv\nraw/[ab\n",
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        r#"/repo/data/.gitignore:2:5: Unclosed bracket: "raw/[ab""#
+    );
+
+    let (_, errors) = content_to_patterns(Path::new("/repo"), None, "\n\n!");
+    assert_eq!(errors[0].to_string(), r#"Line 3: Empty pattern: "!""#);
+
+    let error = Error::from(errors[0].clone());
+    assert_eq!(
+        error.to_string(),
+        r#"Invalid pattern: Line 3: Empty pattern: "!""#
+    );
+
+    // The location of patterns not read from files is not known
+    let pattern = Pattern::new(Source::Global, "foo\\");
+    assert_eq!(
+        pattern.is_valid_original().unwrap_err().to_string(),
+        r#"Invalid escape at the end: "foo\\""#
+    );
+
+    let pattern = Pattern::new(
+        Source::File {
+            path: PathBuf::from("dir/.gitignore"),
+            line: 7,
+            resolved_from: None,
+        },
+        "[x",
+    );
+    let error = pattern.is_valid_original().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        r#"dir/.gitignore:7:1: Unclosed bracket: "[x""#
+    );
+    assert!(Pattern::new(Source::Global, "*.log")
+        .is_valid_original()
+        .is_ok());
+}
+
+#[test]
+fn test_verify_not_ignored() {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("build/out")).unwrap();
+    fs::create_dir_all(root.join("data")).unwrap();
+    fs::write(root.join("data/model.bin"), "model").unwrap();
+    fs::write(root.join("data/run.log"), "log").unwrap();
+    let ignore_rules = rules_with_ignore_file(
+        &root,
+        ".gitignore",
+        "build/\n*.log\n!keep.log\ncache\n!cache/kept.txt\n",
+    );
+
+    let targets = [
+        // Existing and not ignored
+        root.join("data/model.bin"),
+        // Existing and ignored
+        root.join("data/run.log"),
+        // Hypothetical and not ignored
+        PathBuf::from("results/metrics.json"),
+        // Hypothetical and ignored
+        PathBuf::from("results/train.log"),
+        PathBuf::from("results/keep.log"),
+        // Under an existing ignored directory
+        root.join("build/out/model.bin"),
+        // Under a hypothetical ignored directory, the whitelist is not reached
+        PathBuf::from("cache/kept.txt"),
+        // A hypothetical directory
+        PathBuf::from("results/cache/"),
+    ];
+    let ignored = ignore_rules.verify_not_ignored(targets.iter().map(PathBuf::as_path));
+
+    let summary: Vec<(&Path, &Path, &str)> = ignored
+        .iter()
+        .map(|t| {
+            (
+                t.path.as_path(),
+                t.ignored_path.as_path(),
+                t.pattern.original.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                root.join("data/run.log").as_path(),
+                Path::new("data/run.log"),
+                "*.log"
+            ),
+            (
+                Path::new("results/train.log"),
+                Path::new("results/train.log"),
+                "*.log"
+            ),
+            (
+                root.join("build/out/model.bin").as_path(),
+                Path::new("build"),
+                "build/"
+            ),
+            (Path::new("cache/kept.txt"), Path::new("cache"), "cache"),
+            (
+                Path::new("results/cache/"),
+                Path::new("results/cache/"),
+                "cache"
+            ),
+        ]
+    );
+    assert_eq!(ignored[0].pattern.line, Some(2));
+}
+
+#[test]
+fn test_pattern_to_line() {
+    for (original, line) in [
+        ("*.log", "*.log"),
+        ("build/", "build/"),
+        ("/build", "/build"),
+        ("/docs/*.md", "docs/*.md"),
+        ("docs/*.md", "docs/*.md"),
+        ("!keep.log", "!keep.log"),
+        ("!/keep/", "!/keep/"),
+        (r"\!bang", r"\!bang"),
+        (r"\#hash", r"\#hash"),
+        (r"\*literal", r"\*literal"),
+        (r"space\ ", r"space\ "),
+        ("trimmed  ", "trimmed"),
+        ("cache/**", "cache/**/"),
+    ] {
+        let pattern = Pattern::new(
+            Source::File {
+                path: PathBuf::from("sub/.gitignore"),
+                line: 1,
+                resolved_from: None,
+            },
+            original,
+        );
+        assert_eq!(pattern.to_line(), line, "{original}");
+    }
+}
+
+#[test]
+fn test_render_for_dir_round_trip() {
+    let root = create_temp_dir();
+    let content = [
+        "*.log",
+        "!important.log",
+        "/build/",
+        "docs/*.md",
+        "!docs/README.md",
+        r"\#hash",
+        r"\!bang",
+        r"space\ ",
+        "cache/**",
+        "[ab].txt",
+        r"\*literal",
+        "tmp/",
+        "!/tmp/keep/",
+    ]
+    .join("\n");
+    let ignore_rules = rules_with_ignore_file(&root, "sub/.gitignore", &content);
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, Some(&root.join(".gitignore")), "*.bak").0)
+        .unwrap();
+
+    // Only the patterns of the directory are rendered
+    assert_eq!(ignore_rules.render_for_dir(&root), "*.bak\n");
+    let rendered = ignore_rules.render_for_dir(&root.join("sub"));
+    assert!(!rendered.contains("*.bak"));
+
+    let rendered_rules = rules_with_ignore_file(&root, "sub/.gitignore", &rendered);
+    assert_eq!(rendered_rules.render_for_dir(&root.join("sub")), rendered);
+
+    let probes = [
+        ("sub/a.log", PathKind::Any),
+        ("sub/x/important.log", PathKind::Any),
+        ("sub/build", PathKind::Directory),
+        ("sub/build", PathKind::Any),
+        ("sub/x/build", PathKind::Directory),
+        ("sub/docs/guide.md", PathKind::Any),
+        ("sub/docs/README.md", PathKind::Any),
+        ("sub/x/docs/guide.md", PathKind::Any),
+        ("sub/#hash", PathKind::Any),
+        ("sub/!bang", PathKind::Any),
+        ("sub/bang", PathKind::Any),
+        ("sub/space ", PathKind::Any),
+        ("sub/space", PathKind::Any),
+        ("sub/cache/data", PathKind::Any),
+        ("sub/a.txt", PathKind::Any),
+        ("sub/c.txt", PathKind::Any),
+        ("sub/*literal", PathKind::Any),
+        ("sub/xliteral", PathKind::Any),
+        ("sub/tmp/keep", PathKind::Directory),
+        ("sub/tmp/other", PathKind::Directory),
+        ("sub/x/tmp", PathKind::Directory),
+        ("other/a.log", PathKind::Any),
+    ];
+    for (path, kind) in probes {
+        assert_eq!(
+            rendered_rules.check_hypothetical(path, kind.clone()),
+            ignore_rules.check_hypothetical(path, kind.clone()),
+            "{path} {kind:?}"
+        );
+    }
+}
+
+#[test]
+fn test_check_path_str_matches_check() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(
+        &root,
+        "a/.gitignore",
+        "*.log\n!keep.log\n/build/\ncache/\n/b/*\n!/b/c",
+    );
+    for dir in ["a/build", "a/cache", "a/x/build", "a/b/c", "a/b/d"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["a/x.log", "a/keep.log", "a/cache.txt", "a/b/e.txt"] {
+        fs::write(root.join(file), file).unwrap();
+    }
+
+    for (path, kind) in [
+        ("a/build", PathKind::Directory),
+        ("a/cache", PathKind::Directory),
+        ("a/x/build", PathKind::Directory),
+        ("a/b/c", PathKind::Directory),
+        ("a/b/d", PathKind::Directory),
+        ("a/x.log", PathKind::Any),
+        ("a/keep.log", PathKind::Any),
+        ("a/cache.txt", PathKind::Any),
+        ("a/b/e.txt", PathKind::Any),
+        ("a", PathKind::Directory),
+    ] {
+        let expected = ignore_rules.check(&root.join(path));
+        assert_eq!(
+            ignore_rules.check_path_str_with_kind(path, kind.clone()),
+            expected,
+            "{path}"
+        );
+        let path_str = match kind {
+            PathKind::Directory => format!("{path}/"),
+            PathKind::Any => path.to_string(),
+        };
+        assert_eq!(
+            ignore_rules.check_path_str(&path_str),
+            expected,
+            "{path_str}"
+        );
+    }
+
+    // Directory patterns don't match files
+    assert_eq!(ignore_rules.check_path_str("a/build/"), MatchResult::Ignore);
+    assert_eq!(ignore_rules.check_path_str("a/build"), MatchResult::NoMatch);
+}
+
+#[test]
+fn test_ignore_rules_builder() {
+    let default_rules = IgnoreRules::default();
+    assert_eq!(default_rules.root, std::env::current_dir().unwrap());
+    assert_eq!(default_rules.ignore_filename, None);
+    assert!(default_rules.is_empty());
+
+    let root = create_temp_dir();
+    let ignore_rules = IgnoreRules::default()
+        .with_root(&root)
+        .with_ignore_filename(".xvcignore");
+    assert_eq!(ignore_rules.root, root);
+    assert_eq!(ignore_rules.ignore_filename.as_deref(), Some(".xvcignore"));
+
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, Some(&root.join(".xvcignore")), "*.log").0)
+        .unwrap();
+    assert_eq!(ignore_rules.check(&root.join("a.log")), MatchResult::Ignore);
+}
+
+#[test]
+fn test_scoped_to_subdir() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(
+        &root,
+        ".gitignore",
+        "*.log\n/src/gen/\nsrc/*.tmp\n/docs/\n!keep.log\n/top.txt",
+    );
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, Some(&root.join("src/lib/.gitignore")), "/out/").0)
+        .unwrap();
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, Some(&root.join("docs/.gitignore")), "*.md").0)
+        .unwrap();
+    for dir in ["src/gen", "src/lib/out", "src/docs"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+
+    let src = root.join("src");
+    let scoped = ignore_rules.scoped_to(&src).unwrap();
+    assert_eq!(scoped.root, src);
+    assert_eq!(scoped.ignore_filename.as_deref(), Some(".gitignore"));
+    assert_eq!(ignore_rules.scoped_to(Path::new("src")).unwrap().root, src);
+
+    for (path, kind) in [
+        ("a.log", PathKind::Any),
+        ("keep.log", PathKind::Any),
+        ("gen", PathKind::Directory),
+        ("gen/a.rs", PathKind::Any),
+        ("a.tmp", PathKind::Any),
+        ("lib/a.tmp", PathKind::Any),
+        ("lib/out", PathKind::Directory),
+        ("docs", PathKind::Directory),
+        ("docs/a.md", PathKind::Any),
+        ("top.txt", PathKind::Any),
+        ("main.rs", PathKind::Any),
+    ] {
+        assert_eq!(
+            scoped.check_path_str_with_kind(path, kind.clone()),
+            ignore_rules.check_path_str_with_kind(&format!("src/{path}"), kind),
+            "{path}"
+        );
+    }
+    assert_eq!(scoped.check(&src.join("a.log")), MatchResult::Ignore);
+    assert_eq!(scoped.check(&src.join("gen")), MatchResult::Ignore);
+    assert_eq!(scoped.check(&src.join("lib/out")), MatchResult::Ignore);
+    assert_eq!(scoped.check(&src.join("docs")), MatchResult::NoMatch);
+
+    // The patterns for the siblings of `src` are dropped
+    let originals: Vec<String> = scoped
+        .patterns_snapshot()
+        .iter()
+        .map(|p| p.original_pattern().to_string())
+        .collect();
+    assert!(!originals.contains(&"/docs/".to_string()));
+    assert!(!originals.contains(&"*.md".to_string()));
+    assert!(!originals.contains(&"/top.txt".to_string()));
+    assert!(scoped.patterns_snapshot().iter().any(|p| matches!(
+        &p.source,
+        Source::File { path, .. } if path == Path::new("lib/.gitignore")
+    )));
+
+    // Scoping to the whole tree ignores everything
+    let gen_rules = ignore_rules.scoped_to(&src.join("gen")).unwrap();
+    assert_eq!(gen_rules.check_path_str("a.rs"), MatchResult::Ignore);
+
+    assert!(matches!(
+        ignore_rules.scoped_to(Path::new("/elsewhere")),
+        Err(Error::PathEscapesBase { .. })
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_patterns_in_insertion_order() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(&root, ".gitignore", "*.log\n!keep.log\n");
+    ignore_rules
+        .push_pattern(Pattern::new(Source::Global, "*.tmp"))
+        .unwrap();
+    ignore_rules
+        .add_patterns(vec![
+            Pattern::new(Source::Global, "build/"),
+            Pattern::new(Source::Global, "!build/keep"),
+        ])
+        .unwrap();
+
+    let expected = vec!["*.log", "!keep.log", "*.tmp", "build/", "!build/keep"];
+    let cloned = ignore_rules.patterns_cloned();
+    assert_eq!(
+        cloned
+            .iter()
+            .map(|p| p.original_pattern())
+            .collect::<Vec<_>>(),
+        expected
+    );
+    {
+        let locked = ignore_rules.patterns_locked();
+        assert_eq!(
+            locked
+                .iter()
+                .map(|p| p.original_pattern())
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(locked.len(), expected.len());
+        let mut count = 0;
+        for (pattern, cloned) in (&locked).into_iter().zip(&cloned) {
+            assert_eq!(pattern.original_pattern(), cloned.original_pattern());
+            assert_eq!(pattern.source, cloned.source);
+            count += 1;
+        }
+        assert_eq!(count, cloned.len());
+    }
+
+    // Override patterns are stored before the others
+    ignore_rules
+        .prepend_pattern(Pattern::new(Source::Global, "keep.log"))
+        .unwrap();
+    let locked = ignore_rules.patterns_locked();
+    assert_eq!(locked[0].original_pattern(), "keep.log");
+    assert_eq!(
+        locked
+            .iter()
+            .skip(1)
+            .map(|p| p.original_pattern())
+            .collect::<Vec<_>>(),
+        expected
+    );
+}
+
+#[test]
+fn test_patterns_snapshot() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(&root, ".gitignore", "*.log\n!keep.log\n");
+    assert_eq!(ignore_rules.len(), 2);
+    assert!(!ignore_rules.is_empty());
+
+    let snapshot = ignore_rules.patterns_snapshot();
+    assert_eq!(
+        snapshot
+            .iter()
+            .map(|p| p.original_pattern())
+            .collect::<Vec<_>>(),
+        ["*.log", "!keep.log"]
+    );
+    // The snapshot is shared until the patterns change
+    assert!(Arc::ptr_eq(&snapshot, &ignore_rules.patterns_snapshot()));
+
+    ignore_rules
+        .push_pattern(Pattern::new(Source::Global, "*.tmp"))
+        .unwrap();
+    assert_eq!(snapshot.len(), 2);
+    let changed = ignore_rules.patterns_snapshot();
+    assert_eq!(changed.len(), 3);
+    assert_eq!(ignore_rules.len(), 3);
+    // The clones share the patterns and their snapshot
+    assert!(Arc::ptr_eq(
+        &changed,
+        &ignore_rules.clone().patterns_snapshot()
+    ));
+    assert!(!Arc::ptr_eq(
+        &changed,
+        &ignore_rules.deep_clone().patterns_snapshot()
+    ));
+}
+
+#[test]
+fn test_iter_patterns_callback_uses_the_rules() {
+    let root = create_temp_dir();
+    let ignore_rules = rules_with_ignore_file(&root, ".gitignore", "*.log\n!keep.log\n*.tmp");
+
+    // The patterns are not locked in the callback, which would deadlock on add_patterns
+    let (done_sender, done_receiver) = mpsc::channel();
+    let rules = ignore_rules.clone();
+    let root_dir = root.clone();
+    thread::spawn(move || {
+        let mut seen = Vec::new();
+        rules.iter_patterns(|pattern| {
+            let path = root_dir.join(pattern.original_pattern().trim_start_matches(['!', '*']));
+            seen.push((pattern.original_pattern().to_string(), rules.check(&path)));
+            rules
+                .add_patterns(vec![Pattern::new(
+                    Source::Global,
+                    &format!("{}.bak", pattern.original_pattern()),
+                )])
+                .unwrap();
+        });
+        done_sender.send(seen).unwrap();
+    });
+    let seen = done_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("iter_patterns deadlocked");
+
+    // The patterns added in the callback are not iterated
+    assert_eq!(
+        seen,
+        [
+            ("*.log".to_string(), MatchResult::Ignore),
+            ("!keep.log".to_string(), MatchResult::Whitelist),
+            ("*.tmp".to_string(), MatchResult::Ignore),
+        ]
+    );
+    assert_eq!(ignore_rules.len(), 6);
+    assert_eq!(
+        ignore_rules.check(&root.join("a.log.bak")),
+        MatchResult::Ignore
+    );
+}
+
+#[test]
+fn test_add_patterns_from_dirs() {
+    let root = create_temp_dir();
+    for dir in ["a", "b/c", "d"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+    fs::write(root.join("a/.gitignore"), "*.tmp\n").unwrap();
+    fs::write(root.join("b/c/.gitignore"), "!keep-*.log\nbuild/\n").unwrap();
+
+    let ignore_rules = IgnoreRules::empty(&root, Some(".gitignore"));
+    let dirs: Vec<PathBuf> = ["", "a", "b/c", "d"]
+        .iter()
+        .map(|dir| root.join(dir))
+        .collect();
+    ignore_rules.add_patterns_from_dirs(&dirs).unwrap();
+
+    // The patterns are in the order of the directories, as if they were added one by one
+    let originals: Vec<String> = ignore_rules
+        .patterns_snapshot()
+        .iter()
+        .map(|p| p.original_pattern().to_string())
+        .collect();
+    assert_eq!(originals, ["*.log", "*.tmp", "!keep-*.log", "build/"]);
+
+    let check = |path: &str| ignore_rules.check(&root.join(path));
+    assert_eq!(check("x.log"), MatchResult::Ignore);
+    assert_eq!(check("a/x.tmp"), MatchResult::Ignore);
+    assert_eq!(check("x.tmp"), MatchResult::NoMatch);
+    assert_eq!(check("b/c/keep-1.log"), MatchResult::Whitelist);
+    assert_eq!(check("b/keep-1.log"), MatchResult::Ignore);
+    assert_eq!(
+        ignore_rules.check_hypothetical("b/c/build", PathKind::Directory),
+        MatchResult::Ignore
+    );
+    assert_eq!(
+        ignore_rules.check_hypothetical("d/build", PathKind::Directory),
+        MatchResult::NoMatch
+    );
+
+    // Nothing is added if an ignore file can't be read
+    fs::write(root.join("d/.gitignore"), [0xff, 0xfe]).unwrap();
+    let ignore_rules = IgnoreRules::empty(&root, Some(".gitignore"));
+    assert!(matches!(
+        ignore_rules.add_patterns_from_dirs(&dirs),
+        Err(Error::UnreadableIgnoreFile { .. })
+    ));
+    assert!(ignore_rules.is_empty());
+}
+
+#[test]
+fn test_trailing_spaces_are_trimmed_like_git() {
+    let root = create_temp_dir();
+    for (line, glob, effect) in [
+        ("foo  ", "**/foo", PatternEffect::Ignore),
+        ("foo\\ ", "**/foo\\ ", PatternEffect::Ignore),
+        // Only the escaped space is kept
+        ("foo\\  ", "**/foo\\ ", PatternEffect::Ignore),
+        ("foo\\ \\  ", "**/foo\\ \\ ", PatternEffect::Ignore),
+        ("!foo\\  ", "**/foo\\ ", PatternEffect::Whitelist),
+        // The space after an escaped backslash is not escaped
+        ("foo\\\\ ", "**/foo\\\\", PatternEffect::Ignore),
+        // The spaces in the middle are part of the pattern
+        ("foo\\ bar  ", "**/foo\\ bar", PatternEffect::Ignore),
+        ("foo bar ", "**/foo bar", PatternEffect::Ignore),
+        ("build/  ", "**/build/", PatternEffect::Ignore),
+        ("foo\t ", "**/foo\t", PatternEffect::Ignore),
+    ] {
+        let from_file = content_to_patterns(&root, Some(&root.join(".gitignore")), line).0;
+        assert_eq!(from_file.len(), 1, "{line:?}");
+        let given = Pattern::new(Source::Global, line);
+        for pattern in [&from_file[0], &given] {
+            assert_eq!(pattern.effective_glob(), glob, "{line:?}");
+            assert_eq!(pattern.effect, effect, "{line:?}");
+        }
+    }
+
+    // A line of spaces is skipped
+    let (patterns, errors) = content_to_patterns(&root, Some(&root.join(".gitignore")), "   ");
+    assert!(patterns.is_empty() && errors.is_empty());
+    fs::create_dir_all(root.join("dir")).unwrap();
+    fs::write(root.join("file"), "").unwrap();
+    let ignore_rules = IgnoreRules::from_global_patterns(&root, None, "   ");
+    assert!(ignore_rules.is_empty());
+    for path in ["dir", "file", " "] {
+        assert_eq!(ignore_rules.check(&root.join(path)), MatchResult::NoMatch);
+    }
+}
+
+#[test]
+fn test_effective_rules_for_dir() {
+    let root = create_temp_dir();
+    for dir in ["a/b/c", "d"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    fs::write(root.join(".gitignore"), "*.log\n!keep.log\n").unwrap();
+    fs::write(root.join("a/.gitignore"), "*.tmp\n/build/\n").unwrap();
+    fs::write(root.join("a/b/.gitignore"), "!keep.tmp\n").unwrap();
+    fs::write(root.join("a/b/c/.gitignore"), "data/\n*.bak\n").unwrap();
+    fs::write(root.join("d/.gitignore"), "*.csv\n").unwrap();
+
+    let ignore_rules =
+        IgnoreRules::from_global_patterns(&root, Some(".gitignore"), "*.swp\n.cache/");
+    // Read in an order other than the precedence
+    let dirs: Vec<PathBuf> = ["a/b/c", "d", "", "a/b", "a"]
+        .iter()
+        .map(|dir| root.join(dir))
+        .collect();
+    ignore_rules.add_patterns_from_dirs(&dirs).unwrap();
+    ignore_rules
+        .add_patterns(vec![Pattern::new(
+            Source::CommandLine {
+                current_dir: PathBuf::from("d"),
+            },
+            "*.json",
+        )])
+        .unwrap();
+    ignore_rules
+        .add_override_patterns(vec![Pattern::new(
+            Source::CommandLine {
+                current_dir: PathBuf::new(),
+            },
+            "secret",
+        )])
+        .unwrap();
+
+    let effective = |dir: &str| -> Vec<String> {
+        ignore_rules
+            .effective_rules_for_dir(&root.join(dir))
+            .iter()
+            .map(|p| p.original_pattern().to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        effective("a/b/c"),
+        [
+            "secret",
+            "*.bak",
+            "data/",
+            "!keep.tmp",
+            "/build/",
+            "*.tmp",
+            "!keep.log",
+            "*.log",
+            ".cache/",
+            "*.swp",
+        ]
+    );
+    assert_eq!(
+        effective(""),
+        ["secret", "!keep.log", "*.log", ".cache/", "*.swp"]
+    );
+    assert_eq!(
+        effective("d"),
+        [
+            "secret",
+            "*.csv",
+            "!keep.log",
+            "*.log",
+            "*.json",
+            ".cache/",
+            "*.swp"
+        ]
+    );
+}
+
+#[test]
+fn test_deeper_ignore_file_decides_regardless_of_order() {
+    let root = create_temp_dir();
+    let root_patterns = content_to_patterns(&root, Some(&root.join(".gitignore")), "*.js\n").0;
+    let dir_patterns = content_to_patterns(&root, Some(&root.join("dir/.gitignore")), "!b.js\n").0;
+
+    for patterns in [
+        [root_patterns.clone(), dir_patterns.clone()],
+        [dir_patterns, root_patterns],
+    ] {
+        let ignore_rules = IgnoreRules::empty(&root, Some(".gitignore"));
+        for patterns in patterns {
+            ignore_rules.add_patterns(patterns).unwrap();
+        }
+        assert_eq!(
+            ignore_rules.check_path_str("dir/b.js"),
+            MatchResult::Whitelist
+        );
+        assert_eq!(ignore_rules.check_path_str("dir/a.js"), MatchResult::Ignore);
+        assert_eq!(ignore_rules.check_path_str("b.js"), MatchResult::Ignore);
+    }
+
+    // The global patterns are overridden by the ignore files
+    let ignore_rules = rules_with_ignore_file(&root, ".gitignore", "!keep.log\n");
+    ignore_rules
+        .add_patterns(content_to_patterns(&root, None, "*.log\n").0)
+        .unwrap();
+    assert_eq!(
+        ignore_rules.check_path_str("keep.log"),
+        MatchResult::Whitelist
+    );
+    assert_eq!(ignore_rules.check_path_str("a.log"), MatchResult::Ignore);
+}
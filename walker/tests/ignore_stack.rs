@@ -0,0 +1,126 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns, walk_parallel_with_check, walk_serial_with_check, Error, IgnoreCheck,
+    IgnoreRules, IgnoreRulesStack, PathMetadata, WalkAction, WalkOptions, WalkVisitor,
+};
+
+/// A workspace with a project that whitelists a file ignored in the workspace.
+fn setup_workspace() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for dir in ["project/src", "other"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for file in [
+        "project/debug.log",
+        "project/app.log",
+        "project/src/main.rs",
+        "other/x.log",
+        "other/y.txt",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    fs::write(root.join(".xvcignore"), "*.log\n")?;
+    fs::write(root.join("project/.xvcignore"), "!debug.log\n")?;
+    Ok(root)
+}
+
+fn workspace_stack(root: &Path) -> Result<IgnoreRulesStack> {
+    let mut stack = IgnoreRulesStack::new();
+    stack.push(IgnoreRules::from_global_patterns(root, None, "*.log"));
+    stack.push(build_ignore_patterns(
+        "",
+        &root.join("project"),
+        ".xvcignore",
+    )?);
+    Ok(stack)
+}
+
+#[derive(Default)]
+struct CollectingVisitor {
+    paths: RefCell<Vec<PathBuf>>,
+}
+
+impl WalkVisitor for CollectingVisitor {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths.borrow_mut().push(entry.path.clone());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        WalkAction::Continue
+    }
+}
+
+/// Walks `root` with both walkers and returns the paths relative to `root` after checking that
+/// the walkers agree.
+fn walk(root: &Path, ignore_check: &impl IgnoreCheck) -> Result<Vec<String>> {
+    let walk_options = WalkOptions::xvcignore();
+    let visitor = CollectingVisitor::default();
+    walk_serial_with_check(ignore_check, root, &walk_options, &visitor)?;
+    let mut serial = visitor.paths.into_inner();
+    serial.sort();
+
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel_with_check(ignore_check, root, walk_options, path_sender)?;
+    let mut parallel = path_receiver
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    parallel.sort();
+
+    assert_eq!(serial, parallel);
+    Ok(serial
+        .into_iter()
+        .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().to_string())
+        .collect())
+}
+
+#[test]
+fn test_walk_with_ignore_rules_stack() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_workspace()?;
+    let stack = workspace_stack(&root)?;
+
+    let paths = walk(&root, &stack)?;
+    assert_eq!(
+        paths,
+        vec![
+            ".xvcignore",
+            "other/y.txt",
+            "project/.xvcignore",
+            "project/debug.log",
+            "project/src/main.rs",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_walk_with_single_ignore_rules() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_workspace()?;
+    let ignore_rules = IgnoreRules::from_global_patterns(&root, None, "*.log");
+
+    let paths = walk(&root, &ignore_rules)?;
+    assert_eq!(
+        paths,
+        vec![
+            ".xvcignore",
+            "other/y.txt",
+            "project/.xvcignore",
+            "project/src/main.rs",
+        ]
+    );
+
+    // Without the project rules, the whitelisted file is ignored again
+    let mut stack = workspace_stack(&root)?;
+    stack.pop();
+    assert_eq!(walk(&root, &stack)?, paths);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel, walk_parallel_interned, IgnoreRules, InternedPath, PathInterner, WalkOptions,
+};
+
+fn setup_deep_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..4 {
+        let dir = root.join(format!("level-{i}/a/b/c/d/e"));
+        fs::create_dir_all(&dir)?;
+        for j in 0..100 {
+            fs::write(dir.join(format!("file-{j}.txt")), "x")?;
+            fs::write(dir.parent().unwrap().join(format!("file-{j}.txt")), "x")?;
+        }
+    }
+    Ok(root)
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    }
+}
+
+fn ignore_rules(root: &Path) -> Arc<RwLock<IgnoreRules>> {
+    Arc::new(RwLock::new(IgnoreRules::empty(root, None)))
+}
+
+#[test]
+fn test_interned_walk_resolves_to_plain_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_deep_tree()?;
+
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel(ignore_rules(&root), &root, walk_options(), path_sender)?;
+    let mut plain_paths = path_receiver
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    plain_paths.sort();
+
+    let interner = PathInterner::new();
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel_interned(
+        ignore_rules(&root),
+        &root,
+        walk_options(),
+        &interner,
+        path_sender,
+    )?;
+    let interned = path_receiver
+        .into_iter()
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    let mut resolved_paths: Vec<PathBuf> = interned
+        .iter()
+        .map(|ipm| ipm.resolve(&interner).path)
+        .collect();
+    resolved_paths.sort();
+
+    assert_eq!(plain_paths.len(), 800);
+    assert_eq!(resolved_paths, plain_paths);
+
+    // The directories are stored once, and each path keeps only its file name
+    let plain_bytes: usize = plain_paths
+        .iter()
+        .map(|p| size_of::<PathBuf>() + p.capacity())
+        .sum();
+    let interned_bytes: usize = interned
+        .iter()
+        .map(|ipm| size_of::<InternedPath>() + ipm.path.name.len())
+        .sum::<usize>()
+        + interner.estimated_bytes();
+    assert!(interned_bytes * 2 < plain_bytes);
+    Ok(())
+}
+
+#[test]
+fn test_interner_is_shared_between_threads() {
+    let interner = Arc::new(PathInterner::new());
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let interner = interner.clone();
+            thread::spawn(move || {
+                let path = PathBuf::from(format!("/data/dir-{}/file-{i}", i % 2));
+                (path.clone(), interner.intern_path(&path))
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let (path, interned) = handle.join().unwrap();
+        assert_eq!(interned.resolve(&interner), path);
+    }
+    // `/`, `data`, `dir-0` and `dir-1`
+    assert_eq!(interner.len(), 4);
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns, LintFinding, LintKind};
+
+fn lint(ignore_files: &[(&str, &str)]) -> Result<Vec<LintFinding>> {
+    let root = create_temp_dir();
+    for (path_str, content) in ignore_files {
+        let path = root.join(path_str);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+    }
+    Ok(build_ignore_patterns("", &root, ".gitignore")?.lint())
+}
+
+/// Returns the kind, the pattern and the cause of each finding with their sources.
+fn summarize(findings: &[LintFinding]) -> Vec<(LintKind, String, String)> {
+    let with_source = |original: &str, path: &Option<PathBuf>, line: &Option<usize>| {
+        let path = path.as_deref().unwrap_or(Path::new(""));
+        format!(
+            "{}:{}:{}",
+            path.to_string_lossy(),
+            line.unwrap_or(0),
+            original
+        )
+    };
+    findings
+        .iter()
+        .map(|f| {
+            (
+                f.kind,
+                with_source(&f.pattern.original, &f.pattern.source_path, &f.pattern.line),
+                with_source(&f.cause.original, &f.cause.source_path, &f.cause.line),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_whitelist_shadowed_by_later_ignore() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let findings = lint(&[(".gitignore", "!important.log\n*.log\n")])?;
+    assert_eq!(
+        summarize(&findings),
+        vec![(
+            LintKind::ShadowedWhitelist,
+            ".gitignore:1:!important.log".to_string(),
+            ".gitignore:2:*.log".to_string()
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_whitelist_under_ignored_directory() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let findings = lint(&[(".gitignore", "build/\n!build/keep.txt\n")])?;
+    assert_eq!(
+        summarize(&findings),
+        vec![(
+            LintKind::WhitelistUnderIgnoredDirectory,
+            ".gitignore:2:!build/keep.txt".to_string(),
+            ".gitignore:1:build/".to_string()
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_rules_across_files() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let findings = lint(&[(".gitignore", "*.tmp\n"), ("src/.gitignore", "*.o\n/*.o\n")])?;
+    assert!(findings.is_empty());
+
+    let findings = lint(&[
+        (".gitignore", "data/*.csv\n"),
+        ("data/.gitignore", "/*.csv\n"),
+    ])?;
+    assert_eq!(
+        summarize(&findings),
+        vec![(
+            LintKind::DuplicateRule,
+            "data/.gitignore:1:/*.csv".to_string(),
+            ".gitignore:1:data/*.csv".to_string()
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_clean_rules_have_no_findings() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let fixtures: &[&[(&str, &str)]] = &[
+        &[(".gitignore", "*.log\n!important.log\n")],
+        &[(".gitignore", "build/*\n!build/keep.txt\n")],
+        &[(".gitignore", "build/\n!build/\n!build/keep.txt\n")],
+        &[(".gitignore", "*\n!*/\n!*.txt\n")],
+        &[
+            (".gitignore", "*.tmp\ntarget/\n"),
+            ("src/.gitignore", "!keep.tmp\n"),
+            ("docs/.gitignore", "*.bak\n!keep.bak\n"),
+        ],
+    ];
+    for fixture in fixtures {
+        let findings = lint(fixture)?;
+        assert!(findings.is_empty(), "{fixture:?}: {findings:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_serial, WalkOptions,
+    PRUNE_LOGS_PER_PATTERN,
+};
+
+/// Collects the warnings logged in this test binary.
+///
+/// The tests don't call `test_logging`, as only one logger can be set in a process.
+struct CapturingLogger {
+    warnings: Mutex<Vec<String>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.warnings
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<&'static CapturingLogger> = OnceLock::new();
+
+/// Returns the prune warnings logged since the last call.
+fn take_prune_warnings() -> Vec<String> {
+    let logger = LOGGER.get_or_init(|| {
+        let logger = Box::leak(Box::new(CapturingLogger {
+            warnings: Mutex::new(Vec::new()),
+        }));
+        log::set_logger(logger).unwrap();
+        log::set_max_level(LevelFilter::Warn);
+        logger
+    });
+    let mut warnings = logger.warnings.lock().unwrap();
+    warnings
+        .drain(..)
+        .filter(|w| w.starts_with("Pruned "))
+        .collect()
+}
+
+const NODE_MODULES_DIRS: usize = PRUNE_LOGS_PER_PATTERN + 5;
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..NODE_MODULES_DIRS {
+        fs::create_dir_all(root.join(format!("pkg-{i:02}/node_modules/dep")))?;
+        fs::write(root.join(format!("pkg-{i:02}/index.js")), "js")?;
+    }
+    fs::create_dir_all(root.join("build/out"))?;
+    fs::create_dir_all(root.join("tmp"))?;
+    fs::write(root.join("debug.log"), "log")?;
+    fs::write(root.join(".gitignore"), "node_modules/\nbuild\n*.log\n")?;
+    Ok(root)
+}
+
+fn walk_both(root: &Path, walk_options: &WalkOptions) -> Result<(Vec<String>, Vec<String>)> {
+    take_prune_warnings();
+    let (output_sender, _output_receiver) = unbounded();
+    walk_serial(&output_sender, "tmp/", root, walk_options)?;
+    let serial = take_prune_warnings();
+
+    let ignore_rules = build_ignore_patterns_with_options("tmp/", root, walk_options)?;
+    let (path_sender, _path_receiver) = unbounded();
+    walk_parallel(
+        Arc::new(RwLock::new(ignore_rules)),
+        root,
+        walk_options.clone(),
+        path_sender,
+    )?;
+    let parallel = take_prune_warnings();
+    Ok((serial, parallel))
+}
+
+fn check_messages(mut messages: Vec<String>) {
+    messages.sort();
+
+    let (node_modules, others): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .partition(|m| m.contains("node_modules"));
+    assert_eq!(
+        others,
+        [
+            "Pruned \"build/\": ignored by \"build\" at .gitignore:2",
+            "Pruned \"tmp/\": ignored by \"tmp/\" not read from a file",
+        ]
+    );
+
+    // Only the first directories ignored by the same pattern are logged
+    assert_eq!(node_modules.len(), PRUNE_LOGS_PER_PATTERN);
+    let suffix = " (not logging more directories ignored by this pattern)";
+    assert_eq!(
+        node_modules.iter().filter(|m| m.ends_with(suffix)).count(),
+        1
+    );
+    for message in node_modules {
+        let message = message.trim_end_matches(suffix);
+        let dir = message
+            .strip_prefix("Pruned \"")
+            .and_then(|m| m.strip_suffix("/\": ignored by \"node_modules/\" at .gitignore:1"))
+            .unwrap_or_else(|| panic!("Unexpected message: {message}"));
+        assert!(
+            dir.starts_with("pkg-") && dir.ends_with("/node_modules"),
+            "{dir}"
+        );
+    }
+}
+
+#[test]
+fn test_log_prunes() -> Result<()> {
+    let root = setup_tree()?;
+    let walk_options = WalkOptions {
+        log_prunes: true,
+        ..WalkOptions::gitignore()
+    };
+    let (serial, parallel) = walk_both(&root, &walk_options)?;
+    check_messages(serial);
+    check_messages(parallel);
+
+    // Nothing is logged by default
+    let (serial, parallel) = walk_both(&root, &WalkOptions::gitignore())?;
+    assert!(serial.is_empty(), "{serial:?}");
+    assert!(parallel.is_empty(), "{parallel:?}");
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Result};
+use crossbeam_channel::Receiver;
+use log::LevelFilter;
+use std::fs::{self, File};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns, make_watcher, ModifiedFields, PathEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sets only the modification time of `path` to an hour before now.
+fn touch_mtime(path: &Path) -> Result<()> {
+    let mtime = SystemTime::now() - Duration::from_secs(3600);
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(mtime)?;
+    Ok(())
+}
+
+/// Receives the events until one of `path` matches `predicate`.
+fn wait_for_event(
+    receiver: &Receiver<Option<PathEvent>>,
+    path: &Path,
+    predicate: impl Fn(&PathEvent) -> bool,
+) -> Result<PathEvent> {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        let Ok(Some(event)) = receiver.recv_timeout(Duration::from_millis(100)) else {
+            continue;
+        };
+        let event_path = match &event {
+            PathEvent::Create { path, .. }
+            | PathEvent::Update { path, .. }
+            | PathEvent::Delete { path } => path,
+        };
+        if event_path == path && predicate(&event) {
+            return Ok(event);
+        }
+    }
+    Err(anyhow!("No event for {path:?}"))
+}
+
+#[test]
+fn test_modified_fields_between() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let path = root.join("a.txt");
+    fs::write(&path, "a")?;
+    let old = path.metadata()?;
+
+    touch_mtime(&path)?;
+    let fields = ModifiedFields::between(&old, &path.metadata()?);
+    assert_eq!(
+        fields,
+        ModifiedFields {
+            mtime_changed: true,
+            ..ModifiedFields::default()
+        }
+    );
+
+    fs::write(&path, "longer")?;
+    let fields = ModifiedFields::between(&old, &path.metadata()?);
+    assert!(fields.size_changed);
+    assert!(!fields.permissions_changed);
+    assert_eq!(
+        ModifiedFields::between(&old, &old),
+        ModifiedFields::default()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_event_fields() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    let (_watcher, receiver) = make_watcher(ignore_rules)?;
+
+    let path = root.join("a.txt");
+    fs::write(&path, "a")?;
+    wait_for_event(&receiver, &path, |e| matches!(e, PathEvent::Create { .. }))?;
+    // Skip the other events of the write
+    thread::sleep(Duration::from_millis(200));
+    while receiver.try_recv().is_ok() {}
+
+    touch_mtime(&path)?;
+    let event = wait_for_event(
+        &receiver,
+        &path,
+        |e| matches!(e, PathEvent::Update { fields, .. } if fields.mtime_changed),
+    )?;
+    let PathEvent::Update { fields, .. } = event else {
+        unreachable!()
+    };
+    assert_eq!(
+        fields,
+        ModifiedFields {
+            mtime_changed: true,
+            ..ModifiedFields::default()
+        }
+    );
+
+    fs::remove_file(&path)?;
+    wait_for_event(&receiver, &path, |e| matches!(e, PathEvent::Delete { .. }))?;
+    Ok(())
+}
+
+#[test]
+fn test_update_event_fields_of_existing_file() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let path = root.join("a.txt");
+    fs::write(&path, "a")?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    let (_watcher, receiver) = make_watcher(ignore_rules)?;
+
+    touch_mtime(&path)?;
+    let event = wait_for_event(&receiver, &path, |e| matches!(e, PathEvent::Update { .. }))?;
+    let PathEvent::Update { fields, .. } = event else {
+        unreachable!()
+    };
+    assert_eq!(
+        fields,
+        ModifiedFields {
+            mtime_changed: true,
+            ..ModifiedFields::default()
+        }
+    );
+    Ok(())
+}
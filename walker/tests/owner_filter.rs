@@ -0,0 +1,78 @@
+#![cfg(unix)]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, walk_serial, IgnoreRules, OwnerFilter, WalkOptions};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir/sub"))?;
+    fs::write(root.join("a.txt"), "a")?;
+    fs::write(root.join("dir/b.txt"), "b")?;
+    fs::write(root.join("dir/sub/c.txt"), "c")?;
+    Ok(root)
+}
+
+fn walk_options(owner_filter: OwnerFilter) -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        owner_filter: Some(owner_filter),
+        ..WalkOptions::gitignore()
+    }
+}
+
+fn walk_both(root: &Path, walk_options: WalkOptions) -> Result<(usize, usize)> {
+    let (output_sender, _output_receiver) = unbounded();
+    let (serial_paths, _) = walk_serial(&output_sender, "", root, &walk_options)?;
+
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    walk_parallel(ignore_rules, root, walk_options, path_sender)?;
+    let parallel_paths = path_receiver
+        .into_iter()
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+
+    Ok((serial_paths.len(), parallel_paths.len()))
+}
+
+#[test]
+fn test_current_user_owns_test_files() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    assert_eq!(
+        walk_both(&root, walk_options(OwnerFilter::CurrentUserOnly))?,
+        (3, 3)
+    );
+
+    let uid = root.join("a.txt").metadata()?.uid();
+    assert_eq!(
+        walk_both(&root, walk_options(OwnerFilter::Uid(uid)))?,
+        (3, 3)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_other_users_paths_are_skipped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let other_uid = root.join("a.txt").metadata()?.uid().wrapping_add(1);
+
+    assert_eq!(
+        walk_both(&root, walk_options(OwnerFilter::Uid(other_uid)))?,
+        (0, 0)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_filter_by_uid() {
+    assert!(OwnerFilter::Uid(1000).allows_uid(1000));
+    assert!(!OwnerFilter::Uid(1000).allows_uid(0));
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, walk_serial, IgnoreRules, PathMetadata, WalkOptions};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..8 {
+        for j in 0..4 {
+            let dir = root.join(format!("d{i}/e{j}/f"));
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("a.txt"), "a")?;
+            fs::write(dir.parent().unwrap().join("b.txt"), "b")?;
+        }
+    }
+    Ok(root)
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        parents_first: true,
+        ..WalkOptions::gitignore()
+    }
+}
+
+/// Asserts that the parent of every path is reported before it.
+fn assert_parents_first(root: &Path, paths: &[PathMetadata]) {
+    let mut seen = HashSet::new();
+    for pm in paths {
+        let parent = pm.path.parent().unwrap();
+        assert!(
+            parent == root || seen.contains(parent),
+            "{:?} is reported before its parent",
+            pm.path
+        );
+        seen.insert(pm.path.as_path());
+    }
+    // 8 + 8 * 4 * 2 directories and 8 * 4 * 2 files
+    assert_eq!(paths.iter().filter(|pm| pm.metadata.is_dir()).count(), 72);
+    assert_eq!(paths.len(), 136);
+}
+
+#[test]
+fn test_parents_first_parallel() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    for _ in 0..10 {
+        let (path_sender, path_receiver) = unbounded();
+        let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+        walk_parallel(ignore_rules, &root, walk_options(), path_sender)?;
+        let paths = path_receiver
+            .into_iter()
+            .collect::<xvc_walker::Result<Vec<_>>>()?;
+        assert_parents_first(&root, &paths);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parents_first_serial() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let (output_sender, _output_receiver) = unbounded();
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options())?;
+    assert_parents_first(&root, &paths);
+    Ok(())
+}
+
+#[test]
+fn test_directories_are_not_reported_by_default() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let (output_sender, _output_receiver) = unbounded();
+    let walk_options = WalkOptions {
+        parents_first: false,
+        ..walk_options()
+    };
+    let (paths, _) = walk_serial(&output_sender, "", &root, &walk_options)?;
+    assert!(paths.iter().all(|pm| pm.metadata.is_file()));
+    assert_eq!(paths.len(), 64);
+    Ok(())
+}
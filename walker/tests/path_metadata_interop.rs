@@ -0,0 +1,94 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns, filter_path_metadata, walk_parallel_collect, Error, PathMetadata,
+    WalkOptions,
+};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("src/gen"))?;
+    fs::create_dir_all(root.join("target/debug"))?;
+    fs::write(root.join(".gitignore"), "target/\n*.tmp\n/src/gen/\n")?;
+    fs::write(root.join("a.txt"), "a")?;
+    fs::write(root.join("b.tmp"), "b")?;
+    fs::write(root.join("src/lib.rs"), "lib")?;
+    fs::write(root.join("src/gen/out.rs"), "out")?;
+    fs::write(root.join("target/debug/bin"), "bin")?;
+    Ok(root)
+}
+
+/// Lists all paths under `dir` with [PathMetadata::from_path], like another walker would.
+fn list_all(dir: &Path, paths: &mut Vec<PathMetadata>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let pm = PathMetadata::from_path(&entry?.path())?;
+        if pm.metadata.is_dir() {
+            list_all(&pm.path, paths)?;
+        }
+        paths.push(pm);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_filter_path_metadata() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+
+    let mut items = Vec::new();
+    list_all(&root, &mut items)?;
+    // Paths out of the root are skipped
+    items.push(PathMetadata::from_path(&std::env::temp_dir())?);
+    let mut filtered: Vec<PathBuf> = filter_path_metadata(&ignore_rules, items.into_iter())
+        .filter(|pm| !pm.metadata.is_dir())
+        .map(|pm| pm.path)
+        .collect();
+    filtered.sort();
+
+    let (walked, _) = walk_parallel_collect(
+        Arc::new(RwLock::new(ignore_rules)),
+        &root,
+        WalkOptions::gitignore(),
+    )?;
+    let mut walked = walked
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<PathBuf>>>()?;
+    walked.sort();
+
+    assert_eq!(filtered, walked);
+    assert_eq!(
+        filtered,
+        vec![
+            root.join(".gitignore"),
+            root.join("a.txt"),
+            root.join("src/lib.rs")
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_path_metadata_from_path() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let pm = PathMetadata::from_path(&root.join("a.txt"))?;
+    assert_eq!(pm.path, root.join("a.txt"));
+    assert_eq!(pm.metadata.len(), 1);
+
+    let missing = root.join("missing.txt");
+    match PathMetadata::from_path(&missing) {
+        Err(Error::UnreadableMetadata { path, source }) => {
+            assert_eq!(path, missing);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        res => panic!("Unexpected result: {res:?}"),
+    }
+    Ok(())
+}
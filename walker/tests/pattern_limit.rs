@@ -0,0 +1,98 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::Path;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns, build_ignore_patterns_with_options, content_to_patterns_limited,
+    walk_serial, Error, MatchResult, PatternLimitError, WalkOptions, DEFAULT_MAX_PATTERNS_PER_FILE,
+};
+
+fn many_patterns(count: usize) -> String {
+    (0..count).map(|i| format!("file-{i}.txt\n")).collect()
+}
+
+#[test]
+fn test_content_to_patterns_limited() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = Path::new("/repo");
+    let source = root.join(".gitignore");
+    let content = "# comment\n\n*.log\n[unclosed\nbuild/\n";
+
+    let patterns = content_to_patterns_limited(root, Some(&source), content, 2)?;
+    assert_eq!(patterns.len(), 2);
+
+    let err = content_to_patterns_limited(root, Some(&source), content, 1).unwrap_err();
+    assert_eq!(
+        err,
+        PatternLimitError {
+            source_file: Some(source.clone()),
+            found: 2,
+            limit: 1,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "/repo/.gitignore: 2 patterns found, more than the limit of 1"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_build_ignore_patterns_default_limit() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(
+        root.join("dir/.gitignore"),
+        many_patterns(DEFAULT_MAX_PATTERNS_PER_FILE + 1),
+    )?;
+
+    match build_ignore_patterns("", &root, ".gitignore") {
+        Err(Error::TooManyPatterns { source }) => {
+            assert_eq!(source.source_file, Some(root.join("dir/.gitignore")));
+            assert_eq!(source.found, DEFAULT_MAX_PATTERNS_PER_FILE + 1);
+            assert_eq!(source.limit, DEFAULT_MAX_PATTERNS_PER_FILE);
+        }
+        res => panic!("expected TooManyPatterns, got {:?}", res.map(|_| ())),
+    }
+
+    fs::write(
+        root.join("dir/.gitignore"),
+        many_patterns(DEFAULT_MAX_PATTERNS_PER_FILE),
+    )?;
+    let rules = build_ignore_patterns("", &root, ".gitignore")?;
+    assert_eq!(
+        rules.check(&root.join("dir/file-0.txt")),
+        MatchResult::Ignore
+    );
+    Ok(())
+}
+
+#[test]
+fn test_max_patterns_per_file_option() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join(".gitignore"), many_patterns(5))?;
+    fs::write(root.join("file-4.txt"), "")?;
+
+    let walk_options = WalkOptions {
+        max_patterns_per_file: Some(4),
+        ..WalkOptions::gitignore()
+    };
+    let res = build_ignore_patterns_with_options("", &root, &walk_options);
+    assert!(matches!(res, Err(Error::TooManyPatterns { .. })));
+
+    let (output_sender, _output_receiver) = unbounded();
+    let res = walk_serial(&output_sender, "", &root, &walk_options);
+    assert!(matches!(res, Err(Error::TooManyPatterns { .. })));
+
+    let walk_options = WalkOptions {
+        max_patterns_per_file: None,
+        ..WalkOptions::gitignore()
+    };
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("file-4.txt")), MatchResult::Ignore);
+    Ok(())
+}
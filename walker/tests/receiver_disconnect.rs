@@ -0,0 +1,127 @@
+use anyhow::Result;
+use crossbeam_channel::{bounded, unbounded};
+use log::LevelFilter;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, Error, IgnoreRules, PathMetadata, WalkOptions};
+
+#[test]
+fn test_receiver_disconnected_mid_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let (dirs, files) = (100, 20);
+    for d in 0..dirs {
+        let dir = root.join(format!("dir-{d:03}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..files {
+            fs::write(dir.join(format!("file-{f:02}.txt")), "x")?;
+        }
+    }
+
+    // The bounded channel blocks the walk until the receiver reads, so it can't finish before
+    let (path_sender, path_receiver) = bounded(1);
+    let received = 10;
+    let receiver = thread::spawn(move || {
+        for _ in 0..received {
+            let _ = path_receiver.recv().unwrap();
+        }
+    });
+
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+    let start = Instant::now();
+    let res = walk_parallel(ignore_rules, &root, walk_options, path_sender);
+    assert!(start.elapsed() < Duration::from_secs(30));
+    receiver.join().unwrap();
+
+    match res {
+        Err(Error::DeliveryIncomplete {
+            paths_sent,
+            dirs_scanned,
+            undelivered_dirs,
+        }) => {
+            assert!(paths_sent >= received);
+            assert!(paths_sent < dirs * files);
+            assert!(dirs_scanned >= 1);
+            assert!(dirs_scanned <= dirs + 1);
+            assert!(!undelivered_dirs.is_empty());
+        }
+        res => panic!("Unexpected result: {res:?}"),
+    }
+    Ok(())
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    }
+}
+
+/// Walks `dir` with the rules rooted at `root` and returns the paths sent.
+fn walk_all(root: &Path, dir: &Path) -> Result<BTreeSet<PathBuf>> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    walk_parallel(ignore_rules, dir, walk_options(), path_sender)?;
+    path_receiver
+        .into_iter()
+        .map(|pm_res| Ok(pm_res?.path))
+        .collect()
+}
+
+#[test]
+fn test_resume_undelivered_dirs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    for d in 0..20 {
+        let dir = root.join(format!("dir-{d:02}/sub-{d:02}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..5 {
+            fs::write(dir.join(format!("file-{f}.txt")), "x")?;
+            fs::write(dir.with_file_name(format!("file-{f}.txt")), "x")?;
+        }
+    }
+    let all_paths = walk_all(&root, &root)?;
+
+    for received in [0, 1, 7, 50, 150] {
+        // Each path sent is received with a zero capacity channel
+        let (path_sender, path_receiver) = bounded(0);
+        let receiver = thread::spawn(move || {
+            path_receiver
+                .iter()
+                .take(received)
+                .map(|pm_res: xvc_walker::Result<PathMetadata>| pm_res.unwrap().path)
+                .collect::<BTreeSet<_>>()
+        });
+        let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+        let res = walk_parallel(ignore_rules, &root, walk_options(), path_sender);
+        let mut paths = receiver.join().unwrap();
+        assert_eq!(paths.len(), received);
+
+        let undelivered_dirs = match res {
+            Err(Error::DeliveryIncomplete {
+                undelivered_dirs, ..
+            }) => undelivered_dirs,
+            res => panic!("Unexpected result: {res:?}"),
+        };
+        assert!(!undelivered_dirs.is_empty());
+        for (i, dir) in undelivered_dirs.iter().enumerate() {
+            assert!(dir.starts_with(&root));
+            // The directories under the others are not returned
+            assert!(!undelivered_dirs[..i]
+                .iter()
+                .any(|other| dir.starts_with(other)));
+            paths.extend(walk_all(&root, dir)?);
+        }
+        assert_eq!(paths, all_paths);
+    }
+    Ok(())
+}
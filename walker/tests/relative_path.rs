@@ -0,0 +1,35 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{PathBuf, MAIN_SEPARATOR};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::PathMetadata;
+
+#[test]
+fn test_relative_path() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("a/b"))?;
+    let path = root.join("a").join("b").join("c.txt");
+    fs::write(&path, "c")?;
+    let pm = PathMetadata::new(path.clone(), path.metadata()?);
+
+    let with_separator = PathBuf::from(format!("{}{MAIN_SEPARATOR}", root.to_string_lossy()));
+    for root in [&root, &with_separator] {
+        assert_eq!(pm.relative_path(root), Some(PathBuf::from("a/b/c.txt")));
+        assert_eq!(pm.relative_path_str(root).as_deref(), Some("a/b/c.txt"));
+    }
+    assert_eq!(
+        pm.relative_path_str(&root.join("a")).as_deref(),
+        Some("b/c.txt")
+    );
+    assert_eq!(pm.relative_path(&path), Some(PathBuf::new()));
+    assert_eq!(pm.relative_path_str(&path).as_deref(), Some(""));
+
+    // Not under the root
+    for other in [root.join("b"), root.join("a/b/c"), create_temp_dir()] {
+        assert_eq!(pm.relative_path(&other), None);
+        assert_eq!(pm.relative_path_str(&other), None);
+    }
+    Ok(())
+}
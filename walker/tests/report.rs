@@ -0,0 +1,88 @@
+#![cfg(feature = "report")]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::report::{read_walk_json, write_walk_json, WalkRecord};
+use xvc_walker::{walk_serial, Error, PathMetadata, WalkOptions};
+
+const GOLDEN: &str = include_str!("golden/walk_report.jsonl");
+
+fn setup_fixture() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir/sub"))?;
+    for (i, (path, content)) in [
+        ("a.txt", "a"),
+        ("dir/b.txt", "bb"),
+        ("dir/sub/c d.txt", "ccc"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        fs::write(root.join(path), content)?;
+        let mtime = UNIX_EPOCH + Duration::new(1_704_164_645 + i as u64 * 86400, 6);
+        fs::File::options()
+            .write(true)
+            .open(root.join(path))?
+            .set_modified(mtime)?;
+    }
+    Ok(root)
+}
+
+fn walk(root: &Path) -> Result<Vec<PathMetadata>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+    let (mut paths, _) = walk_serial(&output_sender, "", root, &walk_options)?;
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
+
+#[test]
+fn test_walk_json_matches_golden_file() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fixture()?;
+    let paths = walk(&root)?;
+    let error = Error::BrokenSymlink {
+        path: PathBuf::from("broken"),
+    };
+    let items = paths.iter().cloned().map(Ok).chain([Err(error)]);
+
+    let mut output = Vec::new();
+    write_walk_json(items, &root, &mut output)?;
+
+    assert_eq!(String::from_utf8(output)?, GOLDEN);
+    Ok(())
+}
+
+#[test]
+fn test_walk_json_round_trip() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fixture()?;
+    let paths = walk(&root)?;
+
+    let mut output = Vec::new();
+    write_walk_json(paths.iter().cloned().map(Ok), &root, &mut output)?;
+    let records = read_walk_json(output.as_slice())?;
+
+    let expected: Vec<WalkRecord> = paths
+        .iter()
+        .map(|pm| WalkRecord::from_path_metadata(pm, &root))
+        .collect();
+    assert_eq!(records, expected);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_line_is_reported() {
+    let input = "{\"path\": \"a\", \"size\": 1, \"mtime\": null, \"is_dir\": false, \"error\": null}\n\nnot json\n";
+    assert!(matches!(
+        read_walk_json(input.as_bytes()),
+        Err(Error::InvalidWalkRecord { line: 3, .. })
+    ));
+}
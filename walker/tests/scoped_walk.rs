@@ -0,0 +1,93 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel_scoped, IgnoreRules, MatchResult, Pattern, WalkOptions};
+
+fn run_scoped_walk(
+    ignore_rules: &IgnoreRules,
+    root: &Path,
+    dir: &Path,
+    read_gitattributes_export_ignore: bool,
+) -> Result<(HashSet<String>, Vec<Pattern>)> {
+    let (path_sender, path_receiver) = unbounded();
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        read_gitattributes_export_ignore,
+        ..WalkOptions::gitignore()
+    };
+    let found_patterns = walk_parallel_scoped(ignore_rules, dir, walk_options, path_sender)?;
+
+    let mut found_paths = HashSet::new();
+    for path_res in path_receiver {
+        let path_meta = path_res?;
+        let relative_path = path_meta.path.strip_prefix(root)?;
+        found_paths.insert(relative_path.to_string_lossy().replace('\\', "/"));
+    }
+    Ok((found_paths, found_patterns))
+}
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for dir in ["a", "b"] {
+        fs::create_dir_all(root.join(dir).join("raw"))?;
+        fs::write(root.join(dir).join("raw/data.bin"), dir)?;
+        fs::write(root.join(dir).join("keep.txt"), dir)?;
+    }
+    // Matches `raw` in both subtrees when read from the root
+    fs::write(root.join(".gitattributes"), "raw export-ignore\n")?;
+    Ok(root)
+}
+
+#[test]
+fn test_scoped_walks_dont_share_found_patterns() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let ignore_rules = IgnoreRules::from_global_patterns(&root, None, "*.log");
+
+    let (with_attributes, without_attributes) = thread::scope(|s| {
+        let with_attributes = s.spawn(|| run_scoped_walk(&ignore_rules, &root, &root, true));
+        let without_attributes =
+            s.spawn(|| run_scoped_walk(&ignore_rules, &root, &root.join("b"), false));
+        (
+            with_attributes.join().unwrap(),
+            without_attributes.join().unwrap(),
+        )
+    });
+
+    let (paths, found_patterns) = with_attributes?;
+    let expected: HashSet<String> = [".gitattributes", "a/keep.txt", "b/keep.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(paths, expected);
+    assert_eq!(found_patterns.len(), 1);
+    assert_eq!(found_patterns[0].original_pattern(), "raw");
+
+    // The other walk doesn't see the pattern found in the first
+    let (paths, found_patterns) = without_attributes?;
+    let expected: HashSet<String> = ["b/keep.txt", "b/raw/data.bin"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(paths, expected);
+    assert!(found_patterns.is_empty());
+
+    // Neither are the shared rules
+    assert_eq!(ignore_rules.snapshot_patterns().len(), 1);
+    assert_eq!(
+        ignore_rules.check(&root.join("b/raw")),
+        MatchResult::NoMatch
+    );
+
+    // The caller merges them deliberately
+    let (_, found_patterns) = run_scoped_walk(&ignore_rules, &root, &root, true)?;
+    ignore_rules.add_patterns(found_patterns)?;
+    let (paths, _) = run_scoped_walk(&ignore_rules, &root, &root.join("b"), false)?;
+    assert_eq!(paths, HashSet::from(["b/keep.txt".to_string()]));
+    Ok(())
+}
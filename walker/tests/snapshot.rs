@@ -0,0 +1,119 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::create_temp_dir;
+use xvc_walker::{build_ignore_patterns, diff_pattern_sets, PatternSetDiff, PatternSummary};
+
+fn snapshot(root: &Path, ignore_files: &[(&str, &str)]) -> Result<Vec<PatternSummary>> {
+    for (path, content) in ignore_files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+    }
+    Ok(build_ignore_patterns("", root, ".gitignore")?.snapshot_patterns())
+}
+
+fn originals(patterns: &[PatternSummary]) -> Vec<(Option<PathBuf>, &str)> {
+    patterns
+        .iter()
+        .map(|p| (p.source_path.clone(), p.original.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_snapshot_is_in_precedence_order() -> Result<()> {
+    let root = create_temp_dir();
+    let patterns = snapshot(
+        &root,
+        &[
+            ("b/c/.gitignore", "*.c"),
+            ("a/.gitignore", "*.a\n!x.a"),
+            (".gitignore", "*.log"),
+        ],
+    )?;
+
+    assert_eq!(
+        originals(&patterns),
+        vec![
+            (Some(PathBuf::from(".gitignore")), "*.log"),
+            (Some(PathBuf::from("a/.gitignore")), "*.a"),
+            (Some(PathBuf::from("a/.gitignore")), "!x.a"),
+            (Some(PathBuf::from("b/c/.gitignore")), "*.c"),
+        ]
+    );
+    assert_eq!(patterns[2].line, Some(2));
+    Ok(())
+}
+
+#[test]
+fn test_rule_edited_in_place() -> Result<()> {
+    let root = create_temp_dir();
+    let old = snapshot(&root, &[(".gitignore", "*.log\n*.tmp\n*.bak")])?;
+    let new = snapshot(&root, &[(".gitignore", "*.log\n*.temp\n*.bak")])?;
+
+    let diff = diff_pattern_sets(&old, &new);
+    assert_eq!(originals(&diff.removed), originals(&old[1..2]));
+    assert_eq!(originals(&diff.added), originals(&new[1..2]));
+    assert!(diff.moved.is_empty());
+    assert!(diff_pattern_sets(&new, &new).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_rule_moved_between_files() -> Result<()> {
+    let root = create_temp_dir();
+    let old = snapshot(
+        &root,
+        &[(".gitignore", "*.log\n*.tmp"), ("a/.gitignore", "")],
+    )?;
+    let new = snapshot(&root, &[(".gitignore", "*.tmp"), ("a/.gitignore", "*.log")])?;
+
+    let diff = diff_pattern_sets(&old, &new);
+    assert_eq!(
+        originals(&diff.removed),
+        vec![(Some(PathBuf::from(".gitignore")), "*.log")]
+    );
+    assert_eq!(
+        originals(&diff.added),
+        vec![(Some(PathBuf::from("a/.gitignore")), "*.log")]
+    );
+    // The line of *.tmp changed but its order relative to the others didn't
+    assert!(diff.moved.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_deleted_ignore_file() -> Result<()> {
+    let root = create_temp_dir();
+    let old = snapshot(
+        &root,
+        &[(".gitignore", "*.log"), ("a/.gitignore", "*.a\n*.b")],
+    )?;
+    fs::remove_file(root.join("a/.gitignore"))?;
+    let new = snapshot(&root, &[])?;
+
+    let diff = diff_pattern_sets(&old, &new);
+    assert_eq!(
+        diff,
+        PatternSetDiff {
+            removed: old[1..].to_vec(),
+            ..PatternSetDiff::default()
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rule_moved_in_file() -> Result<()> {
+    let root = create_temp_dir();
+    let old = snapshot(&root, &[(".gitignore", "a\nb\nc\nd")])?;
+    let new = snapshot(&root, &[(".gitignore", "new\nd\na\nb\nc")])?;
+
+    let diff = diff_pattern_sets(&old, &new);
+    assert_eq!(originals(&diff.added), originals(&new[..1]));
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.moved.len(), 1);
+    assert_eq!(diff.moved[0].pattern.original, "d");
+    assert_eq!((diff.moved[0].old_index, diff.moved[0].new_index), (3, 1));
+    Ok(())
+}
@@ -0,0 +1,123 @@
+#![cfg(unix)]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel, walk_serial, Error, FileKind, IgnoreRules, PathMetadata, SpecialFileBehavior,
+    WalkOptions,
+};
+
+fn setup_fifo_directory() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    let status = Command::new("mkfifo").arg(root.join("pipe")).status()?;
+    assert!(status.success());
+    Ok(root)
+}
+
+fn run_walk(root: &Path, walk_options: WalkOptions) -> Result<(Vec<PathMetadata>, Vec<Error>)> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    walk_parallel(ignore_rules, root, walk_options, path_sender)?;
+
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for path_res in path_receiver {
+        match path_res {
+            Ok(pm) => paths.push(pm),
+            Err(e) => errors.push(e),
+        }
+    }
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((paths, errors))
+}
+
+fn run_walk_serial(root: &Path, walk_options: WalkOptions) -> Result<Vec<PathMetadata>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let (mut paths, _) = walk_serial(&output_sender, "", root, &walk_options)?;
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
+
+fn walk_options(special_files: SpecialFileBehavior) -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        special_files,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[test]
+fn test_special_files_skip() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fifo_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(SpecialFileBehavior::Skip))?;
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, root.join("a.txt"));
+    assert_eq!(paths[0].file_kind, FileKind::Regular);
+    assert!(errors.is_empty());
+
+    let paths = run_walk_serial(&root, walk_options(SpecialFileBehavior::Skip))?;
+    assert_eq!(paths.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_special_files_report() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fifo_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(SpecialFileBehavior::Report))?;
+
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[1].path, root.join("pipe"));
+    assert_eq!(paths[1].file_kind, FileKind::Fifo);
+    assert!(errors.is_empty());
+
+    let paths = run_walk_serial(&root, walk_options(SpecialFileBehavior::Report))?;
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[1].file_kind, FileKind::Fifo);
+    Ok(())
+}
+
+#[test]
+fn test_special_files_error() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fifo_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(SpecialFileBehavior::Error))?;
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, root.join("a.txt"));
+    assert_eq!(
+        errors,
+        vec![Error::SpecialFile {
+            path: root.join("pipe"),
+            kind: FileKind::Fifo,
+        }]
+    );
+
+    // The serial walker reports the errors to the output channel
+    let paths = run_walk_serial(&root, walk_options(SpecialFileBehavior::Error))?;
+    assert_eq!(paths.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_special_file_as_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_fifo_directory()?;
+    let pipe = root.join("pipe");
+
+    let (paths, _) = run_walk(&pipe, walk_options(SpecialFileBehavior::Skip))?;
+    assert!(paths.is_empty());
+
+    let (paths, _) = run_walk(&pipe, walk_options(SpecialFileBehavior::Report))?;
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].file_kind, FileKind::Fifo);
+    Ok(())
+}
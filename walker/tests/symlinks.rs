@@ -0,0 +1,189 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    walk_parallel, BrokenSymlinkHandling, Error, IgnoreRules, PathMetadata, TraversalOrder,
+    WalkOptions,
+};
+
+fn setup_broken_symlink_directory() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    make_symlink(root.join("missing.txt"), root.join("broken-link"))?;
+    Ok(root)
+}
+
+fn run_walk(root: &Path, walk_options: WalkOptions) -> Result<(Vec<PathMetadata>, Vec<Error>)> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    walk_parallel(ignore_rules, root, walk_options, path_sender)?;
+
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for path_res in path_receiver {
+        match path_res {
+            Ok(pm) => paths.push(pm),
+            Err(e) => errors.push(e),
+        }
+    }
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((paths, errors))
+}
+
+fn walk_options(broken_symlink_handling: BrokenSymlinkHandling) -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        broken_symlink_handling,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[test]
+fn test_broken_symlink_skip() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_broken_symlink_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(BrokenSymlinkHandling::Skip))?;
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, root.join("a.txt"));
+    assert!(errors.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_broken_symlink_emit_as_error() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_broken_symlink_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(BrokenSymlinkHandling::EmitAsError))?;
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, root.join("a.txt"));
+    assert_eq!(
+        errors,
+        vec![Error::BrokenSymlink {
+            path: root.join("broken-link")
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_broken_symlink_emit_as_entry() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_broken_symlink_directory()?;
+    let (paths, errors) = run_walk(&root, walk_options(BrokenSymlinkHandling::EmitAsEntry))?;
+
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[1].path, root.join("broken-link"));
+    assert!(paths[1].metadata.is_symlink());
+    assert!(errors.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_circular_symlink_is_reported() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("a"))?;
+    fs::write(root.join("a/b.txt"), "b")?;
+    make_symlink(root.join("a"), root.join("a/loop"))?;
+
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        follow_symlinks: true,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, errors) = run_walk(&root, walk_options)?;
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, root.join("a/b.txt"));
+    assert_eq!(
+        errors,
+        vec![Error::CircularSymlink {
+            link: root.join("a/loop"),
+            target: root.join("a").canonicalize()?,
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_symlink_to_walked_sibling_is_not_circular() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("a/c"))?;
+    fs::create_dir_all(root.join("b"))?;
+    fs::write(root.join("a/c/d.txt"), "d")?;
+    make_symlink(root.join("a"), root.join("b/link"))?;
+    make_symlink(root.join("a"), root.join("a/c/up"))?;
+
+    // Only the symlinks to the directories they are under are circular, whichever is walked first
+    for traversal_order in [
+        TraversalOrder::DepthFirst,
+        TraversalOrder::BreadthFirst,
+        TraversalOrder::ShallowestFirst,
+    ] {
+        let walk_options = WalkOptions {
+            ignore_filename: None,
+            follow_symlinks: true,
+            skip_duplicate_dirs: false,
+            traversal_order,
+            ..WalkOptions::gitignore()
+        };
+        let (paths, mut errors) = run_walk(&root, walk_options)?;
+        let paths: Vec<PathBuf> = paths.into_iter().map(|pm| pm.path).collect();
+        assert_eq!(
+            paths,
+            vec![root.join("a/c/d.txt"), root.join("b/link/c/d.txt")]
+        );
+        errors.sort_by_key(|e| e.to_string());
+        assert_eq!(
+            errors,
+            vec![
+                Error::CircularSymlink {
+                    link: root.join("a/c/up"),
+                    target: root.join("a").canonicalize()?,
+                },
+                Error::CircularSymlink {
+                    link: root.join("b/link/c/up"),
+                    target: root.join("a").canonicalize()?,
+                },
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_directory_walked_twice_is_skipped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("a"))?;
+    fs::write(root.join("a/b.txt"), "b")?;
+    make_symlink(root.join("a"), root.join("link"))?;
+
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        follow_symlinks: true,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, errors) = run_walk(&root, walk_options.clone())?;
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path.file_name().unwrap(), "b.txt");
+    assert!(errors.is_empty());
+
+    let (paths, _) = run_walk(
+        &root,
+        WalkOptions {
+            skip_duplicate_dirs: false,
+            ..walk_options
+        },
+    )?;
+    assert_eq!(paths.len(), 2);
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{builtin_system_patterns, walk_serial, Source, WalkOptions};
+
+fn run_walk(root: &Path, skip_system_files: bool) -> Result<HashSet<String>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let walk_options = WalkOptions {
+        skip_system_files,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, _) = walk_serial(&output_sender, "", root, &walk_options)?;
+    paths
+        .into_iter()
+        .map(|pm| {
+            let relative_path = pm.path.strip_prefix(root)?;
+            Ok(relative_path.to_string_lossy().replace('\\', "/"))
+        })
+        .collect()
+}
+
+#[test]
+fn test_skip_system_files() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("photos"))?;
+    for file in [
+        ".DS_Store",
+        "Thumbs.db",
+        "desktop.ini",
+        "Icon\r",
+        "Icon",
+        "photos/.DS_Store",
+        "photos/a.jpg",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    let paths = run_walk(&root, true)?;
+    let expected: HashSet<String> = ["Icon", "photos/a.jpg"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(paths, expected);
+
+    // The user's whitelist takes precedence over the builtin patterns
+    fs::write(root.join(".gitignore"), "!.DS_Store\n")?;
+    let paths = run_walk(&root, true)?;
+    let expected: HashSet<String> = [
+        ".DS_Store",
+        ".gitignore",
+        "Icon",
+        "photos/.DS_Store",
+        "photos/a.jpg",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    assert_eq!(paths, expected);
+
+    // All files are reported by default
+    let paths = run_walk(&root, false)?;
+    assert_eq!(paths.len(), 8);
+    assert!(paths.contains(".DS_Store"));
+    assert!(paths.contains("Icon\r"));
+
+    let patterns = builtin_system_patterns();
+    assert_eq!(patterns.len(), 4);
+    assert!(patterns.iter().all(|p| p.source == Source::Builtin));
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel_collect, IgnoreRules, TraversalOrder, WalkOptions};
+
+/// Creates a tree with files at depths 1 to 4 under the root.
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..5 {
+        fs::write(root.join(format!("top-{i}.txt")), "1")?;
+        for j in 0..4 {
+            let dir = root.join(format!("dir-{i}/sub-{j}"));
+            fs::create_dir_all(dir.join("deep"))?;
+            fs::write(root.join(format!("dir-{i}/file-{j}.txt")), "2")?;
+            fs::write(dir.join("file.txt"), "3")?;
+            fs::write(dir.join("deep/file.txt"), "4")?;
+        }
+    }
+    Ok(root)
+}
+
+fn walk(root: &Path, traversal_order: TraversalOrder) -> Result<Vec<PathBuf>> {
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        traversal_order,
+        ..WalkOptions::gitignore()
+    };
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    let (paths, _) = walk_parallel_collect(ignore_rules, root, walk_options)?;
+    paths
+        .into_iter()
+        .map(|pm_res| Ok(pm_res?.path.strip_prefix(root)?.to_path_buf()))
+        .collect()
+}
+
+#[test]
+fn test_traversal_orders_find_same_paths() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let mut expected = walk(&root, TraversalOrder::BreadthFirst)?;
+    expected.sort();
+    assert_eq!(expected.len(), 5 + 5 * 4 * 3);
+    for traversal_order in [TraversalOrder::DepthFirst, TraversalOrder::ShallowestFirst] {
+        let mut paths = walk(&root, traversal_order)?;
+        paths.sort();
+        assert_eq!(paths, expected, "{traversal_order:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_shallowest_first_reports_shallow_paths_first() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let depths: Vec<usize> = walk(&root, TraversalOrder::ShallowestFirst)?
+        .iter()
+        .map(|p| p.components().count())
+        .collect();
+    let last_depth_1 = depths.iter().rposition(|d| *d == 1).unwrap();
+    let first_depth_3 = depths.iter().position(|d| *d == 3).unwrap();
+    assert!(last_depth_1 < first_depth_3);
+    Ok(())
+}
@@ -0,0 +1,140 @@
+#![cfg(feature = "unicode-normalization")]
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::Path;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    content_to_patterns, glob_match, glob_match_nfc, walk_serial_visit, Error, GlobPattern,
+    IgnoreRules, MatchResult, PathMetadata, WalkAction, WalkOptions, WalkVisitor,
+};
+
+/// `é` composed, as most patterns are written.
+const NFC: &str = "caf\u{E9}";
+/// `é` decomposed, as macOS file systems store it.
+const NFD: &str = "cafe\u{301}";
+
+fn rules(root: &Path, content: &str, unicode_normalize: bool) -> IgnoreRules {
+    let mut ignore_rules = IgnoreRules::empty(root, Some(".gitignore"));
+    ignore_rules.unicode_normalize = unicode_normalize;
+    ignore_rules
+        .add_patterns(content_to_patterns(root, Some(&root.join(".gitignore")), content).0)
+        .unwrap();
+    ignore_rules
+}
+
+#[test]
+fn test_glob_match_nfc() {
+    for (glob, path) in [(NFC, NFD), (NFD, NFC), (NFC, NFC), (NFD, NFD)] {
+        assert!(glob_match_nfc(
+            &format!("{glob}.txt"),
+            &format!("{path}.txt")
+        ));
+        assert!(glob_match_nfc(&format!("{glob}/*"), &format!("{path}/a")));
+        assert!(GlobPattern::new(format!("{glob}.txt")).matches_nfc(&format!("{path}.txt")));
+    }
+    assert!(!glob_match(format!("{NFC}.txt"), format!("{NFD}.txt")));
+    assert!(!GlobPattern::new(format!("{NFC}.txt")).matches(&format!("{NFD}.txt")));
+}
+
+#[test]
+fn test_normalized_rules() {
+    let root = create_temp_dir();
+    for (pattern, path) in [(NFC, NFD), (NFD, NFC)] {
+        let content = format!("{pattern}.txt\n/{pattern}/*.o\n*.{pattern}\n");
+        let paths = [
+            format!("{path}.txt"),
+            format!("{path}/a.o"),
+            format!("a.{path}"),
+        ];
+
+        let ignore_rules = rules(&root, &content, true);
+        let root_patterns = ignore_rules.directory_patterns(&root);
+        let dir_patterns = ignore_rules.child_directory_patterns(&root_patterns, &root.join(path));
+        for path in &paths {
+            assert_eq!(
+                ignore_rules.check(&root.join(path)),
+                MatchResult::Ignore,
+                "{path}"
+            );
+        }
+        assert_eq!(
+            ignore_rules.check_in_directory(&root.join(&paths[1]), &dir_patterns),
+            MatchResult::Ignore
+        );
+
+        // Case insensitive rules are normalized too
+        let mut ignore_rules = rules(&root, &content.to_uppercase(), true);
+        ignore_rules.case_insensitive = true;
+        for path in &paths {
+            assert_eq!(
+                ignore_rules.check(&root.join(path)),
+                MatchResult::Ignore,
+                "{path}"
+            );
+        }
+
+        let ignore_rules = rules(&root, &content, false);
+        for path in &paths {
+            assert_eq!(
+                ignore_rules.check(&root.join(path)),
+                MatchResult::NoMatch,
+                "{path}"
+            );
+        }
+    }
+}
+
+struct Collector(std::sync::Mutex<Vec<String>>);
+
+impl WalkVisitor for Collector {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        let name = entry
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        self.0.lock().unwrap().push(name);
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        WalkAction::Continue
+    }
+}
+
+#[test]
+fn test_walk_unicode_normalize() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join(".gitignore"), format!("{NFC}.txt\n"))?;
+    fs::write(root.join(format!("{NFD}.txt")), "decomposed")?;
+    fs::write(root.join("other.txt"), "other")?;
+
+    let walk = |unicode_normalize| -> Result<Vec<String>> {
+        let walk_options = WalkOptions {
+            unicode_normalize,
+            ..WalkOptions::gitignore()
+        };
+        let visitor = Collector(Default::default());
+        walk_serial_visit("", &root, &walk_options, &visitor)?;
+        let mut names = visitor.0.into_inner().unwrap();
+        names.sort();
+        Ok(names)
+    };
+
+    assert_eq!(walk(true)?, vec![".gitignore", "other.txt"]);
+    // The file system may normalize the name, e.g., on macOS
+    if fs::read_dir(&root)?.any(|e| e.is_ok_and(|e| e.file_name() == *format!("{NFD}.txt"))) {
+        assert_eq!(
+            walk(false)?,
+            vec![
+                ".gitignore".to_string(),
+                format!("{NFD}.txt"),
+                "other.txt".to_string()
+            ]
+        );
+    }
+    Ok(())
+}
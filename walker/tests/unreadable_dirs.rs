@@ -0,0 +1,113 @@
+#![cfg(unix)]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel, walk_serial_visit, Error, IgnoreRules, PathMetadata, WalkAction, WalkOptions,
+    WalkVisitor,
+};
+
+/// Creates a tree with a directory that can't be listed, and returns the root and the directory.
+fn setup_tree() -> Result<(PathBuf, PathBuf)> {
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("open/sub"))?;
+    fs::create_dir_all(root.join("locked/inner"))?;
+    fs::write(root.join("a.txt"), "a")?;
+    fs::write(root.join("open/sub/b.txt"), "b")?;
+    fs::write(root.join("locked/c.txt"), "c")?;
+    let locked = root.join("locked");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+    Ok((root, locked))
+}
+
+/// Restores the permissions so the directory can be removed.
+fn unlock(locked: &Path) -> Result<()> {
+    fs::set_permissions(locked, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Collector {
+    paths: Mutex<Vec<PathBuf>>,
+    errors: Mutex<usize>,
+}
+
+impl WalkVisitor for Collector {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths.lock().unwrap().push(entry.path.clone());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        *self.errors.lock().unwrap() += 1;
+        WalkAction::Continue
+    }
+}
+
+fn walk_options(skip_unreadable_dirs: bool) -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        skip_unreadable_dirs,
+        ..WalkOptions::gitignore()
+    }
+}
+
+/// Walks `root` in parallel and returns the paths found, sorted, and the number of errors.
+fn walk_parallel_paths(root: &Path, walk_options: WalkOptions) -> Result<(Vec<PathBuf>, usize)> {
+    let (path_sender, path_receiver) = unbounded();
+    walk_parallel(
+        Arc::new(RwLock::new(IgnoreRules::empty(root, None))),
+        root,
+        walk_options,
+        path_sender,
+    )?;
+    let mut paths = Vec::new();
+    let mut errors = 0;
+    for pm_res in path_receiver {
+        match pm_res {
+            Ok(pm) => paths.push(pm.path),
+            Err(_) => errors += 1,
+        }
+    }
+    paths.sort();
+    Ok((paths, errors))
+}
+
+#[test]
+fn test_skip_unreadable_dirs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let (root, locked) = setup_tree()?;
+    // Root can list the directory anyway
+    if fs::read_dir(&locked).is_ok() {
+        return unlock(&locked);
+    }
+
+    let expected = vec![root.join("a.txt"), root.join("open/sub/b.txt")];
+
+    let (paths, errors) = walk_parallel_paths(&root, walk_options(true))?;
+    assert_eq!(paths, expected);
+    assert_eq!(errors, 0);
+
+    let visitor = Collector::default();
+    walk_serial_visit("", &root, &walk_options(true), &visitor)?;
+    let mut paths = visitor.paths.into_inner().unwrap();
+    paths.sort();
+    assert_eq!(paths, expected);
+    assert_eq!(visitor.errors.into_inner().unwrap(), 0);
+
+    // By default, the parallel walker reports the error and the serial walker stops with it
+    let (paths, errors) = walk_parallel_paths(&root, walk_options(false))?;
+    assert_eq!(paths, expected);
+    assert_eq!(errors, 1);
+
+    let visitor = Collector::default();
+    let res = walk_serial_visit("", &root, &walk_options(false), &visitor);
+    assert!(res.is_err());
+
+    unlock(&locked)
+}
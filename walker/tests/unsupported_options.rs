@@ -0,0 +1,83 @@
+#![cfg(any(not(feature = "unicode-normalization"), not(unix)))]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_serial, Error, IgnoreRules, WalkOptions,
+};
+
+fn assert_unsupported<T: std::fmt::Debug>(res: xvc_walker::Result<T>, option: &str) {
+    match res {
+        Err(Error::UnsupportedWalkOption { option: o, .. }) => assert_eq!(o, option),
+        res => panic!("Unexpected result: {res:?}"),
+    }
+}
+
+#[cfg(not(feature = "unicode-normalization"))]
+#[test]
+fn test_unicode_normalize_without_feature() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    let walk_options = WalkOptions {
+        unicode_normalize: true,
+        ..WalkOptions::gitignore()
+    };
+
+    let (output_sender, _output_receiver) = unbounded();
+    assert_unsupported(
+        walk_serial(&output_sender, "", &root, &walk_options),
+        "unicode_normalize",
+    );
+    let (path_sender, _path_receiver) = unbounded();
+    assert_unsupported(
+        walk_parallel(
+            Arc::new(RwLock::new(IgnoreRules::empty(&root, None))),
+            &root,
+            walk_options.clone(),
+            path_sender,
+        ),
+        "unicode_normalize",
+    );
+    assert_unsupported(
+        build_ignore_patterns_with_options("", &root, &walk_options),
+        "unicode_normalize",
+    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+#[test]
+fn test_owner_filter_off_unix() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    let walk_options = WalkOptions {
+        owner_filter: Some(xvc_walker::OwnerFilter::CurrentUserOnly),
+        ..WalkOptions::gitignore()
+    };
+
+    let (output_sender, _output_receiver) = unbounded();
+    assert_unsupported(
+        walk_serial(&output_sender, "", &root, &walk_options),
+        "owner_filter",
+    );
+    let (path_sender, _path_receiver) = unbounded();
+    assert_unsupported(
+        walk_parallel(
+            Arc::new(RwLock::new(IgnoreRules::empty(&root, None))),
+            &root,
+            walk_options.clone(),
+            path_sender,
+        ),
+        "owner_filter",
+    );
+    assert_unsupported(
+        build_ignore_patterns_with_options("", &root, &walk_options),
+        "owner_filter",
+    );
+    Ok(())
+}
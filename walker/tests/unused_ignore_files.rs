@@ -0,0 +1,115 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_parallel_collect,
+    walk_parallel_with_check, IgnoreRules, UnusedIgnoreFiles, WalkOptions,
+    UNUSED_IGNORE_FILE_EXAMPLES,
+};
+
+const PACKAGES: usize = UNUSED_IGNORE_FILE_EXAMPLES + 2;
+
+/// A tree migrated from `.gitignore` to `.xvcignore` with both files left in it.
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for i in 0..PACKAGES {
+        let package = root.join(format!("pkg-{i}"));
+        fs::create_dir_all(&package)?;
+        fs::write(package.join(".gitignore"), "*.tmp\n")?;
+        fs::write(package.join("data.tmp"), "tmp")?;
+    }
+    fs::create_dir_all(root.join("target/debug"))?;
+    fs::write(root.join("target/debug/.gitignore"), "*\n")?;
+    // A directory with an ignore filename is not counted
+    fs::create_dir_all(root.join(".ignore"))?;
+    fs::write(root.join(".gitignore"), "*.tmp\ntarget/\n")?;
+    fs::write(root.join(".xvcignore"), "*.tmp\ntarget/\n")?;
+    fs::write(root.join("pkg-0/.xvcignore"), "!data.tmp\n")?;
+    Ok(root)
+}
+
+fn walk(root: &Path, walk_options: WalkOptions) -> Result<UnusedIgnoreFiles> {
+    let ignore_rules = build_ignore_patterns_with_options("", root, &walk_options)?;
+    let (path_sender, _path_receiver) = unbounded();
+    let summary = walk_parallel(
+        Arc::new(RwLock::new(ignore_rules)),
+        root,
+        walk_options,
+        path_sender,
+    )?;
+    Ok(summary.metrics.unused_ignore_files)
+}
+
+#[test]
+fn test_unused_ignore_files() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    // The .gitignore files in the root and the packages, not the one in the ignored directory
+    let unused = walk(&root, WalkOptions::xvcignore())?;
+    assert_eq!(unused.count, PACKAGES + 1);
+    assert_eq!(unused.examples.len(), UNUSED_IGNORE_FILE_EXAMPLES);
+    let mut sorted = unused.examples.clone();
+    sorted.sort();
+    assert_eq!(unused.examples, sorted);
+    for example in &unused.examples {
+        assert_eq!(example.file_name().unwrap(), ".gitignore");
+        assert!(example.starts_with(&root));
+        assert!(!example.starts_with(root.join("target")));
+    }
+
+    // The other way around, only the .xvcignore files are unused
+    let unused = walk(&root, WalkOptions::gitignore())?;
+    assert_eq!(
+        unused,
+        UnusedIgnoreFiles {
+            count: 2,
+            examples: vec![root.join(".xvcignore"), root.join("pkg-0/.xvcignore")],
+        }
+    );
+
+    let unused = walk(
+        &root,
+        WalkOptions {
+            known_ignore_filenames: Vec::new(),
+            ..WalkOptions::xvcignore()
+        },
+    )?;
+    assert_eq!(unused, UnusedIgnoreFiles::default());
+
+    // The metrics have the same files
+    let (_, metrics) = walk_parallel_collect(
+        Arc::new(RwLock::new(IgnoreRules::empty(&root, None))),
+        &root,
+        WalkOptions {
+            known_ignore_filenames: vec![".xvcignore".to_string()],
+            ..WalkOptions::gitignore()
+        },
+    )?;
+    assert_eq!(metrics.unused_ignore_files.count, 2);
+    Ok(())
+}
+
+#[test]
+fn test_unused_ignore_files_with_check() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = WalkOptions::xvcignore();
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+
+    // The .xvcignore files the rules are built from are not counted
+    let (path_sender, _path_receiver) = unbounded();
+    let summary = walk_parallel_with_check(&ignore_rules, &root, walk_options, path_sender)?;
+    assert_eq!(summary.metrics.unused_ignore_files.count, PACKAGES + 1);
+    assert!(summary
+        .metrics
+        .unused_ignore_files
+        .examples
+        .iter()
+        .all(|p| p.file_name().unwrap() == ".gitignore"));
+    Ok(())
+}
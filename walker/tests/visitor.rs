@@ -0,0 +1,151 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel, walk_parallel_visit, walk_serial_visit, Error, IgnoreRules, PathMetadata,
+    WalkAction, WalkOptions, WalkVisitor,
+};
+
+/// Records the visited paths and returns `action` after `limit` entries.
+struct RecordingVisitor {
+    paths: Mutex<Vec<PathBuf>>,
+    errors: AtomicUsize,
+    limit: usize,
+    action: WalkAction,
+}
+
+impl RecordingVisitor {
+    fn new(limit: usize, action: WalkAction) -> Self {
+        RecordingVisitor {
+            paths: Mutex::new(Vec::new()),
+            errors: AtomicUsize::new(0),
+            limit,
+            action,
+        }
+    }
+
+    fn sorted_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.paths.lock().unwrap().clone();
+        paths.sort();
+        paths
+    }
+}
+
+impl WalkVisitor for RecordingVisitor {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        let mut paths = self.paths.lock().unwrap();
+        paths.push(entry.path.clone());
+        if paths.len() >= self.limit {
+            self.action
+        } else {
+            WalkAction::Continue
+        }
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        WalkAction::Continue
+    }
+}
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for dir in ["dir-1", "dir-2"] {
+        fs::create_dir_all(root.join(dir))?;
+        for file in ["a.txt", "b.txt", "c.log"] {
+            fs::write(root.join(dir).join(file), file)?;
+        }
+    }
+    Ok(root)
+}
+
+fn expected_paths(root: &Path) -> Vec<PathBuf> {
+    ["dir-1/a.txt", "dir-1/b.txt", "dir-2/a.txt", "dir-2/b.txt"]
+        .iter()
+        .map(|p| root.join(p))
+        .collect()
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[test]
+fn test_walk_visit() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let visitor = RecordingVisitor::new(usize::MAX, WalkAction::Continue);
+    walk_serial_visit("*.log", &root, &walk_options(), &visitor)?;
+    assert_eq!(visitor.sorted_paths(), expected_paths(&root));
+
+    let visitor = RecordingVisitor::new(usize::MAX, WalkAction::Continue);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        &root, None, "*.log",
+    )));
+    let metrics = walk_parallel_visit(ignore_rules.clone(), &root, walk_options(), &visitor)?;
+    assert_eq!(visitor.sorted_paths(), expected_paths(&root));
+    assert_eq!(metrics.files_visited, 4);
+
+    // The channel walk is a visitor walk too
+    let (path_sender, path_receiver) = crossbeam_channel::unbounded();
+    walk_parallel(ignore_rules, &root, walk_options(), path_sender)?;
+    let mut paths = path_receiver
+        .iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    paths.sort();
+    assert_eq!(paths, expected_paths(&root));
+    Ok(())
+}
+
+#[test]
+fn test_walk_visit_stop() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let visitor = RecordingVisitor::new(1, WalkAction::Stop);
+    walk_serial_visit("*.log", &root, &walk_options(), &visitor)?;
+    assert_eq!(visitor.sorted_paths().len(), 1);
+
+    // The other threads may visit a few more paths before they see the walk is stopped
+    let visitor = RecordingVisitor::new(1, WalkAction::Stop);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        &root, None, "*.log",
+    )));
+    let metrics = walk_parallel_visit(ignore_rules, &root, walk_options(), &visitor)?;
+    assert!(visitor.sorted_paths().len() < expected_paths(&root).len());
+    // The paths the visitor stopped at aren't counted
+    assert_eq!(metrics.files_visited, 0);
+    Ok(())
+}
+
+#[test]
+fn test_walk_visit_skip_siblings() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    // Each directory has two files, only the first one is visited
+    let visitor = RecordingVisitor::new(0, WalkAction::SkipSiblings);
+    walk_serial_visit("*.log", &root, &walk_options(), &visitor)?;
+    let paths = visitor.sorted_paths();
+    assert_eq!(paths.len(), 2);
+    assert!(paths[0].starts_with(root.join("dir-1")));
+    assert!(paths[1].starts_with(root.join("dir-2")));
+
+    let visitor = RecordingVisitor::new(0, WalkAction::SkipSiblings);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        &root, None, "*.log",
+    )));
+    walk_parallel_visit(ignore_rules, &root, walk_options(), &visitor)?;
+    assert_eq!(visitor.sorted_paths().len(), 2);
+    assert_eq!(visitor.errors.load(Ordering::Relaxed), 0);
+    Ok(())
+}
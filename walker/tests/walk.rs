@@ -5,6 +5,7 @@ use log::LevelFilter;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::{Arc, RwLock};
 use std::thread;
 use xvc_test_helper::{create_temp_dir, test_logging};
@@ -61,7 +62,7 @@ fn run_walk(root: &Path, ignore_filename: &str) -> Result<HashSet<String>> {
     let root_owned = root.to_path_buf();
     let walk_options = WalkOptions {
         ignore_filename: Some(ignore_filename.to_string()),
-        ignore_dot_git: true,
+        ..WalkOptions::gitignore()
     };
 
     let walk_thread =
@@ -128,6 +129,42 @@ fn get_git_expected_paths(root: &Path) -> Result<HashSet<String>> {
     Ok(expected_paths)
 }
 
+/// Returns the paths that are not ignored according to the `git` command line.
+///
+/// libgit2 decides some conflicts between a pattern in a parent `.gitignore` and a bare file name
+/// whitelist in a nested one differently from git. For example, with `*.js` in the root and `!c.js`
+/// in `dir1/.gitignore`, git reports `dir1/c.js` as untracked while libgit2 reports it ignored. The
+/// tests of these conflicts use git itself as the reference.
+fn get_git_cli_expected_paths(root: &Path) -> Result<HashSet<String>> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "-z", "-uall", "--ignored"])
+        .current_dir(root)
+        .output()?;
+    assert!(output.status.success(), "git status failed: {output:?}");
+    let stdout = String::from_utf8(output.stdout)?;
+
+    let mut expected_paths = HashSet::new();
+    for entry in stdout.split('\0').filter(|e| !e.is_empty()) {
+        let (status, path_str) = entry.split_at(3);
+        if status == "!! " {
+            continue;
+        }
+
+        let path = PathBuf::from(path_str);
+        let mut current = path.parent();
+        while let Some(p) = current {
+            if p.as_os_str().is_empty() {
+                break;
+            }
+            expected_paths.insert(p.to_string_lossy().to_string());
+            current = p.parent();
+        }
+
+        expected_paths.insert(path_str.to_string());
+    }
+    Ok(expected_paths)
+}
+
 #[test]
 fn test_simple_ignore() -> Result<()> {
     test_logging(LevelFilter::Trace);
@@ -181,7 +218,7 @@ fn test_nested_ignore_files() -> Result<()> {
         &[(".gitignore", "*.js"), ("dir1/.gitignore", "!c.js\nb.txt")],
     )?;
     let result = run_walk(&root, ".gitignore")?;
-    let expected = get_git_expected_paths(&root)?;
+    let expected = get_git_cli_expected_paths(&root)?;
     assert_eq_and_print!(result, expected);
     Ok(())
 }
@@ -232,6 +269,58 @@ fn test_character_class_in_pattern() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_negated_character_class_in_pattern() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["data1.csv", "dataA.csv", "dataR.csv", "data..csv", "other.txt"],
+        &[(".gitignore", "data[!0-9R].csv\nother[^.]txt")],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
+
+#[test]
+fn test_character_class_grammar_in_pattern() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &[
+            "filea.dat",
+            "fileZ.dat",
+            "file5.dat",
+            "file_.dat",
+            "file-.dat",
+            "file].dat",
+            "a.log",
+            "-.log",
+            "x.log",
+            "z.tmp",
+            "a.tmp",
+            "-.tmp",
+            "m.bak",
+            "_.bak",
+            "]/x.txt",
+            "d/x",
+            "d.x",
+        ],
+        &[(
+            ".gitignore",
+            "file[a-zA-Z0-9_].dat
+[a-].log
+[]x].log
+[z-a].tmp
+[a-c-m].bak
+d[!a]x",
+        )],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
+
 #[test]
 fn test_whitelisting_subdirectory_in_ignored_directory() -> Result<()> {
     test_logging(LevelFilter::Trace);
@@ -431,6 +520,20 @@ fn test_trailing_escaped_spaces_in_pattern() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_escaped_space_before_trailing_spaces_in_pattern() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    // "foo\  " is "foo " with one space, the unescaped space after it is removed
+    let root = setup_test_directory(
+        &["foo", "foo ", "foo  ", "bar"],
+        &[(".gitignore", "foo\\  \nbar\\\\ ")],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
+
 #[test]
 fn test_reinclude_file_in_ignored_dir_tree() -> Result<()> {
     test_logging(LevelFilter::Trace);
@@ -504,4 +607,54 @@ fn test_unignoring_gitignore_itself() -> Result<()> {
     let expected = get_git_expected_paths(&root)?;
     assert_eq_and_print!(result, expected);
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_bare_filename_whitelist_in_nested_file() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["a.js", "b.js", "dir/a.js", "dir/b.js", "dir/sub/b.js"],
+        &[(".gitignore", "*.js"), ("dir/.gitignore", "!b.js")],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_cli_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
+
+#[test]
+fn test_bare_filename_ignore_in_nested_file_overrides_whitelist() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["keep.log", "a.log", "dir/keep.log", "dir/sub/keep.log"],
+        &[(".gitignore", "*.log\n!keep.log"), ("dir/.gitignore", "keep.log")],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
+
+#[test]
+fn test_bare_filename_rules_in_three_levels() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &[
+            "b.js",
+            "a/b.js",
+            "a/c.js",
+            "a/x/b.js",
+            "a/x/y/b.js",
+            "a/x/y/c.js",
+        ],
+        &[
+            (".gitignore", "*.js"),
+            ("a/.gitignore", "!b.js"),
+            ("a/x/.gitignore", "b.js"),
+            ("a/x/y/.gitignore", "!*.js\nc.js"),
+        ],
+    )?;
+    let result = run_walk(&root, ".gitignore")?;
+    let expected = get_git_cli_expected_paths(&root)?;
+    assert_eq_and_print!(result, expected);
+    Ok(())
+}
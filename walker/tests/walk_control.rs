@@ -0,0 +1,122 @@
+use anyhow::Result;
+use crossbeam_channel::{bounded, Receiver};
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel_controlled, IgnoreRules, PathMetadata, WalkHandle, WalkHandleState, WalkOptions,
+};
+
+const DIRS: usize = 100;
+const FILES: usize = 10;
+const THREADS: usize = 2;
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir-{d:03}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..FILES {
+            fs::write(dir.join(format!("file-{f:02}.txt")), "x")?;
+        }
+    }
+    Ok(root)
+}
+
+/// Starts a walk that can't get past the first directories until the paths are received, as the
+/// channel is smaller.
+fn start_walk(root: &Path) -> (WalkHandle, Receiver<xvc_walker::Result<PathMetadata>>) {
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        num_threads: Some(THREADS),
+        ..WalkOptions::gitignore()
+    };
+    let (path_sender, path_receiver) = bounded(1);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(root, None)));
+    let handle = walk_parallel_controlled(ignore_rules, root, walk_options, path_sender);
+    (handle, path_receiver)
+}
+
+/// Receives the paths until none is sent for a while.
+fn receive_until_idle(
+    path_receiver: &Receiver<xvc_walker::Result<PathMetadata>>,
+    received: &mut Vec<PathBuf>,
+) -> Result<()> {
+    while let Ok(pm_res) = path_receiver.recv_timeout(Duration::from_millis(200)) {
+        received.push(pm_res?.path);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pause_and_resume_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let (handle, path_receiver) = start_walk(&root);
+    assert_eq!(handle.state(), WalkHandleState::Running);
+
+    handle.pause();
+    assert_eq!(handle.state(), WalkHandleState::Paused);
+
+    // Each thread finishes the directory it's listing
+    let mut received = Vec::new();
+    receive_until_idle(&path_receiver, &mut received)?;
+    assert!(received.len() <= THREADS * FILES, "{}", received.len());
+    let paused_count = received.len();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(path_receiver.try_recv().is_err());
+    assert_eq!(handle.progress().files_visited, paused_count);
+    assert!(!handle.is_finished());
+    assert_eq!(handle.state(), WalkHandleState::Paused);
+
+    handle.resume();
+    assert_eq!(handle.state(), WalkHandleState::Running);
+    received.extend(
+        path_receiver
+            .iter()
+            .map(|pm_res| pm_res.map(|pm| pm.path))
+            .collect::<xvc_walker::Result<Vec<_>>>()?,
+    );
+    let expected: HashSet<PathBuf> = (0..DIRS)
+        .flat_map(|d| {
+            let root = &root;
+            (0..FILES).map(move |f| root.join(format!("dir-{d:03}/file-{f:02}.txt")))
+        })
+        .collect();
+    assert_eq!(received.len(), expected.len());
+    assert_eq!(received.into_iter().collect::<HashSet<_>>(), expected);
+
+    while !handle.is_finished() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(handle.state(), WalkHandleState::Finished);
+    let metrics = handle.join()?;
+    assert_eq!(metrics.files_visited, DIRS * FILES);
+    assert_eq!(metrics.directories_visited, DIRS + 1);
+    Ok(())
+}
+
+#[test]
+fn test_abort_paused_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let (handle, path_receiver) = start_walk(&root);
+
+    handle.pause();
+    let mut received = Vec::new();
+    receive_until_idle(&path_receiver, &mut received)?;
+
+    // The paused threads stop without listing the other directories
+    handle.abort();
+    assert_eq!(handle.state(), WalkHandleState::Aborted);
+    receive_until_idle(&path_receiver, &mut received)?;
+    let metrics = handle.join()?;
+    assert_eq!(metrics.files_visited, received.len());
+    assert!(received.len() <= THREADS * FILES, "{}", received.len());
+    assert!(metrics.directories_visited <= THREADS + 1);
+    Ok(())
+}
@@ -0,0 +1,180 @@
+#![cfg(feature = "test-support")]
+use anyhow::Result;
+use log::LevelFilter;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_result, build_ignore_patterns_result_with_fs, Error, FileKind,
+    MatchResult, MemFs, OsFs, PathKind, WalkFs,
+};
+
+const ROOT: &str = "/archive";
+
+fn mem_fs(structure: &[&str], ignore_files: &[(&str, &str)]) -> MemFs {
+    let root = Path::new(ROOT);
+    let mem_fs = structure
+        .iter()
+        .fold(MemFs::new().with_dir(root), |mem_fs, path| {
+            mem_fs.with_file(root.join(path), path)
+        });
+    ignore_files.iter().fold(mem_fs, |mem_fs, (path, content)| {
+        mem_fs.with_file(root.join(path), content)
+    })
+}
+
+fn relative(paths: &[PathBuf]) -> Vec<String> {
+    let mut relative: Vec<String> = paths
+        .iter()
+        .map(|p| p.strip_prefix(ROOT).unwrap().to_string_lossy().to_string())
+        .collect();
+    relative.sort();
+    relative
+}
+
+#[test]
+fn test_ignored_dir_is_reported_as_unscanned() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let mem_fs = mem_fs(
+        &["a.txt", "build/out.bin", "src/main.rs"],
+        &[
+            (".gitignore", "build/"),
+            ("build/.gitignore", "!out.bin"),
+            ("src/.gitignore", "*.o"),
+        ],
+    );
+
+    let result = build_ignore_patterns_result_with_fs("", Path::new(ROOT), ".gitignore", &mem_fs)?;
+
+    assert_eq!(relative(&result.unscanned_dirs), vec!["build"]);
+    assert_eq!(
+        relative(&result.files_read),
+        vec![".gitignore", "src/.gitignore"]
+    );
+    let check = |path: &str, kind| result.rules.check_hypothetical(path, kind);
+    assert_eq!(check("build", PathKind::Directory), MatchResult::Ignore);
+    assert_eq!(check("build/out.bin", PathKind::Any), MatchResult::Ignore);
+    assert_eq!(check("src/x.o", PathKind::Any), MatchResult::Ignore);
+    assert_eq!(check("a.txt", PathKind::Any), MatchResult::NoMatch);
+    Ok(())
+}
+
+#[test]
+fn test_ignore_files_in_ignored_dirs_are_reported() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let mem_fs = mem_fs(
+        &[
+            "a.txt",
+            "legacy/old.txt",
+            "cache/x.bin",
+            "legacy/nested/y.txt",
+        ],
+        &[
+            (".gitignore", "legacy/\ncache\n"),
+            ("legacy/.gitignore", "!old.txt"),
+            ("legacy/nested/.gitignore", "*.txt"),
+        ],
+    );
+
+    let result = build_ignore_patterns_result_with_fs("", Path::new(ROOT), ".gitignore", &mem_fs)?;
+
+    assert_eq!(relative(&result.unscanned_dirs), vec!["cache", "legacy"]);
+    assert_eq!(relative(&result.files_read), vec![".gitignore"]);
+    assert_eq!(result.skipped_ignore_files.len(), 1);
+    let (skipped_file, pattern) = &result.skipped_ignore_files[0];
+    assert_eq!(*skipped_file, Path::new(ROOT).join("legacy/.gitignore"));
+    assert_eq!(pattern.original_pattern(), "legacy/");
+    Ok(())
+}
+
+#[test]
+fn test_mem_fs_matches_os_fs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let ignore_files = [
+        (".gitignore", "*.log\ntarget/\n"),
+        ("pkg/.gitignore", "!keep.log\n"),
+        ("pkg/target/.gitignore", "!*\n"),
+        (".git/info/exclude", "*.tmp\n"),
+    ];
+    let mut mem_fs = MemFs::new().with_dir(&root);
+    for (path, content) in ignore_files {
+        std::fs::create_dir_all(root.join(path).parent().unwrap())?;
+        std::fs::write(root.join(path), content)?;
+        mem_fs = mem_fs.with_file(root.join(path), content);
+    }
+
+    let from_os = build_ignore_patterns_result("", &root, ".gitignore")?;
+    let from_mem = build_ignore_patterns_result_with_fs("", &root, ".gitignore", &mem_fs)?;
+    assert_eq!(
+        from_mem.rules.snapshot_patterns(),
+        from_os.rules.snapshot_patterns()
+    );
+    assert_eq!(from_mem.files_read, from_os.files_read);
+    assert_eq!(from_mem.unscanned_dirs, from_os.unscanned_dirs);
+
+    // The same as reading with OsFs explicitly
+    let with_os_fs = build_ignore_patterns_result_with_fs("", &root, ".gitignore", &OsFs)?;
+    assert_eq!(with_os_fs.files_read, from_os.files_read);
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_ignore_file_fails() {
+    test_logging(LevelFilter::Trace);
+    let mem_fs = mem_fs(&["src/main.rs"], &[("src/.gitignore", "*.o")]).with_error(
+        Path::new(ROOT).join("src/.gitignore"),
+        ErrorKind::PermissionDenied,
+    );
+
+    let result = build_ignore_patterns_result_with_fs("", Path::new(ROOT), ".gitignore", &mem_fs);
+    assert!(
+        matches!(&result, Err(Error::UnreadableIgnoreFile { .. })),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_unlistable_dir_fails() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let dir = Path::new(ROOT).join("a/b");
+    let mem_fs = mem_fs(&["a/b/c.txt"], &[]).with_error(&dir, ErrorKind::PermissionDenied);
+
+    // The directory is found in its parent, but it can't be listed
+    assert_eq!(
+        mem_fs.read_dir(&Path::new(ROOT).join("a"))?,
+        vec![(dir.clone(), FileKind::Dir)]
+    );
+    assert!(mem_fs.read_dir(&dir).is_err());
+    let result = build_ignore_patterns_result_with_fs("", Path::new(ROOT), ".gitignore", &mem_fs);
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_vanished_dir_is_skipped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    // Listed in the root, but removed before it's read
+    let mem_fs = mem_fs(
+        &["gone/a.txt", "kept/b.txt"],
+        &[("kept/.gitignore", "*.txt")],
+    )
+    .with_error(Path::new(ROOT).join("gone"), ErrorKind::NotFound);
+
+    let result = build_ignore_patterns_result_with_fs("", Path::new(ROOT), ".gitignore", &mem_fs)?;
+    assert_eq!(relative(&result.files_read), vec!["kept/.gitignore"]);
+    assert!(result.unscanned_dirs.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_missing_root() {
+    test_logging(LevelFilter::Trace);
+    let result = build_ignore_patterns_result_with_fs(
+        "",
+        Path::new("/elsewhere"),
+        ".gitignore",
+        &MemFs::new(),
+    );
+    assert!(matches!(result, Err(Error::RootNotFound { .. })));
+}
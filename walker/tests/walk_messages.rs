@@ -0,0 +1,110 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::notify::PathEvent;
+use xvc_walker::{events_to_walk_messages, IgnoreRules, ModifiedFields, WalkMessage};
+
+fn create_event(path: PathBuf) -> Option<PathEvent> {
+    let metadata = path.metadata().unwrap();
+    Some(PathEvent::Create { path, metadata })
+}
+
+fn convert(root: &Path, events: Vec<Option<PathEvent>>) -> Result<Vec<WalkMessage>> {
+    let (event_sender, event_receiver) = unbounded();
+    for event in events {
+        event_sender.send(event)?;
+    }
+    drop(event_sender);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        root, None, "*.tmp",
+    )));
+    let (message_sender, message_receiver) = unbounded();
+    events_to_walk_messages(event_receiver, ignore_rules, message_sender)?;
+    Ok(message_receiver.into_iter().collect())
+}
+
+fn relative_paths(root: &Path, messages: &[WalkMessage]) -> HashSet<String> {
+    messages
+        .iter()
+        .filter_map(|m| match m {
+            WalkMessage::Path(pm) => Some(
+                pm.path
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+            ),
+            WalkMessage::Removed { .. } => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_created_dir_is_walked() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("new/sub"))?;
+    fs::write(root.join("new/a.txt"), "a")?;
+    fs::write(root.join("new/b.tmp"), "b")?;
+    fs::write(root.join("new/sub/c.txt"), "c")?;
+
+    let messages = convert(&root, vec![create_event(root.join("new")), None])?;
+
+    let expected: HashSet<String> = ["new/a.txt", "new/sub/c.txt"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(relative_paths(&root, &messages), expected);
+    assert_eq!(messages.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_updates_and_removals() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    fs::write(root.join("b.tmp"), "b")?;
+    let metadata = root.join("a.txt").metadata()?;
+
+    let events = vec![
+        Some(PathEvent::Update {
+            path: root.join("a.txt"),
+            metadata,
+            fields: ModifiedFields::ALL,
+        }),
+        create_event(root.join("b.tmp")),
+        Some(PathEvent::Delete {
+            path: root.join("c.txt"),
+        }),
+        Some(PathEvent::Delete {
+            path: root.join("d.tmp"),
+        }),
+    ];
+    // The stream also ends when the sender is dropped
+    let messages = convert(&root, events)?;
+
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(&messages[0], WalkMessage::Path(pm) if pm.path == root.join("a.txt")));
+    assert!(matches!(&messages[1], WalkMessage::Removed { path } if *path == root.join("c.txt")));
+    Ok(())
+}
+
+#[test]
+fn test_vanished_path_is_skipped() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join("a.txt"), "a")?;
+    let event = create_event(root.join("a.txt"));
+    fs::remove_file(root.join("a.txt"))?;
+
+    let messages = convert(&root, vec![event, None])?;
+
+    assert!(messages.is_empty());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    walk_parallel, walk_parallel_collect, BrokenSymlinkHandling, IgnoreRules, UnusedIgnoreFiles,
+    WalkMetrics, WalkOptions, WalkSummary, WalkTimings,
+};
+
+#[test]
+fn test_walk_metrics() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir-1/sub"))?;
+    fs::create_dir_all(root.join("ignored-dir"))?;
+    for file in [
+        "a.txt",
+        "b.log",
+        "dir-1/c.txt",
+        "dir-1/d.log",
+        "dir-1/sub/e.txt",
+        "ignored-dir/f.txt",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    make_symlink(root.join("missing.txt"), root.join("dir-1/broken-link"))?;
+
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        &root,
+        None,
+        "*.log\nignored-dir/",
+    )));
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        broken_symlink_handling: BrokenSymlinkHandling::EmitAsError,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, metrics) = walk_parallel_collect(ignore_rules, &root, walk_options)?;
+
+    assert_eq!(paths.len(), 4);
+    assert_eq!(paths.iter().filter(|p| p.is_err()).count(), 1);
+    assert_eq!(
+        metrics,
+        WalkMetrics {
+            // a.txt, c.txt and e.txt
+            files_visited: 3,
+            // The root, dir-1 and dir-1/sub
+            directories_visited: 3,
+            // b.log, d.log and ignored-dir
+            entries_ignored: 3,
+            // The broken symlink
+            errors_encountered: 1,
+            elapsed: metrics.elapsed,
+            // The timings are not collected by default
+            timings: WalkTimings::default(),
+            unused_ignore_files: UnusedIgnoreFiles::default(),
+            extensions: HashMap::new(),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_walk_summary() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir-1/sub"))?;
+    for file in ["a.txt", "dir-1/c.txt", "dir-1/sub/e.txt"] {
+        fs::write(root.join(file), file)?;
+    }
+    make_symlink(root.join("missing.txt"), root.join("dir-1/broken-link"))?;
+
+    let walk = |broken_symlink_handling| -> Result<(usize, WalkSummary)> {
+        let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+        let walk_options = WalkOptions {
+            ignore_filename: None,
+            broken_symlink_handling,
+            ..WalkOptions::gitignore()
+        };
+        let (path_sender, path_receiver) = unbounded();
+        let summary = walk_parallel(ignore_rules, &root, walk_options, path_sender)?;
+        // The sender is dropped when the summary is returned
+        Ok((path_receiver.iter().count(), summary))
+    };
+
+    let (received, summary) = walk(BrokenSymlinkHandling::EmitAsEntry)?;
+    assert_eq!(received, 4);
+    assert_eq!(
+        summary,
+        WalkSummary {
+            metrics: WalkMetrics {
+                // The files and the broken symlink
+                files_visited: 4,
+                directories_visited: 3,
+                entries_ignored: 0,
+                errors_encountered: 0,
+                elapsed: summary.metrics.elapsed,
+                timings: WalkTimings::default(),
+                unused_ignore_files: UnusedIgnoreFiles::default(),
+                extensions: HashMap::new(),
+            },
+            was_cancelled: false,
+        }
+    );
+    assert!(summary.is_complete());
+
+    let (received, summary) = walk(BrokenSymlinkHandling::EmitAsError)?;
+    assert_eq!(received, 4);
+    assert_eq!(
+        summary,
+        WalkSummary {
+            metrics: WalkMetrics {
+                files_visited: 3,
+                directories_visited: 3,
+                entries_ignored: 0,
+                errors_encountered: 1,
+                elapsed: summary.metrics.elapsed,
+                timings: WalkTimings::default(),
+                unused_ignore_files: UnusedIgnoreFiles::default(),
+                extensions: HashMap::new(),
+            },
+            was_cancelled: false,
+        }
+    );
+    assert!(!summary.is_complete());
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use crossbeam_channel::bounded;
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, ThreadId};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    default_num_threads, num_threads_within, walk_parallel_visit, walk_parallel_with_stats, Error,
+    IgnoreRules, PathMetadata, WalkAction, WalkOptions, WalkVisitor, MAX_DEFAULT_NUM_THREADS,
+    MIN_DEFAULT_NUM_THREADS,
+};
+
+const DIRS: usize = 20;
+const FILES: usize = 10;
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir-{d:02}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..FILES {
+            fs::write(dir.join(format!("file-{f:02}.txt")), "x")?;
+        }
+    }
+    Ok(root)
+}
+
+#[test]
+fn test_walk_pool_stats() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+
+    // The walk can't finish before the paths are received, as the channel is smaller
+    let (path_sender, path_receiver) = bounded::<xvc_walker::Result<PathMetadata>>(1);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    let (handle, pool_stats) =
+        walk_parallel_with_stats(ignore_rules, &root, walk_options, path_sender);
+
+    let mut received = vec![path_receiver.recv()?];
+    let inspector = {
+        let pool_stats = pool_stats.clone();
+        thread::spawn(move || *pool_stats.lock().unwrap())
+    };
+    let stats = inspector.join().unwrap();
+    assert!(stats.active_threads >= 1);
+    assert!(stats.completed_dirs >= 1);
+    assert!(!handle.is_finished());
+
+    received.extend(path_receiver);
+    assert_eq!(received.len(), DIRS * FILES);
+    let metrics = handle.join()?;
+    assert_eq!(metrics.directories_visited, DIRS + 1);
+
+    let stats = *pool_stats.lock().unwrap();
+    assert_eq!(stats.active_threads, 0);
+    assert_eq!(stats.queued_dirs, 0);
+    assert_eq!(stats.completed_dirs, DIRS + 1);
+    Ok(())
+}
+
+#[test]
+fn test_walk_pool_stats_missing_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir().join("missing");
+    let (path_sender, _path_receiver) = bounded(1);
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    let (handle, pool_stats) =
+        walk_parallel_with_stats(ignore_rules, &root, WalkOptions::gitignore(), path_sender);
+    assert!(handle.join().is_err());
+    assert_eq!(*pool_stats.lock().unwrap(), Default::default());
+    Ok(())
+}
+
+/// Records the threads the paths are visited in.
+#[derive(Default)]
+struct ThreadVisitor {
+    threads: Mutex<HashSet<ThreadId>>,
+}
+
+impl WalkVisitor for ThreadVisitor {
+    fn on_entry(&self, _entry: &PathMetadata) -> WalkAction {
+        self.threads.lock().unwrap().insert(thread::current().id());
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        WalkAction::Continue
+    }
+}
+
+#[test]
+fn test_walk_num_threads() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    for num_threads in [Some(0), Some(1), Some(3), None] {
+        let walk_options = WalkOptions {
+            ignore_filename: None,
+            num_threads,
+            ..WalkOptions::gitignore()
+        };
+        let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+        let visitor = ThreadVisitor::default();
+        let metrics = walk_parallel_visit(ignore_rules, &root, walk_options, &visitor)?;
+        assert_eq!(metrics.files_visited, DIRS * FILES);
+
+        // The root is listed in the calling thread
+        let max_threads = num_threads.unwrap_or_else(default_num_threads).max(1) + 1;
+        let threads = visitor.threads.lock().unwrap().len();
+        assert!(threads <= max_threads, "{num_threads:?}: {threads} threads");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_default_num_threads() {
+    let cpus = thread::available_parallelism().map_or(8, |n| n.get());
+    assert_eq!(
+        default_num_threads(),
+        cpus.clamp(MIN_DEFAULT_NUM_THREADS, MAX_DEFAULT_NUM_THREADS)
+    );
+    assert_eq!(num_threads_within(1, 1), 1);
+    assert_eq!(num_threads_within(1000, 2000), 1000);
+    assert_eq!(num_threads_within(4, 2), 4);
+}
@@ -0,0 +1,190 @@
+#![cfg(feature = "rayon")]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::ThreadId;
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_rayon, walk_rayon_visit,
+    BrokenSymlinkHandling, EmitKinds, Error, IgnoreRules, PathMetadata, WalkAction, WalkOptions,
+    WalkVisitor,
+};
+
+/// A tree with nested ignore files, a `.git` directory and symlinks, like in the walker parity
+/// tests.
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for dir in ["a/b/c", "a/d", ".git/objects", "logs"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for i in 0..20 {
+        fs::create_dir_all(root.join(format!("many/dir-{i:02}/sub")))?;
+        fs::write(root.join(format!("many/dir-{i:02}/sub/{i}.txt")), "x")?;
+    }
+    for file in [
+        "a/1.txt",
+        "a/1.log",
+        "a/b/2.txt",
+        "a/b/c/3.txt",
+        "a/d/4.txt",
+        ".git/HEAD",
+        ".git/objects/o",
+        "logs/5.log",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    fs::write(root.join(".gitignore"), "*.log\nlogs/\n")?;
+    fs::write(root.join("a/b/.gitignore"), "2.txt\n")?;
+
+    let outside = create_temp_dir();
+    fs::create_dir_all(outside.join("e"))?;
+    fs::write(outside.join("e/6.txt"), "6")?;
+    make_symlink(&outside, root.join("a/outside"))?;
+    make_symlink(root.join("a"), root.join("a/d/loop"))?;
+    make_symlink(root.join("missing.txt"), root.join("a/broken"))?;
+    Ok(root)
+}
+
+type Walked = (BTreeSet<(PathBuf, bool)>, BTreeSet<String>);
+
+fn collect(paths: impl IntoIterator<Item = xvc_walker::Result<PathMetadata>>) -> Walked {
+    let mut walked = Walked::default();
+    for path_res in paths {
+        match path_res {
+            Ok(pm) => {
+                walked.0.insert((pm.path, pm.metadata.is_dir()));
+            }
+            Err(e) => {
+                walked.1.insert(e.to_string());
+            }
+        }
+    }
+    walked
+}
+
+fn walk_with(
+    ignore_rules: &IgnoreRules,
+    root: &Path,
+    walk_options: &WalkOptions,
+    rayon: bool,
+) -> Result<Walked> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(ignore_rules.clone()));
+    if rayon {
+        walk_rayon(ignore_rules, root, walk_options.clone(), path_sender)?;
+    } else {
+        walk_parallel(ignore_rules, root, walk_options.clone(), path_sender)?;
+    }
+    Ok(collect(path_receiver))
+}
+
+#[test]
+fn test_rayon_and_parallel_walkers_agree() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &WalkOptions::gitignore())?;
+    for parents_first in [false, true] {
+        for emit in [EmitKinds::ALL, EmitKinds::FILES, EmitKinds::DIRS] {
+            for follow_symlinks in [false, true] {
+                for ignore_dot_git in [false, true] {
+                    for broken_symlink_handling in [
+                        BrokenSymlinkHandling::Skip,
+                        BrokenSymlinkHandling::EmitAsError,
+                    ] {
+                        let walk_options = WalkOptions {
+                            parents_first,
+                            emit,
+                            follow_symlinks,
+                            ignore_dot_git,
+                            broken_symlink_handling,
+                            ..WalkOptions::gitignore()
+                        };
+                        let parallel = walk_with(&ignore_rules, &root, &walk_options, false)?;
+                        let rayon = walk_with(&ignore_rules, &root, &walk_options, true)?;
+                        assert_eq!(parallel, rayon, "{walk_options:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    // A file as the root is sent as the only path
+    let file_root = root.join("a/1.txt");
+    let file_rules = IgnoreRules::empty(file_root.parent().unwrap(), None);
+    let walked = walk_with(&file_rules, &file_root, &WalkOptions::gitignore(), true)?;
+    assert_eq!(walked.0, BTreeSet::from([(file_root, false)]));
+    Ok(())
+}
+
+/// Records the paths with the rayon thread that visits them.
+#[derive(Default)]
+struct ThreadRecorder {
+    paths: Mutex<Vec<xvc_walker::Result<PathMetadata>>>,
+    threads: Mutex<HashSet<(Option<usize>, ThreadId)>>,
+}
+
+impl ThreadRecorder {
+    fn record(&self, path_res: xvc_walker::Result<PathMetadata>) -> WalkAction {
+        self.threads
+            .lock()
+            .unwrap()
+            .insert((rayon::current_thread_index(), std::thread::current().id()));
+        self.paths.lock().unwrap().push(path_res);
+        WalkAction::Continue
+    }
+}
+
+impl WalkVisitor for ThreadRecorder {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.record(Ok(entry.clone()))
+    }
+
+    fn on_error(&self, _err: &Error) -> WalkAction {
+        WalkAction::Continue
+    }
+}
+
+#[test]
+fn test_rayon_walker_uses_the_current_pool() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &WalkOptions::gitignore())?;
+    let expected = walk_with(&ignore_rules, &root, &WalkOptions::gitignore(), false)?;
+
+    for num_threads in [1, 2] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()?;
+        let recorder = ThreadRecorder::default();
+        let metrics = pool.install(|| {
+            walk_rayon_visit(
+                Arc::new(RwLock::new(ignore_rules.clone())),
+                &root,
+                WalkOptions::gitignore(),
+                &recorder,
+            )
+        })?;
+
+        let walked = collect(recorder.paths.into_inner().unwrap());
+        assert_eq!(walked.0, expected.0);
+        assert_eq!(
+            metrics.files_visited,
+            walked.0.iter().filter(|(_, is_dir)| !is_dir).count()
+        );
+
+        // All paths are visited in the threads of the pool
+        let threads = recorder.threads.into_inner().unwrap();
+        assert!(threads.len() <= num_threads, "{threads:?}");
+        assert!(
+            threads
+                .iter()
+                .all(|(index, _)| index.is_some_and(|i| i < num_threads)),
+            "{threads:?}"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    build_ignore_patterns, walk_parallel, walk_serial, Error, IgnoreRules, PathMetadata,
+    WalkOptions,
+};
+
+fn run_parallel(
+    root: &Path,
+    global_patterns: &str,
+    walk_options: WalkOptions,
+) -> xvc_walker::Result<Vec<PathMetadata>> {
+    let (path_sender, path_receiver) = unbounded();
+    // The rules of a file are rooted at its directory, like in the serial walker
+    let ignore_root = if root.is_dir() {
+        root
+    } else {
+        root.parent().unwrap()
+    };
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        ignore_root,
+        None,
+        global_patterns,
+    )));
+    walk_parallel(ignore_rules, root, walk_options, path_sender)?;
+    let mut paths = path_receiver
+        .into_iter()
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
+
+fn run_serial(
+    root: &Path,
+    global_patterns: &str,
+    walk_options: WalkOptions,
+) -> xvc_walker::Result<Vec<PathMetadata>> {
+    let (output_sender, _output_receiver) = unbounded();
+    let (mut paths, _) = walk_serial(&output_sender, global_patterns, root, &walk_options)?;
+    paths.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
+
+#[test]
+fn test_missing_root() {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir().join("missing");
+    let expected = Error::RootNotFound { path: root.clone() };
+
+    assert_eq!(
+        run_parallel(&root, "", WalkOptions::gitignore()).unwrap_err(),
+        expected
+    );
+    assert_eq!(
+        run_serial(&root, "", WalkOptions::gitignore()).unwrap_err(),
+        expected
+    );
+    assert_eq!(
+        build_ignore_patterns("", &root, ".gitignore").unwrap_err(),
+        expected
+    );
+}
+
+#[test]
+fn test_file_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir().join("a.txt");
+    fs::write(&root, "a")?;
+
+    for run in [run_parallel, run_serial] {
+        let paths = run(&root, "", WalkOptions::gitignore())?;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].path, root);
+        assert!(paths[0].metadata.is_file());
+
+        assert!(run(&root, "*.txt", WalkOptions::gitignore())?.is_empty());
+    }
+
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    assert!(ignore_rules.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_symlink_root() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let dir = create_temp_dir();
+    fs::create_dir_all(dir.join("target"))?;
+    fs::write(dir.join("target/a.txt"), "a")?;
+    let root = dir.join("link");
+    make_symlink(dir.join("target"), &root)?;
+
+    let follow = WalkOptions {
+        follow_symlinks: true,
+        ..WalkOptions::gitignore()
+    };
+
+    for run in [run_parallel, run_serial] {
+        let paths = run(&root, "", WalkOptions::gitignore())?;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].path, root);
+        assert!(paths[0].metadata.is_symlink());
+
+        // The followed root is walked with its canonical path
+        let paths = run(&root, "", follow.clone())?;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(
+            paths[0].path,
+            dir.join("target").canonicalize()?.join("a.txt")
+        );
+        assert!(run(&root, "a.txt", follow.clone())?.is_empty());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_symlink_root_to_parent() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let dir = create_temp_dir();
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "a")?;
+    fs::write(dir.join("sub/b.txt"), "b")?;
+    let root = dir.join("sub/up");
+    make_symlink(&dir, &root)?;
+
+    let follow = WalkOptions {
+        follow_symlinks: true,
+        ..WalkOptions::gitignore()
+    };
+    let canonical = dir.canonicalize()?;
+    let expected = [canonical.join("a.txt"), canonical.join("sub/b.txt")];
+
+    // The symlink back to the root is reported as circular, not entered again
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::empty(&root, None)));
+    walk_parallel(ignore_rules, &root, follow.clone(), path_sender)?;
+    let (paths, errors): (Vec<_>, Vec<_>) = path_receiver.into_iter().partition(|r| r.is_ok());
+    let mut paths: Vec<_> = paths.into_iter().map(|r| r.unwrap().path).collect();
+    paths.sort();
+    assert_eq!(paths, expected);
+    assert!(matches!(errors[..], [Err(Error::CircularSymlink { .. })]));
+
+    let paths = run_serial(&root, "", follow)?;
+    let paths: Vec<_> = paths.into_iter().map(|pm| pm.path).collect();
+    assert_eq!(paths, expected);
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::thread;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_serial, WalkOptions};
+
+const DEPTH: usize = 1000;
+
+#[test]
+fn test_walk_serial_deep_tree() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let mut dir = root.clone();
+    for _ in 0..DEPTH {
+        dir.push("d");
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("leaf.txt"), "leaf")?;
+
+    // The walk doesn't recurse, so a small stack is enough for any depth
+    let walk = thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(move || {
+            let (output_sender, _output_receiver) = unbounded();
+            walk_serial(&output_sender, "", &root, &WalkOptions::gitignore())
+                .map(|(paths, _)| paths)
+        })?;
+    let paths = walk.join().unwrap()?;
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, dir.join("leaf.txt"));
+    Ok(())
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "async")]
+use anyhow::Result;
+use futures::StreamExt;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time::timeout;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel_stream, Error, IgnoreRules, SharedIgnoreRules, WalkOptions};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn setup_tree(dirs: usize, files: usize) -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..dirs {
+        let dir = root.join(format!("dir-{d}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..files {
+            fs::write(dir.join(format!("file-{f}.txt")), format!("{d} {f}"))?;
+        }
+    }
+    Ok(root)
+}
+
+fn ignore_rules(root: &Path) -> SharedIgnoreRules {
+    Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        root, None, "*.log",
+    )))
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[tokio::test]
+async fn test_walk_stream_ends_after_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree(4, 5)?;
+    fs::write(root.join("dir-0/ignored.log"), "log")?;
+
+    let stream = walk_parallel_stream(ignore_rules(&root), &root, walk_options());
+    let mut paths = timeout(TIMEOUT, stream.collect::<Vec<_>>())
+        .await?
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut expected = (0..4)
+        .flat_map(|d| (0..5).map(move |f| format!("dir-{d}/file-{f}.txt")))
+        .map(|p| root.join(p))
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(paths, expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_walk_stream_ends_with_error() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let missing = root.join("missing");
+
+    let mut stream = walk_parallel_stream(ignore_rules(&root), &missing, walk_options());
+    let first = timeout(TIMEOUT, stream.next()).await?;
+    assert!(matches!(first, Some(Err(Error::RootNotFound { path })) if path == missing));
+    assert!(timeout(TIMEOUT, stream.next()).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_walk_stream_dropped_early() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree(200, 20)?;
+
+    let mut stream = walk_parallel_stream(ignore_rules(&root), &root, walk_options());
+    let first = timeout(TIMEOUT, stream.next()).await?;
+    assert!(matches!(first, Some(Ok(_))));
+
+    // Dropping joins the walk threads, so it returns only after they finish
+    timeout(TIMEOUT, tokio::task::spawn_blocking(move || drop(stream))).await??;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "tokio")]
+use anyhow::Result;
+use futures::StreamExt;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    walk_parallel_collect, walk_parallel_tokio_stream, Error, IgnoreRules, SharedIgnoreRules,
+    WalkOptions,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn setup_tree(dirs: usize, files: usize) -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..dirs {
+        let dir = root.join(format!("dir-{d}"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..files {
+            fs::write(dir.join(format!("file-{f}.txt")), format!("{d} {f}"))?;
+        }
+    }
+    Ok(root)
+}
+
+fn ignore_rules(root: &Path) -> SharedIgnoreRules {
+    Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        root, None, "*.log",
+    )))
+}
+
+fn walk_options() -> WalkOptions {
+    WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    }
+}
+
+#[tokio::test]
+async fn test_tokio_stream_starts_when_polled() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree(4, 5)?;
+    fs::write(root.join("dir-0/ignored.log"), "log")?;
+
+    let mut stream = walk_parallel_tokio_stream(ignore_rules(&root), &root, walk_options());
+    // The walk doesn't start before the stream is polled, so it finds the files created after
+    fs::write(root.join("dir-1/late.txt"), "late")?;
+    let mut paths = Vec::new();
+    while let Some(pm_res) = timeout(TIMEOUT, stream.next()).await? {
+        paths.push(pm_res?.path);
+    }
+    paths.sort();
+
+    let (expected, _) = walk_parallel_collect(ignore_rules(&root), &root, walk_options())?;
+    let mut expected = expected
+        .into_iter()
+        .map(|pm_res| Ok(pm_res?.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    expected.sort();
+    assert!(paths.contains(&root.join("dir-1/late.txt")));
+    assert_eq!(paths, expected);
+    assert_eq!(paths.len(), 4 * 5 + 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tokio_stream_ends_with_error() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let missing = root.join("missing");
+
+    let mut stream = walk_parallel_tokio_stream(ignore_rules(&root), &missing, walk_options());
+    let first = timeout(TIMEOUT, stream.next()).await?;
+    assert!(matches!(first, Some(Err(Error::RootNotFound { path })) if path == missing));
+    assert!(timeout(TIMEOUT, stream.next()).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tokio_stream_dropped_early() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree(200, 20)?;
+    let rules = ignore_rules(&root);
+
+    let mut stream = walk_parallel_tokio_stream(rules.clone(), &root, walk_options());
+    let first = timeout(TIMEOUT, stream.next()).await?;
+    assert!(matches!(first, Some(Ok(_))));
+    drop(stream);
+
+    // The walk task drops its copy of the rules when it stops
+    let start = Instant::now();
+    while Arc::strong_count(&rules) > 1 {
+        assert!(start.elapsed() < TIMEOUT, "The walk didn't stop");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel_collect, IgnoreRules, WalkOptions, WalkTimings};
+
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for d in 0..20 {
+        let dir = root.join(format!("dir-{d:02}/sub"));
+        fs::create_dir_all(&dir)?;
+        for f in 0..10 {
+            fs::write(dir.join(format!("file-{f}.txt")), "x")?;
+            fs::write(dir.join(format!("file-{f}.log")), "x")?;
+        }
+    }
+    Ok(root)
+}
+
+fn walk(root: &Path, collect_timings: bool) -> Result<(Vec<PathBuf>, WalkTimings)> {
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        root, None, "*.log",
+    )));
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        collect_timings,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, metrics) = walk_parallel_collect(ignore_rules, root, walk_options)?;
+    let mut paths = paths
+        .into_iter()
+        .map(|pm_res| Ok(pm_res?.path))
+        .collect::<Result<Vec<_>>>()?;
+    paths.sort();
+    Ok((paths, metrics.timings))
+}
+
+#[test]
+fn test_walk_timings() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+
+    let (timed_paths, timings) = walk(&root, true)?;
+    assert!(timings.directory_read > Duration::ZERO);
+    assert!(timings.metadata > Duration::ZERO);
+    assert!(timings.ignore_checks > Duration::ZERO);
+    assert!(timings.rule_loading > Duration::ZERO);
+    assert!(timings.send_blocking > Duration::ZERO);
+    assert!(timings.total() >= timings.directory_read + timings.metadata);
+
+    let printed = timings.to_string();
+    for phase in [
+        "directory read",
+        "metadata",
+        "ignore checks",
+        "rule loading",
+        "send blocking",
+        "total",
+    ] {
+        assert!(printed.contains(phase), "{printed}");
+    }
+
+    // Timings don't change the results
+    let (paths, timings) = walk(&root, false)?;
+    assert_eq!(timings, WalkTimings::default());
+    assert_eq!(paths, timed_paths);
+    assert_eq!(paths.len(), 20 * 10);
+    Ok(())
+}
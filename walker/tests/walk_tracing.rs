@@ -0,0 +1,52 @@
+#![cfg(feature = "tracing")]
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{walk_parallel, walk_parallel_with_tracing, IgnoreRules, WalkOptions};
+
+type WalkFn = fn(
+    xvc_walker::SharedIgnoreRules,
+    &Path,
+    WalkOptions,
+    crossbeam_channel::Sender<xvc_walker::Result<xvc_walker::PathMetadata>>,
+) -> xvc_walker::Result<xvc_walker::WalkSummary>;
+
+fn run_walk(root: &Path, walk: WalkFn) -> Result<Vec<PathBuf>> {
+    let (path_sender, path_receiver) = unbounded();
+    let ignore_rules = Arc::new(RwLock::new(IgnoreRules::from_global_patterns(
+        root, None, "*.log",
+    )));
+    let walk_options = WalkOptions {
+        ignore_filename: None,
+        ..WalkOptions::gitignore()
+    };
+    walk(ignore_rules, root, walk_options, path_sender)?;
+
+    let mut paths = path_receiver
+        .into_iter()
+        .map(|pm_res| pm_res.map(|pm| pm.path))
+        .collect::<xvc_walker::Result<Vec<_>>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+#[test]
+fn test_walk_with_tracing_matches_walk_parallel() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    for d in 0..5 {
+        let dir = root.join(format!("dir-{d}/sub-{d}"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("a.txt"), "a")?;
+        fs::write(dir.join("b.log"), "b")?;
+    }
+
+    let paths = run_walk(&root, walk_parallel_with_tracing)?;
+    assert_eq!(paths.len(), 5);
+    assert_eq!(paths, run_walk(&root, walk_parallel)?);
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use log::LevelFilter;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use xvc_test_helper::{create_temp_dir, make_symlink, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, walk_parallel, walk_serial_visit, BrokenSymlinkHandling,
+    EmitKinds, Error, IgnoreRules, PathMetadata, TraversalOrder, UnusedIgnoreFiles, WalkAction,
+    WalkMetrics, WalkOptions, WalkVisitor,
+};
+
+/// A tree with nested ignore files, a `.git` directory and symlinks. The followed symlinks point
+/// outside the root or to an ancestor, so the same paths are walked in any order.
+fn setup_tree() -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for dir in ["a/b/c", "a/d", ".git/objects", "logs"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for file in [
+        "a/1.txt",
+        "a/1.log",
+        "a/b/2.txt",
+        "a/b/c/3.txt",
+        "a/d/4.txt",
+        ".git/HEAD",
+        ".git/objects/o",
+        "logs/5.log",
+    ] {
+        fs::write(root.join(file), file)?;
+    }
+    fs::write(root.join(".gitignore"), "*.log\nlogs/\n")?;
+    fs::write(root.join("a/b/.gitignore"), "2.txt\n")?;
+
+    let outside = create_temp_dir();
+    fs::create_dir_all(outside.join("e"))?;
+    fs::write(outside.join("e/6.txt"), "6")?;
+    make_symlink(&outside, root.join("a/outside"))?;
+    make_symlink(root.join("a"), root.join("a/d/loop"))?;
+    make_symlink(root.join("missing.txt"), root.join("a/broken"))?;
+    Ok(root)
+}
+
+/// Records the paths, whether each is a directory, and the errors.
+#[derive(Default)]
+struct Recorder {
+    paths: Mutex<BTreeSet<(PathBuf, bool)>>,
+    errors: Mutex<BTreeSet<String>>,
+}
+
+impl WalkVisitor for Recorder {
+    fn on_entry(&self, entry: &PathMetadata) -> WalkAction {
+        self.paths
+            .lock()
+            .unwrap()
+            .insert((entry.path.clone(), entry.metadata.is_dir()));
+        WalkAction::Continue
+    }
+
+    fn on_error(&self, err: &Error) -> WalkAction {
+        self.errors.lock().unwrap().insert(err.to_string());
+        WalkAction::Continue
+    }
+}
+
+type Walked = (BTreeSet<(PathBuf, bool)>, BTreeSet<String>);
+
+/// The metrics that don't depend on the timing of the walk: the files, directories, ignored
+/// paths and errors counted, the unused ignore files and the extensions, sorted.
+type Counts = (
+    [usize; 4],
+    UnusedIgnoreFiles,
+    BTreeMap<std::ffi::OsString, xvc_walker::ExtensionSummary>,
+);
+
+fn counts(metrics: WalkMetrics) -> Counts {
+    (
+        [
+            metrics.files_visited,
+            metrics.directories_visited,
+            metrics.entries_ignored,
+            metrics.errors_encountered,
+        ],
+        metrics.unused_ignore_files,
+        metrics.extensions.into_iter().collect(),
+    )
+}
+
+fn walk_with_serial_metrics(
+    root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<(Walked, WalkMetrics)> {
+    let recorder = Recorder::default();
+    let (_, metrics) = walk_serial_visit("", root, walk_options, &recorder)?;
+    let walked = (
+        recorder.paths.into_inner().unwrap(),
+        recorder.errors.into_inner().unwrap(),
+    );
+    Ok((walked, metrics))
+}
+
+fn walk_with_serial(root: &Path, walk_options: &WalkOptions) -> Result<Walked> {
+    Ok(walk_with_serial_metrics(root, walk_options)?.0)
+}
+
+/// Walks `root` in parallel with `ignore_rules`, which is built once for all options as the walk
+/// options in the tests don't change the rules.
+fn walk_with_parallel(
+    ignore_rules: &IgnoreRules,
+    root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<Walked> {
+    Ok(walk_with_parallel_metrics(ignore_rules, root, walk_options)?.0)
+}
+
+fn walk_with_parallel_metrics(
+    ignore_rules: &IgnoreRules,
+    root: &Path,
+    walk_options: &WalkOptions,
+) -> Result<(Walked, WalkMetrics)> {
+    let (path_sender, path_receiver) = unbounded();
+    let summary = walk_parallel(
+        Arc::new(RwLock::new(ignore_rules.clone())),
+        root,
+        walk_options.clone(),
+        path_sender,
+    )?;
+    let mut paths = BTreeSet::new();
+    let mut errors = BTreeSet::new();
+    for path_res in path_receiver {
+        match path_res {
+            Ok(pm) => {
+                paths.insert((pm.path, pm.metadata.is_dir()));
+            }
+            Err(e) => {
+                errors.insert(e.to_string());
+            }
+        }
+    }
+    Ok(((paths, errors), summary.metrics))
+}
+
+#[test]
+fn test_serial_and_parallel_walkers_agree_on_all_options() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let file_root = root.join("a/1.txt");
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &WalkOptions::gitignore())?;
+    let file_ignore_rules = IgnoreRules::empty(file_root.parent().unwrap(), None);
+    let mut combinations = 0;
+    for parents_first in [false, true] {
+        for emit in [EmitKinds::ALL, EmitKinds::FILES, EmitKinds::DIRS] {
+            for traversal_order in [
+                TraversalOrder::DepthFirst,
+                TraversalOrder::BreadthFirst,
+                TraversalOrder::ShallowestFirst,
+            ] {
+                for follow_symlinks in [false, true] {
+                    for ignore_dot_git in [false, true] {
+                        for broken_symlink_handling in [
+                            BrokenSymlinkHandling::Skip,
+                            BrokenSymlinkHandling::EmitAsError,
+                            BrokenSymlinkHandling::EmitAsEntry,
+                        ] {
+                            let walk_options = WalkOptions {
+                                parents_first,
+                                emit,
+                                traversal_order,
+                                follow_symlinks,
+                                ignore_dot_git,
+                                broken_symlink_handling,
+                                ..WalkOptions::gitignore()
+                            };
+                            let (serial, serial_metrics) =
+                                walk_with_serial_metrics(&root, &walk_options)?;
+                            let (parallel, parallel_metrics) =
+                                walk_with_parallel_metrics(&ignore_rules, &root, &walk_options)?;
+                            assert_eq!(serial, parallel, "{walk_options:?}");
+                            assert_eq!(
+                                counts(serial_metrics),
+                                counts(parallel_metrics),
+                                "{walk_options:?}"
+                            );
+                            combinations += 1;
+
+                            let serial = walk_with_serial(&file_root, &walk_options)?;
+                            let parallel =
+                                walk_with_parallel(&file_ignore_rules, &file_root, &walk_options)?;
+                            assert_eq!(serial, parallel, "{walk_options:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(combinations, 216);
+    Ok(())
+}
+
+#[test]
+fn test_serial_walker_follows_the_options() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    let walk_options = WalkOptions {
+        parents_first: true,
+        follow_symlinks: true,
+        broken_symlink_handling: BrokenSymlinkHandling::EmitAsError,
+        ..WalkOptions::gitignore()
+    };
+    let (paths, errors) = walk_with_serial(&root, &walk_options)?;
+    let paths = paths
+        .into_iter()
+        .map(|(p, is_dir)| (p.strip_prefix(&root).unwrap().to_path_buf(), is_dir))
+        .collect::<BTreeSet<_>>();
+    let expected = [
+        (".gitignore", false),
+        ("a", true),
+        ("a/1.txt", false),
+        ("a/b", true),
+        ("a/b/.gitignore", false),
+        ("a/b/c", true),
+        ("a/b/c/3.txt", false),
+        ("a/d", true),
+        ("a/d/4.txt", false),
+        ("a/outside", true),
+        ("a/outside/e", true),
+        ("a/outside/e/6.txt", false),
+    ]
+    .into_iter()
+    .map(|(p, is_dir)| (PathBuf::from(p), is_dir))
+    .collect::<BTreeSet<_>>();
+    assert_eq!(paths, expected);
+    // The broken symlink and the symlink to an ancestor.
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    Ok(())
+}
+
+#[test]
+fn test_serial_and_parallel_walkers_agree_on_metrics() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_tree()?;
+    fs::write(root.join("a/d/.xvcignore"), "4.txt\n")?;
+    let walk_options = WalkOptions {
+        collect_timings: true,
+        summarize_extensions: true,
+        follow_symlinks: true,
+        ..WalkOptions::gitignore()
+    };
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    let (serial, serial_metrics) = walk_with_serial_metrics(&root, &walk_options)?;
+    let (parallel, parallel_metrics) =
+        walk_with_parallel_metrics(&ignore_rules, &root, &walk_options)?;
+    assert_eq!(serial, parallel);
+    assert!(serial_metrics.timings.directory_read > Duration::ZERO);
+    assert!(serial_metrics.timings.total() <= serial_metrics.elapsed);
+    assert!(parallel_metrics.timings.directory_read > Duration::ZERO);
+
+    let serial_counts = counts(serial_metrics);
+    assert_eq!(serial_counts, counts(parallel_metrics));
+    // 1.txt, 3.txt, 4.txt, 6.txt, the broken symlink and 3 ignore files
+    assert_eq!(serial_counts.0[0], 8);
+    assert_eq!(serial_counts.1.count, 1);
+    assert_eq!(serial_counts.2[std::ffi::OsStr::new("txt")].count, 4);
+
+    // The timings are not collected by default
+    let (_, metrics) = walk_with_serial_metrics(&root, &WalkOptions::gitignore())?;
+    assert_eq!(metrics.timings.total(), Duration::ZERO);
+    Ok(())
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn test_serial_and_parallel_walkers_agree_on_unicode_normalize() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::write(root.join(".gitignore"), "caf\u{E9}.txt\n")?;
+    fs::write(root.join("cafe\u{301}.txt"), "a")?;
+    fs::write(root.join("other.txt"), "b")?;
+    // The rules are built without normalization, the walk options set it
+    let ignore_rules = build_ignore_patterns_with_options("", &root, &WalkOptions::gitignore())?;
+    for unicode_normalize in [false, true] {
+        let walk_options = WalkOptions {
+            unicode_normalize,
+            ..WalkOptions::gitignore()
+        };
+        let serial = walk_with_serial(&root, &walk_options)?;
+        let parallel = walk_with_parallel(&ignore_rules, &root, &walk_options)?;
+        assert_eq!(serial, parallel);
+        assert_eq!(
+            serial.0.contains(&(root.join("cafe\u{301}.txt"), false)),
+            !unicode_normalize
+        );
+    }
+    Ok(())
+}
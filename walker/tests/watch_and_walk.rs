@@ -0,0 +1,84 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns, watch_and_walk, PathEvent, WalkOptions};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn test_watch_and_walk() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join(".gitignore"), "*.tmp\n")?;
+    fs::write(root.join("a.txt"), "a")?;
+    fs::write(root.join("b.tmp"), "b")?;
+    fs::write(root.join("dir/.gitattributes"), "*.log export-ignore\n")?;
+    fs::write(root.join("dir/c.txt"), "c")?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+
+    let handle = watch_and_walk(
+        &root,
+        Arc::new(RwLock::new(ignore_rules)),
+        WalkOptions {
+            read_gitattributes_export_ignore: true,
+            ..WalkOptions::gitignore()
+        },
+    )?;
+    let mut snapshot: Vec<PathBuf> = handle
+        .initial_snapshot
+        .iter()
+        .map(|pm| pm.path.clone())
+        .collect();
+    snapshot.sort();
+    assert_eq!(
+        snapshot,
+        vec![
+            root.join(".gitignore"),
+            root.join("a.txt"),
+            root.join("dir/.gitattributes"),
+            root.join("dir/c.txt")
+        ]
+    );
+
+    // The changes after the walk are in the events, the ignored ones are not, including the ones
+    // ignored by the patterns the walk added
+    fs::write(root.join("d.tmp"), "d")?;
+    fs::write(root.join("dir/f.log"), "f")?;
+    fs::write(root.join("dir/e.txt"), "e")?;
+    let start = Instant::now();
+    let mut created = Vec::new();
+    while start.elapsed() < TIMEOUT && !created.contains(&root.join("dir/e.txt")) {
+        if let Ok(Some(PathEvent::Create { path, .. })) =
+            handle.events.recv_timeout(Duration::from_millis(100))
+        {
+            created.push(path);
+        }
+    }
+    // Receive the other events of the writes, which may come after the one of dir/e.txt
+    std::thread::sleep(Duration::from_millis(200));
+    while let Ok(event) = handle.events.try_recv() {
+        if let Some(PathEvent::Create { path, .. }) = event {
+            created.push(path);
+        }
+    }
+    assert!(created.contains(&root.join("dir/e.txt")));
+    assert!(!created.contains(&root.join("d.tmp")));
+    assert!(!created.contains(&root.join("dir/f.log")));
+
+    fs::remove_file(root.join("a.txt"))?;
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT && handle.events.is_empty() {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    // The events not received yet are returned when the watcher is stopped
+    let events = handle.join();
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, PathEvent::Delete { path } if *path == root.join("a.txt"))));
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns, make_scoped_watcher, minimal_watch_set, ScopedWatcher};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn setup_test_directory(structure: &[&str], ignore_files: &[(&str, &str)]) -> Result<PathBuf> {
+    let root = create_temp_dir();
+    for path_str in structure {
+        let path = root.join(path_str);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, path_str)?;
+    }
+    for (path_str, content) in ignore_files {
+        fs::write(root.join(path_str), content)?;
+    }
+    Ok(root)
+}
+
+fn relative_dirs(root: &Path, dirs: &[PathBuf]) -> Vec<String> {
+    dirs.iter()
+        .map(|d| d.strip_prefix(root).unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Waits until `dir` is watched by `watcher`.
+fn wait_for_watch(watcher: &ScopedWatcher, dir: &Path) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if watcher.watched_dirs().iter().any(|d| d == dir) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn test_minimal_watch_set_excludes_ignored_dirs() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &[
+            "output/logs/a.log",
+            "output/data/b.dat",
+            "src/lib/c.rs",
+            "config.txt",
+        ],
+        &[(".gitignore", "output/\n!output/data/")],
+    )?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+
+    // The whitelisted directory is in an ignored one, so it's not walked nor watched
+    let watch_set = minimal_watch_set(&ignore_rules, &root);
+    assert_eq!(relative_dirs(&root, &watch_set), vec!["", "src", "src/lib"]);
+    Ok(())
+}
+
+#[test]
+fn test_minimal_watch_set_whitelisted_subtree() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &[
+            "data/raw/a.csv",
+            "data/processed/b.csv",
+            "other/deep/deeper/c.txt",
+            "cache/d.bin",
+        ],
+        &[(".gitignore", "/*\n!/data/")],
+    )?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+
+    let watch_set = minimal_watch_set(&ignore_rules, &root);
+    assert_eq!(
+        relative_dirs(&root, &watch_set),
+        vec!["", "data", "data/processed", "data/raw"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_scoped_watcher_expands() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = setup_test_directory(
+        &["src/lib/c.rs", "output/logs/a.log", "other/d.txt"],
+        &[(".gitignore", "/output/\n/other/\n")],
+    )?;
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    // No pattern can match under src/, so it's watched recursively without its subdirectories
+    let watch_set = minimal_watch_set(&ignore_rules, &root);
+    assert_eq!(relative_dirs(&root, &watch_set), vec!["", "src"]);
+
+    let (watcher, _receiver) = make_scoped_watcher(ignore_rules)?;
+    assert_eq!(watcher.watched_dirs(), watch_set);
+
+    // New directories are watched
+    fs::create_dir(root.join("new"))?;
+    assert!(wait_for_watch(&watcher, &root.join("new")));
+
+    // Whitelisted directories are watched after the ignore file changes
+    fs::write(root.join(".gitignore"), "/output/\n/other/\n!/other/\n")?;
+    assert!(wait_for_watch(&watcher, &root.join("other")));
+    assert!(!watcher.watched_dirs().contains(&root.join("output")));
+    assert!(!watcher.watched_dirs().contains(&root.join("src/lib")));
+    Ok(())
+}
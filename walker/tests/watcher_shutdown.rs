@@ -0,0 +1,83 @@
+use anyhow::Result;
+use crossbeam_channel::{Receiver, RecvTimeoutError, TryRecvError};
+use log::LevelFilter;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns, make_polling_watcher, make_watcher, PathEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Receives the events until there is one for each of `paths`.
+fn wait_for_paths(receiver: &Receiver<Option<PathEvent>>, paths: &[PathBuf]) -> bool {
+    let mut remaining: HashSet<&PathBuf> = paths.iter().collect();
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT && !remaining.is_empty() {
+        if let Ok(Some(event)) = receiver.recv_timeout(Duration::from_millis(100)) {
+            let path = match &event {
+                PathEvent::Create { path, .. }
+                | PathEvent::Update { path, .. }
+                | PathEvent::Delete { path } => path,
+            };
+            remaining.remove(path);
+        }
+    }
+    remaining.is_empty()
+}
+
+#[test]
+fn test_polling_watcher_shutdown() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    let (watcher, receiver) = make_polling_watcher(ignore_rules)?;
+    assert!(watcher.is_alive());
+
+    let paths = [root.join("a.txt"), root.join("b.txt")];
+    for path in &paths {
+        fs::write(path, "content")?;
+    }
+    assert!(wait_for_paths(&receiver, &paths));
+
+    watcher.shutdown()?;
+    // The events sent before the shutdown are received, then the channel is disconnected
+    let start = Instant::now();
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(_) => assert!(start.elapsed() < TIMEOUT),
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => panic!("Receiver is not disconnected"),
+        }
+    }
+
+    fs::write(root.join("c.txt"), "c")?;
+    fs::remove_file(&paths[0])?;
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Disconnected);
+    Ok(())
+}
+
+#[test]
+fn test_watcher_is_not_alive_without_receiver() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    let ignore_rules = build_ignore_patterns("", &root, ".gitignore")?;
+    let (watcher, receiver) = make_watcher(ignore_rules)?;
+    assert!(watcher.is_alive());
+    drop(receiver);
+
+    // The watcher finds out when it can't send an event
+    let start = Instant::now();
+    let mut i = 0;
+    while watcher.is_alive() && start.elapsed() < TIMEOUT {
+        fs::write(root.join(format!("{i}.txt")), "content")?;
+        i += 1;
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!watcher.is_alive());
+    watcher.shutdown()?;
+    Ok(())
+}
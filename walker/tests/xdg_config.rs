@@ -0,0 +1,47 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::env;
+use std::fs;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{build_ignore_patterns_with_options, IgnoreRules, MatchResult, WalkOptions};
+
+// The environment is shared by the tests in a binary, so this is the only test here.
+#[test]
+fn test_xdg_config_ignore_file() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let config_home = create_temp_dir();
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join("a.bak"), "a")?;
+    fs::write(root.join("dir/b.bak"), "b")?;
+    fs::write(root.join("keep.bak"), "keep")?;
+    fs::write(root.join(".xvcignore"), "!keep.bak\n")?;
+    env::set_var("XDG_CONFIG_HOME", &config_home);
+
+    // Missing file gives empty rules
+    let rules = IgnoreRules::from_xdg_config(&root)?;
+    assert!(rules.snapshot_patterns().is_empty());
+    assert_eq!(rules.root, root);
+
+    fs::create_dir_all(config_home.join("xvc"))?;
+    fs::write(config_home.join("xvc/ignore"), "# user rules\n*.bak\n")?;
+    let rules = IgnoreRules::from_xdg_config(&root)?;
+    assert_eq!(rules.check(&root.join("a.bak")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("dir/b.bak")), MatchResult::Ignore);
+    assert_eq!(
+        rules.snapshot_patterns()[0].source_path,
+        Some(config_home.join("xvc/ignore"))
+    );
+
+    let walk_options = WalkOptions {
+        respect_xdg_config: true,
+        ..WalkOptions::xvcignore()
+    };
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("a.bak")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("keep.bak")), MatchResult::Whitelist);
+
+    let rules = build_ignore_patterns_with_options("", &root, &WalkOptions::xvcignore())?;
+    assert_eq!(rules.check(&root.join("a.bak")), MatchResult::NoMatch);
+    Ok(())
+}
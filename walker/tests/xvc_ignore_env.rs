@@ -0,0 +1,53 @@
+use anyhow::Result;
+use log::LevelFilter;
+use std::env;
+use std::fs;
+use xvc_test_helper::{create_temp_dir, test_logging};
+use xvc_walker::{
+    build_ignore_patterns_with_options, IgnoreRules, MatchResult, WalkOptions, XVC_IGNORE_ENV,
+};
+
+// The environment is shared by the tests in a binary, so this is the only test here.
+#[test]
+fn test_xvc_ignore_env() -> Result<()> {
+    test_logging(LevelFilter::Trace);
+    let root = create_temp_dir();
+    fs::create_dir_all(root.join("dir"))?;
+    fs::write(root.join("a.log"), "a")?;
+    fs::write(root.join("dir/b.tmp"), "b")?;
+    fs::write(root.join("keep.log"), "keep")?;
+    fs::write(root.join("c.txt"), "c")?;
+    fs::write(root.join(".xvcignore"), "!keep.log\n")?;
+
+    // Unset variable adds nothing
+    env::remove_var(XVC_IGNORE_ENV);
+    let rules = IgnoreRules::empty(&root, None);
+    rules.add_from_env(XVC_IGNORE_ENV)?;
+    assert!(rules.snapshot_patterns().is_empty());
+
+    env::set_var(XVC_IGNORE_ENV, "*.log:*.tmp\ndir/");
+    let rules = IgnoreRules::empty(&root, None);
+    rules.add_from_env(XVC_IGNORE_ENV)?;
+    assert_eq!(rules.snapshot_patterns().len(), 3);
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("dir/b.tmp")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("dir")), MatchResult::Ignore);
+    assert_eq!(rules.check(&root.join("c.txt")), MatchResult::NoMatch);
+
+    let walk_options = WalkOptions {
+        respect_xvc_ignore_env: true,
+        ..WalkOptions::xvcignore()
+    };
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::Ignore);
+    // The ignore files in the tree override the variable
+    assert_eq!(rules.check(&root.join("keep.log")), MatchResult::Whitelist);
+
+    let rules = build_ignore_patterns_with_options("", &root, &WalkOptions::xvcignore())?;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::NoMatch);
+
+    env::remove_var(XVC_IGNORE_ENV);
+    let rules = build_ignore_patterns_with_options("", &root, &walk_options)?;
+    assert_eq!(rules.check(&root.join("a.log")), MatchResult::NoMatch);
+    Ok(())
+}